@@ -0,0 +1,83 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    // Embedded by `sqlx::migrate!`, which does not notice new files.
+    println!("cargo:rerun-if-changed=migrations");
+
+    let git_hash = git_cmd(&["rev-parse", "--short=6", "HEAD"]);
+
+    let git_date =
+        git_cmd(&["show", "-s", "--format=%cd", "--date=short", "HEAD"]);
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let rustc_version = cmd(&rustc, &["--version"]);
+    let profile =
+        std::env::var("PROFILE").unwrap_or_else(|_| "unknown".into());
+
+    println!("cargo:rustc-env=GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=BUILD_DATE={git_date}");
+    println!("cargo:rustc-env=RUSTC_VERSION={rustc_version}");
+    println!("cargo:rustc-env=BUILD_PROFILE={profile}");
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Generates the gRPC services of `proto/`, and the descriptor set served
+/// by the reflection service. The files are parsed by protox, no `protoc`
+/// needed.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto");
+
+    let mut compiler = protox::Compiler::new(["proto"]).unwrap();
+    compiler
+        .include_source_info(true)
+        .include_imports(true)
+        .open_files(["users.proto"])
+        .expect("could not parse the proto files");
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(
+        out_dir.join("descriptor.bin"),
+        compiler.encode_file_descriptor_set(),
+    )
+    .unwrap();
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(compiler.file_descriptor_set())
+        .expect("could not generate the gRPC services");
+}
+
+fn git_cmd(args: &[&str]) -> String {
+    cmd("git", args)
+}
+
+fn cmd(program: &str, args: &[&str]) -> String {
+    use std::process::Command;
+
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,4 @@
+-- Set while the user is in the trash, microseconds since the epoch.
+ALTER TABLE users ADD COLUMN deleted_at BIGINT;
+
+CREATE INDEX users_deleted_at ON users (deleted_at);
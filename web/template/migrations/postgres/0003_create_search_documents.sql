@@ -0,0 +1,17 @@
+-- Full-text index of the searchable content, one row per document. The
+-- weighted tsvector ranks title matches over body ones.
+CREATE TABLE search_documents (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    url TEXT NOT NULL,
+    document TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A')
+        || setweight(to_tsvector('english', body), 'B')
+    ) STORED,
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX search_documents_document ON search_documents
+    USING GIN (document);
@@ -0,0 +1,31 @@
+-- Endpoints receiving the outbox events, managed on /admin/webhooks, and
+-- the attempts to deliver them. Timestamps are microseconds since the
+-- epoch.
+CREATE TABLE webhook_endpoints (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- Key of the webhook-signature header, kept in clear to sign with.
+    secret TEXT NOT NULL,
+    -- JSON array, every topic when empty.
+    topics TEXT NOT NULL,
+    enabled BIGINT NOT NULL DEFAULT 1,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE webhook_deliveries (
+    id TEXT PRIMARY KEY,
+    endpoint_id TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    attempt BIGINT NOT NULL,
+    -- HTTP status of the response, none when the request failed.
+    status BIGINT,
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX webhook_deliveries_endpoint
+    ON webhook_deliveries (endpoint_id, created_at);
+CREATE INDEX webhook_deliveries_created_at
+    ON webhook_deliveries (created_at);
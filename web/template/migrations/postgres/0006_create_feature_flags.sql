@@ -0,0 +1,7 @@
+-- Feature flags forced on or off on /admin/flags, the others follow their
+-- definition in [flags]. Timestamps are microseconds since the epoch.
+CREATE TABLE feature_flags (
+    name TEXT PRIMARY KEY,
+    enabled BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
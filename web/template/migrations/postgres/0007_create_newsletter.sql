@@ -0,0 +1,16 @@
+-- Newsletter subscribers, confirmed once they followed the link of the
+-- confirmation email, and the addresses never to be emailed again, e.g.
+-- after unsubscribing. Timestamps are microseconds since the epoch.
+CREATE TABLE newsletter_subscribers (
+    id TEXT PRIMARY KEY,
+    email TEXT NOT NULL UNIQUE,
+    created_at BIGINT NOT NULL,
+    confirmed_at BIGINT
+);
+
+CREATE TABLE newsletter_suppressions (
+    email TEXT PRIMARY KEY,
+    -- Why it is suppressed, e.g. "unsubscribed", "bounced" or "complaint".
+    reason TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
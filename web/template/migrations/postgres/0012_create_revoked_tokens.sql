@@ -0,0 +1,8 @@
+-- Ids of the revoked and the already used refresh tokens, kept until the
+-- token expires. exp is in seconds since the epoch, as in the token.
+CREATE TABLE revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    exp BIGINT NOT NULL
+);
+
+CREATE INDEX revoked_tokens_exp ON revoked_tokens (exp);
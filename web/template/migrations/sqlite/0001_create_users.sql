@@ -0,0 +1,16 @@
+CREATE TABLE users (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    -- 0 or 1, the Any driver does not map SQLite booleans.
+    email_verified INTEGER NOT NULL DEFAULT 0,
+    -- JSON array of role names.
+    roles TEXT NOT NULL,
+    -- Microseconds since the epoch.
+    created_at INTEGER NOT NULL,
+    deletion_scheduled_at INTEGER
+);
+
+CREATE INDEX users_created_at ON users (created_at);
+CREATE INDEX users_deletion_scheduled_at ON users (deletion_scheduled_at);
@@ -0,0 +1,9 @@
+-- Full-text index of the searchable content, one row per document.
+CREATE VIRTUAL TABLE search_documents USING fts5(
+    kind UNINDEXED,
+    id UNINDEXED,
+    title,
+    body,
+    url UNINDEXED,
+    tokenize = 'porter unicode61'
+);
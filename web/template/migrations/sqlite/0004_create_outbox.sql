@@ -0,0 +1,20 @@
+-- Domain events, written in the transaction of the change they describe
+-- and published by the relay. Timestamps are microseconds since the epoch.
+CREATE TABLE outbox (
+    id TEXT PRIMARY KEY,
+    topic TEXT NOT NULL,
+    -- JSON object.
+    payload TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    -- Deliveries started, the running one included.
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Next time the relay may claim the event, pushed back while it is
+    -- being delivered and after a failed delivery.
+    available_at INTEGER NOT NULL,
+    published_at INTEGER,
+    last_error TEXT
+);
+
+CREATE INDEX outbox_pending ON outbox (available_at)
+    WHERE published_at IS NULL;
+CREATE INDEX outbox_published_at ON outbox (published_at);
@@ -0,0 +1,21 @@
+-- Blog posts of the database source of [posts], bodies in markdown. Posts
+-- without published_at are drafts, those with a future one scheduled.
+-- Timestamps are microseconds since the epoch.
+CREATE TABLE posts (
+    slug TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    summary TEXT NOT NULL DEFAULT '',
+    body TEXT NOT NULL,
+    published_at INTEGER,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX posts_published_at ON posts (published_at);
+
+CREATE TABLE post_tags (
+    slug TEXT NOT NULL REFERENCES posts (slug) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (slug, tag)
+);
+
+CREATE INDEX post_tags_tag ON post_tags (tag);
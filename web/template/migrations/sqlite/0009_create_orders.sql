@@ -0,0 +1,27 @@
+-- Orders paid through the checkout of [payments], and the ids of the
+-- payment events already applied to them, so a redelivered event changes
+-- nothing. Orders outlive their user, who is detached on erasure.
+-- Amounts are in the smallest unit of the currency, timestamps
+-- microseconds since the epoch.
+CREATE TABLE orders (
+    id TEXT PRIMARY KEY,
+    user_id TEXT,
+    product TEXT NOT NULL,
+    description TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    -- "pending", "paid", "failed" or "expired".
+    status TEXT NOT NULL,
+    checkout_session TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX orders_user_id ON orders (user_id);
+
+CREATE TABLE payment_events (
+    id TEXT PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    received_at INTEGER NOT NULL
+);
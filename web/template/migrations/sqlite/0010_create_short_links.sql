@@ -0,0 +1,10 @@
+-- Short links redirecting /s/<code> to url, managed by the user who made
+-- them. Timestamps are microseconds since the epoch.
+CREATE TABLE short_links (
+    code TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX short_links_user_id ON short_links (user_id, created_at);
@@ -0,0 +1,15 @@
+-- API keys of the users, looked up by the SHA-256 of the secret. Timestamps
+-- are microseconds since the epoch.
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    hash TEXT NOT NULL UNIQUE,
+    -- JSON array of scopes.
+    scopes TEXT NOT NULL,
+    expires_at INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX api_keys_user_id ON api_keys (user_id, created_at);
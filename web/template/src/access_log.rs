@@ -0,0 +1,111 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    body::HttpBody as _,
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use axum_client_ip::ClientIp;
+use serde::Deserialize;
+use tracing::{field::display, info};
+use uuid::Uuid;
+
+use crate::metric::UNMATCHED_PATH;
+use crate::state::AppState;
+use crate::tenancy::Tenant;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AccessLogSettings {
+    pub(crate) enabled: bool,
+    /// Path prefixes not logged, e.g. health checks polled every second.
+    pub(crate) exclude: Vec<String>,
+}
+
+impl AccessLogSettings {
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|prefix| path.starts_with(prefix.as_str()))
+    }
+}
+
+/// Id of the logged in user, put on the response by [`auth::load_user`],
+/// which runs inside this layer and so after the request was logged.
+///
+/// [`auth::load_user`]: crate::auth::load_user
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UserId(pub(crate) Uuid);
+
+/// Logs one event per request once the response is ready, with the same
+/// fields whatever the log format, for access logs and their dashboards.
+/// The query string is left out as it may carry tokens.
+pub(crate) async fn log(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !state.settings.access_log.enabled
+        || state.settings.access_log.is_excluded(req.uri().path())
+    {
+        return next.run(req).await;
+    }
+
+    let start = Instant::now();
+    let (mut parts, body) = req.into_parts();
+    let ip = ClientIp::from_request_parts(&mut parts, &state)
+        .await
+        .ok()
+        .map(|ClientIp(ip)| display(ip));
+    let method = parts.method.clone();
+    let path = parts.uri.path().to_owned();
+    let route = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str)
+        .to_owned();
+    let request_id = parts
+        .headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    // Unknown for streamed bodies, e.g. file downloads.
+    let bytes = response.body().size_hint().exact();
+    let user_id =
+        response.extensions().get::<UserId>().map(|UserId(id)| display(*id));
+    let tenant =
+        response.extensions().get::<Tenant>().map(|tenant| tenant.id.as_str());
+    info!(
+        %method,
+        path,
+        route,
+        status = response.status().as_u16(),
+        latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+        bytes,
+        ip,
+        user_id,
+        tenant,
+        request_id,
+        "request"
+    );
+    response
+}
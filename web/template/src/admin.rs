@@ -0,0 +1,143 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+
+use crate::audit::Audit;
+use crate::auth::CurrentUser;
+use crate::pagination::Pagination;
+use crate::problem::{Problem, internal};
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::view::View;
+
+/// Audit events listed on the dashboard.
+const RECENT_EVENTS: usize = 10;
+
+/// Key figure of the dashboard, linking to the page listing it.
+#[derive(Debug, Serialize)]
+struct Metric {
+    label: &'static str,
+    value: u64,
+    link: Option<String>,
+}
+
+/// Dashboard of the administrators, guarded by the `admin.access`
+/// permission: record counts, the recent audit events and the resources
+/// the user may manage.
+pub(crate) async fn handler_admin(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, ServerError> {
+    let permissions = state.settings.rbac.permissions(&user);
+
+    let mut metrics = vec![Metric {
+        label: "Users",
+        value: state.users.count().await?,
+        link: permissions
+            .can("users.manage")
+            .then(|| "/admin/users".to_string()),
+    }];
+    for resource in &state.admin_resources {
+        if permissions.can(resource.permission()) {
+            metrics.push(Metric {
+                label: resource.title(),
+                value: resource.count(&state).await?,
+                link: Some(format!("/admin/{}", resource.name())),
+            });
+        }
+    }
+
+    let events = if permissions.can("audit.view") {
+        let recent =
+            Pagination { page: 1, per_page: RECENT_EVENTS, after: None };
+        state.audit.recent(recent).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(view
+        .render(
+            "admin",
+            context! {
+                title => "Admin",
+                metrics => metrics,
+                events => events,
+                roles => state.settings.rbac.roles,
+            },
+        )
+        .unwrap())
+}
+
+/// Body of `PUT /admin/log-level` and of its answers.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LogLevel {
+    /// `EnvFilter` directives, e.g. `info,tower_http=debug`.
+    filter: String,
+}
+
+/// Filter currently applied to the logs.
+pub(crate) async fn handler_log_level(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<LogLevel>, Response> {
+    let filter = state
+        .log_filter
+        .with_current(ToString::to_string)
+        .map_err(internal)?;
+    Ok(Json(LogLevel { filter }))
+}
+
+/// Replaces the log filter until the next restart, e.g. to turn on debug
+/// logs of one module while chasing a bug.
+pub(crate) async fn handler_log_level_put(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    Json(input): Json<LogLevel>,
+) -> Result<Json<LogLevel>, Response> {
+    let filter = EnvFilter::try_new(&input.filter).map_err(|e| {
+        Problem::new(StatusCode::UNPROCESSABLE_ENTITY)
+            .detail(e.to_string())
+            .into_response()
+    })?;
+    let previous = state
+        .log_filter
+        .with_current(ToString::to_string)
+        .map_err(internal)?;
+    state.log_filter.reload(filter).map_err(internal)?;
+
+    warn!(from = %previous, to = %input.filter, "log filter changed");
+    audit
+        .record(
+            "admin.log_level",
+            None,
+            json!({ "from": previous, "to": input.filter }),
+        )
+        .await;
+    handler_log_level(State(state)).await
+}
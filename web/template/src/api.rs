@@ -0,0 +1,393 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Json, Router,
+    extract::{FromRequestParts, Request, State},
+    http::{
+        HeaderName, HeaderValue, Method, StatusCode, header, request::Parts,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use axum_client_ip::ClientIp;
+use serde::Deserialize;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::warn;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{ApiKey as ApiKeyScheme, ApiKeyValue, HttpAuthScheme},
+    openapi::security::{HttpBuilder, SecurityScheme},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_swagger_ui::SwaggerUi;
+use uuid::Uuid;
+
+use crate::api_key::{self, ApiKey};
+use crate::error_reporting;
+use crate::jwt::{self, Claims};
+use crate::problem::{ApiError, PROBLEM_JSON, Problem};
+use crate::rate_limit::RateLimitSettings;
+use crate::state::AppState;
+use crate::transaction;
+use crate::upload;
+use crate::users::User;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Bytes of an error body kept as the detail of its problem.
+const MAX_DETAIL: usize = 1024;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiSettings {
+    /// Requests per client IP, whatever their credentials.
+    pub(crate) rate_limit: RateLimitSettings,
+    pub(crate) cors: CorsSettings,
+    /// Serves the OpenAPI document on `/api/openapi.json` and Swagger UI
+    /// on `/api/docs`.
+    pub(crate) docs: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CorsSettings {
+    /// Origins of the pages allowed to call the API, e.g.
+    /// `https://app.example.com`, or `*` for any. None when empty.
+    pub(crate) allowed_origins: Vec<String>,
+    /// Seconds browsers cache a preflight response.
+    pub(crate) max_age: u64,
+}
+
+impl CorsSettings {
+    /// Credentials are headers rather than cookies, so none are allowed.
+    fn layer(&self) -> CorsLayer {
+        let origins = if self.allowed_origins.iter().any(|o| o == "*") {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(self.allowed_origins.iter().filter_map(
+                |origin| {
+                    let value = HeaderValue::from_str(origin);
+                    if value.is_err() {
+                        warn!(origin, "ignoring invalid CORS origin");
+                    }
+                    value.ok()
+                },
+            ))
+        };
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static(api_key::API_KEY_HEADER),
+            ])
+            .max_age(Duration::from_secs(self.max_age))
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "API", version = "1"),
+    servers((url = "/api/v1")),
+    modifiers(&SecuritySchemes),
+)]
+struct ApiDoc;
+
+/// The `bearer` and `api_key` schemes the paths refer to.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_default();
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKeyScheme::Header(ApiKeyValue::new(
+                api_key::API_KEY_HEADER,
+            ))),
+        );
+    }
+}
+
+/// Routes of `/api`, with the version routers and their documentation.
+///
+/// The routes of a version are registered with `routes!`, from the path
+/// and method of their `#[utoipa::path]`, so the document cannot describe
+/// a route the router lacks or miss one it has.
+pub(crate) fn routes(state: &Arc<AppState>) -> Router<Arc<AppState>> {
+    let (v1, openapi) = v1().split_for_parts();
+    let v1 = v1
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(middleware::from_fn(transaction::commit))
+        .layer(middleware::from_fn(error_reporting::capture))
+        .layer(middleware::from_fn(problems))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(state.settings.api.cors.layer());
+    let router = Router::new().nest("/api/v1", v1);
+    if !state.settings.api.docs {
+        return router;
+    }
+    router.merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", openapi))
+}
+
+/// Routes of `/api/v1`, for clients other than the pages: no session,
+/// CSRF or flash messages, credentials in the headers, and JSON both ways
+/// with every error as problem details.
+///
+/// A breaking change goes in a new `/api/v2` router, nested next to this
+/// one until its clients moved.
+fn v1() -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .routes(routes!(jwt::handler_token))
+        .routes(routes!(jwt::handler_token_refresh))
+        .routes(routes!(jwt::handler_token_revoke))
+        .routes(routes!(handler_me))
+        .routes(routes!(
+            api_key::handler_api_keys_list,
+            api_key::handler_api_keys_create
+        ))
+        .routes(routes!(api_key::handler_api_keys_delete))
+        .routes(routes!(api_key::handler_api_key_info))
+        .routes(routes!(upload::handler_upload_presign))
+}
+
+async fn not_found() -> Problem {
+    Problem::new(StatusCode::NOT_FOUND)
+}
+
+async fn method_not_allowed() -> Problem {
+    Problem::new(StatusCode::METHOD_NOT_ALLOWED)
+}
+
+/// Sets the `instance` of the problem details to the request id, which
+/// support can grep the logs for.
+///
+/// Error responses which are not problem details yet, e.g. the 413 of the
+/// body limit or the 415 of a form sent to a JSON route, are turned into
+/// ones, keeping their headers. The bodies of server errors are dropped.
+async fn problems(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_string);
+    let response = next.run(req).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let mut problem = match parts.extensions.remove::<Problem>() {
+        Some(problem) => problem,
+        None => {
+            let mut problem = Problem::new(status);
+            if status.is_client_error()
+                && let Ok(bytes) = axum::body::to_bytes(body, MAX_DETAIL).await
+                && let Ok(detail) = String::from_utf8(bytes.to_vec())
+                && !detail.trim().is_empty()
+            {
+                problem = problem.detail(detail);
+            }
+            problem
+        }
+    };
+    problem.instance = problem.instance.or(request_id);
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let mut response = problem.into_response();
+    response.headers_mut().extend(parts.headers);
+    response
+}
+
+/// Limits the requests of each client IP, before any credential is
+/// checked, e.g. against guessing passwords on `/token`.
+async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !state.api_limiter.check(&ip.to_string()).await {
+        return Problem::new(StatusCode::TOO_MANY_REQUESTS)
+            .detail("Too many requests from this address.")
+            .into_response();
+    }
+    next.run(req).await
+}
+
+/// Who an API request is made for: the user of an `Authorization: Bearer`
+/// access token, or an `X-Api-Key`, whose scopes the handler checks.
+pub(crate) enum Caller {
+    User(Claims),
+    Key(ApiKey),
+}
+
+impl Caller {
+    /// The user of the token, or the owner of the key.
+    pub(crate) fn user_id(&self) -> Uuid {
+        match self {
+            Caller::User(claims) => claims.sub,
+            Caller::Key(key) => key.0.user_id,
+        }
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Caller {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(api_key::API_KEY_HEADER) {
+            let key = ApiKey::from_request_parts(parts, state).await?;
+            return Ok(Caller::Key(key));
+        }
+        let claims = Claims::from_request_parts(parts, state).await?;
+        Ok(Caller::User(claims))
+    }
+}
+
+/// Example endpoint, the user of the token or of the key, which needs the
+/// `read` scope.
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "users",
+    security(("bearer" = []), ("api_key" = ["read"])),
+    responses(
+        (status = 200, body = User),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 403, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_me(
+    State(state): State<Arc<AppState>>,
+    caller: Caller,
+) -> Result<Json<User>, ApiError> {
+    if let Caller::Key(key) = &caller {
+        key.require("read")?;
+    }
+    match state.users.find_by_id(caller.user_id()).await? {
+        Some(user) => Ok(Json(user)),
+        None => Err(ApiError::Unauthorized(
+            "The user of these credentials is gone.",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use tower::Service;
+
+    use super::*;
+    use crate::helpers::test_log_filter;
+    use crate::settings::Settings;
+    use crate::state;
+
+    /// Answers in place of the handler of any route it wraps.
+    async fn matched(_req: Request, _next: Next) -> StatusCode {
+        StatusCode::IM_A_TEAPOT
+    }
+
+    /// Sends a `method` request to `path`, its parameters filled in.
+    async fn status_of(
+        router: &mut Router,
+        method: Method,
+        path: &str,
+    ) -> StatusCode {
+        let uri = path
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') {
+                    Uuid::new_v4().to_string()
+                } else {
+                    segment.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let req = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty());
+        router.call(req.unwrap()).await.unwrap().status()
+    }
+
+    /// Every operation of the document reaches a route of `v1`, and the
+    /// methods it leaves out of a path are refused by the router.
+    #[tokio::test]
+    async fn openapi_matches_routes() {
+        let settings = Settings::new().unwrap();
+        let (state, _jobs) =
+            state::build(settings, test_log_filter()).await.unwrap();
+        let (routes, openapi) = v1().split_for_parts();
+        let mut probe = routes
+            .clone()
+            .route_layer(middleware::from_fn(matched))
+            .with_state(state.clone());
+        let mut plain = routes.with_state(state);
+
+        assert!(!openapi.paths.paths.is_empty());
+        for (path, item) in &openapi.paths.paths {
+            let operations = [
+                (Method::GET, item.get.is_some()),
+                (Method::POST, item.post.is_some()),
+                (Method::PUT, item.put.is_some()),
+                (Method::PATCH, item.patch.is_some()),
+                (Method::DELETE, item.delete.is_some()),
+            ];
+            for (method, documented) in operations {
+                if documented {
+                    let status =
+                        status_of(&mut probe, method.clone(), path).await;
+                    assert_eq!(
+                        status,
+                        StatusCode::IM_A_TEAPOT,
+                        "{method} {path} is documented but not routed"
+                    );
+                } else {
+                    let status =
+                        status_of(&mut plain, method.clone(), path).await;
+                    assert_eq!(
+                        status,
+                        StatusCode::METHOD_NOT_ALLOWED,
+                        "{method} {path} is routed but not documented"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,709 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{Context, anyhow};
+use axum::{
+    Form, Json,
+    extract::{FromRequestParts, Path, State},
+    http::{StatusCode, request::Parts},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, any::AnyRow, query};
+use time::{Duration, OffsetDateTime};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::audit::Audit;
+use crate::auth::CurrentUser;
+use crate::database::{Access, Database};
+use crate::flash::Flash;
+use crate::form::{Field, FieldKind, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::jwt::Claims;
+use crate::problem::{ApiError, PROBLEM_JSON, Problem, internal};
+use crate::rate_limit::{RateLimitSettings, RateLimiter};
+use crate::router::{ServerError, ValidatedJson};
+use crate::state::AppState;
+use crate::view::View;
+
+/// Header carrying the key.
+pub(crate) const API_KEY_HEADER: &str = "x-api-key";
+/// Prefix of the generated keys, so leaked ones are easy to spot.
+const KEY_PREFIX: &str = "sk_";
+/// Characters of a key kept in clear to tell keys apart.
+const DISPLAY_LEN: usize = 10;
+
+/// Scopes a key can be granted.
+pub(crate) const SCOPES: &[&str] = &["read", "write"];
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiKeySettings {
+    /// Requests allowed per key.
+    pub(crate) rate_limit: RateLimitSettings,
+}
+
+/// A stored key. Only the hash of the secret is kept, the key itself is
+/// shown once when created.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct ApiKeyRecord {
+    pub(crate) id: Uuid,
+    pub(crate) user_id: Uuid,
+    pub(crate) name: String,
+    /// Start of the key, e.g. `sk_3fQ9aZ1`.
+    pub(crate) prefix: String,
+    #[serde(skip)]
+    pub(crate) hash: String,
+    pub(crate) scopes: Vec<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+}
+
+impl ApiKeyRecord {
+    pub(crate) fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires| expires <= OffsetDateTime::now_utc())
+    }
+
+    pub(crate) fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct NewApiKey {
+    pub(crate) user_id: Uuid,
+    pub(crate) name: String,
+    pub(crate) prefix: String,
+    pub(crate) hash: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) expires_at: Option<OffsetDateTime>,
+}
+
+/// Persistence of the API keys, looked up by the hash of the secret.
+pub(crate) trait ApiKeyStore: Send + Sync {
+    fn create(
+        &self,
+        key: NewApiKey,
+    ) -> BoxFuture<'_, anyhow::Result<ApiKeyRecord>>;
+
+    fn find_by_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ApiKeyRecord>>>;
+
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ApiKeyRecord>>>;
+
+    /// Deletes the key `id` of `user_id`, false when there is none.
+    fn revoke(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<bool>>;
+
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// Keys in the database when there is one, else in process memory.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn ApiKeyStore> {
+    match db {
+        None => Box::new(MemoryApiKeyStore::default()),
+        Some(db) => Box::new(SqlApiKeyStore { db: db.clone() }),
+    }
+}
+
+/// Keys kept in process memory, lost on restart.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryApiKeyStore {
+    keys: RwLock<HashMap<Uuid, ApiKeyRecord>>,
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+    fn create(
+        &self,
+        key: NewApiKey,
+    ) -> BoxFuture<'_, anyhow::Result<ApiKeyRecord>> {
+        Box::pin(async move {
+            let record = ApiKeyRecord {
+                id: Uuid::new_v4(),
+                user_id: key.user_id,
+                name: key.name,
+                prefix: key.prefix,
+                hash: key.hash,
+                scopes: key.scopes,
+                expires_at: key.expires_at,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            self.keys.write().unwrap().insert(record.id, record.clone());
+            Ok(record)
+        })
+    }
+
+    fn find_by_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ApiKeyRecord>>> {
+        Box::pin(async move {
+            let keys = self.keys.read().unwrap();
+            Ok(keys.values().find(|key| key.hash == hash).cloned())
+        })
+    }
+
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ApiKeyRecord>>> {
+        Box::pin(async move {
+            let mut keys: Vec<_> = self
+                .keys
+                .read()
+                .unwrap()
+                .values()
+                .filter(|key| key.user_id == user_id)
+                .cloned()
+                .collect();
+            keys.sort_by_key(|key| key.created_at);
+            Ok(keys)
+        })
+    }
+
+    fn revoke(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut keys = self.keys.write().unwrap();
+            if keys.get(&id).is_none_or(|key| key.user_id != user_id) {
+                return Ok(false);
+            }
+            keys.remove(&id);
+            Ok(true)
+        })
+    }
+
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.keys.write().unwrap().retain(|_, key| key.user_id != user_id);
+            Ok(())
+        })
+    }
+}
+
+/// Keys in the `api_keys` table.
+pub(crate) struct SqlApiKeyStore {
+    db: Database,
+}
+
+const KEY_COLUMNS: &str =
+    "id, user_id, name, prefix, hash, scopes, expires_at, created_at";
+
+fn key_from_row(row: &AnyRow) -> anyhow::Result<ApiKeyRecord> {
+    let scopes: String = row.try_get(5)?;
+    let expires_at: Option<i64> = row.try_get(6)?;
+    Ok(ApiKeyRecord {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        user_id: Uuid::try_parse(&row.try_get::<String, _>(1)?)?,
+        name: row.try_get(2)?,
+        prefix: row.try_get(3)?,
+        hash: row.try_get(4)?,
+        scopes: serde_json::from_str(&scopes)
+            .context("malformed api key scopes")?,
+        expires_at: expires_at.map(from_micros).transpose()?,
+        created_at: from_micros(row.try_get(7)?)?,
+    })
+}
+
+impl ApiKeyStore for SqlApiKeyStore {
+    fn create(
+        &self,
+        key: NewApiKey,
+    ) -> BoxFuture<'_, anyhow::Result<ApiKeyRecord>> {
+        Box::pin(async move {
+            let record = ApiKeyRecord {
+                id: Uuid::new_v4(),
+                user_id: key.user_id,
+                name: key.name,
+                prefix: key.prefix,
+                hash: key.hash,
+                scopes: key.scopes,
+                expires_at: key.expires_at,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            let sql = format!(
+                "INSERT INTO api_keys ({KEY_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+            );
+            let insert = query(&sql)
+                .bind(record.id.to_string())
+                .bind(record.user_id.to_string())
+                .bind(record.name.clone())
+                .bind(record.prefix.clone())
+                .bind(record.hash.clone())
+                .bind(serde_json::to_string(&record.scopes)?)
+                .bind(record.expires_at.map(to_micros))
+                .bind(to_micros(record.created_at))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("api_keys.create", &sql, insert).await?;
+            Ok(record)
+        })
+    }
+
+    fn find_by_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ApiKeyRecord>>> {
+        Box::pin(async move {
+            let sql =
+                format!("SELECT {KEY_COLUMNS} FROM api_keys WHERE hash = $1");
+            let query = query(&sql)
+                .bind(hash)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row =
+                self.db.observe("api_keys.find_by_hash", &sql, query).await?;
+            row.as_ref().map(key_from_row).transpose()
+        })
+    }
+
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ApiKeyRecord>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {KEY_COLUMNS} FROM api_keys WHERE user_id = $1 \
+                 ORDER BY created_at, id"
+            );
+            let query = query(&sql)
+                .bind(user_id.to_string())
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows =
+                self.db.observe("api_keys.list_for_user", &sql, query).await?;
+            rows.iter().map(key_from_row).collect()
+        })
+    }
+
+    fn revoke(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let sql = "DELETE FROM api_keys WHERE id = $1 AND user_id = $2";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(user_id.to_string())
+                .execute(self.db.pool(Access::ReadWrite));
+            let result =
+                self.db.observe("api_keys.revoke", sql, query).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "DELETE FROM api_keys WHERE user_id = $1";
+            let query = query(sql)
+                .bind(user_id.to_string())
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("api_keys.delete_for_user", sql, query).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Hook deciding whether a request made with a key goes through, checked by
+/// the [`ApiKey`] extractor once the key is known.
+pub(crate) trait ApiKeyRateLimit: Send + Sync {
+    fn check<'a>(&'a self, key: &'a ApiKeyRecord) -> BoxFuture<'a, bool>;
+}
+
+/// The same limit for every key, counted per key id.
+impl ApiKeyRateLimit for RateLimiter {
+    fn check<'a>(&'a self, key: &'a ApiKeyRecord) -> BoxFuture<'a, bool> {
+        Box::pin(
+            async move { RateLimiter::check(self, &key.id.to_string()).await },
+        )
+    }
+}
+
+/// Keys hold 256 random bits, so a fast hash is enough to store them.
+fn hash_key(key: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(key.as_bytes()))
+}
+
+/// A fresh random key.
+fn generate_key() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow!("could not generate an api key: {e}"))?;
+    Ok(format!("{KEY_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+/// The key of the request, from the `X-Api-Key` header.
+///
+/// Unknown and expired keys are rejected with a 401, and keys over their
+/// rate limit with a 429. Handlers check the scopes they need with
+/// [`ApiKey::require`].
+#[derive(Debug, Clone)]
+pub(crate) struct ApiKey(pub(crate) ApiKeyRecord);
+
+impl ApiKey {
+    /// Rejects with a 403 unless the key was granted `scope`.
+    pub(crate) fn require(&self, scope: &str) -> Result<(), ApiError> {
+        if self.0.has_scope(scope) {
+            return Ok(());
+        }
+        Err(ApiError::Forbidden(format!(
+            "The API key lacks the {scope} scope."
+        )))
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for ApiKey {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let unauthorized = |detail: &str| {
+            Problem::new(StatusCode::UNAUTHORIZED)
+                .detail(detail)
+                .into_response()
+        };
+        let Some(key) = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Err(unauthorized("Missing API key."));
+        };
+
+        let record = state
+            .api_keys
+            .find_by_hash(&hash_key(key.trim()))
+            .await
+            .map_err(internal)?
+            .filter(|record| !record.is_expired());
+        let Some(record) = record else {
+            return Err(unauthorized("Invalid or expired API key."));
+        };
+        if !state.api_key_limiter.check(&record).await {
+            return Err(Problem::new(StatusCode::TOO_MANY_REQUESTS)
+                .detail("Too many requests with this API key.")
+                .into_response());
+        }
+        Ok(ApiKey(record))
+    }
+}
+
+fn validate_scopes(scopes: &str) -> Result<(), ValidationError> {
+    let mut scopes = scopes.split_whitespace().peekable();
+    if scopes.peek().is_none() {
+        return Err(ValidationError::new("scopes")
+            .with_message("Can not be empty".into()));
+    }
+    if scopes.all(|scope| SCOPES.contains(&scope)) {
+        return Ok(());
+    }
+    Err(ValidationError::new("scopes")
+        .with_message(format!("Must be among {}", SCOPES.join(", ")).into()))
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct ApiKeyInput {
+    #[validate(length(min = 1, max = 100, message = "Can not be empty"))]
+    pub(crate) name: String,
+    /// Space separated, e.g. `read write`.
+    #[validate(custom(function = "validate_scopes"))]
+    pub(crate) scopes: String,
+    /// Days the key stays valid, 0 for until revoked.
+    #[serde(default)]
+    #[validate(range(max = 3650, message = "Must be at most 3650"))]
+    pub(crate) expires_in_days: u32,
+}
+
+impl FormDefinition for ApiKeyInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/account/api-keys")
+            .submit("Create key")
+            .field(Field::text("name", "Name").length(Some(1), Some(100)))
+            .field(
+                Field::text("scopes", "Scopes")
+                    .required()
+                    .help("Space separated: read, write"),
+            )
+            .field(
+                Field::new(
+                    "expires_in_days",
+                    "Expires in (days)",
+                    FieldKind::Number,
+                )
+                .required()
+                .range(Some(0.0), Some(3650.0))
+                .help("0 keeps the key until it is revoked"),
+            )
+    }
+}
+
+/// Stores a new key for `user_id` and returns it with its record, the only
+/// time the key is available.
+async fn create_key(
+    state: &AppState,
+    audit: Audit,
+    user_id: Uuid,
+    input: ApiKeyInput,
+) -> anyhow::Result<(String, ApiKeyRecord)> {
+    let key = generate_key()?;
+    let expires_at = (input.expires_in_days > 0).then(|| {
+        OffsetDateTime::now_utc()
+            + Duration::days(i64::from(input.expires_in_days))
+    });
+    let record = state
+        .api_keys
+        .create(NewApiKey {
+            user_id,
+            name: input.name,
+            prefix: key[..DISPLAY_LEN].to_string(),
+            hash: hash_key(&key),
+            scopes: input.scopes.split_whitespace().map(Into::into).collect(),
+            expires_at,
+        })
+        .await?;
+    info!(user = %user_id, key = %record.id, "api key created");
+    audit
+        .actor(user_id)
+        .record(
+            "api_key.create",
+            Some(record.id.to_string()),
+            json!({ "scopes": record.scopes }),
+        )
+        .await;
+    Ok((key, record))
+}
+
+/// Deletes the key `id` of `user_id`, false when there is none.
+async fn revoke_key(
+    state: &AppState,
+    audit: Audit,
+    user_id: Uuid,
+    id: Uuid,
+) -> anyhow::Result<bool> {
+    let revoked = state.api_keys.revoke(user_id, id).await?;
+    if revoked {
+        info!(user = %user_id, key = %id, "api key revoked");
+        audit
+            .actor(user_id)
+            .record("api_key.revoke", Some(id.to_string()), json!({}))
+            .await;
+    }
+    Ok(revoked)
+}
+
+async fn render_keys(
+    state: &AppState,
+    view: &View,
+    user_id: Uuid,
+    input: &impl Serialize,
+    errors: &FormErrors,
+    created_key: Option<&str>,
+) -> Result<Html<String>, ServerError> {
+    let keys = state.api_keys.list_for_user(user_id).await?;
+    Ok(view
+        .render(
+            "api_keys",
+            context! {
+                title => "API keys",
+                keys => keys,
+                created_key => created_key,
+                form => ApiKeyInput::form().bind(input, errors),
+            },
+        )
+        .unwrap())
+}
+
+pub(crate) async fn handler_api_keys(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, ServerError> {
+    render_keys(&state, &view, user.id, &(), &FormErrors::default(), None)
+        .await
+}
+
+pub(crate) async fn handler_api_keys_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    Form(input): Form<ApiKeyInput>,
+) -> Result<Response, ServerError> {
+    if let Err(errors) = input.validate() {
+        let errors = FormErrors::from(&errors);
+        let rendered =
+            render_keys(&state, &view, user.id, &input, &errors, None).await?;
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let (key, _) = create_key(&state, audit, user.id, input).await?;
+    let rendered = render_keys(
+        &state,
+        &view,
+        user.id,
+        &(),
+        &FormErrors::default(),
+        Some(&key),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, rendered).into_response())
+}
+
+pub(crate) async fn handler_api_key_revoke(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, ServerError> {
+    if revoke_key(&state, audit, user.id, id).await? {
+        flash.success("API key revoked.");
+    } else {
+        flash.error("No such API key.");
+    }
+    Ok(Redirect::to("/account/api-keys"))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct CreatedApiKey {
+    /// The secret, not retrievable afterwards.
+    key: String,
+    #[serde(flatten)]
+    record: ApiKeyRecord,
+}
+
+/// Keys of the user of the bearer token.
+#[utoipa::path(
+    get,
+    path = "/keys",
+    tag = "keys",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, body = Vec<ApiKeyRecord>),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_api_keys_list(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+) -> Result<Json<Vec<ApiKeyRecord>>, ApiError> {
+    let keys = state.api_keys.list_for_user(claims.sub).await?;
+    Ok(Json(keys))
+}
+
+/// Creates a key for the user of the bearer token.
+#[utoipa::path(
+    post,
+    path = "/keys",
+    tag = "keys",
+    security(("bearer" = [])),
+    request_body = ApiKeyInput,
+    responses(
+        (status = 201, body = CreatedApiKey),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 422, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_api_keys_create(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    audit: Audit,
+    ValidatedJson(input): ValidatedJson<ApiKeyInput>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+    let (key, record) = create_key(&state, audit, claims.sub, input).await?;
+    Ok((StatusCode::CREATED, Json(CreatedApiKey { key, record })))
+}
+
+/// Revokes a key of the user of the bearer token.
+#[utoipa::path(
+    delete,
+    path = "/keys/{id}",
+    tag = "keys",
+    security(("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Id of the key.")),
+    responses(
+        (status = 204),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 404, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_api_keys_delete(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    audit: Audit,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    if revoke_key(&state, audit, claims.sub, id).await? {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    Err(ApiError::NotFound)
+}
+
+/// Example endpoint for API key clients, the key of the request.
+#[utoipa::path(
+    get,
+    path = "/key",
+    tag = "keys",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, body = ApiKeyRecord),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 403, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_api_key_info(
+    key: ApiKey,
+) -> Result<Json<ApiKeyRecord>, ApiError> {
+    key.require("read")?;
+    Ok(Json(key.0))
+}
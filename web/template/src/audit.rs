@@ -0,0 +1,427 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+    response::Html,
+};
+use axum_client_ip::ClientIp;
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row, SqlitePool, query};
+use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::auth::CurrentUser;
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::impersonation::Impersonator;
+use crate::pagination::{Page, Pagination};
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::view::View;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS audit_events (
+    id TEXT PRIMARY KEY,
+    at BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    actor TEXT,
+    target TEXT,
+    ip TEXT,
+    impersonator TEXT,
+    request_id TEXT,
+    details TEXT NOT NULL
+)";
+const CREATE_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS audit_events_at ON audit_events (at)";
+const INSERT: &str = "INSERT INTO audit_events
+    (id, at, action, actor, impersonator, target, ip, request_id, details)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+const SELECT: &str = "SELECT id, at, action, actor, impersonator, target,
+    ip, request_id, details FROM audit_events ORDER BY at DESC LIMIT $1 OFFSET $2";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "sink", rename_all = "lowercase")]
+pub(crate) enum AuditSettings {
+    /// The last `capacity` events in process memory. Fine for development.
+    Memory { capacity: usize },
+    /// One JSON event per line appended to `path`.
+    File { path: PathBuf },
+    /// `audit_events` table of a Postgres database.
+    Postgres { url: String },
+    /// `audit_events` table of a SQLite database.
+    Sqlite { url: String },
+}
+
+/// A security relevant action, e.g. a login or a permission change.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct AuditEvent {
+    pub(crate) id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) at: OffsetDateTime,
+    /// Dotted name of the action, e.g. `auth.login`.
+    pub(crate) action: String,
+    /// User performing the action, if known.
+    pub(crate) actor: Option<Uuid>,
+    /// Admin impersonating the actor, see `impersonation`.
+    pub(crate) impersonator: Option<Uuid>,
+    /// What the action applied to, e.g. a user id or an email.
+    pub(crate) target: Option<String>,
+    pub(crate) ip: Option<String>,
+    pub(crate) request_id: Option<String>,
+    /// Free form context of the action.
+    pub(crate) details: Value,
+}
+
+impl AuditEvent {
+    /// An event happening now, outside of a request, e.g. in a job. See
+    /// [`Audit`] for the events of a request.
+    pub(crate) fn new(
+        action: &str,
+        actor: Option<Uuid>,
+        target: Option<String>,
+        details: Value,
+    ) -> Self {
+        AuditEvent {
+            id: Uuid::new_v4(),
+            at: OffsetDateTime::now_utc(),
+            action: action.to_string(),
+            actor,
+            impersonator: None,
+            target,
+            ip: None,
+            request_id: None,
+            details,
+        }
+    }
+}
+
+/// Where audit events are written and read back from.
+pub(crate) trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Newest events first.
+    fn recent(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<AuditEvent>>>;
+}
+
+/// Opens the sink selected by [`AuditSettings`].
+pub(crate) async fn from_settings(
+    settings: &AuditSettings,
+) -> anyhow::Result<Box<dyn AuditSink>> {
+    Ok(match settings {
+        AuditSettings::Memory { capacity } => Box::new(MemoryAuditSink {
+            capacity: *capacity,
+            events: Mutex::default(),
+        }),
+        AuditSettings::File { path } => {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            Box::new(FileAuditSink {
+                path: path.clone(),
+                lock: tokio::sync::Mutex::default(),
+            })
+        }
+        AuditSettings::Postgres { url } => {
+            let pool = PgPool::connect(url).await?;
+            query(CREATE_TABLE).execute(&pool).await?;
+            query(CREATE_INDEX).execute(&pool).await?;
+            Box::new(SqlAuditSink::Postgres(pool))
+        }
+        AuditSettings::Sqlite { url } => {
+            let pool = SqlitePool::connect(url).await?;
+            query(CREATE_TABLE).execute(&pool).await?;
+            query(CREATE_INDEX).execute(&pool).await?;
+            Box::new(SqlAuditSink::Sqlite(pool))
+        }
+    })
+}
+
+struct MemoryAuditSink {
+    capacity: usize,
+    events: Mutex<VecDeque<AuditEvent>>,
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: AuditEvent) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut events = self.events.lock().unwrap();
+            if events.len() >= self.capacity {
+                events.pop_front();
+            }
+            events.push_back(event);
+            Ok(())
+        })
+    }
+
+    fn recent(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<AuditEvent>>> {
+        Box::pin(async move {
+            let events = self.events.lock().unwrap();
+            Ok(pagination.apply(events.iter().rev().cloned()))
+        })
+    }
+}
+
+struct FileAuditSink {
+    path: PathBuf,
+    /// Keeps concurrent lines from interleaving.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, event: AuditEvent) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(&event)?;
+            line.push(b'\n');
+            let _guard = self.lock.lock().await;
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            file.write_all(&line).await?;
+            Ok(())
+        })
+    }
+
+    /// Reads the whole file, meant for logs rotated by an external tool.
+    fn recent(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<AuditEvent>>> {
+        Box::pin(async move {
+            let content = match tokio::fs::read_to_string(&self.path).await {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Vec::new());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            content
+                .lines()
+                .rev()
+                .skip(pagination.offset())
+                .take(pagination.limit())
+                .map(|line| {
+                    serde_json::from_str(line)
+                        .context("malformed audit log line")
+                })
+                .collect()
+        })
+    }
+}
+
+enum SqlAuditSink {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+/// Maps a row of either database, whose columns decode the same way.
+fn event_from_row<R>(row: &R) -> anyhow::Result<AuditEvent>
+where
+    R: Row,
+    usize: sqlx::ColumnIndex<R>,
+    for<'r> String: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'r> Option<String>:
+        sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'r> i64: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+    let actor: Option<String> = row.try_get(3)?;
+    let impersonator: Option<String> = row.try_get(4)?;
+    let details: String = row.try_get(8)?;
+    Ok(AuditEvent {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        at: from_micros(row.try_get(1)?)?,
+        action: row.try_get(2)?,
+        actor: actor.as_deref().map(Uuid::try_parse).transpose()?,
+        impersonator: impersonator
+            .as_deref()
+            .map(Uuid::try_parse)
+            .transpose()?,
+        target: row.try_get(5)?,
+        ip: row.try_get(6)?,
+        request_id: row.try_get(7)?,
+        details: serde_json::from_str(&details)?,
+    })
+}
+
+impl AuditSink for SqlAuditSink {
+    fn record(&self, event: AuditEvent) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let id = event.id.to_string();
+            let actor = event.actor.map(|actor| actor.to_string());
+            let impersonator =
+                event.impersonator.map(|admin| admin.to_string());
+            let details = event.details.to_string();
+            macro_rules! insert {
+                ($pool:expr) => {
+                    query(INSERT)
+                        .bind(&id)
+                        .bind(to_micros(event.at))
+                        .bind(&event.action)
+                        .bind(&actor)
+                        .bind(&impersonator)
+                        .bind(&event.target)
+                        .bind(&event.ip)
+                        .bind(&event.request_id)
+                        .bind(&details)
+                        .execute($pool)
+                        .await
+                        .map(drop)
+                };
+            }
+            match self {
+                SqlAuditSink::Postgres(pool) => insert!(pool)?,
+                SqlAuditSink::Sqlite(pool) => insert!(pool)?,
+            }
+            Ok(())
+        })
+    }
+
+    fn recent(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<AuditEvent>>> {
+        Box::pin(async move {
+            let (limit, offset) =
+                (pagination.limit() as i64, pagination.offset() as i64);
+            match self {
+                SqlAuditSink::Postgres(pool) => query(SELECT)
+                    .bind(limit)
+                    .bind(offset)
+                    .fetch_all(pool)
+                    .await?
+                    .iter()
+                    .map(event_from_row)
+                    .collect(),
+                SqlAuditSink::Sqlite(pool) => query(SELECT)
+                    .bind(limit)
+                    .bind(offset)
+                    .fetch_all(pool)
+                    .await?
+                    .iter()
+                    .map(event_from_row)
+                    .collect(),
+            }
+        })
+    }
+}
+
+/// Records audit events on behalf of the request, filling in the logged in
+/// user as actor, the admin impersonating them, the client IP and the
+/// request id.
+pub(crate) struct Audit {
+    state: Arc<AppState>,
+    actor: Option<Uuid>,
+    impersonator: Option<Uuid>,
+    ip: Option<String>,
+    request_id: Option<String>,
+}
+
+impl Audit {
+    /// Sets the actor, for actions like a login where it is only known once
+    /// the handler ran.
+    pub(crate) fn actor(mut self, actor: Uuid) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Records `action` on `target`. A failing sink is logged rather than
+    /// failing the request.
+    pub(crate) async fn record(
+        &self,
+        action: &str,
+        target: Option<String>,
+        details: Value,
+    ) {
+        let event = AuditEvent {
+            impersonator: self.impersonator,
+            ip: self.ip.clone(),
+            request_id: self.request_id.clone(),
+            ..AuditEvent::new(action, self.actor, target, details)
+        };
+        if let Err(e) = self.state.audit.record(event).await {
+            error!("could not record the {action} audit event: {e:#}");
+        }
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Audit {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let ip = ClientIp::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|ClientIp(ip)| ip.to_string());
+        Ok(Audit {
+            state: state.clone(),
+            actor: parts
+                .extensions
+                .get::<CurrentUser>()
+                .map(|CurrentUser(user)| user.id),
+            impersonator: parts
+                .extensions
+                .get::<Impersonator>()
+                .map(|Impersonator(admin)| admin.id),
+            ip,
+            request_id: parts
+                .headers
+                .get(REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Admin page browsing the events, newest first.
+pub(crate) async fn handler_audit(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    pagination: Pagination,
+) -> Result<Html<String>, ServerError> {
+    let events = state.audit.recent(pagination).await?;
+    Ok(view
+        .render(
+            "audit",
+            context! {
+                title => "Audit log",
+                page => Page::new(events, pagination),
+            },
+        )
+        .unwrap())
+}
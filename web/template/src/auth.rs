@@ -0,0 +1,574 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::{Arc, LazyLock};
+
+use anyhow::anyhow;
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHasher, PasswordVerifier},
+};
+use axum::{
+    Form,
+    extract::{FromRequestParts, Request, State},
+    http::{StatusCode, request::Parts},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tower_sessions::Session;
+use tracing::{
+    Instrument, Span, error,
+    field::{Empty, display},
+    info, info_span,
+};
+use validator::Validate;
+
+use crate::access_log;
+use crate::audit::Audit;
+use crate::flash::Flash;
+use crate::form::{Field, FormDefinition, FormErrors, FormSpec};
+use crate::honeypot::Honeypot;
+use crate::impersonation::{IMPERSONATOR_KEY, Impersonator};
+use crate::metric;
+use crate::rate_limit::RateLimitSettings;
+use crate::router::ServerError;
+use crate::session;
+use crate::state::AppState;
+use crate::sudo::{self, RequireSudo};
+use crate::users::{NewUser, User, UserStoreError};
+use crate::verification;
+use crate::view::{View, ViewContext};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AuthSettings {
+    /// Seconds a password reset link stays valid.
+    pub(crate) reset_token_ttl: i64,
+    /// Password reset requests allowed per client IP and per email.
+    pub(crate) reset_rate_limit: RateLimitSettings,
+    /// Seconds an email verification link stays valid.
+    pub(crate) verify_token_ttl: i64,
+    /// Verification emails a user can ask for.
+    pub(crate) verify_rate_limit: RateLimitSettings,
+    /// Keep users out of the protected pages until they verify their email.
+    pub(crate) require_verified_email: bool,
+    /// Seconds a password confirmation unlocks the actions guarded by
+    /// `RequireSudo`.
+    pub(crate) sudo_window: i64,
+}
+
+/// Session key holding the id of the logged in user.
+pub(crate) const USER_ID_KEY: &str = "auth.user_id";
+/// Session key holding the page to go back to after logging in.
+const NEXT_KEY: &str = "auth.next";
+
+/// Hash checked when the email is unknown, so a login attempt takes as long
+/// whether the account exists or not.
+pub(crate) static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    Argon2::default()
+        .hash_password(b"dummy password")
+        .expect("could not hash the dummy password")
+        .to_string()
+});
+
+/// Hashes `password` with argon2id off the async runtime.
+pub(crate) async fn hash_password(password: String) -> anyhow::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        Argon2::default()
+            .hash_password(password.as_bytes())
+            .map(|hash| hash.to_string())
+            .map_err(|e| anyhow!("could not hash password: {e}"))
+    })
+    .await?
+}
+
+/// Checks `password` against a PHC string produced by [`hash_password`].
+pub(crate) async fn verify_password(
+    password: String,
+    hash: String,
+) -> anyhow::Result<bool> {
+    Ok(tokio::task::spawn_blocking(move || {
+        Argon2::default()
+            .verify_password(password.as_bytes(), hash.as_str())
+            .is_ok()
+    })
+    .await?)
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct RegisterInput {
+    #[validate(length(min = 1, max = 100, message = "Can not be empty"))]
+    pub(crate) name: String,
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+    #[serde(skip_serializing)]
+    #[validate(length(
+        min = 8,
+        max = 128,
+        message = "Must have between 8 and 128 characters"
+    ))]
+    pub(crate) password: String,
+    #[serde(skip_serializing)]
+    #[validate(must_match(other = "password", message = "Does not match"))]
+    pub(crate) password_confirmation: String,
+}
+
+impl FormDefinition for RegisterInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/register")
+            .submit("Register")
+            .field(Field::text("name", "Name").length(Some(1), Some(100)))
+            .field(Field::email("email", "Email").required())
+            .field(
+                Field::password("password", "Password")
+                    .length(Some(8), Some(128)),
+            )
+            .field(
+                Field::password("password_confirmation", "Confirm password")
+                    .required(),
+            )
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct LoginInput {
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+    #[serde(skip_serializing)]
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    pub(crate) password: String,
+}
+
+impl FormDefinition for LoginInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/login")
+            .submit("Log in")
+            .field(Field::email("email", "Email").required())
+            .field(Field::password("password", "Password").required())
+    }
+}
+
+/// Makes `user_id` the user of the session, under a fresh session id. A
+/// login counts as a password confirmation for `RequireSudo`.
+pub(crate) async fn login_session(
+    session: &Session,
+    user_id: uuid::Uuid,
+) -> anyhow::Result<()> {
+    session::rotate(session).await?;
+    session.insert(USER_ID_KEY, user_id).await?;
+    sudo::grant(session).await?;
+    Ok(())
+}
+
+fn render_form<T: Serialize>(
+    view: &View,
+    template: &str,
+    title: &str,
+    form: &FormSpec,
+    values: &T,
+    errors: &FormErrors,
+) -> Html<String> {
+    view.render(
+        template,
+        context! {
+            title => title,
+            form => form.bind(values, errors),
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_register(view: View) -> Html<String> {
+    render_form(
+        &view,
+        "register",
+        "Register",
+        &RegisterInput::form(),
+        &(),
+        &FormErrors::default(),
+    )
+}
+
+pub(crate) async fn handler_register_post(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Honeypot(Form(input)): Honeypot<Form<RegisterInput>>,
+) -> Result<Response, ServerError> {
+    let form = RegisterInput::form();
+    if let Err(errors) = input.validate() {
+        let errors = FormErrors::from(&errors);
+        let rendered =
+            render_form(&view, "register", "Register", &form, &input, &errors);
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let password_hash = hash_password(input.password.clone()).await?;
+    let user = match state
+        .users
+        .create(NewUser {
+            name: input.name.clone(),
+            email: input.email.clone(),
+            password_hash,
+            roles: state.settings.rbac.roles_for_new_user(&input.email),
+        })
+        .await
+    {
+        Ok(user) => user,
+        Err(UserStoreError::EmailTaken) => {
+            let mut errors = FormErrors::default();
+            errors.add("email", "Is already registered");
+            let rendered = render_form(
+                &view, "register", "Register", &form, &input, &errors,
+            );
+            return Ok(
+                (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+            );
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    info!(user = %user.id, "user registered");
+    metric::count!("signup");
+    audit
+        .actor(user.id)
+        .record("auth.register", Some(user.id.to_string()), json!({}))
+        .await;
+    verification::send_verification_email(&state, &user).await;
+    login_session(&session, user.id).await?;
+    flash.success(format!("Welcome, {}!", user.name));
+    Ok(Redirect::to("/").into_response())
+}
+
+pub(crate) async fn handler_login(view: View) -> Html<String> {
+    render_form(
+        &view,
+        "login",
+        "Log in",
+        &LoginInput::form(),
+        &(),
+        &FormErrors::default(),
+    )
+}
+
+pub(crate) async fn handler_login_post(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Form(input): Form<LoginInput>,
+) -> Result<Response, ServerError> {
+    let form = LoginInput::form();
+    if let Err(errors) = input.validate() {
+        let errors = FormErrors::from(&errors);
+        let rendered =
+            render_form(&view, "login", "Log in", &form, &input, &errors);
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let user = state.users.find_by_email(&input.email).await?;
+    let hash = user
+        .as_ref()
+        .map_or_else(|| DUMMY_HASH.clone(), |user| user.password_hash.clone());
+    let valid = verify_password(input.password.clone(), hash).await?;
+
+    let Some(user) = user.filter(|_| valid) else {
+        audit
+            .record(
+                "auth.login_failed",
+                Some(input.email.to_lowercase()),
+                json!({ "method": "password" }),
+            )
+            .await;
+        let mut errors = FormErrors::default();
+        errors.add("email", "Invalid email or password");
+        let rendered =
+            render_form(&view, "login", "Log in", &form, &input, &errors);
+        return Ok((StatusCode::UNAUTHORIZED, rendered).into_response());
+    };
+
+    login_session(&session, user.id).await?;
+    audit
+        .actor(user.id)
+        .record(
+            "auth.login",
+            Some(user.id.to_string()),
+            json!({ "method": "password" }),
+        )
+        .await;
+    let next = session
+        .remove::<String>(NEXT_KEY)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "/".to_string());
+    flash.success(format!("Welcome back, {}.", user.name));
+    Ok(Redirect::to(&next).into_response())
+}
+
+pub(crate) async fn handler_logout(
+    session: Session,
+    audit: Audit,
+    flash: Flash,
+) -> Result<Redirect, ServerError> {
+    session.flush().await.map_err(anyhow::Error::from)?;
+    audit.record("auth.logout", None, json!({})).await;
+    flash.info("You have been logged out.");
+    Ok(Redirect::to("/"))
+}
+
+/// The logged in user, rejecting anonymous requests with a redirect to the
+/// login page.
+#[derive(Debug, Clone)]
+pub(crate) struct CurrentUser(pub(crate) User);
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Redirect;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or_else(|| Redirect::to("/login"))
+    }
+}
+
+/// The logged in user, if any.
+#[derive(Debug, Clone)]
+pub(crate) struct OptionalUser(pub(crate) Option<User>);
+
+impl<S> FromRequestParts<S> for OptionalUser
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(OptionalUser(
+            parts.extensions.get::<CurrentUser>().map(|user| user.0.clone()),
+        ))
+    }
+}
+
+/// Loads the user of the session for the [`CurrentUser`] and
+/// [`OptionalUser`] extractors and exposes it to templates as
+/// `current_user`, along with its `permissions` for `can()`. The rest of the
+/// request runs in a `user` span naming the user and any impersonator.
+pub(crate) async fn load_user(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let span = info_span!("user", id = Empty, impersonator = Empty);
+    let user_id = session.get::<uuid::Uuid>(USER_ID_KEY).await.ok().flatten();
+    if let Some(user_id) = user_id {
+        match state.users.find_by_id(user_id).await {
+            Ok(Some(user)) => {
+                let permissions = state.settings.rbac.permissions(&user);
+                ViewContext::insert(
+                    req.extensions_mut(),
+                    "current_user",
+                    &user,
+                );
+                ViewContext::insert(
+                    req.extensions_mut(),
+                    "permissions",
+                    &permissions.0,
+                );
+                req.extensions_mut().insert(permissions);
+                span.record("id", display(user.id));
+                let id = user.id;
+                req.extensions_mut().insert(CurrentUser(user));
+                load_impersonator(&state, &session, &span, &mut req).await;
+                let mut response = next.run(req).instrument(span).await;
+                response.extensions_mut().insert(access_log::UserId(id));
+                return response;
+            }
+            // Deleted since the login.
+            Ok(None) => {
+                let _ = session.remove::<uuid::Uuid>(USER_ID_KEY).await;
+            }
+            Err(e) => error!("could not load the session user: {e}"),
+        }
+    }
+    next.run(req).instrument(span).await
+}
+
+/// Adds the [`Impersonator`] of the session, if any, for the banner of the
+/// templates, the audit log and the `user` span.
+async fn load_impersonator(
+    state: &AppState,
+    session: &Session,
+    span: &Span,
+    req: &mut Request,
+) {
+    let Some(admin_id) =
+        session.get::<uuid::Uuid>(IMPERSONATOR_KEY).await.ok().flatten()
+    else {
+        return;
+    };
+    match state.users.find_by_id(admin_id).await {
+        Ok(Some(admin)) => {
+            ViewContext::insert(req.extensions_mut(), "impersonator", &admin);
+            span.record("impersonator", display(admin.id));
+            req.extensions_mut().insert(Impersonator(admin));
+        }
+        // The admin is gone, end the session rather than leave it to the
+        // impersonated user.
+        Ok(None) => {
+            let _ = session.flush().await;
+        }
+        Err(e) => error!("could not load the impersonator: {e}"),
+    }
+}
+
+/// Route layer for protected scopes: anonymous requests are sent to the
+/// login page, which brings them back once logged in.
+pub(crate) async fn require_auth(
+    session: Session,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.extensions().get::<CurrentUser>().is_some() {
+        return next.run(req).await;
+    }
+
+    let back = req
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str())
+        .to_string();
+    if let Err(e) = session.insert(NEXT_KEY, back).await {
+        error!("could not remember the page before login: {e}");
+    }
+    Redirect::to("/login").into_response()
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct EmailInput {
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+}
+
+impl FormDefinition for EmailInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/account/email")
+            .submit("Change email")
+            .field(Field::email("email", "New email").required())
+    }
+}
+
+pub(crate) async fn handler_email(_: RequireSudo, view: View) -> Html<String> {
+    render_form(
+        &view,
+        "change_email",
+        "Change email",
+        &EmailInput::form(),
+        &(),
+        &FormErrors::default(),
+    )
+}
+
+/// Changes the email of the user, who verifies the new one before getting
+/// back to the protected pages.
+pub(crate) async fn handler_email_post(
+    _: RequireSudo,
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+    Form(input): Form<EmailInput>,
+) -> Result<Response, ServerError> {
+    let form = EmailInput::form();
+    let title = "Change email";
+    if let Err(errors) = input.validate() {
+        let errors = FormErrors::from(&errors);
+        let rendered =
+            render_form(&view, "change_email", title, &form, &input, &errors);
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    match state.users.update_email(user.id, &input.email).await {
+        Ok(()) => {}
+        Err(UserStoreError::EmailTaken) => {
+            let mut errors = FormErrors::default();
+            errors.add("email", "Is already registered");
+            let rendered = render_form(
+                &view,
+                "change_email",
+                title,
+                &form,
+                &input,
+                &errors,
+            );
+            return Ok(
+                (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+            );
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    info!(user = %user.id, "email changed");
+    audit
+        .record(
+            "auth.email_changed",
+            Some(user.id.to_string()),
+            json!({ "from": user.email, "to": input.email.to_lowercase() }),
+        )
+        .await;
+    if let Some(user) = state.users.find_by_id(user.id).await? {
+        verification::send_verification_email(&state, &user).await;
+    }
+    flash.info("Check your new address for the verification link.");
+    Ok(Redirect::to("/verify").into_response())
+}
+
+pub(crate) async fn handler_account(
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Html<String> {
+    view.render(
+        "account",
+        context! {
+            title => "Account",
+            user => user,
+        },
+    )
+    .unwrap()
+}
@@ -0,0 +1,178 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use tracing::info;
+
+use crate::audit::AuditSettings;
+use crate::database::BackendSettings;
+use crate::mailer::{MailerSettings, TransportSettings};
+use crate::metric::{ExporterSettings, MetricsSettings};
+use crate::redis::RedisSettings;
+use crate::router;
+use crate::session::StoreSettings;
+use crate::settings::Settings;
+use crate::storage::StorageSettings;
+use crate::version;
+
+/// Cargo features of the build and whether they are on.
+const FEATURES: &[(&str, bool)] = &[
+    ("otlp", cfg!(feature = "otlp")),
+    ("pprof", cfg!(feature = "pprof")),
+    ("runtime-metrics", cfg!(feature = "runtime-metrics")),
+    ("sea-orm", cfg!(feature = "sea-orm")),
+    ("sentry", cfg!(feature = "sentry")),
+    ("tokio-console", cfg!(feature = "tokio-console")),
+];
+
+/// Logs what the process is about to run: binary, build, listeners,
+/// backends and timeouts, for operators to check a deployment against what they meant
+/// to deploy. Passwords in URLs are masked, secrets never logged.
+pub(crate) fn log(settings: &Settings, binary: &str, listen: &str) {
+    let features: Vec<_> = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+
+    info!(
+        version = version::VERSION,
+        git_hash = version::GIT_HASH,
+        build_date = version::BUILD_DATE,
+        profile = if cfg!(debug_assertions) { "debug" } else { "release" },
+        run_mode = settings.run_mode,
+        features = ?features,
+        binary,
+        listen,
+        metrics = metrics(&settings.metrics),
+        database = database(&settings.database.backend),
+        redis = redis(&settings.redis),
+        sessions = sessions(&settings.session.store),
+        storage = storage(&settings.storage),
+        tenancy = ?settings.tenancy.resolver,
+        audit = audit(&settings.audit),
+        mailer = mailer(&settings.mailer),
+        error_reporting = settings.error_reporting.dsn.is_some(),
+        log_format = ?settings.log.format,
+        request_timeout_s = router::REQUEST_TIMEOUT.as_secs(),
+        session_idle_timeout_s = settings.session.idle_timeout,
+        health_timeout_ms = settings.health.timeout_ms,
+        drain_s = settings.health.drain_seconds,
+        "starting {}",
+        settings.site.name,
+    );
+}
+
+fn metrics(settings: &MetricsSettings) -> String {
+    match &settings.exporter {
+        ExporterSettings::Prometheus => {
+            let scheme = if settings.tls.is_some() { "https" } else { "http" };
+            let auth = if settings.basic_auth.is_some() {
+                " with basic auth"
+            } else {
+                ""
+            };
+            format!("prometheus on {scheme}://{}{auth}", settings.address)
+        }
+        ExporterSettings::Pushgateway { endpoint, interval } => {
+            format!("pushgateway {} every {interval}s", redact(endpoint))
+        }
+        ExporterSettings::Statsd { host, port, interval, .. } => {
+            format!("statsd {host}:{port} every {interval}s")
+        }
+        ExporterSettings::Otlp { endpoint, interval } => {
+            format!("otlp {} every {interval}s", redact(endpoint))
+        }
+    }
+}
+
+fn database(backend: &BackendSettings) -> String {
+    match backend {
+        BackendSettings::None => "none".to_string(),
+        BackendSettings::Postgres { url, replicas } if replicas.is_empty() => {
+            redact(url)
+        }
+        BackendSettings::Postgres { url, replicas } => {
+            let replicas: Vec<_> =
+                replicas.iter().map(|url| redact(url)).collect();
+            format!("{} with replicas {}", redact(url), replicas.join(", "))
+        }
+        BackendSettings::Sqlite { path, wal } => {
+            let journal = if *wal { " with WAL" } else { "" };
+            format!("sqlite {}{journal}", path.display())
+        }
+    }
+}
+
+fn redis(settings: &RedisSettings) -> String {
+    match &settings.url {
+        None => "none".to_string(),
+        Some(url) if settings.rate_limits => {
+            format!("{} with the rate limits", redact(url))
+        }
+        Some(url) => redact(url),
+    }
+}
+
+fn sessions(store: &StoreSettings) -> String {
+    match store {
+        StoreSettings::Memory => "memory".to_string(),
+        StoreSettings::Cookie => "cookie".to_string(),
+        StoreSettings::Redis { url: None, .. } => "redis".to_string(),
+        StoreSettings::Redis { url: Some(url), .. }
+        | StoreSettings::Postgres { url, .. }
+        | StoreSettings::Sqlite { url, .. } => redact(url),
+    }
+}
+
+fn storage(settings: &StorageSettings) -> String {
+    match settings {
+        StorageSettings::Local { root } => format!("local {root}"),
+        StorageSettings::S3 { bucket, region, endpoint, .. } => {
+            let endpoint = endpoint.as_deref().map(redact).unwrap_or_default();
+            format!("s3 {bucket} in {region} {endpoint}").trim().to_string()
+        }
+    }
+}
+
+fn audit(settings: &AuditSettings) -> String {
+    match settings {
+        AuditSettings::Memory { capacity } => format!("memory of {capacity}"),
+        AuditSettings::File { path } => format!("file {}", path.display()),
+        AuditSettings::Postgres { url } | AuditSettings::Sqlite { url } => {
+            redact(url)
+        }
+    }
+}
+
+/// `url` with its password masked.
+fn mailer(settings: &MailerSettings) -> String {
+    let transport = match &settings.transport {
+        TransportSettings::Log => "log".to_string(),
+        TransportSettings::Smtp { url, .. } => redact(url),
+    };
+    if settings.queue { format!("{transport} queued") } else { transport }
+}
+
+fn redact(url: &str) -> String {
+    let Ok(mut url) = reqwest::Url::parse(url) else {
+        return "<invalid url>".to_string();
+    };
+    if url.password().is_some() {
+        // Only fails for URLs that can not have a password.
+        let _ = url.set_password(Some("***"));
+    }
+    url.to_string()
+}
@@ -0,0 +1,187 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, HttpBody as _},
+    extract::{Request, State},
+    http::{HeaderMap, header},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::state::AppState;
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BodyLogSettings {
+    /// Only honored by debug builds, release builds never log bodies.
+    pub(crate) enabled: bool,
+    /// Bodies longer than this are neither buffered nor logged.
+    pub(crate) max_bytes: usize,
+    /// Fields whose name contains one of these words, ignoring case, are
+    /// logged as `[redacted]`.
+    pub(crate) redact: Vec<String>,
+}
+
+impl BodyLogSettings {
+    fn is_secret(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.redact.iter().any(|word| name.contains(&word.to_lowercase()))
+    }
+
+    fn redacted(&self, kind: BodyKind, bytes: &[u8]) -> String {
+        match kind {
+            BodyKind::Json => match serde_json::from_slice(bytes) {
+                Ok(mut value) => {
+                    self.redact_json(&mut value);
+                    value.to_string()
+                }
+                Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+            },
+            BodyKind::Form => {
+                match serde_urlencoded::from_bytes::<Vec<(String, String)>>(
+                    bytes,
+                ) {
+                    Ok(fields) => fields
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = if self.is_secret(name) {
+                                REDACTED
+                            } else {
+                                value
+                            };
+                            format!("{name}={value}")
+                        })
+                        .collect::<Vec<_>>()
+                        .join("&"),
+                    Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+                }
+            }
+        }
+    }
+
+    fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (name, value) in fields {
+                    if self.is_secret(name) {
+                        *value = Value::from(REDACTED);
+                    } else {
+                        self.redact_json(value);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.redact_json(item))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Bodies worth reading in a log, uploads and pages are left out.
+#[derive(Debug, Clone, Copy)]
+enum BodyKind {
+    Json,
+    Form,
+}
+
+impl BodyKind {
+    fn of(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())?;
+        let essence = content_type.split(';').next()?.trim();
+        if essence == "application/json" || essence.ends_with("+json") {
+            Some(Self::Json)
+        } else if essence == "application/x-www-form-urlencoded" {
+            Some(Self::Form)
+        } else {
+            None
+        }
+    }
+}
+
+/// Logs JSON and form bodies of requests and responses at debug level,
+/// with secrets redacted, to follow what a form or an API client sent.
+/// Off unless enabled, and always off in release builds.
+pub(crate) async fn log(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let settings = &state.settings.body_log;
+    if !cfg!(debug_assertions) || !settings.enabled {
+        return next.run(req).await;
+    }
+
+    let (parts, body) = req.into_parts();
+    let (body, logged) = capture(settings, &parts.headers, body).await;
+    if let Some(logged) = logged {
+        debug!(
+            method = %parts.method,
+            path = parts.uri.path(),
+            body = logged,
+            "request body"
+        );
+    }
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = response.into_parts();
+    let (body, logged) = capture(settings, &parts.headers, body).await;
+    if let Some(logged) = logged {
+        debug!(status = parts.status.as_u16(), body = logged, "response body");
+    }
+    Response::from_parts(parts, body)
+}
+
+/// Buffers `body` when it is JSON or a form of a known length within the
+/// cap, and gives it back along with its redacted text.
+async fn capture(
+    settings: &BodyLogSettings,
+    headers: &HeaderMap,
+    body: Body,
+) -> (Body, Option<String>) {
+    let Some(kind) = BodyKind::of(headers) else {
+        return (body, None);
+    };
+    match body.size_hint().exact() {
+        Some(len) if len <= settings.max_bytes as u64 => {}
+        Some(len) => {
+            return (body, Some(format!("({len} bytes, over max_bytes)")));
+        }
+        // Streamed, reading it would hold the whole stream.
+        None => return (body, None),
+    }
+
+    match axum::body::to_bytes(body, settings.max_bytes).await {
+        Ok(bytes) => {
+            let logged = settings.redacted(kind, &bytes);
+            (Body::from(bytes), Some(logged))
+        }
+        Err(e) => {
+            warn!("could not read the body to log it: {e}");
+            (Body::empty(), None)
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use moka::Expiry;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CacheSettings {
+    /// Entries kept before the least used are evicted.
+    pub(crate) max_capacity: u64,
+}
+
+/// Name of a cached value of type `T`. Keys of different types never
+/// collide, even under the same name.
+pub(crate) struct CacheKey<T> {
+    name: Cow<'static, str>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> CacheKey<T> {
+    pub(crate) const fn new(name: &'static str) -> Self {
+        CacheKey { name: Cow::Borrowed(name), _type: PhantomData }
+    }
+
+    /// Key `<prefix>:<id>`, e.g. a fragment per user, all dropped
+    /// together by [`Cache::invalidate_prefix`].
+    pub(crate) fn scoped(prefix: &str, id: impl std::fmt::Display) -> Self {
+        CacheKey {
+            name: Cow::Owned(format!("{prefix}:{id}")),
+            _type: PhantomData,
+        }
+    }
+
+    fn entry_key(&self) -> (String, TypeId)
+    where
+        T: 'static,
+    {
+        (self.name.to_string(), TypeId::of::<T>())
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    value: Arc<dyn Any + Send + Sync>,
+    ttl: Duration,
+}
+
+/// Expires every entry after its own `ttl`, counted from the last write.
+struct PerEntryTtl;
+
+impl Expiry<(String, TypeId), Entry> for PerEntryTtl {
+    fn expire_after_create(
+        &self,
+        _key: &(String, TypeId),
+        entry: &Entry,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(entry.ttl)
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &(String, TypeId),
+        entry: &Entry,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(entry.ttl)
+    }
+}
+
+/// In-process cache of computed values, e.g. rendered fragments, local to
+/// each instance. Values are shared as `Arc<T>` and concurrent misses of a
+/// key compute it once, see [`Cache::get_or_insert_with`].
+#[derive(Clone)]
+pub(crate) struct Cache {
+    inner: moka::future::Cache<(String, TypeId), Entry>,
+}
+
+impl Cache {
+    pub(crate) fn new(settings: &CacheSettings) -> Self {
+        let inner = moka::future::Cache::builder()
+            .max_capacity(settings.max_capacity)
+            .expire_after(PerEntryTtl)
+            .support_invalidation_closures()
+            .build();
+        Cache { inner }
+    }
+
+    /// Cached value of `key`, none when missing or expired.
+    #[allow(dead_code)]
+    pub(crate) async fn get<T>(&self, key: &CacheKey<T>) -> Option<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        let entry = self.inner.get(&key.entry_key()).await?;
+        entry.value.downcast().ok()
+    }
+
+    /// Caches `value` under `key` for `ttl`, replacing the previous one.
+    #[allow(dead_code)]
+    pub(crate) async fn insert<T>(
+        &self,
+        key: &CacheKey<T>,
+        value: T,
+        ttl: Duration,
+    ) where
+        T: Send + Sync + 'static,
+    {
+        let entry = Entry { value: Arc::new(value), ttl };
+        self.inner.insert(key.entry_key(), entry).await;
+    }
+
+    /// Caches `value` under `key` for `ttl` unless it is already there,
+    /// telling whether it was. Concurrent callers agree on a single first
+    /// one, e.g. to refuse a request seen before.
+    pub(crate) async fn insert_new<T>(
+        &self,
+        key: &CacheKey<T>,
+        value: T,
+        ttl: Duration,
+    ) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        let entry = Entry { value: Arc::new(value), ttl };
+        self.inner
+            .entry(key.entry_key())
+            .or_insert_with(async { entry })
+            .await
+            .is_fresh()
+    }
+
+    /// Cached value of `key`, computed by `compute` and cached for `ttl`
+    /// when missing. Concurrent callers missing the same key wait for a
+    /// single computation instead of each running their own, and errors
+    /// are returned to all of them without being cached.
+    pub(crate) async fn get_or_insert_with<T, F, Fut>(
+        &self,
+        key: &CacheKey<T>,
+        ttl: Duration,
+        compute: F,
+    ) -> anyhow::Result<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let entry = self
+            .inner
+            .try_get_with(key.entry_key(), async {
+                let value = compute().await?;
+                Ok::<_, anyhow::Error>(Entry { value: Arc::new(value), ttl })
+            })
+            .await
+            .map_err(|e| anyhow!("{e:#}"))?;
+        entry
+            .value
+            .downcast()
+            .map_err(|_| anyhow!("cache entry {} has another type", key.name))
+    }
+
+    /// Drops the cached `key`, for the writes making it stale.
+    pub(crate) async fn invalidate<T>(&self, key: &CacheKey<T>)
+    where
+        T: 'static,
+    {
+        self.inner.invalidate(&key.entry_key()).await;
+    }
+
+    /// Drops every key made by [`CacheKey::scoped`] with `prefix`,
+    /// whatever their type.
+    #[allow(dead_code)]
+    pub(crate) fn invalidate_prefix(&self, prefix: &str) {
+        let prefix = format!("{prefix}:");
+        // Only fails without support_invalidation_closures, set by new.
+        let _ = self.inner.invalidate_entries_if(move |(name, _), _| {
+            name.starts_with(&prefix)
+        });
+    }
+
+    /// Drops every entry.
+    #[allow(dead_code)]
+    pub(crate) fn invalidate_all(&self) {
+        self.inner.invalidate_all();
+    }
+}
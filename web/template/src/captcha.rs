@@ -0,0 +1,171 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_client_ip::ClientIp;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::form::peek_form_fields;
+use crate::state::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Provider {
+    Disabled,
+    Turnstile,
+    Hcaptcha,
+}
+
+impl Provider {
+    fn verify_url(self) -> Option<&'static str> {
+        match self {
+            Provider::Disabled => None,
+            Provider::Turnstile => Some(
+                "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            ),
+            Provider::Hcaptcha => Some("https://api.hcaptcha.com/siteverify"),
+        }
+    }
+
+    /// Form field the widget fills with its token.
+    fn response_field(self) -> &'static str {
+        match self {
+            Provider::Disabled => "",
+            Provider::Turnstile => "cf-turnstile-response",
+            Provider::Hcaptcha => "h-captcha-response",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CaptchaSettings {
+    pub(crate) provider: Provider,
+    pub(crate) site_key: String,
+    pub(crate) secret_key: String,
+    /// Seconds to wait for the provider, retries included, before
+    /// answering 503.
+    pub(crate) timeout: u64,
+}
+
+/// Part of the settings exposed to templates as the `captcha` global.
+#[derive(Debug, Serialize)]
+pub(crate) struct CaptchaWidget<'a> {
+    provider: Provider,
+    site_key: &'a str,
+}
+
+impl CaptchaSettings {
+    pub(crate) fn widget(&self) -> CaptchaWidget<'_> {
+        CaptchaWidget { provider: self.provider, site_key: &self.site_key }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Wraps the extractor `E` of an url-encoded form and only lets the request
+/// through once the CAPTCHA token it carries is accepted by the provider.
+///
+/// Forms using it must call the `captcha()` macro. With the `disabled`
+/// provider every request passes, which suits development.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptchaVerified<E>(pub E);
+
+impl<E> FromRequest<Arc<AppState>> for CaptchaVerified<E>
+where
+    E: FromRequest<Arc<AppState>>,
+{
+    type Rejection = Response;
+
+    async fn from_request(
+        req: Request,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Response> {
+        let settings = &state.settings.captcha;
+        if let Some(url) = settings.provider.verify_url() {
+            let (req, fields) = peek_form_fields(req, state).await?;
+            let token = fields
+                .into_iter()
+                .find(|(name, _)| name == settings.provider.response_field())
+                .map(|(_, value)| value)
+                .unwrap_or_default();
+
+            let (mut parts, body) = req.into_parts();
+            let ClientIp(ip) = ClientIp::from_request_parts(&mut parts, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+
+            let request = state.http.post(url).form(&[
+                ("secret", settings.secret_key.as_str()),
+                ("response", token.as_str()),
+                ("remoteip", ip.to_string().as_str()),
+            ]);
+            let verify = async {
+                let response =
+                    state.http.send(request).await?.error_for_status()?;
+                anyhow::Ok(response.json::<VerifyResponse>().await?)
+            };
+            let timeout = Duration::from_secs(settings.timeout);
+            let verified =
+                tokio::time::timeout(timeout, verify).await.unwrap_or_else(
+                    |_| Err(anyhow!("timed out after {timeout:?}")),
+                );
+
+            match verified {
+                Ok(response) if response.success => {}
+                Ok(response) => {
+                    warn!(errors = ?response.error_codes, "captcha rejected");
+                    return Err((
+                        StatusCode::FORBIDDEN,
+                        "Captcha verification failed",
+                    )
+                        .into_response());
+                }
+                Err(error) => {
+                    error!("could not verify captcha: {error}");
+                    return Err((
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Captcha verification unavailable",
+                    )
+                        .into_response());
+                }
+            }
+
+            let req = Request::from_parts(parts, body);
+            return E::from_request(req, state)
+                .await
+                .map(CaptchaVerified)
+                .map_err(IntoResponse::into_response);
+        }
+
+        E::from_request(req, state)
+            .await
+            .map(CaptchaVerified)
+            .map_err(IntoResponse::into_response)
+    }
+}
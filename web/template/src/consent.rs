@@ -0,0 +1,205 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, request::Parts},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    SignedCookieJar,
+    cookie::{Cookie, Key, SameSite},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use crate::flash::Flash;
+use crate::helpers::referer_path;
+use crate::state::AppState;
+use crate::view::{View, ViewContext};
+
+pub(crate) const CONSENT_COOKIE: &str = "consent";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ConsentSettings {
+    /// Version of the consent text, raising it asks everyone again.
+    pub(crate) version: u32,
+    /// Days the choice is remembered.
+    pub(crate) max_age_days: i64,
+    /// HTML added to the pages once the category is accepted.
+    pub(crate) snippets: Snippets,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Snippets {
+    #[serde(default)]
+    pub(crate) analytics: Vec<String>,
+    #[serde(default)]
+    pub(crate) marketing: Vec<String>,
+}
+
+/// Optional cookie categories, the strictly necessary ones need no consent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Category {
+    Analytics,
+    Marketing,
+}
+
+/// The visitor's choice, kept in a signed cookie.
+///
+/// Extracting it never fails: a missing, tampered or outdated cookie yields
+/// an undecided consent refusing every category, and the layout shows the
+/// banner. The [`inject`] middleware exposes it to templates as `consent`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct Consent {
+    /// False until the visitor made a choice, see the banner.
+    #[serde(default)]
+    pub(crate) decided: bool,
+    version: u32,
+    pub(crate) analytics: bool,
+    pub(crate) marketing: bool,
+}
+
+impl Consent {
+    pub(crate) fn allows(&self, category: Category) -> bool {
+        match category {
+            Category::Analytics => self.analytics,
+            Category::Marketing => self.marketing,
+        }
+    }
+
+    fn from_headers(headers: &HeaderMap, key: Key, version: u32) -> Self {
+        SignedCookieJar::from_headers(headers, key)
+            .get(CONSENT_COOKIE)
+            .and_then(|cookie| {
+                serde_urlencoded::from_str::<Consent>(cookie.value()).ok()
+            })
+            .filter(|consent| consent.version == version)
+            .map(|consent| Consent { decided: true, ..consent })
+            .unwrap_or_default()
+    }
+
+    fn into_cookie(self, max_age_days: i64) -> Cookie<'static> {
+        let value = serde_urlencoded::to_string(&self).unwrap();
+        Cookie::build((CONSENT_COOKIE, value))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(Duration::days(max_age_days))
+            .build()
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Consent {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(consent) = parts.extensions.get::<Consent>() {
+            return Ok(consent.clone());
+        }
+        Ok(Consent::from_headers(
+            &parts.headers,
+            state.cookie_key.clone(),
+            state.settings.consent.version,
+        ))
+    }
+}
+
+/// Exposes the consent to templates along with `consented_snippets`, the
+/// snippets of the accepted categories only, which the layout includes.
+pub(crate) async fn inject(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let settings = &state.settings.consent;
+    let consent = Consent::from_headers(
+        req.headers(),
+        state.cookie_key.clone(),
+        settings.version,
+    );
+    let snippets = [
+        (Category::Analytics, &settings.snippets.analytics),
+        (Category::Marketing, &settings.snippets.marketing),
+    ]
+    .into_iter()
+    .filter(|(category, _)| consent.allows(*category))
+    .flat_map(|(_, snippets)| snippets.iter().cloned())
+    .collect::<Vec<_>>();
+    ViewContext::insert(req.extensions_mut(), "consented_snippets", snippets);
+    ViewContext::insert(req.extensions_mut(), "consent", &consent);
+    req.extensions_mut().insert(consent);
+    next.run(req).await
+}
+
+pub(crate) async fn handler_consent(
+    view: View,
+    consent: Consent,
+) -> Html<String> {
+    view.render(
+        "consent",
+        context! {
+            title => "Cookies",
+            current => consent,
+        },
+    )
+    .unwrap()
+}
+
+/// Choice posted by the banner or the cookies page. `choice` is `all`,
+/// `necessary` or, for the checkboxes, `custom`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ConsentInput {
+    choice: String,
+    #[serde(default)]
+    analytics: bool,
+    #[serde(default)]
+    marketing: bool,
+}
+
+pub(crate) async fn update_consent(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    flash: Flash,
+    Form(input): Form<ConsentInput>,
+) -> impl IntoResponse {
+    let (analytics, marketing) = match input.choice.as_str() {
+        "all" => (true, true),
+        "custom" => (input.analytics, input.marketing),
+        _ => (false, false),
+    };
+    let settings = &state.settings.consent;
+    let consent = Consent {
+        decided: true,
+        version: settings.version,
+        analytics,
+        marketing,
+    };
+    flash.success("Your cookie preferences were saved.");
+
+    let back = referer_path(&state.settings.site.url, &headers);
+    let jar =
+        SignedCookieJar::from_headers(&headers, state.cookie_key.clone());
+    (jar.add(consent.into_cookie(settings.max_age_days)), Redirect::to(&back))
+}
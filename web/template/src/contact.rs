@@ -0,0 +1,154 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_client_ip::ClientIp;
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use validator::Validate;
+
+use crate::captcha::CaptchaVerified;
+use crate::flash::Flash;
+use crate::form::{Field, FieldKind, FormDefinition, FormErrors, FormSpec};
+use crate::honeypot::Honeypot;
+use crate::mailer;
+use crate::meta::Meta;
+use crate::metric;
+use crate::rate_limit::RateLimitSettings;
+use crate::state::AppState;
+use crate::view::View;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ContactSettings {
+    /// Inbox the messages are sent to.
+    pub(crate) to: String,
+    /// Messages allowed per client IP.
+    pub(crate) rate_limit: RateLimitSettings,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct ContactInput {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Must have between 1 and 100 characters"
+    ))]
+    pub(crate) name: String,
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+    #[validate(length(
+        min = 10,
+        max = 5000,
+        message = "Must have between 10 and 5000 characters"
+    ))]
+    pub(crate) message: String,
+}
+
+impl FormDefinition for ContactInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/contact")
+            .submit("Send message")
+            .field(Field::text("name", "Name").length(Some(1), Some(100)))
+            .field(Field::email("email", "Email").required())
+            .field(
+                Field::new("message", "Message", FieldKind::Textarea)
+                    .length(Some(10), Some(5000)),
+            )
+    }
+}
+
+fn render<T: Serialize>(
+    state: &AppState,
+    view: &View,
+    values: &T,
+    errors: &FormErrors,
+) -> Html<String> {
+    view.render(
+        "contact",
+        context! {
+            title => "Contact",
+            meta => Meta::new(&state.settings.site, "Contact", "/contact"),
+            form => ContactInput::form().bind(values, errors),
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_contact(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Html<String> {
+    render(&state, &view, &(), &FormErrors::default())
+}
+
+/// Emails the message to `contact.to` and redirects back with a flash.
+///
+/// The CSRF token is checked by the middleware, bots are turned away by
+/// the honeypot and the CAPTCHA before the form is read, and each client
+/// IP can only send a few messages per window.
+pub(crate) async fn handler_contact_post(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    view: View,
+    flash: Flash,
+    CaptchaVerified(Honeypot(Form(input))): CaptchaVerified<
+        Honeypot<Form<ContactInput>>,
+    >,
+) -> Response {
+    let fail = |status: StatusCode, errors: &FormErrors| {
+        (status, render(&state, &view, &input, errors)).into_response()
+    };
+
+    if let Err(errors) = input.validate() {
+        return fail(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &FormErrors::from(&errors),
+        );
+    }
+
+    if !state.contact_limiter.check(&format!("ip:{ip}")).await {
+        let mut errors = FormErrors::default();
+        errors.add("message", "Too many messages, try again later");
+        return fail(StatusCode::TOO_MANY_REQUESTS, &errors);
+    }
+
+    let ctx = context! {
+        name => &input.name,
+        email => &input.email,
+        message => &input.message,
+        ip => ip.to_string(),
+    };
+    let to = &state.settings.contact.to;
+    if let Err(e) = mailer::deliver(&state, to, "contact", ctx).await {
+        error!("could not send the contact message: {e:#}");
+        let mut errors = FormErrors::default();
+        errors.add("message", "Could not send your message, try again later");
+        return fail(StatusCode::SERVICE_UNAVAILABLE, &errors);
+    }
+
+    info!(%ip, "contact message sent");
+    metric::count!("contact");
+    flash.success("Thanks, your message was sent.");
+    Redirect::to("/contact").into_response()
+}
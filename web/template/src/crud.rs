@@ -0,0 +1,403 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+//! Back-office scaffolding: generic list, create, edit and delete pages
+//! under `/admin/<resource>` for the records of a store, described by a
+//! [`Resource`] registered in `AppState::admin_resources`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use anyhow::bail;
+use axum::{
+    Form,
+    extract::{FromRequestParts, Path, State},
+    http::{StatusCode, request::Parts},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use sqlx::AnyConnection;
+use tracing::info;
+use validator::Validate;
+
+use crate::audit::Audit;
+use crate::flash::Flash;
+use crate::form::{Field, FormErrors, FormSpec};
+use crate::helpers::BoxFuture;
+use crate::pagination::{Page, Pagination};
+use crate::rbac::Permissions;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::transaction::Tx;
+use crate::view::View;
+
+/// Submitted fields of a resource form, in their order.
+pub(crate) type Fields = Vec<(String, String)>;
+
+/// Column of a resource table, showing the `key` of its rows.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct Column {
+    pub(crate) key: &'static str,
+    pub(crate) label: &'static str,
+}
+
+/// Records managed from the admin area, read and written through their
+/// store. Rows and form values are JSON objects, read by the generic
+/// `admin_table` and `admin_form` templates.
+///
+/// The writes get `conn`, the transaction of the request when there is a
+/// database, so a record spread over several tables is committed whole or
+/// not at all, see [`Tx`].
+pub(crate) trait Resource: Send + Sync {
+    /// Path segment of the pages, e.g. `subscribers`.
+    fn name(&self) -> &'static str;
+
+    /// Title of the list, e.g. `Newsletter subscribers`.
+    fn title(&self) -> &'static str;
+
+    /// Name of one record in messages, e.g. `subscriber`.
+    fn singular(&self) -> &'static str;
+
+    /// Permission needed for every page of the resource.
+    fn permission(&self) -> &'static str;
+
+    fn columns(&self) -> &'static [Column];
+
+    /// Rows of one page, objects holding an `id` and the column keys.
+    fn list<'a>(
+        &'a self,
+        state: &'a AppState,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Value>>>;
+
+    /// Number of records, shown on the dashboard.
+    fn count<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<u64>>;
+
+    /// Fields of the edit form when `editing`, else of the creation one,
+    /// none when the records are not created or edited from the admin
+    /// area.
+    fn fields(&self, editing: bool) -> Option<Vec<Field>>;
+
+    /// Creates a record from the submitted `fields`, returning its id or
+    /// the errors to show next to them.
+    fn create<'a>(
+        &'a self,
+        state: &'a AppState,
+        conn: Option<&'a mut AnyConnection>,
+        fields: &'a Fields,
+    ) -> BoxFuture<'a, anyhow::Result<Result<String, FormErrors>>>;
+
+    /// Values of the edit form of `id`, none when there is no such record.
+    /// Only called when `fields(true)` offers an edit form.
+    fn find<'a>(
+        &'a self,
+        _state: &'a AppState,
+        _id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Value>>> {
+        Box::pin(async move {
+            bail!("{} are not edited", self.name());
+        })
+    }
+
+    /// Updates `id` from the submitted `fields`. Only called when
+    /// `fields(true)` offers an edit form.
+    fn update<'a>(
+        &'a self,
+        _state: &'a AppState,
+        _conn: Option<&'a mut AnyConnection>,
+        _id: &'a str,
+        _fields: &'a Fields,
+    ) -> BoxFuture<'a, anyhow::Result<Result<(), FormErrors>>> {
+        Box::pin(async move {
+            bail!("{} are not edited", self.name());
+        })
+    }
+
+    /// Deletes `id`, false when there is no such record.
+    fn delete<'a>(
+        &'a self,
+        state: &'a AppState,
+        conn: Option<&'a mut AnyConnection>,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+}
+
+/// Deserializes and validates submitted `fields` as the `Form` extractor
+/// and `#[validate]` would, for the `create` and `update` of resources.
+/// Malformed fields are reported under `form`.
+pub(crate) fn parse<T: DeserializeOwned + Validate>(
+    fields: &Fields,
+) -> Result<T, FormErrors> {
+    let encoded = serde_urlencoded::to_string(fields).unwrap_or_default();
+    let input: T = serde_urlencoded::from_str(&encoded).map_err(|e| {
+        let mut errors = FormErrors::default();
+        errors.add("form", &e.to_string());
+        errors
+    })?;
+    input.validate().map_err(|e| FormErrors::from(&e))?;
+    Ok(input)
+}
+
+/// Resource named by the `resource` path parameter. Unknown names are
+/// answered with a 404, users without its permission with a 403.
+pub(crate) struct Managed(pub(crate) Arc<dyn Resource>);
+
+impl FromRequestParts<Arc<AppState>> for Managed {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(params) =
+            Path::<HashMap<String, String>>::from_request_parts(parts, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+        let name = params.get("resource").map(String::as_str);
+        let Some(resource) = state
+            .admin_resources
+            .iter()
+            .find(|resource| Some(resource.name()) == name)
+        else {
+            return Err(StatusCode::NOT_FOUND.into_response());
+        };
+        let allowed = parts
+            .extensions
+            .get::<Permissions>()
+            .is_some_and(|permissions| permissions.can(resource.permission()));
+        if !allowed {
+            return Err(StatusCode::FORBIDDEN.into_response());
+        }
+        Ok(Managed(resource.clone()))
+    }
+}
+
+/// Form of `resource`, editing `id` when given.
+fn form(resource: &dyn Resource, id: Option<&str>) -> Option<FormSpec> {
+    let fields = resource.fields(id.is_some())?;
+    let name = resource.name();
+    let spec = match id {
+        None => FormSpec::new(format!("/admin/{name}/new")).submit("Create"),
+        Some(id) => {
+            FormSpec::new(format!("/admin/{name}/{id}")).submit("Save")
+        }
+    };
+    Some(fields.into_iter().fold(spec, FormSpec::field))
+}
+
+fn render_form<T: Serialize>(
+    view: &View,
+    resource: &dyn Resource,
+    form: &FormSpec,
+    values: &T,
+    errors: &FormErrors,
+    id: Option<&str>,
+) -> Html<String> {
+    let title = match id {
+        None => format!("New {}", resource.singular()),
+        Some(id) => format!("Edit {} {id}", resource.singular()),
+    };
+    view.render(
+        "admin_form",
+        context! {
+            title => title,
+            name => resource.name(),
+            list_title => resource.title(),
+            form => form.bind(values, errors),
+            errors => errors,
+            id => id,
+        },
+    )
+    .unwrap()
+}
+
+/// Answers an invalid submission with the form, its values and errors.
+fn invalid(
+    view: &View,
+    resource: &dyn Resource,
+    form: &FormSpec,
+    fields: &Fields,
+    errors: &FormErrors,
+    id: Option<&str>,
+) -> Response {
+    let values: BTreeMap<&str, &str> = fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let rendered = render_form(view, resource, form, &values, errors, id);
+    (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+}
+
+pub(crate) async fn handler_resource_list(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Managed(resource): Managed,
+    pagination: Pagination,
+) -> Result<Html<String>, ServerError> {
+    let rows = resource.list(&state, pagination).await?;
+    Ok(view
+        .render(
+            "admin_table",
+            context! {
+                title => resource.title(),
+                name => resource.name(),
+                columns => resource.columns(),
+                page => Page::new(rows, pagination),
+                creatable => resource.fields(false).is_some(),
+                editable => resource.fields(true).is_some(),
+            },
+        )
+        .unwrap())
+}
+
+pub(crate) async fn handler_resource_new(
+    view: View,
+    Managed(resource): Managed,
+) -> Response {
+    let Some(form) = form(resource.as_ref(), None) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    render_form(
+        &view,
+        resource.as_ref(),
+        &form,
+        &(),
+        &FormErrors::default(),
+        None,
+    )
+    .into_response()
+}
+
+pub(crate) async fn handler_resource_new_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Managed(resource): Managed,
+    mut tx: Option<Tx>,
+    Form(fields): Form<Fields>,
+) -> Result<Response, ServerError> {
+    let Some(form) = form(resource.as_ref(), None) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let conn = tx.as_deref_mut();
+    let id = match resource.create(&state, conn, &fields).await? {
+        Ok(id) => id,
+        Err(errors) => {
+            let resource = resource.as_ref();
+            return Ok(invalid(
+                &view, resource, &form, &fields, &errors, None,
+            ));
+        }
+    };
+
+    let name = resource.name();
+    info!(resource = name, id, "record created by an administrator");
+    audit
+        .record(&format!("admin.{name}_create"), Some(id.clone()), json!({}))
+        .await;
+    flash.success(format!("Created {} {id}.", resource.singular()));
+    Ok(Redirect::to(&format!("/admin/{name}")).into_response())
+}
+
+pub(crate) async fn handler_resource_edit(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Managed(resource): Managed,
+    Path((_, id)): Path<(String, String)>,
+) -> Result<Response, ServerError> {
+    let Some(form) = form(resource.as_ref(), Some(&id)) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let Some(values) = resource.find(&state, &id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    Ok(render_form(
+        &view,
+        resource.as_ref(),
+        &form,
+        &values,
+        &FormErrors::default(),
+        Some(&id),
+    )
+    .into_response())
+}
+
+pub(crate) async fn handler_resource_edit_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Managed(resource): Managed,
+    Path((_, id)): Path<(String, String)>,
+    mut tx: Option<Tx>,
+    Form(fields): Form<Fields>,
+) -> Result<Response, ServerError> {
+    let Some(form) = form(resource.as_ref(), Some(&id)) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if resource.find(&state, &id).await?.is_none() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let conn = tx.as_deref_mut();
+    if let Err(errors) = resource.update(&state, conn, &id, &fields).await? {
+        let resource = resource.as_ref();
+        return Ok(invalid(
+            &view,
+            resource,
+            &form,
+            &fields,
+            &errors,
+            Some(&id),
+        ));
+    }
+
+    let name = resource.name();
+    info!(resource = name, id, "record updated by an administrator");
+    audit
+        .record(&format!("admin.{name}_update"), Some(id.clone()), json!({}))
+        .await;
+    flash.success(format!("Saved {} {id}.", resource.singular()));
+    Ok(Redirect::to(&format!("/admin/{name}")).into_response())
+}
+
+pub(crate) async fn handler_resource_delete(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Managed(resource): Managed,
+    Path((_, id)): Path<(String, String)>,
+    mut tx: Option<Tx>,
+) -> Result<Response, ServerError> {
+    if !resource.delete(&state, tx.as_deref_mut(), &id).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let name = resource.name();
+    info!(resource = name, id, "record deleted by an administrator");
+    audit
+        .record(&format!("admin.{name}_delete"), Some(id.clone()), json!({}))
+        .await;
+    flash.success(format!("Deleted {} {id}.", resource.singular()));
+    Ok(Redirect::to(&format!("/admin/{name}")).into_response())
+}
@@ -0,0 +1,317 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequestParts, Request, State},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_csrf::CsrfToken;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use futures_util::{StreamExt, stream};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tracing::{error, warn};
+
+use crate::form::peek_form_fields;
+use crate::helpers;
+use crate::state::AppState;
+use crate::view::ViewContext;
+
+/// Form field carrying the authenticity token, see the `csrf_field()` macro.
+pub(crate) const CSRF_FIELD: &str = "authenticity_token";
+/// Header carrying the authenticity token for scripts and API clients.
+pub(crate) const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Cookie holding the double-submit token. The `__Host-` prefix, used in
+/// production where the cookie is secure, stops subdomains from setting it.
+const DOUBLE_SUBMIT_COOKIE: &str = "csrf";
+const DOUBLE_SUBMIT_HOST_COOKIE: &str = "__Host-csrf";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How authenticity tokens are issued and checked.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CsrfMode {
+    /// `axum_csrf` tokens, checked against a hash kept in its cookie.
+    #[default]
+    Session,
+    /// Signed token in a cookie readable by scripts, sent back in the
+    /// header or form field. Needs no server state, so any instance sharing
+    /// `cookies.key` accepts it.
+    DoubleSubmit,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CsrfSettings {
+    #[serde(default)]
+    pub(crate) mode: CsrfMode,
+    /// Paths skipped by [`verify`] with everything below them, e.g. API or
+    /// webhook routes.
+    pub(crate) exempt: Vec<String>,
+}
+
+impl CsrfSettings {
+    /// Whether `path` is an exempt path or below one, whole segments only:
+    /// `/api` covers `/api/v1` but not `/apifoo`.
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt.iter().any(|prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+fn is_unsafe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+fn rejection(req: &Request) -> Response {
+    warn!(path = req.uri().path(), "invalid csrf token");
+    (StatusCode::FORBIDDEN, "Invalid authenticity token").into_response()
+}
+
+/// Rejects state-changing requests without a valid authenticity token, in
+/// the `csrf.mode` of the settings.
+///
+/// The token is read from the `x-csrf-token` header, the
+/// `authenticity_token` field of url-encoded forms or, for multipart forms
+/// whose body is streamed, their first part. Every page gets the token as
+/// `csrf_token`.
+pub(crate) async fn verify(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match state.settings.csrf.mode {
+        CsrfMode::Session => verify_session(state, req, next).await,
+        CsrfMode::DoubleSubmit => verify_double_submit(state, req, next).await,
+    }
+}
+
+/// Checks `axum_csrf` tokens and refreshes their cookie.
+async fn verify_session(
+    state: Arc<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = req.into_parts();
+    let token = match CsrfToken::from_request_parts(&mut parts, &state).await {
+        Ok(token) => token,
+        Err(e) => {
+            error!("csrf layer missing: {e:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let mut req = Request::from_parts(parts, body);
+
+    if let Ok(authenticity_token) = token.authenticity_token() {
+        ViewContext::insert(
+            req.extensions_mut(),
+            "csrf_token",
+            authenticity_token,
+        );
+    }
+
+    if is_unsafe(req.method())
+        && !state.settings.csrf.is_exempt(req.uri().path())
+    {
+        let (rebuilt, submitted) = match submitted_token(req, &state).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        };
+        req = rebuilt;
+
+        let valid = submitted
+            .is_some_and(|submitted| token.verify(&submitted).is_ok());
+        if !valid {
+            return rejection(&req);
+        }
+    }
+
+    let response = next.run(req).await;
+    (token, response).into_response()
+}
+
+/// Checks that the submitted token is the one of the cookie, which a
+/// cross-site request cannot read, and that the server signed it. Sets the
+/// cookie when the browser has none.
+async fn verify_double_submit(
+    state: Arc<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let key = state.settings.cookies.key.as_bytes();
+    let secure = state.settings.is_production();
+    let name =
+        if secure { DOUBLE_SUBMIT_HOST_COOKIE } else { DOUBLE_SUBMIT_COOKIE };
+    let jar = CookieJar::from_headers(req.headers());
+    let existing = jar
+        .get(name)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|token| is_signed(key, token));
+    let issued = existing.is_none();
+    let token = existing.unwrap_or_else(|| sign(key));
+    ViewContext::insert(req.extensions_mut(), "csrf_token", token.clone());
+
+    if is_unsafe(req.method())
+        && !state.settings.csrf.is_exempt(req.uri().path())
+    {
+        let (rebuilt, submitted) = match submitted_token(req, &state).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        };
+        req = rebuilt;
+
+        let valid = !issued
+            && submitted.is_some_and(|submitted| {
+                helpers::constant_time_eq(
+                    submitted.as_bytes(),
+                    token.as_bytes(),
+                )
+            });
+        if !valid {
+            return rejection(&req);
+        }
+    }
+
+    let response = next.run(req).await;
+    if !issued {
+        return response;
+    }
+    // Readable by scripts, which send it back in the header.
+    let cookie = Cookie::build((name, token))
+        .path("/")
+        .secure(secure)
+        .same_site(SameSite::Lax);
+    (jar.add(cookie), response).into_response()
+}
+
+/// A new `<nonce>.<signature>` token.
+fn sign(key: &[u8]) -> String {
+    let mut nonce = [0u8; 32];
+    getrandom::fill(&mut nonce).expect("the system random source failed");
+    let nonce = URL_SAFE_NO_PAD.encode(nonce);
+    let signature = mac(key, &nonce).finalize().into_bytes();
+    format!("{nonce}.{}", URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn is_signed(key: &[u8], token: &str) -> bool {
+    let Some((nonce, signature)) = token.split_once('.') else {
+        return false;
+    };
+    URL_SAFE_NO_PAD.decode(signature).is_ok_and(|signature| {
+        mac(key, nonce).verify_slice(&signature).is_ok()
+    })
+}
+
+fn mac(key: &[u8], nonce: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key)
+        .expect("hmac accepts keys of any size");
+    mac.update(b"csrf\0");
+    mac.update(nonce.as_bytes());
+    mac
+}
+
+async fn submitted_token(
+    req: Request,
+    state: &Arc<AppState>,
+) -> Result<(Request, Option<String>), Response> {
+    if let Some(value) = req.headers().get(CSRF_HEADER) {
+        let value = value.to_str().ok().map(str::to_string);
+        return Ok((req, value));
+    }
+
+    if let Some(boundary) = multipart_boundary(&req) {
+        return Ok(peek_multipart_token(req, &boundary).await);
+    }
+
+    let (req, fields) = peek_form_fields(req, state).await?;
+    let from_form = fields
+        .into_iter()
+        .find(|(name, _)| name == CSRF_FIELD)
+        .map(|(_, value)| value);
+    Ok((req, from_form))
+}
+
+/// Bytes of a multipart body read to find the token in its first part.
+const MULTIPART_PEEK_LIMIT: usize = 8 * 1024;
+
+fn multipart_boundary(req: &Request) -> Option<String> {
+    let content_type = req.headers().get(header::CONTENT_TYPE)?;
+    multer::parse_boundary(content_type.to_str().ok()?).ok()
+}
+
+/// Reads the start of a multipart body, which must open with the
+/// `authenticity_token` part as `csrf_field()` does, and rebuilds the
+/// request with the bytes read put back in front of the rest of the
+/// stream, so uploads stay streamed.
+async fn peek_multipart_token(
+    req: Request,
+    boundary: &str,
+) -> (Request, Option<String>) {
+    let (parts, body) = req.into_parts();
+    let mut stream = body.into_data_stream();
+    let mut head = Vec::new();
+    let delimiter = format!("\r\n--{boundary}");
+    while head.len() < MULTIPART_PEEK_LIMIT {
+        let ends_first_part = head.len() > delimiter.len()
+            && find(&head[delimiter.len()..], delimiter.as_bytes()).is_some();
+        if ends_first_part {
+            break;
+        }
+        match stream.next().await {
+            Some(Ok(chunk)) => head.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+    let head = Bytes::from(head);
+    let token = first_part_token(&head, boundary).await;
+
+    let body = Body::from_stream(
+        stream::once(async move { Ok::<_, axum::Error>(head) }).chain(stream),
+    );
+    (Request::from_parts(parts, body), token)
+}
+
+/// Value of the first part of `head` when it is the authenticity token.
+async fn first_part_token(head: &Bytes, boundary: &str) -> Option<String> {
+    let head = head.clone();
+    let mut multipart = multer::Multipart::new(
+        stream::once(async move { Ok::<_, multer::Error>(head) }),
+        boundary,
+    );
+    let field = multipart.next_field().await.ok()??;
+    if field.name() != Some(CSRF_FIELD) {
+        return None;
+    }
+    field.text().await.ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,110 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use minijinja::{Environment, Value};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct EmailSettings {
+    /// Sender of the application emails, e.g. `Website <no-reply@host>`.
+    pub(crate) from: String,
+}
+
+/// Rendered email ready to be sent as a `multipart/alternative` message.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EmailContent {
+    pub(crate) subject: String,
+    pub(crate) text: String,
+    pub(crate) html: String,
+}
+
+/// Builds the environment holding the email templates.
+///
+/// Every email `name` is made of two templates: `name.txt`, which provides
+/// the `subject` and `body` blocks, and `name.html`, which extends the
+/// email layout. The extensions also drive minijinja auto escaping.
+pub(crate) fn environment() -> anyhow::Result<Environment<'static>> {
+    let mut env = Environment::new();
+    env.add_template(
+        "layout.html",
+        include_str!("../templates/email/layout.html.jinja"),
+    )?;
+    env.add_template(
+        "welcome.txt",
+        include_str!("../templates/email/welcome.txt.jinja"),
+    )?;
+    env.add_template(
+        "welcome.html",
+        include_str!("../templates/email/welcome.html.jinja"),
+    )?;
+    env.add_template(
+        "password_reset.txt",
+        include_str!("../templates/email/password_reset.txt.jinja"),
+    )?;
+    env.add_template(
+        "password_reset.html",
+        include_str!("../templates/email/password_reset.html.jinja"),
+    )?;
+    env.add_template(
+        "verify_email.txt",
+        include_str!("../templates/email/verify_email.txt.jinja"),
+    )?;
+    env.add_template(
+        "verify_email.html",
+        include_str!("../templates/email/verify_email.html.jinja"),
+    )?;
+    env.add_template(
+        "data_export.txt",
+        include_str!("../templates/email/data_export.txt.jinja"),
+    )?;
+    env.add_template(
+        "data_export.html",
+        include_str!("../templates/email/data_export.html.jinja"),
+    )?;
+    env.add_template(
+        "contact.txt",
+        include_str!("../templates/email/contact.txt.jinja"),
+    )?;
+    env.add_template(
+        "contact.html",
+        include_str!("../templates/email/contact.html.jinja"),
+    )?;
+    env.add_template(
+        "newsletter_confirm.txt",
+        include_str!("../templates/email/newsletter_confirm.txt.jinja"),
+    )?;
+    env.add_template(
+        "newsletter_confirm.html",
+        include_str!("../templates/email/newsletter_confirm.html.jinja"),
+    )?;
+    Ok(env)
+}
+
+/// Renders the text and HTML parts of the email `name` with `ctx`.
+pub(crate) fn render_email(
+    env: &Environment<'static>,
+    name: &str,
+    ctx: Value,
+) -> Result<EmailContent, minijinja::Error> {
+    let text = env.get_template(&format!("{name}.txt"))?;
+    let mut state = text.eval_to_state(&ctx)?;
+    let subject = state.render_block("subject")?.trim().to_string();
+    let text = state.render_block("body")?.trim().to_string();
+
+    let html = env.get_template(&format!("{name}.html"))?.render(&ctx)?;
+
+    Ok(EmailContent { subject, text, html })
+}
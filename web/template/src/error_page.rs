@@ -0,0 +1,91 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use minijinja::context;
+use tracing::error;
+
+use crate::view::View;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Replaces the plain bodies of 404 and 5xx responses to browsers with the
+/// `error` page, which shows the request id support can grep the logs for.
+/// Pages rendered by the handlers and API responses go through untouched.
+pub(crate) async fn render(view: View, req: Request, next: Next) -> Response {
+    let wants_html = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(req).await;
+    let status = response.status();
+    if !wants_html
+        || !(status == StatusCode::NOT_FOUND || status.is_server_error())
+        || is_html(&response)
+    {
+        return response;
+    }
+
+    let message = if status == StatusCode::NOT_FOUND {
+        "The page you asked for does not exist."
+    } else {
+        "Something went wrong on our side, please try again later."
+    };
+    let page = match view.render(
+        "error",
+        context! {
+            title => status.canonical_reason().unwrap_or("Error"),
+            status => status.as_u16(),
+            message,
+            request_id,
+        },
+    ) {
+        Ok(page) => page,
+        Err(e) => {
+            error!("could not render the error page: {e:#}");
+            return response;
+        }
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    Response::from_parts(parts, Body::from(page.0))
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"))
+}
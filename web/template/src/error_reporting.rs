@@ -0,0 +1,134 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ErrorReportingSettings {
+    /// Sentry compatible DSN, e.g. `https://key@sentry.example.com/42`.
+    /// Nothing is reported without one. Needs the `sentry` feature.
+    pub(crate) dsn: Option<String>,
+    /// Share of the errors sent, from 0.0 to 1.0.
+    #[cfg_attr(not(feature = "sentry"), allow(dead_code))]
+    pub(crate) sample_rate: f32,
+}
+
+/// Sends the reports still queued when dropped, keep it until `main`
+/// returns.
+pub(crate) struct ReportGuard {
+    #[cfg(feature = "sentry")]
+    _client: Option<sentry::ClientInitGuard>,
+}
+
+/// Starts reporting panics, and the 5xx responses seen by [`capture`], to
+/// the DSN of the settings.
+#[cfg(feature = "sentry")]
+pub(crate) fn init(
+    settings: &ErrorReportingSettings,
+    environment: &str,
+) -> anyhow::Result<ReportGuard> {
+    let Some(dsn) = &settings.dsn else {
+        return Ok(ReportGuard { _client: None });
+    };
+    let client = sentry::init(sentry::ClientOptions {
+        dsn: Some(dsn.parse()?),
+        release: Some(
+            format!("{}@{}", env!("CARGO_PKG_NAME"), crate::version::VERSION)
+                .into(),
+        ),
+        environment: Some(environment.to_owned().into()),
+        sample_rate: settings.sample_rate,
+        ..Default::default()
+    });
+    if let Some(dsn) = client.dsn() {
+        tracing::info!("reporting errors to {}", dsn.host());
+    }
+    Ok(ReportGuard { _client: Some(client) })
+}
+
+#[cfg(not(feature = "sentry"))]
+pub(crate) fn init(
+    settings: &ErrorReportingSettings,
+    _: &str,
+) -> anyhow::Result<ReportGuard> {
+    if settings.dsn.is_some() {
+        anyhow::bail!("error_reporting.dsn needs the sentry feature");
+    }
+    Ok(ReportGuard {})
+}
+
+/// Runs the request with its own scope tagged with the method, route,
+/// request id and user, which panics inside it are reported with, and
+/// reports 5xx responses.
+#[cfg(feature = "sentry")]
+pub(crate) async fn capture(req: Request, next: Next) -> Response {
+    use std::sync::Arc;
+
+    use axum::extract::MatchedPath;
+    use sentry::{Hub, Level, SentryFutureExt};
+
+    use crate::auth::CurrentUser;
+    use crate::metric::UNMATCHED_PATH;
+
+    if Hub::current().client().is_none() {
+        return next.run(req).await;
+    }
+
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str)
+        .to_owned();
+    let hub = Arc::new(Hub::new_from_top(Hub::current()));
+    hub.configure_scope(|scope| {
+        scope.set_transaction(Some(&format!("{method} {route}")));
+        scope.set_tag("method", &method);
+        scope.set_tag("route", &route);
+        scope.set_tag("path", req.uri().path());
+        if let Some(request_id) = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|value| value.to_str().ok())
+        {
+            scope.set_tag("request_id", request_id);
+        }
+        if let Some(CurrentUser(user)) = req.extensions().get::<CurrentUser>()
+        {
+            scope.set_user(Some(sentry::User {
+                id: Some(user.id.to_string()),
+                ..Default::default()
+            }));
+        }
+    });
+
+    let response = next.run(req).bind_hub(hub.clone()).await;
+
+    let status = response.status();
+    if status.is_server_error() {
+        hub.capture_message(
+            &format!("{method} {route} answered {status}"),
+            Level::Error,
+        );
+    }
+    response
+}
+
+#[cfg(not(feature = "sentry"))]
+pub(crate) async fn capture(req: Request, next: Next) -> Response {
+    next.run(req).await
+}
@@ -0,0 +1,87 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::{Stream, StreamExt, stream};
+use serde::Serialize;
+use tracing::error;
+
+/// Bytes of CSV gathered before they are sent as a chunk.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Streams `rows`, e.g. the rows of a query, as a CSV attachment named
+/// `filename`, with a header line taken from the fields of the first row.
+///
+/// Rows are written as they come and sent in chunks of about 8 KiB, so the
+/// whole result is never held in memory. A row failing ends the body
+/// early, which clients see as an incomplete download.
+pub(crate) fn csv<T, S>(filename: &str, rows: S) -> Response
+where
+    T: Serialize,
+    S: Stream<Item = anyhow::Result<T>> + Send + 'static,
+{
+    let body =
+        stream::unfold(Some((Box::pin(rows), true)), |state| async move {
+            let (mut rows, first) = state?;
+            match next_chunk(&mut rows, first).await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some((rows, false)))),
+                Ok(None) => None,
+                Err(e) => {
+                    error!("CSV export failed: {e:#}");
+                    Some((Err(e), None))
+                }
+            }
+        });
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Writes rows until a chunk is full or none are left, `None` once they
+/// are all sent. The `first` chunk starts with the header line.
+async fn next_chunk<T, S>(
+    rows: &mut S,
+    first: bool,
+) -> anyhow::Result<Option<Bytes>>
+where
+    T: Serialize,
+    S: Stream<Item = anyhow::Result<T>> + Unpin,
+{
+    let mut writer =
+        csv::WriterBuilder::new().has_headers(first).from_writer(Vec::new());
+    while writer.get_ref().len() < CHUNK_SIZE {
+        let Some(row) = rows.next().await else {
+            break;
+        };
+        writer.serialize(row?)?;
+        writer.flush()?;
+    }
+    let chunk = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok((!chunk.is_empty()).then(|| Bytes::from(chunk)))
+}
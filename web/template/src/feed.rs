@@ -0,0 +1,175 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use minijinja::{Environment, context};
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::error;
+
+use crate::cache::CacheKey;
+use crate::pagination::Pagination;
+use crate::state::AppState;
+
+/// Rendered entries of `/feed.xml`, kept for `feed.max_age`.
+const ENTRIES: CacheKey<Vec<FeedEntry>> = CacheKey::new("feed:entries");
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FeedSettings {
+    pub(crate) title: String,
+    /// `max-age` of the `Cache-Control` header sent with feeds.
+    pub(crate) max_age: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Feed {
+    pub(crate) id: String,
+    pub(crate) title: String,
+    /// Absolute URL of the feed itself.
+    pub(crate) self_url: String,
+    /// Absolute URL of the HTML page listing the entries.
+    pub(crate) alternate_url: String,
+    /// RFC 3339 timestamp of the most recent change.
+    pub(crate) updated: String,
+    pub(crate) entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FeedEntry {
+    pub(crate) id: String,
+    pub(crate) title: String,
+    pub(crate) url: String,
+    pub(crate) updated: String,
+    /// HTML body, rendered from the `feed_entry.html` template.
+    pub(crate) content: String,
+}
+
+/// Renders `feed` as an Atom document with caching headers, answering
+/// `304 Not Modified` when the client already holds the same version.
+pub(crate) fn render_feed(
+    env: &Environment<'static>,
+    feed: &Feed,
+    max_age: u64,
+    headers: &HeaderMap,
+) -> Response {
+    let rendered = env
+        .get_template("feed.xml")
+        .unwrap()
+        .render(context! { feed => feed })
+        .unwrap();
+
+    let mut hasher = DefaultHasher::new();
+    rendered.hash(&mut hasher);
+    let etag = format!("\"{:x}\"", hasher.finish());
+    let cache_control = format!("public, max-age={max_age}");
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+            rendered,
+        )
+            .into_response()
+    };
+
+    let response_headers = response.headers_mut();
+    response_headers
+        .insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_str(&cache_control).unwrap(),
+    );
+    response
+}
+
+pub(crate) async fn handler_feed(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response {
+    let site = &state.settings.site;
+    let ttl = Duration::from_secs(state.settings.feed.max_age);
+    let entries = state
+        .cache
+        .get_or_insert_with(&ENTRIES, ttl, || render_entries(&state))
+        .await;
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("could not list the feed entries: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Entries are in publication order, the last change may be any of them.
+    let updated = entries
+        .iter()
+        .map(|entry| entry.updated.clone())
+        .max()
+        .unwrap_or_else(|| {
+            OffsetDateTime::UNIX_EPOCH.format(&Rfc3339).unwrap()
+        });
+    let feed = Feed {
+        id: site.url_for("/feed.xml"),
+        title: state.settings.feed.title.clone(),
+        self_url: site.url_for("/feed.xml"),
+        alternate_url: site.url_for("/blog"),
+        updated,
+        entries: entries.to_vec(),
+    };
+
+    render_feed(&state.env, &feed, state.settings.feed.max_age, &headers)
+}
+
+/// Entries of the `posts.feed_size` most recently published posts.
+async fn render_entries(state: &AppState) -> anyhow::Result<Vec<FeedEntry>> {
+    let site = &state.settings.site;
+    let entry_template = state.env.get_template("feed_entry.html").unwrap();
+    let size = state.settings.posts.feed_size;
+    let pagination = Pagination { page: 1, per_page: size, after: None };
+    let posts = state.posts.published(None, pagination).await?;
+
+    posts
+        .iter()
+        .take(size)
+        .map(|post| {
+            let url = site.url_for(&post.path());
+            Ok(FeedEntry {
+                id: url.clone(),
+                title: post.title.clone(),
+                url,
+                updated: post.updated_at.format(&Rfc3339)?,
+                content: entry_template
+                    .render(context! { post => post })
+                    .unwrap(),
+            })
+        })
+        .collect()
+}
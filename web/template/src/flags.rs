@@ -0,0 +1,434 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::Infallible,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    Form,
+    extract::{FromRequestParts, Path, Request, State},
+    http::{StatusCode, request::Parts},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, query};
+use time::OffsetDateTime;
+use tower::{Layer, Service};
+use tracing::{error, info};
+
+use crate::audit::Audit;
+use crate::auth::CurrentUser;
+use crate::cache::CacheKey;
+use crate::database::{Access, Database};
+use crate::flash::Flash;
+use crate::helpers::{BoxFuture, to_micros};
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::tenancy::Tenant;
+use crate::users::User;
+use crate::view::{View, ViewContext};
+
+const OVERRIDES: CacheKey<HashMap<String, bool>> =
+    CacheKey::new("flags:overrides");
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FlagsSettings {
+    /// Seconds the overrides set on `/admin/flags` are cached, the time the
+    /// other instances take to see them.
+    pub(crate) cache_ttl: u64,
+    /// Flags by name, e.g. `[flags.definitions.new_dashboard]`.
+    #[serde(default)]
+    pub(crate) definitions: BTreeMap<String, FlagDefinition>,
+}
+
+/// Who a flag is on for, unless forced on or off on `/admin/flags`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct FlagDefinition {
+    #[serde(default)]
+    pub(crate) description: String,
+    /// On for everyone, anonymous visitors included.
+    #[serde(default)]
+    pub(crate) enabled: bool,
+    /// Emails of the users it is on for.
+    #[serde(default)]
+    pub(crate) users: Vec<String>,
+    /// Roles of the users it is on for.
+    #[serde(default)]
+    pub(crate) roles: Vec<String>,
+    /// Share of the logged in users it is on for, from 0 to 100. Raising it
+    /// keeps the users it was already on for.
+    #[serde(default)]
+    pub(crate) percentage: u8,
+}
+
+impl FlagDefinition {
+    fn enabled_for(&self, name: &str, user: Option<&User>) -> bool {
+        if self.enabled {
+            return true;
+        }
+        let Some(user) = user else {
+            return false;
+        };
+        self.users.iter().any(|email| email.eq_ignore_ascii_case(&user.email))
+            || self.roles.iter().any(|role| user.roles.contains(role))
+            || bucket(name, user) < self.percentage
+    }
+}
+
+/// Bucket of `user` from 0 to 99 for the flag `name`, stable across
+/// instances and restarts. Each flag orders the users its own way, so the
+/// same ones do not get every rollout first.
+fn bucket(name: &str, user: &User) -> u8 {
+    let digest = Sha256::new()
+        .chain_update(name)
+        .chain_update(user.id.as_bytes())
+        .finalize();
+    (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+}
+
+/// Flags on for the request, resolved once by [`inject`].
+///
+/// Handlers gate unfinished work with [`Flags::enabled`], templates with
+/// `feature("name")`. Unknown flags are off.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Flags(BTreeSet<String>);
+
+impl Flags {
+    pub(crate) fn enabled(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Flags {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(flags) = parts.extensions.get::<Flags>() {
+            return Ok(flags.clone());
+        }
+        let tenant = parts.extensions.get::<Tenant>();
+        let user = parts.extensions.get::<CurrentUser>().map(|user| &user.0);
+        Ok(resolve(state, tenant, user).await)
+    }
+}
+
+/// Flags on for `user`, or for an anonymous visitor, of `tenant` if any,
+/// for the code running outside of a request.
+pub(crate) async fn resolve(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    user: Option<&User>,
+) -> Flags {
+    let overrides = overrides(state).await;
+    let enabled = state
+        .settings
+        .flags
+        .definitions
+        .iter()
+        .filter(|(name, definition)| {
+            overrides
+                .get(*name)
+                .or_else(|| tenant?.settings.flags.get(*name))
+                .copied()
+                .unwrap_or_else(|| definition.enabled_for(name, user))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    Flags(enabled)
+}
+
+/// Cached overrides, none when the store fails so the flags fall back to
+/// their definitions.
+async fn overrides(state: &AppState) -> Arc<HashMap<String, bool>> {
+    let ttl = Duration::from_secs(state.settings.flags.cache_ttl);
+    let overrides = state
+        .cache
+        .get_or_insert_with(&OVERRIDES, ttl, || state.flags.overrides())
+        .await;
+    overrides.unwrap_or_else(|e| {
+        error!("could not load the flag overrides: {e}");
+        Arc::default()
+    })
+}
+
+/// Resolves the flags of the request for [`Flags`] and [`RequireFlag`],
+/// and exposes them to templates as `features` for `feature()`.
+pub(crate) async fn inject(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let tenant = req.extensions().get::<Tenant>();
+    let user = req.extensions().get::<CurrentUser>().map(|user| &user.0);
+    let flags = resolve(&state, tenant, user).await;
+    ViewContext::insert(req.extensions_mut(), "features", &flags.0);
+    req.extensions_mut().insert(flags);
+    next.run(req).await
+}
+
+/// `feature("new_dashboard")` template function, reading the `features`
+/// the view context holds for the request.
+pub(crate) fn feature(state: &minijinja::State, name: &str) -> bool {
+    let Some(features) = state.lookup("features") else {
+        return false;
+    };
+    let Ok(mut features) = features.try_iter() else {
+        return false;
+    };
+    features.any(|feature| feature.as_str() == Some(name))
+}
+
+/// Route guard hiding a route behind a flag, e.g.
+/// `.route_layer(RequireFlag("new_dashboard"))`, answering 404 while it is
+/// off.
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub(crate) struct RequireFlag(pub(crate) &'static str);
+
+impl<S> Layer<S> for RequireFlag {
+    type Service = RequireFlagService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireFlagService { inner, flag: self.0 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RequireFlagService<S> {
+    inner: S,
+    flag: &'static str,
+}
+
+impl<S> Service<Request> for RequireFlagService<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let enabled = req
+            .extensions()
+            .get::<Flags>()
+            .is_some_and(|flags| flags.enabled(self.flag));
+        if !enabled {
+            return Box::pin(async {
+                Ok(StatusCode::NOT_FOUND.into_response())
+            });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+/// Where the overrides set on `/admin/flags` are kept.
+pub(crate) trait FlagStore: Send + Sync {
+    /// Flags forced on or off, by name.
+    fn overrides(
+        &self,
+    ) -> BoxFuture<'_, anyhow::Result<HashMap<String, bool>>>;
+
+    /// Forces `name` on or off, or back to its definition with none.
+    fn set<'a>(
+        &'a self,
+        name: &'a str,
+        enabled: Option<bool>,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Table of the database, kept in memory without one.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn FlagStore> {
+    match db {
+        None => Box::new(MemoryFlagStore::default()),
+        Some(db) => Box::new(SqlFlagStore { db: db.clone() }),
+    }
+}
+
+/// Overrides lasting until the process exits, local to it.
+#[derive(Default)]
+pub(crate) struct MemoryFlagStore {
+    overrides: RwLock<HashMap<String, bool>>,
+}
+
+impl FlagStore for MemoryFlagStore {
+    fn overrides(
+        &self,
+    ) -> BoxFuture<'_, anyhow::Result<HashMap<String, bool>>> {
+        Box::pin(async move { Ok(self.overrides.read().unwrap().clone()) })
+    }
+
+    fn set<'a>(
+        &'a self,
+        name: &'a str,
+        enabled: Option<bool>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut overrides = self.overrides.write().unwrap();
+            match enabled {
+                Some(enabled) => overrides.insert(name.to_string(), enabled),
+                None => overrides.remove(name),
+            };
+            Ok(())
+        })
+    }
+}
+
+pub(crate) struct SqlFlagStore {
+    db: Database,
+}
+
+impl FlagStore for SqlFlagStore {
+    fn overrides(
+        &self,
+    ) -> BoxFuture<'_, anyhow::Result<HashMap<String, bool>>> {
+        Box::pin(async move {
+            let sql = "SELECT name, enabled FROM feature_flags";
+            let query = query(sql).fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("flags.overrides", sql, query).await?;
+            rows.iter()
+                .map(|row| {
+                    let enabled = row.try_get::<i64, _>(1)? != 0;
+                    Ok((row.try_get(0)?, enabled))
+                })
+                .collect()
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        name: &'a str,
+        enabled: Option<bool>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let pool = self.db.pool(Access::ReadWrite);
+            let Some(enabled) = enabled else {
+                let sql = "DELETE FROM feature_flags WHERE name = $1";
+                let query = query(sql).bind(name).execute(pool);
+                self.db.observe("flags.reset", sql, query).await?;
+                return Ok(());
+            };
+            let sql = "INSERT INTO feature_flags (name, enabled, updated_at) \
+                       VALUES ($1, $2, $3) \
+                       ON CONFLICT (name) DO UPDATE \
+                       SET enabled = excluded.enabled, \
+                       updated_at = excluded.updated_at";
+            let query = query(sql)
+                .bind(name)
+                .bind(i64::from(enabled))
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(pool);
+            self.db.observe("flags.set", sql, query).await?;
+            Ok(())
+        })
+    }
+}
+
+/// A flag as listed on `/admin/flags`.
+#[derive(Debug, Serialize)]
+struct FlagRow<'a> {
+    name: &'a str,
+    definition: &'a FlagDefinition,
+    /// Forced on or off, none when following its definition.
+    forced: Option<bool>,
+    /// On for the admin looking at the page.
+    enabled: bool,
+}
+
+pub(crate) async fn handler_flags(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    flags: Flags,
+) -> Result<Html<String>, ServerError> {
+    let overrides = state.flags.overrides().await?;
+    let rows: Vec<_> = state
+        .settings
+        .flags
+        .definitions
+        .iter()
+        .map(|(name, definition)| FlagRow {
+            name,
+            definition,
+            forced: overrides.get(name).copied(),
+            enabled: flags.enabled(name),
+        })
+        .collect();
+    Ok(view
+        .render(
+            "flags",
+            context! {
+                title => "Feature flags",
+                flags => rows,
+            },
+        )
+        .unwrap())
+}
+
+/// Posted by the buttons of `/admin/flags`: `on`, `off` or `default`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct FlagInput {
+    state: String,
+}
+
+pub(crate) async fn handler_flag_set(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Path(name): Path<String>,
+    Form(input): Form<FlagInput>,
+) -> Result<Redirect, ServerError> {
+    if !state.settings.flags.definitions.contains_key(&name) {
+        flash.error("No such flag.");
+        return Ok(Redirect::to("/admin/flags"));
+    }
+    let enabled = match input.state.as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    };
+    state.flags.set(&name, enabled).await?;
+    state.cache.invalidate(&OVERRIDES).await;
+    let forced = match enabled {
+        Some(true) => "on",
+        Some(false) => "off",
+        None => "default",
+    };
+    info!(flag = name, forced, "flag set");
+    audit
+        .record("flag.set", Some(name.clone()), json!({ "forced": forced }))
+        .await;
+    flash.success(format!("Flag {name} set to {forced}."));
+    Ok(Redirect::to("/admin/flags"))
+}
@@ -0,0 +1,131 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_messages::{Level, Messages};
+use serde::Serialize;
+use tracing::error;
+
+/// Category of a flash message, also its CSS class in the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FlashLevel {
+    Success,
+    Info,
+    Error,
+}
+
+impl From<Level> for FlashLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Success => FlashLevel::Success,
+            Level::Warning | Level::Error => FlashLevel::Error,
+            Level::Debug | Level::Info => FlashLevel::Info,
+        }
+    }
+}
+
+/// Message rendered by the `flash.jinja` partial of the layout.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FlashMessage {
+    pub(crate) level: FlashLevel,
+    pub(crate) message: String,
+}
+
+/// Messages shown once on the next rendered page, usually the target of a
+/// redirect.
+///
+/// Wraps `axum_messages`, whose messages are loaded once per request by
+/// [`load`]: extracting [`Messages`] again in the same request would drop
+/// the loaded ones.
+#[derive(Debug, Clone)]
+pub(crate) struct Flash(Messages);
+
+impl Flash {
+    pub(crate) fn success(self, message: impl Into<String>) -> Self {
+        Flash(self.0.success(message))
+    }
+
+    pub(crate) fn info(self, message: impl Into<String>) -> Self {
+        Flash(self.0.info(message))
+    }
+
+    pub(crate) fn error(self, message: impl Into<String>) -> Self {
+        Flash(self.0.error(message))
+    }
+
+    /// Takes the messages left by previous requests, marking them as
+    /// shown. Called by `View::render`.
+    pub(crate) fn take(&self) -> Vec<FlashMessage> {
+        self.0
+            .clone()
+            .map(|message| FlashMessage {
+                level: message.level.into(),
+                message: message.message,
+            })
+            .collect()
+    }
+}
+
+impl<S> FromRequestParts<S> for Flash
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Flash>().cloned().expect(
+            "flash::load must run inside MessagesManagerLayer on every route",
+        ))
+    }
+}
+
+/// Loads the messages of the session into a [`Flash`] for the request.
+///
+/// Messages not shown by a page rendered on a redirect are queued again,
+/// so they reach its target along with the ones the handler added.
+pub(crate) async fn load(req: Request, next: Next) -> Response {
+    let (mut parts, body) = req.into_parts();
+    let messages = match Messages::from_request_parts(&mut parts, &()).await {
+        Ok(messages) => messages,
+        Err(rejection) => {
+            error!("could not load the flash messages: {}", rejection.1);
+            return rejection.into_response();
+        }
+    };
+    let flash = Flash(messages.clone());
+    parts.extensions.insert(flash.clone());
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    if response.status().is_redirection() {
+        let unread = messages.collect::<Vec<_>>();
+        for message in unread {
+            flash.0.clone().push(message.level, message.message, None);
+        }
+    }
+    response
+}
@@ -0,0 +1,278 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use minijinja::Value;
+use serde::Serialize;
+use validator::ValidationErrors;
+
+use crate::problem::collect_violations;
+
+/// Validation messages keyed by field, for re-rendering a form.
+///
+/// Templates read it as `errors.<field>`, a list of messages, next to the
+/// submitted `values`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct FormErrors(BTreeMap<String, Vec<String>>);
+
+impl From<&ValidationErrors> for FormErrors {
+    fn from(errors: &ValidationErrors) -> Self {
+        let mut violations = Vec::new();
+        collect_violations(errors, "", &mut violations);
+
+        let mut fields = BTreeMap::<String, Vec<String>>::new();
+        for violation in violations {
+            fields.entry(violation.field).or_default().push(violation.message);
+        }
+        FormErrors(fields)
+    }
+}
+
+impl FormErrors {
+    /// Adds an error found outside of validation, e.g. a taken email.
+    pub(crate) fn add(&mut self, field: &str, message: &str) {
+        self.0.entry(field.to_string()).or_default().push(message.to_string());
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn get(&self, field: &str) -> &[String] {
+        self.0.get(field).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Input types understood by the `form()` macro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(unused)]
+pub(crate) enum FieldKind {
+    Text,
+    Email,
+    Password,
+    Number,
+    Url,
+    Textarea,
+    Checkbox,
+    Hidden,
+}
+
+/// One form field with the HTML constraints that mirror its `#[validate]`
+/// attributes, so the browser rejects what the server would.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Field {
+    name: &'static str,
+    label: &'static str,
+    kind: FieldKind,
+    required: bool,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    pattern: Option<&'static str>,
+    help: Option<&'static str>,
+}
+
+#[allow(unused)]
+impl Field {
+    pub(crate) fn new(
+        name: &'static str,
+        label: &'static str,
+        kind: FieldKind,
+    ) -> Self {
+        Field {
+            name,
+            label,
+            kind,
+            required: false,
+            min_length: None,
+            max_length: None,
+            min: None,
+            max: None,
+            pattern: None,
+            help: None,
+        }
+    }
+
+    pub(crate) fn text(name: &'static str, label: &'static str) -> Self {
+        Field::new(name, label, FieldKind::Text)
+    }
+
+    pub(crate) fn email(name: &'static str, label: &'static str) -> Self {
+        Field::new(name, label, FieldKind::Email)
+    }
+
+    pub(crate) fn password(name: &'static str, label: &'static str) -> Self {
+        Field::new(name, label, FieldKind::Password)
+    }
+
+    pub(crate) fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Mirrors `#[validate(length(min, max))]`.
+    pub(crate) fn length(
+        mut self,
+        min: Option<u64>,
+        max: Option<u64>,
+    ) -> Self {
+        self.required |= min.is_some_and(|min| min > 0);
+        self.min_length = min;
+        self.max_length = max;
+        self
+    }
+
+    /// Mirrors `#[validate(range(min, max))]`.
+    pub(crate) fn range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    pub(crate) fn pattern(mut self, pattern: &'static str) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub(crate) fn help(mut self, help: &'static str) -> Self {
+        self.help = Some(help);
+        self
+    }
+}
+
+/// Declarative description of a form, rendered by the `form()` macro.
+#[derive(Debug, Clone)]
+pub(crate) struct FormSpec {
+    action: Cow<'static, str>,
+    submit: &'static str,
+    fields: Vec<Field>,
+}
+
+impl FormSpec {
+    pub(crate) fn new(action: impl Into<Cow<'static, str>>) -> Self {
+        FormSpec {
+            action: action.into(),
+            submit: "Submit",
+            fields: Vec::new(),
+        }
+    }
+
+    pub(crate) fn submit(mut self, label: &'static str) -> Self {
+        self.submit = label;
+        self
+    }
+
+    pub(crate) fn field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Template context for an empty form.
+    pub(crate) fn empty(&self) -> FormView<'_> {
+        self.bind(&(), &FormErrors::default())
+    }
+
+    /// Template context holding the submitted `values` and their `errors`.
+    pub(crate) fn bind<T: Serialize>(
+        &self,
+        values: &T,
+        errors: &FormErrors,
+    ) -> FormView<'_> {
+        let values = Value::from_serialize(values);
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let value = values
+                    .get_attr(field.name)
+                    .ok()
+                    .filter(|value| !value.is_undefined() && !value.is_none())
+                    .map(|value| value.to_string())
+                    .unwrap_or_default();
+                BoundField {
+                    field,
+                    value,
+                    errors: errors.get(field.name).to_vec(),
+                }
+            })
+            .collect();
+
+        FormView { action: &self.action, submit: self.submit, fields }
+    }
+}
+
+/// Types whose form is described once, next to their `#[validate]`
+/// attributes.
+pub(crate) trait FormDefinition {
+    fn form() -> FormSpec;
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FormView<'a> {
+    action: &'a str,
+    submit: &'static str,
+    fields: Vec<BoundField<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct BoundField<'a> {
+    #[serde(flatten)]
+    field: &'a Field,
+    value: String,
+    errors: Vec<String>,
+}
+
+/// Buffers the body of an url-encoded form so wrapping extractors can look
+/// at its fields before handing the request over to the inner extractor.
+///
+/// Returns the rebuilt request and the decoded fields, which are empty for
+/// any other content type.
+pub(crate) async fn peek_form_fields<S>(
+    req: Request,
+    state: &S,
+) -> Result<(Request, Vec<(String, String)>), Response>
+where
+    S: Send + Sync,
+{
+    let is_form = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.starts_with("application/x-www-form-urlencoded")
+        });
+    if !is_form {
+        return Ok((req, Vec::new()));
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes =
+        Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+    let fields = serde_urlencoded::from_bytes(&bytes).unwrap_or_default();
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), fields))
+}
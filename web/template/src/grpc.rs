@@ -0,0 +1,211 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    Router,
+    extract::Request,
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::Response,
+};
+use serde::Deserialize;
+use time::format_description::well_known::Rfc3339;
+use tonic::{Code, Status, metadata::MetadataMap, server::NamedService};
+use tonic_health::ServingStatus;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::helpers;
+use crate::jwt::TokenKind;
+use crate::state::AppState;
+use crate::users::User;
+
+mod proto {
+    tonic::include_proto!("users.v1");
+
+    pub(crate) const DESCRIPTOR: &[u8] =
+        tonic::include_file_descriptor_set!("descriptor");
+}
+
+use proto::users_server::{Users, UsersServer};
+
+/// Name the health service reports the whole process under.
+const PROCESS: &str = "";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GrpcSettings {
+    /// Serves the reflection service, describing the services to tools
+    /// like `grpcurl`.
+    pub(crate) reflection: bool,
+}
+
+/// Routes of the gRPC services, with the health service and, unless turned
+/// off, the reflection service. They are served over HTTP/2 on the main
+/// listener, outside the session and CSRF layers of the pages.
+///
+/// The health service reports them serving until the shutdown signal.
+pub(crate) fn routes(state: &Arc<AppState>) -> Router<Arc<AppState>> {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    tokio::spawn(async move {
+        reporter.set_serving::<UsersServer<UsersService>>().await;
+        reporter.set_service_status(PROCESS, ServingStatus::Serving).await;
+        helpers::shutdown_signal().await;
+        reporter.set_not_serving::<UsersServer<UsersService>>().await;
+        reporter.set_service_status(PROCESS, ServingStatus::NotServing).await;
+    });
+
+    let users = UsersServer::new(UsersService { state: state.clone() });
+    let mut router = Router::new()
+        .route_service(&path(&users), users)
+        .route_service(&path(&health), health);
+    if state.settings.grpc.reflection {
+        let reflection = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(proto::DESCRIPTOR)
+            .register_encoded_file_descriptor_set(
+                tonic_health::pb::FILE_DESCRIPTOR_SET,
+            )
+            .build_v1()
+            .expect("the descriptor sets are valid");
+        router = router.route_service(&path(&reflection), reflection);
+    }
+    router.layer(middleware::from_fn(track_metrics))
+}
+
+/// Route of the methods of `service`, e.g. `/users.v1.Users/{*rest}`.
+fn path<S: NamedService>(_: &S) -> String {
+    ["/", S::NAME, "/{*rest}"].concat()
+}
+
+/// Records the count and latency of the calls labelled by service, method
+/// and status code. Failed calls carry their code in the headers, the
+/// others end with an OK in the trailers.
+async fn track_metrics(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let (service, method) = req
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split_once('/')
+        .map(|(service, method)| (service.to_owned(), method.to_owned()))
+        .unwrap_or_default();
+
+    let response = next.run(req).await;
+
+    let code = status_code(response.headers());
+    // Unknown methods would add a series each.
+    let method =
+        if code == Code::Unimplemented { String::new() } else { method };
+    let labels = [
+        ("service", service),
+        ("method", method),
+        ("code", format!("{code:?}")),
+    ];
+    metrics::counter!("grpc_requests_total", &labels).increment(1);
+    metrics::histogram!("grpc_requests_duration_seconds", &labels)
+        .record(start.elapsed().as_secs_f64());
+    response
+}
+
+fn status_code(headers: &HeaderMap) -> Code {
+    headers
+        .get("grpc-status")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i32>().ok())
+        .map_or(Code::Ok, Code::from)
+}
+
+/// `users.v1.Users`, reading the users of [`AppState::users`].
+pub(crate) struct UsersService {
+    state: Arc<AppState>,
+}
+
+impl UsersService {
+    /// The user of the access token in the `authorization` metadata.
+    async fn caller(&self, metadata: &MetadataMap) -> Result<User, Status> {
+        let token = metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim())
+            .ok_or_else(|| Status::unauthenticated("Missing bearer token."))?;
+        let claims = self
+            .state
+            .jwt
+            .verify(token, TokenKind::Access)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| {
+                Status::unauthenticated("Invalid or expired token.")
+            })?;
+        self.state
+            .users
+            .find_by_id(claims.sub)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| {
+                Status::unauthenticated("Invalid or expired token.")
+            })
+    }
+}
+
+#[tonic::async_trait]
+impl Users for UsersService {
+    async fn get_user(
+        &self,
+        request: tonic::Request<proto::GetUserRequest>,
+    ) -> Result<tonic::Response<proto::User>, Status> {
+        let caller = self.caller(request.metadata()).await?;
+        let id = Uuid::parse_str(&request.get_ref().id)
+            .map_err(|_| Status::invalid_argument("The id is not a UUID."))?;
+        let allowed = caller.id == id
+            || self
+                .state
+                .settings
+                .rbac
+                .permissions(&caller)
+                .can("users.manage");
+        if !allowed {
+            return Err(Status::permission_denied(
+                "Reading other users needs the users.manage permission.",
+            ));
+        }
+
+        let user = self
+            .state
+            .users
+            .find_by_id(id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found("No such user."))?;
+        Ok(tonic::Response::new(proto::User {
+            id: user.id.to_string(),
+            name: user.name,
+            email: user.email,
+            email_verified: user.email_verified,
+            roles: user.roles,
+            created_at: user.created_at.format(&Rfc3339).map_err(internal)?,
+        }))
+    }
+}
+
+/// Logs `error` and answers with a bare `INTERNAL` status.
+fn internal(error: impl Into<anyhow::Error>) -> Status {
+    error!("{:#}", error.into());
+    Status::internal("Internal error.")
+}
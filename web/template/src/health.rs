@@ -0,0 +1,308 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow, ensure};
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::helpers::{self, BoxFuture};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct HealthSettings {
+    /// Milliseconds each check may take before it counts as failed.
+    pub(crate) timeout_ms: u64,
+    /// Directories whose file system must keep `min_free_mb` free.
+    pub(crate) disk_paths: Vec<PathBuf>,
+    pub(crate) min_free_mb: u64,
+    /// Seconds between the shutdown signal and closing the listener, while
+    /// `/readyz` already fails, for load balancers to stop routing to it.
+    pub(crate) drain_seconds: u64,
+}
+
+/// Dependency the application needs to serve requests, checked by
+/// `/readyz`, e.g. a database, a cache or the disk.
+pub(crate) trait HealthCheck: Send + Sync {
+    /// Key of the check in the `/readyz` body.
+    fn name(&self) -> &'static str;
+
+    /// Fails when the dependency is unusable. Cut short after
+    /// `health.timeout_ms`.
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Pings the Redis, Postgres or SQLite session store.
+pub(crate) struct Sessions;
+
+impl HealthCheck for Sessions {
+    fn name(&self) -> &'static str {
+        "sessions"
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(state.sessions.ping())
+    }
+}
+
+/// Pings the `[redis]` server, only checked when one is configured.
+pub(crate) struct Redis;
+
+impl HealthCheck for Redis {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let redis = state.redis.as_ref().context("no redis configured")?;
+            redis.ping().await
+        })
+    }
+}
+
+/// Runs a query on the primary database or one of its replicas, only
+/// checked when one is configured.
+pub(crate) struct Database {
+    name: &'static str,
+    replica: Option<usize>,
+}
+
+impl Database {
+    pub(crate) fn primary() -> Self {
+        Database { name: "database", replica: None }
+    }
+
+    /// The name is leaked, replicas are only registered on start.
+    pub(crate) fn replica(index: usize) -> Self {
+        let name = format!("database_replica_{index}").leak();
+        Database { name, replica: Some(index) }
+    }
+}
+
+impl HealthCheck for Database {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let db = state.db.as_ref().context("no database configured")?;
+            db.ping(self.replica).await
+        })
+    }
+}
+
+/// Fails while embedded migrations are not applied to the database, the
+/// queries of this version would run against an older schema.
+pub(crate) struct Migrations;
+
+impl HealthCheck for Migrations {
+    fn name(&self) -> &'static str {
+        "migrations"
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let db = state.db.as_ref().context("no database configured")?;
+            let pending = db.pending_migrations().await?;
+            ensure!(pending.is_empty(), "pending migrations {pending:?}");
+            Ok(())
+        })
+    }
+}
+
+/// Reaches the storage backend, or writes to the local one.
+pub(crate) struct Storage;
+
+impl HealthCheck for Storage {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move { Ok(state.storage.check().await?) })
+    }
+}
+
+/// Free space left on the file systems of `health.disk_paths`.
+pub(crate) struct DiskSpace;
+
+impl HealthCheck for DiskSpace {
+    fn name(&self) -> &'static str {
+        "disk"
+    }
+
+    fn check<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let settings = &state.settings.health;
+            let min_free = settings.min_free_mb * 1024 * 1024;
+            let paths = settings.disk_paths.clone();
+            let free = tokio::task::spawn_blocking(move || {
+                paths
+                    .into_iter()
+                    .map(|path| {
+                        let free = fs4::available_space(&path)
+                            .with_context(|| path.display().to_string())?;
+                        Ok((path, free))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .await??;
+            for (path, free) in free {
+                ensure!(
+                    free >= min_free,
+                    "{} has {} MB free, below {} MB",
+                    path.display(),
+                    free / 1024 / 1024,
+                    settings.min_free_mb
+                );
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckReport {
+    status: Status,
+    duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    status: Status,
+    /// Shutting down, the checks are skipped.
+    draining: bool,
+    checks: BTreeMap<&'static str, CheckReport>,
+}
+
+/// Liveness probe, answers as long as the process serves requests.
+pub(crate) async fn handler_livez() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe running every registered [`HealthCheck`] at once,
+/// `503` with the failed ones in the body when any fails.
+pub(crate) async fn handler_readyz(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    if state.draining.load(Ordering::Relaxed) {
+        let readiness = Readiness {
+            status: Status::Fail,
+            draining: true,
+            checks: BTreeMap::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(readiness));
+    }
+
+    let timeout = Duration::from_millis(state.settings.health.timeout_ms);
+    let mut checks = JoinSet::new();
+    for index in 0..state.health_checks.len() {
+        let state = state.clone();
+        checks.spawn(async move {
+            let check = &state.health_checks[index];
+            let start = Instant::now();
+            let result = tokio::time::timeout(timeout, check.check(&state))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(anyhow!("timed out after {timeout:?}"))
+                });
+            if let Err(e) = &result {
+                warn!(check = check.name(), "health check failed: {e:#}");
+            }
+            let report = CheckReport {
+                status: if result.is_ok() { Status::Ok } else { Status::Fail },
+                duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+                error: result.err().map(|e| format!("{e:#}")),
+            };
+            (check.name(), report)
+        });
+    }
+
+    let checks: BTreeMap<_, _> = checks.join_all().await.into_iter().collect();
+    let status = if checks.values().all(|check| check.status == Status::Ok) {
+        Status::Ok
+    } else {
+        Status::Fail
+    };
+    let code = match status {
+        Status::Ok => StatusCode::OK,
+        Status::Fail => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (code, Json(Readiness { status, draining: false, checks }))
+}
+
+/// Graceful shutdown trigger of the main server. Once the shutdown signal
+/// fires `/readyz` fails and the WebSocket connections and event streams are
+/// closed, so their clients reconnect elsewhere, but requests are still
+/// accepted for
+/// `health.drain_seconds`, or until a second signal, before the listener
+/// closes and in-flight requests are waited for.
+pub(crate) async fn drain(state: Arc<AppState>) {
+    helpers::shutdown_signal().await;
+    state.draining.store(true, Ordering::Relaxed);
+    state.notifications.close();
+    state.hub.close().await;
+
+    let period = Duration::from_secs(state.settings.health.drain_seconds);
+    if period.is_zero() {
+        return;
+    }
+    info!("draining for {period:?} before closing the listener");
+    tokio::select! {
+        () = tokio::time::sleep(period) => {}
+        () = helpers::shutdown_signal() => info!("drain cut short"),
+    }
+}
@@ -0,0 +1,74 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::HashMap, time::Duration};
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct HeartbeatSettings {
+    /// Seconds a ping may take.
+    timeout: u64,
+    /// Appended to the URL of a check when a run starts, so the monitor
+    /// notices runs that never finish.
+    start_suffix: String,
+    /// Appended to the URL of a check when a run fails.
+    fail_suffix: String,
+    /// Ping URL of each job or scheduled task, by name.
+    #[serde(default)]
+    checks: HashMap<String, String>,
+}
+
+/// Progress of a run reported to the monitor.
+pub(crate) enum Ping<'a> {
+    Start,
+    Success,
+    /// Sent with the error as body, shown by the monitor.
+    Failure(&'a anyhow::Error),
+}
+
+/// Reports the run of `check` to its heartbeat URL, healthchecks.io style.
+/// Checks without a URL are not monitored. A ping that fails is only
+/// logged, the monitor alerts on the missing one anyway.
+pub(crate) async fn ping(state: &AppState, check: &str, ping: Ping<'_>) {
+    let settings = &state.settings.heartbeat;
+    let Some(url) = settings.checks.get(check) else {
+        return;
+    };
+    let (url, body) = match ping {
+        Ping::Start => (format!("{url}{}", settings.start_suffix), None),
+        Ping::Success => (url.clone(), None),
+        Ping::Failure(e) => {
+            (format!("{url}{}", settings.fail_suffix), Some(format!("{e:#}")))
+        }
+    };
+
+    let request = state
+        .http
+        .post(&url)
+        .timeout(Duration::from_secs(settings.timeout))
+        .body(body.unwrap_or_default());
+    let result = match state.http.send(request).await {
+        Ok(response) => response.error_for_status().map_err(Into::into),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!(check, "could not ping the heartbeat: {e}");
+    }
+}
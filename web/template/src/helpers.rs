@@ -14,7 +14,69 @@
 // OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 //
 
+use std::{future::Future, net::SocketAddr, pin::Pin};
+
+use axum::http::{HeaderMap, header};
+use serde::Deserialize;
+use time::OffsetDateTime;
 use tokio::signal;
+use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+#[cfg(feature = "tokio-console")]
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry,
+    fmt::{self, MakeWriter},
+    registry::LookupSpan,
+    reload,
+};
+
+use crate::log_file::{self, LogFileSettings};
+
+#[cfg(all(feature = "tokio-console", not(tokio_unstable)))]
+compile_error!(
+    "the tokio-console feature needs RUSTFLAGS=\"--cfg tokio_unstable\""
+);
+
+/// Boxed future returned by the object safe traits of the application.
+pub(crate) type BoxFuture<'a, T> =
+    Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Path of the referring page when it belongs to the site at `site_url`,
+/// `/` otherwise, to redirect back after a form posted from any page.
+///
+/// Browsers read `//host` and `/\\host` as another host, so those are
+/// refused too.
+pub(crate) fn referer_path(site_url: &str, headers: &HeaderMap) -> String {
+    let site = site_url.trim_end_matches('/');
+    headers
+        .get(header::REFERER)
+        .and_then(|referer| referer.to_str().ok())
+        .and_then(|referer| referer.strip_prefix(site))
+        .filter(|path| {
+            path.starts_with('/')
+                && !path.starts_with("//")
+                && !path.starts_with("/\\")
+        })
+        .unwrap_or("/")
+        .to_string()
+}
+
+/// Compares secrets in a time independent of where they differ.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Microseconds since the epoch, how timestamps are stored in SQL columns
+/// both Postgres and SQLite understand.
+pub(crate) fn to_micros(at: OffsetDateTime) -> i64 {
+    (at.unix_timestamp_nanos() / 1000) as i64
+}
+
+pub(crate) fn from_micros(micros: i64) -> anyhow::Result<OffsetDateTime> {
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(i128::from(micros) * 1000)?)
+}
 
 pub(crate) async fn shutdown_signal() {
     let ctrl_c = async {
@@ -38,19 +100,151 @@ pub(crate) async fn shutdown_signal() {
     }
 }
 
-pub(crate) fn init_tracing() {
+#[derive(Debug, Deserialize)]
+pub(crate) struct LogSettings {
+    pub(crate) format: LogFormat,
+    /// `EnvFilter` directives used when `RUST_LOG` is unset, e.g.
+    /// `info,tower_http=debug`. Changeable at runtime on `/admin/log-level`.
+    pub(crate) filter: Option<String>,
+    /// Also write to a rotated file, in addition to stdout.
+    pub(crate) file: Option<LogFileSettings>,
+    /// Address tokio-console connects to, with the `tokio-console` feature.
+    #[cfg_attr(not(feature = "tokio-console"), allow(dead_code))]
+    pub(crate) console_addr: SocketAddr,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogFormat {
+    /// One line per event with its spans and fields.
+    #[default]
+    Full,
+    /// Like `full` with span fields only and shorter targets.
+    Compact,
+    /// Multi-line and colored, for reading in a terminal.
+    Pretty,
+    /// One JSON object per event with a timestamp, its fields and those of
+    /// every enclosing span, e.g. the request id, for log aggregation.
+    Json,
+}
+
+/// Subscriber under the filtered log output.
+#[cfg(feature = "tokio-console")]
+type LogBase = Layered<Box<dyn Layer<Registry> + Send + Sync>, Registry>;
+#[cfg(not(feature = "tokio-console"))]
+type LogBase = Registry;
+
+/// Handle to swap the filter of the log output while running.
+pub(crate) type LogFilter = reload::Handle<EnvFilter, LogBase>;
+
+/// Installs the subscriber. The returned guard flushes the log file when
+/// dropped, keep it until `main` returns.
+pub(crate) fn init_tracing(
+    settings: &LogSettings,
+) -> anyhow::Result<(LogFilter, Option<WorkerGuard>)> {
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| {
-                    format!(
-                        "{}=debug,tower_http=debug,axum=trace",
-                        env!("CARGO_CRATE_NAME")
-                    )
-                    .into()
-                }),
-        )
-        .with(tracing_subscriber::fmt::layer().without_time())
-        .init();
+
+    let filter = match (EnvFilter::try_from_default_env(), &settings.filter) {
+        (Ok(filter), _) => filter,
+        (Err(_), Some(directives)) => EnvFilter::try_new(directives)?,
+        (Err(_), None) => EnvFilter::new(format!(
+            "{}=debug,tower_http=debug,axum=trace",
+            env!("CARGO_CRATE_NAME")
+        )),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let (file_layer, guard) = match &settings.file {
+        Some(file) => {
+            let (writer, guard) = log_file::writer(file)?;
+            (Some(fmt_layer(file.format, writer, false)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    // Filters the log output only, the console layer needs the trace
+    // level spans of tokio whatever the filter.
+    let output = fmt_layer(settings.format, std::io::stdout, true)
+        .and_then(file_layer)
+        .with_filter(filter);
+
+    let registry = tracing_subscriber::registry();
+    #[cfg(feature = "tokio-console")]
+    let registry = registry.with(
+        console_subscriber::ConsoleLayer::builder()
+            .server_addr(settings.console_addr)
+            .spawn()
+            .boxed(),
+    );
+    registry.with(output).init();
+
+    Ok((handle, guard))
+}
+
+/// Handle of a filter bound to no subscriber, for the states built by the
+/// tests.
+#[cfg(test)]
+pub(crate) fn test_log_filter() -> LogFilter {
+    reload::Layer::new(EnvFilter::new("off")).1
+}
+
+/// Events in `format` written to `writer`. Terminal output is colored and
+/// leaves out the time, files get timestamps and no escape codes.
+fn fmt_layer<S, W>(
+    format: LogFormat,
+    writer: W,
+    terminal: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(terminal);
+    match format {
+        LogFormat::Full if terminal => layer.without_time().boxed(),
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact if terminal => {
+            layer.without_time().compact().boxed()
+        }
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty if terminal => layer.without_time().pretty().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const SITE: &str = "https://example.com";
+
+    fn referer(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::REFERER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn referer_path_keeps_pages_of_the_site() {
+        let headers = referer("https://example.com/blog?page=2");
+        assert_eq!(referer_path(SITE, &headers), "/blog?page=2");
+        let headers = referer("https://other.example/blog");
+        assert_eq!(referer_path(SITE, &headers), "/");
+    }
+
+    #[test]
+    fn referer_path_refuses_protocol_relative_paths() {
+        let headers = referer("https://example.com//evil.com");
+        assert_eq!(referer_path(SITE, &headers), "/");
+        let headers = referer("https://example.com/\\evil.com");
+        assert_eq!(referer_path(SITE, &headers), "/");
+    }
 }
@@ -0,0 +1,63 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use axum::{
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Redirect, Response},
+};
+use tracing::warn;
+
+use crate::form::peek_form_fields;
+
+/// Name of the hidden field rendered by the `honeypot()` template macro.
+pub(crate) const HONEYPOT_FIELD: &str = "website";
+
+/// Wraps the extractor `E` of an url-encoded form and rejects submissions
+/// that filled the hidden honeypot field.
+///
+/// Bots get a redirect back to the page, like a successful submission, so
+/// they have no reason to retry; the attempt is counted in the
+/// `honeypot_triggered_total` metric. Forms using it must call the
+/// `honeypot()` macro.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Honeypot<E>(pub E);
+
+impl<E, S> FromRequest<S> for Honeypot<E>
+where
+    E: FromRequest<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Response> {
+        let (req, fields) = peek_form_fields(req, state).await?;
+        let filled = fields
+            .iter()
+            .any(|(name, value)| name == HONEYPOT_FIELD && !value.is_empty());
+        if filled {
+            let path = req.uri().path().to_string();
+            warn!(path, "honeypot field filled, discarding submission");
+            metrics::counter!("honeypot_triggered_total", "path" => path.clone())
+                .increment(1);
+            return Err(Redirect::to(&path).into_response());
+        }
+
+        E::from_request(req, state)
+            .await
+            .map(Honeypot)
+            .map_err(IntoResponse::into_response)
+    }
+}
@@ -0,0 +1,274 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::{IntoUrl, Method, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::version::VERSION;
+
+/// Header marking a request as safe to send again, whatever its method.
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct HttpClientSettings {
+    /// Seconds for a whole request, unless the request sets its own.
+    pub(crate) timeout: u64,
+    /// Seconds to connect.
+    pub(crate) connect_timeout: u64,
+    /// Seconds an unused connection is kept open.
+    pub(crate) pool_idle_timeout: u64,
+    pub(crate) pool_max_idle_per_host: usize,
+    /// `<crate>/<version>` when unset.
+    pub(crate) user_agent: Option<String>,
+    /// Proxy of every request, e.g. `http://proxy.internal:3128`.
+    pub(crate) proxy: Option<String>,
+    /// Retries of a failed idempotent request.
+    pub(crate) max_retries: u32,
+    /// Milliseconds before the first retry, doubled for each following
+    /// one, plus up to as much again at random.
+    pub(crate) retry_backoff: u64,
+    /// Failures in a row opening the circuit of a host.
+    pub(crate) failure_threshold: u32,
+    /// Seconds an open circuit fails the requests to its host without
+    /// sending them, before letting one through to probe it.
+    pub(crate) open_duration: u64,
+}
+
+/// Builder of the clients of the application, with the timeouts, pool,
+/// user agent and proxy of `settings`.
+pub(crate) fn builder(
+    settings: &HttpClientSettings,
+) -> anyhow::Result<reqwest::ClientBuilder> {
+    let user_agent = settings
+        .user_agent
+        .clone()
+        .unwrap_or_else(|| format!("{}/{VERSION}", env!("CARGO_PKG_NAME")));
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(settings.timeout))
+        .connect_timeout(Duration::from_secs(settings.connect_timeout))
+        .pool_idle_timeout(Duration::from_secs(settings.pool_idle_timeout))
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host)
+        .user_agent(user_agent);
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    Ok(builder)
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum HttpError {
+    #[error("circuit open for {0}, not sending the request")]
+    CircuitOpen(String),
+
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// State of the circuit of a host.
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    /// Until when requests fail without being sent. Once past, the next
+    /// request probes the host and pushes it back for its duration.
+    open_until: Option<Instant>,
+}
+
+/// Client of the outgoing requests to external APIs, shared by handlers
+/// and jobs so they all time out, retry and back off the same way.
+///
+/// Requests are built with [`HttpClient::get`], [`HttpClient::post`] or
+/// [`HttpClient::request`] and sent with [`HttpClient::send`]. Those with
+/// an idempotent method or an `Idempotency-Key` header are retried on
+/// connection errors, timeouts and 429, 502, 503 and 504 responses. Each
+/// host has a circuit breaker: after `failure_threshold` failures in a row
+/// its requests fail right away for `open_duration`, then a single one
+/// probes it and closes the circuit when it succeeds.
+pub(crate) struct HttpClient {
+    client: reqwest::Client,
+    max_retries: u32,
+    retry_backoff: Duration,
+    failure_threshold: u32,
+    open_duration: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl HttpClient {
+    pub(crate) fn new(settings: &HttpClientSettings) -> anyhow::Result<Self> {
+        Ok(HttpClient {
+            client: builder(settings)?.build()?,
+            max_retries: settings.max_retries,
+            retry_backoff: Duration::from_millis(settings.retry_backoff),
+            failure_threshold: settings.failure_threshold,
+            open_duration: Duration::from_secs(settings.open_duration),
+            circuits: Mutex::default(),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub(crate) fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn request(
+        &self,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// Sends `request` through the circuit of its host, retrying it when it
+    /// is safe to. Like reqwest, error statuses are responses rather than
+    /// errors.
+    pub(crate) async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Response, HttpError> {
+        let mut request = request.build()?;
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let retryable = request.method().is_idempotent()
+            || request.headers().contains_key(IDEMPOTENCY_KEY);
+
+        let mut attempt = 0;
+        loop {
+            self.acquire(&host)?;
+            // Streamed bodies can not be sent twice.
+            let next = if retryable && attempt < self.max_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let result = self.client.execute(request).await;
+            let failed = match &result {
+                Ok(response) => {
+                    response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(_) => true,
+            };
+            self.record(&host, !failed);
+            let transient = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::TOO_MANY_REQUESTS
+                        | StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            match next {
+                Some(next) if transient => {
+                    let delay = self.backoff(attempt);
+                    attempt += 1;
+                    match &result {
+                        Ok(response) => debug!(
+                            host,
+                            attempt,
+                            "request answered {}, retrying in {delay:?}",
+                            response.status()
+                        ),
+                        Err(e) => debug!(
+                            host,
+                            attempt,
+                            "request failed, retrying in {delay:?}: {e}"
+                        ),
+                    }
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    /// Fails while the circuit of `host` is open, and lets a single
+    /// request through once it is time to probe it.
+    fn acquire(&self, host: &str) -> Result<(), HttpError> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(host.to_string()).or_default();
+        let Some(open_until) = circuit.open_until else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if now < open_until {
+            metrics::counter!(
+                "http_client_requests_total",
+                "host" => host.to_string(),
+                "outcome" => "open"
+            )
+            .increment(1);
+            return Err(HttpError::CircuitOpen(host.to_string()));
+        }
+        circuit.open_until = Some(now + self.open_duration);
+        Ok(())
+    }
+
+    fn record(&self, host: &str, succeeded: bool) {
+        let outcome = if succeeded { "success" } else { "failure" };
+        metrics::counter!(
+            "http_client_requests_total",
+            "host" => host.to_string(),
+            "outcome" => outcome
+        )
+        .increment(1);
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(host.to_string()).or_default();
+        if succeeded {
+            if circuit.open_until.is_some() {
+                info!(host, "circuit closed");
+            }
+            *circuit = Circuit::default();
+            return;
+        }
+        circuit.failures += 1;
+        if circuit.open_until.is_some()
+            || circuit.failures >= self.failure_threshold
+        {
+            warn!(
+                host,
+                failures = circuit.failures,
+                "circuit open for {:?}",
+                self.open_duration
+            );
+            circuit.open_until = Some(Instant::now() + self.open_duration);
+        }
+    }
+
+    /// Delay before retry `attempt`, counting from 0.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let base =
+            self.retry_backoff.saturating_mul(2u32.saturating_pow(attempt));
+        let mut bytes = [0; 8];
+        getrandom::fill(&mut bytes).expect("the system random source failed");
+        let millis = base.as_millis() as u64;
+        base + Duration::from_millis(u64::from_le_bytes(bytes) % (millis + 1))
+    }
+}
@@ -0,0 +1,209 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::{FromRequestParts, State},
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tower_sessions::Session;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::audit::Audit;
+use crate::auth::{CurrentUser, USER_ID_KEY};
+use crate::flash::Flash;
+use crate::rbac::RbacSettings;
+use crate::router::ServerError;
+use crate::session;
+use crate::state::AppState;
+use crate::sudo;
+use crate::users::User;
+
+/// Session key holding the id of the admin impersonating the session user.
+pub(crate) const IMPERSONATOR_KEY: &str = "auth.impersonator";
+
+/// The admin behind the session while they impersonate its user, added to
+/// the request extensions by `auth::load_user`.
+#[derive(Debug, Clone)]
+pub(crate) struct Impersonator(pub(crate) User);
+
+impl<S> FromRequestParts<S> for Impersonator
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Impersonator>()
+            .cloned()
+            .ok_or(StatusCode::BAD_REQUEST)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ImpersonateInput {
+    email: String,
+}
+
+/// Whether `admin` holds every role and permission of `user`, so acting as
+/// them grants nothing the admin could not already do.
+fn may_impersonate(rbac: &RbacSettings, admin: &User, user: &User) -> bool {
+    let held = rbac.permissions(admin);
+    user.roles.iter().all(|role| admin.roles.contains(role))
+        && rbac.permissions(user).0.iter().all(|granted| held.can(granted))
+}
+
+/// Switches the session to the user with the given email, remembering the
+/// admin so they can switch back. Guarded by `users.impersonate`, users
+/// with a role or permission the admin lacks are refused.
+pub(crate) async fn handler_impersonate(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    CurrentUser(admin): CurrentUser,
+    audit: Audit,
+    Form(input): Form<ImpersonateInput>,
+) -> Result<Response, ServerError> {
+    let impersonating = session
+        .get::<Uuid>(IMPERSONATOR_KEY)
+        .await
+        .map_err(anyhow::Error::from)?
+        .is_some();
+    if impersonating {
+        return Ok((StatusCode::CONFLICT, "Already impersonating a user")
+            .into_response());
+    }
+    let Some(user) = state.users.find_by_email(&input.email).await? else {
+        return Ok(
+            (StatusCode::NOT_FOUND, "No user with this email").into_response()
+        );
+    };
+    if user.id == admin.id {
+        return Ok(Redirect::to("/admin").into_response());
+    }
+    if !may_impersonate(&state.settings.rbac, &admin, &user) {
+        warn!(admin = %admin.id, user = %user.id, "impersonation refused");
+        return Ok((
+            StatusCode::FORBIDDEN,
+            "This user has rights you do not hold",
+        )
+            .into_response());
+    }
+
+    session::rotate(&session).await.map_err(anyhow::Error::from)?;
+    session
+        .insert(IMPERSONATOR_KEY, admin.id)
+        .await
+        .map_err(anyhow::Error::from)?;
+    session.insert(USER_ID_KEY, user.id).await.map_err(anyhow::Error::from)?;
+    sudo::revoke(&session).await?;
+    warn!(admin = %admin.id, user = %user.id, "impersonation started");
+    audit
+        .record(
+            "admin.impersonate",
+            Some(user.id.to_string()),
+            json!({ "email": user.email }),
+        )
+        .await;
+    Ok(Redirect::to("/").into_response())
+}
+
+/// Gives the session back to the impersonating admin.
+pub(crate) async fn handler_stop_impersonating(
+    session: Session,
+    Impersonator(admin): Impersonator,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+) -> Result<Redirect, ServerError> {
+    session::rotate(&session).await.map_err(anyhow::Error::from)?;
+    session
+        .insert(USER_ID_KEY, admin.id)
+        .await
+        .map_err(anyhow::Error::from)?;
+    session
+        .remove::<Uuid>(IMPERSONATOR_KEY)
+        .await
+        .map_err(anyhow::Error::from)?;
+    sudo::revoke(&session).await?;
+    info!(admin = %admin.id, user = %user.id, "impersonation stopped");
+    audit
+        .record("admin.impersonate_stop", Some(user.id.to_string()), json!({}))
+        .await;
+    flash.info(format!("You are no longer acting as {}.", user.name));
+    Ok(Redirect::to("/admin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn rbac() -> RbacSettings {
+        RbacSettings {
+            roles: BTreeMap::from([
+                ("admin".to_string(), vec!["*".to_string()]),
+                (
+                    "support".to_string(),
+                    vec!["admin.access".to_string(), "users.*".to_string()],
+                ),
+                ("editor".to_string(), vec!["posts.*".to_string()]),
+                ("member".to_string(), vec!["posts.view".to_string()]),
+            ]),
+            default_roles: vec!["member".to_string()],
+            admins: Vec::new(),
+        }
+    }
+
+    fn user(roles: &[&str]) -> User {
+        User {
+            id: Uuid::new_v4(),
+            name: "user".to_string(),
+            email: "user@example.com".to_string(),
+            password_hash: String::new(),
+            email_verified: true,
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            created_at: OffsetDateTime::now_utc(),
+            deletion_scheduled_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn refuses_users_with_more_rights() {
+        let rbac = rbac();
+        let support = user(&["support", "member"]);
+        assert!(!may_impersonate(&rbac, &support, &user(&["admin"])));
+        assert!(!may_impersonate(&rbac, &support, &user(&["editor"])));
+        assert!(may_impersonate(&rbac, &support, &user(&["member"])));
+        let admin = user(&["admin", "member"]);
+        assert!(may_impersonate(&rbac, &admin, &user(&["member"])));
+        assert!(!may_impersonate(&rbac, &admin, &user(&["editor"])));
+    }
+}
@@ -0,0 +1,338 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Path, Request},
+    http::{HeaderMap, StatusCode},
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, de::DeserializeOwned};
+use sha2::Sha256;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::cache::CacheKey;
+use crate::helpers::constant_time_eq;
+use crate::problem::Problem;
+use crate::state::AppState;
+use crate::webhooks;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct IncomingWebhookSettings {
+    /// Seconds a signed timestamp is accepted for, before and after now.
+    /// Requests seen within it are refused as replays.
+    pub(crate) tolerance: u64,
+    /// Senders by name, each posting to `/webhooks/<name>`.
+    #[serde(default)]
+    pub(crate) sources: HashMap<String, SourceSettings>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SourceSettings {
+    pub(crate) scheme: Scheme,
+    pub(crate) secret: String,
+}
+
+/// How a sender signs its requests.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Scheme {
+    /// `X-Hub-Signature-256: sha256=<hex HMAC-SHA256 of the body>`, with
+    /// the delivery id in `X-GitHub-Delivery`. GitHub signs no timestamp,
+    /// so only replays within the tolerance are caught.
+    Github,
+    /// `Stripe-Signature: t=<timestamp>,v1=<hex HMAC-SHA256 of
+    /// "<t>.<body>">`, several `v1` while a secret is rolled.
+    Stripe,
+    /// Standard Webhooks, as sent by `[webhooks]`: `webhook-id`,
+    /// `webhook-timestamp` and `webhook-signature`, see
+    /// [`webhooks::sign`]. The secret is the key as is.
+    Standard,
+}
+
+/// What a verified signature tells about the request.
+struct Signed {
+    /// Same for the same request sent again, different for a retry.
+    nonce: String,
+    timestamp: Option<i64>,
+}
+
+impl Scheme {
+    /// Checks the signature of `body`, the detail of the 401 when it does
+    /// not match.
+    fn verify(
+        self,
+        secret: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Signed, &'static str> {
+        let header = |name: &str| {
+            headers.get(name).and_then(|value| value.to_str().ok())
+        };
+        match self {
+            Scheme::Github => {
+                let signature = header("x-hub-signature-256")
+                    .and_then(|value| value.strip_prefix("sha256="))
+                    .ok_or("Missing X-Hub-Signature-256 header.")?;
+                let delivery = header("x-github-delivery")
+                    .ok_or("Missing X-GitHub-Delivery header.")?;
+                if !constant_time_eq(
+                    signature.as_bytes(),
+                    hmac_hex(secret, &[body]).as_bytes(),
+                ) {
+                    return Err("Invalid signature.");
+                }
+                Ok(Signed { nonce: delivery.to_string(), timestamp: None })
+            }
+            Scheme::Stripe => {
+                let header = header("stripe-signature")
+                    .ok_or("Missing Stripe-Signature header.")?;
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for part in header.split(',') {
+                    match part.trim().split_once('=') {
+                        Some(("t", t)) => timestamp = t.parse().ok(),
+                        Some(("v1", signature)) => signatures.push(signature),
+                        _ => {}
+                    }
+                }
+                let timestamp: i64 = timestamp
+                    .ok_or("Missing timestamp in Stripe-Signature.")?;
+                let expected = hmac_hex(
+                    secret,
+                    &[format!("{timestamp}.").as_bytes(), body],
+                );
+                let signature = signatures
+                    .into_iter()
+                    .find(|signature| {
+                        constant_time_eq(
+                            signature.as_bytes(),
+                            expected.as_bytes(),
+                        )
+                    })
+                    .ok_or("Invalid signature.")?;
+                Ok(Signed {
+                    nonce: format!("{timestamp}.{signature}"),
+                    timestamp: Some(timestamp),
+                })
+            }
+            Scheme::Standard => {
+                let id = header("webhook-id")
+                    .ok_or("Missing webhook-id header.")?;
+                let timestamp: i64 = header("webhook-timestamp")
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("Missing webhook-timestamp header.")?;
+                let signatures = header("webhook-signature")
+                    .ok_or("Missing webhook-signature header.")?;
+                let expected = webhooks::sign(secret, id, timestamp, body);
+                if !signatures.split_whitespace().any(|signature| {
+                    constant_time_eq(signature.as_bytes(), expected.as_bytes())
+                }) {
+                    return Err("Invalid signature.");
+                }
+                Ok(Signed {
+                    nonce: format!("{id}.{timestamp}"),
+                    timestamp: Some(timestamp),
+                })
+            }
+        }
+    }
+}
+
+/// Lowercase hex HMAC-SHA256 of the concatenated `parts`.
+fn hmac_hex(secret: &str, parts: &[&[u8]]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("hmac accepts keys of any size");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Checks the signature of a webhook `body` from `source`, its timestamp
+/// against `incoming_webhooks.tolerance` and that it was not seen before
+/// within it: the 401 or 409 problem otherwise.
+pub(crate) async fn verify(
+    state: &AppState,
+    source: &str,
+    scheme: Scheme,
+    secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), Problem> {
+    let tolerance = state.settings.incoming_webhooks.tolerance;
+    let unauthorized = |detail: &str| {
+        warn!(source, "webhook rejected: {detail}");
+        Problem::new(StatusCode::UNAUTHORIZED).detail(detail)
+    };
+    let signed = scheme.verify(secret, headers, body).map_err(unauthorized)?;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    if signed
+        .timestamp
+        .is_some_and(|timestamp| timestamp.abs_diff(now) > tolerance)
+    {
+        return Err(unauthorized("Timestamp out of tolerance."));
+    }
+    let key = CacheKey::<()>::scoped(
+        "incoming_webhook",
+        format!("{source}:{}", signed.nonce),
+    );
+    // A timestamp up to `tolerance` ahead stays acceptable for twice as
+    // long, the entry must outlive it.
+    let ttl = Duration::from_secs(2 * tolerance);
+    if !state.cache.insert_new(&key, (), ttl).await {
+        warn!(source, "webhook rejected: replayed");
+        return Err(Problem::new(StatusCode::CONFLICT)
+            .detail("Webhook already received."));
+    }
+    Ok(())
+}
+
+/// JSON body of a webhook posted to `/webhooks/{source}`, deserialized
+/// only once the signature of the raw body is checked against the scheme
+/// and secret of the source in `[incoming_webhooks.sources]`.
+///
+/// Unknown sources are rejected with a 404, bad signatures and timestamps
+/// outside `incoming_webhooks.tolerance` with a 401, and the same signed
+/// request seen again within the tolerance with a 409. Replays are told
+/// apart by this instance only.
+#[derive(Debug)]
+pub(crate) struct VerifiedWebhook<T> {
+    pub(crate) source: String,
+    pub(crate) payload: T,
+}
+
+impl<T> FromRequest<Arc<AppState>> for VerifiedWebhook<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = Problem;
+
+    async fn from_request(
+        req: Request,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let settings = &state.settings.incoming_webhooks;
+        let (mut parts, body) = req.into_parts();
+        let Path(source) =
+            Path::<String>::from_request_parts(&mut parts, state)
+                .await
+                .map_err(|rejection| {
+                    Problem::new(rejection.status())
+                        .detail(rejection.body_text())
+                })?;
+        let Some(source_settings) = settings.sources.get(&source) else {
+            return Err(Problem::new(StatusCode::NOT_FOUND)
+                .detail("Unknown webhook source."));
+        };
+        let headers = parts.headers.clone();
+        let body =
+            Bytes::from_request(Request::from_parts(parts, body), state)
+                .await
+                .map_err(|rejection| {
+                    Problem::new(rejection.status())
+                        .detail(rejection.body_text())
+                })?;
+
+        verify(
+            state,
+            &source,
+            source_settings.scheme,
+            &source_settings.secret,
+            &headers,
+            &body,
+        )
+        .await?;
+
+        let payload = serde_json::from_slice(&body).map_err(|e| {
+            Problem::new(StatusCode::UNPROCESSABLE_ENTITY)
+                .detail(format!("Invalid JSON body: {e}"))
+        })?;
+        Ok(VerifiedWebhook { source, payload })
+    }
+}
+
+/// Acknowledges the verified webhooks, to be replaced by what the
+/// application does with them.
+pub(crate) async fn handler_incoming_webhook(
+    webhook: VerifiedWebhook<serde_json::Value>,
+) -> StatusCode {
+    let kind = webhook
+        .payload
+        .get("type")
+        .or_else(|| webhook.payload.get("topic"))
+        .or_else(|| webhook.payload.get("action"))
+        .and_then(|kind| kind.as_str())
+        .unwrap_or("unknown");
+    info!(source = webhook.source, kind, "webhook received");
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+    use crate::helpers::test_log_filter;
+    use crate::settings::Settings;
+    use crate::state;
+
+    const SECRET: &str = "whsec_test";
+
+    fn stripe_headers(timestamp: i64, body: &[u8]) -> HeaderMap {
+        let signature =
+            hmac_hex(SECRET, &[format!("{timestamp}.").as_bytes(), body]);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "stripe-signature",
+            HeaderValue::from_str(&format!("t={timestamp},v1={signature}"))
+                .unwrap(),
+        );
+        headers
+    }
+
+    /// A delivery stamped `tolerance` ahead is still refused as a replay
+    /// once `tolerance` has passed, while its timestamp is acceptable.
+    #[tokio::test]
+    async fn refuses_replayed_future_delivery() {
+        let mut settings = Settings::new().unwrap();
+        settings.incoming_webhooks.tolerance = 2;
+        let (state, _jobs) =
+            state::build(settings, test_log_filter()).await.unwrap();
+        let body = br#"{"type":"ping"}"#;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let headers = stripe_headers(now + 2, body);
+
+        verify(&state, "stripe", Scheme::Stripe, SECRET, &headers, body)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        let replay =
+            verify(&state, "stripe", Scheme::Stripe, SECRET, &headers, body)
+                .await
+                .unwrap_err();
+        assert_eq!(replay.status, StatusCode::CONFLICT.as_u16());
+    }
+}
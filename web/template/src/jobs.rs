@@ -0,0 +1,130 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use tokio::sync::{Mutex, mpsc};
+use tracing::{error, info, warn};
+
+use crate::heartbeat::{self, Ping};
+use crate::helpers::BoxFuture;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JobSettings {
+    /// Jobs run concurrently.
+    pub(crate) workers: usize,
+    /// Jobs waiting for a worker before `push` waits too.
+    pub(crate) capacity: usize,
+    /// Runs of a failing job before it is dropped.
+    pub(crate) max_attempts: u32,
+    /// Seconds before the first retry, doubled for each following one.
+    pub(crate) retry_backoff: u64,
+}
+
+/// Work done outside of the request that asked for it, e.g. assembling a
+/// data export. Runs again on failure, so it must be safe to retry.
+pub(crate) trait Job: Send + Sync + 'static {
+    /// Name of the job in the logs.
+    fn name(&self) -> &'static str;
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+struct Queued {
+    job: Box<dyn Job>,
+    attempt: u32,
+}
+
+/// In-process job queue. Jobs live in memory, so the pending ones are lost
+/// on restart.
+pub(crate) struct JobQueue {
+    sender: mpsc::Sender<Queued>,
+}
+
+/// Receiving end of a [`JobQueue`], handed to [`spawn_workers`].
+pub(crate) struct JobReceiver(mpsc::Receiver<Queued>);
+
+impl JobQueue {
+    pub(crate) fn new(settings: &JobSettings) -> (Self, JobReceiver) {
+        let (sender, receiver) = mpsc::channel(settings.capacity);
+        (JobQueue { sender }, JobReceiver(receiver))
+    }
+
+    /// Queues `job`, waiting while the queue is full.
+    pub(crate) async fn push(&self, job: impl Job) -> anyhow::Result<()> {
+        self.sender
+            .send(Queued { job: Box::new(job), attempt: 1 })
+            .await
+            .map_err(|_| anyhow!("the job queue is closed"))
+    }
+}
+
+/// Starts `settings.workers` tasks running the queued jobs.
+pub(crate) fn spawn_workers(state: Arc<AppState>, receiver: JobReceiver) {
+    let receiver = Arc::new(Mutex::new(receiver.0));
+    for _ in 0..state.settings.jobs.workers {
+        let state = state.clone();
+        let receiver = receiver.clone();
+        tokio::spawn(async move {
+            loop {
+                let Some(queued) = receiver.lock().await.recv().await else {
+                    return;
+                };
+                run(&state, queued).await;
+            }
+        });
+    }
+}
+
+/// Runs `queued`, requeueing it on failure. The heartbeat of the job only
+/// hears of the failure once it is given up.
+async fn run(state: &Arc<AppState>, queued: Queued) {
+    let name = queued.job.name();
+    let attempt = queued.attempt;
+    heartbeat::ping(state, name, Ping::Start).await;
+    let Err(e) = queued.job.run(state).await else {
+        info!(job = name, attempt, "job done");
+        heartbeat::ping(state, name, Ping::Success).await;
+        return;
+    };
+
+    let settings = &state.settings.jobs;
+    if attempt >= settings.max_attempts {
+        error!(job = name, attempt, "job failed, giving up: {e:#}");
+        heartbeat::ping(state, name, Ping::Failure(&e)).await;
+        return;
+    }
+    let delay = settings
+        .retry_backoff
+        .saturating_mul(2u64.saturating_pow(attempt - 1));
+    warn!(job = name, attempt, "job failed, retrying in {delay}s: {e:#}");
+
+    // Wait outside of the worker so it keeps running other jobs.
+    let sender = state.jobs.sender.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+        let retry = Queued { job: queued.job, attempt: attempt + 1 };
+        if sender.send(retry).await.is_err() {
+            error!(job = name, "could not requeue the job");
+        }
+    });
+}
@@ -0,0 +1,494 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::bail;
+use axum::{
+    Json,
+    extract::{FromRequestParts, State},
+    http::{HeaderValue, StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::query;
+use time::OffsetDateTime;
+use tower_sessions_redis_store::fred::{
+    prelude::{Expiration, KeysInterface},
+    types::SetOptions,
+};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::audit::Audit;
+use crate::auth::{self, DUMMY_HASH};
+use crate::database::{Access, Database};
+use crate::helpers::BoxFuture;
+use crate::problem::{ApiError, PROBLEM_JSON, Problem, internal};
+use crate::redis::Redis;
+use crate::router::ValidatedJson;
+use crate::state::AppState;
+
+/// Shortest secret accepted for HS256.
+const MIN_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct JwtSettings {
+    /// Secret of at least 32 bytes signing the tokens with HS256.
+    pub(crate) secret: String,
+    /// Seconds an access token is accepted.
+    pub(crate) access_ttl: i64,
+    /// Seconds a refresh token can be exchanged for a new pair.
+    pub(crate) refresh_ttl: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TokenKind {
+    Access,
+    Refresh,
+}
+
+/// Claims of the tokens issued by [`Jwt`], and the extractor of the `/api`
+/// routes, taken from an `Authorization: Bearer` access token.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Claims {
+    /// Id of the user.
+    pub(crate) sub: Uuid,
+    pub(crate) iss: String,
+    pub(crate) iat: i64,
+    pub(crate) exp: i64,
+    /// Unique id of the token, the key of the denylist.
+    pub(crate) jti: Uuid,
+    pub(crate) kind: TokenKind,
+}
+
+/// Revoked tokens, by id.
+///
+/// A token is only kept until it expires, validation rejects it afterwards
+/// anyway.
+pub(crate) trait TokenDenylist: Send + Sync {
+    /// Denies `jti`, false when it already was. Checking and inserting is
+    /// one atomic step, so of two concurrent revocations only one wins.
+    fn revoke(
+        &self,
+        jti: Uuid,
+        exp: i64,
+    ) -> BoxFuture<'_, anyhow::Result<bool>>;
+
+    fn is_revoked(&self, jti: Uuid) -> BoxFuture<'_, anyhow::Result<bool>>;
+}
+
+/// Denylist shared by the instances: in Redis when there is a server, else
+/// in the database, else in process memory.
+pub(crate) fn denylist(
+    redis: Option<&Redis>,
+    db: Option<&Database>,
+) -> Box<dyn TokenDenylist> {
+    match (redis, db) {
+        (Some(redis), _) => Box::new(RedisDenylist { redis: redis.clone() }),
+        (None, Some(db)) => Box::new(SqlDenylist { db: db.clone() }),
+        (None, None) => Box::new(MemoryDenylist::default()),
+    }
+}
+
+/// Denylist kept in process memory, so a restart forgets revocations and
+/// every instance tracks its own.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryDenylist {
+    revoked: Mutex<HashMap<Uuid, i64>>,
+}
+
+impl TokenDenylist for MemoryDenylist {
+    fn revoke(
+        &self,
+        jti: Uuid,
+        exp: i64,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            let mut revoked = self.revoked.lock().unwrap();
+            revoked.retain(|_, exp| *exp > now);
+            Ok(revoked.insert(jti, exp).is_none())
+        })
+    }
+
+    fn is_revoked(&self, jti: Uuid) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(
+            async move { Ok(self.revoked.lock().unwrap().contains_key(&jti)) },
+        )
+    }
+}
+
+/// Denylist of `<namespace>:jwt:<jti>` keys, expiring with their token.
+pub(crate) struct RedisDenylist {
+    redis: Redis,
+}
+
+impl TokenDenylist for RedisDenylist {
+    fn revoke(
+        &self,
+        jti: Uuid,
+        exp: i64,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let set: Option<String> = self
+                .redis
+                .pool()
+                .set(
+                    self.redis.key("jwt", &jti.to_string()),
+                    "1",
+                    Some(Expiration::EXAT(exp)),
+                    Some(SetOptions::NX),
+                    false,
+                )
+                .await?;
+            Ok(set.is_some())
+        })
+    }
+
+    fn is_revoked(&self, jti: Uuid) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let count: i64 = self
+                .redis
+                .pool()
+                .exists(self.redis.key("jwt", &jti.to_string()))
+                .await?;
+            Ok(count > 0)
+        })
+    }
+}
+
+/// Denylist in the `revoked_tokens` table. Expired rows are deleted on the
+/// next revocation.
+pub(crate) struct SqlDenylist {
+    db: Database,
+}
+
+impl TokenDenylist for SqlDenylist {
+    fn revoke(
+        &self,
+        jti: Uuid,
+        exp: i64,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            let sql = "DELETE FROM revoked_tokens WHERE exp <= $1";
+            let purge =
+                query(sql).bind(now).execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("revoked_tokens.purge", sql, purge).await?;
+
+            let sql = "INSERT INTO revoked_tokens (jti, exp) VALUES ($1, $2) \
+                       ON CONFLICT (jti) DO NOTHING";
+            let insert = query(sql)
+                .bind(jti.to_string())
+                .bind(exp)
+                .execute(self.db.pool(Access::ReadWrite));
+            let result =
+                self.db.observe("revoked_tokens.revoke", sql, insert).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn is_revoked(&self, jti: Uuid) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            // The primary, a token revoked an instant ago must be seen.
+            let sql = "SELECT 1 FROM revoked_tokens WHERE jti = $1";
+            let query = query(sql)
+                .bind(jti.to_string())
+                .fetch_optional(self.db.pool(Access::ReadWrite));
+            let row = self
+                .db
+                .observe("revoked_tokens.is_revoked", sql, query)
+                .await?;
+            Ok(row.is_some())
+        })
+    }
+}
+
+/// Access and refresh tokens, shaped like an OAuth 2.0 token response.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct TokenPair {
+    pub(crate) access_token: String,
+    pub(crate) refresh_token: String,
+    pub(crate) token_type: &'static str,
+    /// Seconds before the access token expires.
+    pub(crate) expires_in: i64,
+}
+
+/// Issues and checks the tokens of the API.
+pub(crate) struct Jwt {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    validation: Validation,
+    issuer: String,
+    access_ttl: i64,
+    refresh_ttl: i64,
+    denylist: Box<dyn TokenDenylist>,
+}
+
+impl Jwt {
+    /// Tokens name `issuer`, the site URL, and are only accepted from it.
+    pub(crate) fn new(
+        settings: &JwtSettings,
+        issuer: &str,
+        denylist: Box<dyn TokenDenylist>,
+    ) -> anyhow::Result<Self> {
+        if settings.secret.len() < MIN_SECRET_LEN {
+            bail!("jwt.secret must have at least {MIN_SECRET_LEN} bytes");
+        }
+
+        let secret = settings.secret.as_bytes();
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+        Ok(Jwt {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            validation,
+            issuer: issuer.to_string(),
+            access_ttl: settings.access_ttl,
+            refresh_ttl: settings.refresh_ttl,
+            denylist,
+        })
+    }
+
+    fn sign(&self, user_id: Uuid, kind: TokenKind) -> anyhow::Result<String> {
+        let iat = OffsetDateTime::now_utc().unix_timestamp();
+        let ttl = match kind {
+            TokenKind::Access => self.access_ttl,
+            TokenKind::Refresh => self.refresh_ttl,
+        };
+        let claims = Claims {
+            sub: user_id,
+            iss: self.issuer.clone(),
+            iat,
+            exp: iat + ttl,
+            jti: Uuid::new_v4(),
+            kind,
+        };
+        Ok(encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)?)
+    }
+
+    /// Mints a fresh access and refresh token for `user_id`.
+    pub(crate) fn issue(&self, user_id: Uuid) -> anyhow::Result<TokenPair> {
+        Ok(TokenPair {
+            access_token: self.sign(user_id, TokenKind::Access)?,
+            refresh_token: self.sign(user_id, TokenKind::Refresh)?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl,
+        })
+    }
+
+    /// Claims of a well signed, unexpired token, regardless of its kind or
+    /// revocation.
+    fn decode(&self, token: &str) -> Option<Claims> {
+        decode::<Claims>(token, &self.decoding, &self.validation)
+            .ok()
+            .map(|data| data.claims)
+    }
+
+    /// Claims of a valid, unrevoked token of `kind`, `None` otherwise.
+    pub(crate) async fn verify(
+        &self,
+        token: &str,
+        kind: TokenKind,
+    ) -> anyhow::Result<Option<Claims>> {
+        let Some(claims) = self.decode(token).filter(|c| c.kind == kind)
+        else {
+            return Ok(None);
+        };
+        if self.denylist.is_revoked(claims.jti).await? {
+            return Ok(None);
+        }
+        Ok(Some(claims))
+    }
+
+    /// Denies the token until it expires, false when it already was.
+    pub(crate) async fn revoke(
+        &self,
+        claims: &Claims,
+    ) -> anyhow::Result<bool> {
+        self.denylist.revoke(claims.jti, claims.exp).await
+    }
+}
+
+/// 401 asking for a bearer token, as RFC 6750 describes.
+fn unauthorized(detail: &str) -> Response {
+    let mut response =
+        Problem::new(StatusCode::UNAUTHORIZED).detail(detail).into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+impl FromRequestParts<Arc<AppState>> for Claims {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(token) = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim())
+        else {
+            return Err(unauthorized("Missing bearer token."));
+        };
+
+        match state.jwt.verify(token, TokenKind::Access).await {
+            Ok(Some(claims)) => Ok(claims),
+            Ok(None) => Err(unauthorized("Invalid or expired token.")),
+            Err(e) => Err(internal(e)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub(crate) struct TokenRequest {
+    #[validate(email(message = "Must be a valid email"))]
+    email: String,
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    password: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct RevokeRequest {
+    token: String,
+}
+
+/// Exchanges an email and password for a token pair.
+#[utoipa::path(
+    post,
+    path = "/token",
+    tag = "auth",
+    request_body = TokenRequest,
+    responses(
+        (status = 200, body = TokenPair),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 422, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_token(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    ValidatedJson(input): ValidatedJson<TokenRequest>,
+) -> Result<Json<TokenPair>, ApiError> {
+    let user = state.users.find_by_email(&input.email).await?;
+    let hash = user
+        .as_ref()
+        .map_or_else(|| DUMMY_HASH.clone(), |user| user.password_hash.clone());
+    let valid = auth::verify_password(input.password, hash).await?;
+
+    let Some(user) = user.filter(|_| valid) else {
+        audit
+            .record(
+                "auth.login_failed",
+                Some(input.email.to_lowercase()),
+                json!({ "method": "api_token" }),
+            )
+            .await;
+        return Err(ApiError::Unauthorized("Invalid email or password."));
+    };
+    if state.settings.auth.require_verified_email && !user.email_verified {
+        return Err(ApiError::Forbidden(
+            "The email address is not verified yet.".to_string(),
+        ));
+    }
+
+    let pair = state.jwt.issue(user.id)?;
+    audit
+        .actor(user.id)
+        .record(
+            "auth.login",
+            Some(user.id.to_string()),
+            json!({ "method": "api_token" }),
+        )
+        .await;
+    Ok(Json(pair))
+}
+
+/// Trades a refresh token for a new pair. The refresh token is rotated, so
+/// each one works once.
+#[utoipa::path(
+    post,
+    path = "/token/refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, body = TokenPair),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_token_refresh(
+    State(state): State<Arc<AppState>>,
+    Json(input): Json<RefreshRequest>,
+) -> Result<Json<TokenPair>, ApiError> {
+    let expired = ApiError::Unauthorized("Invalid or expired token.");
+    let Some(claims) =
+        state.jwt.verify(&input.refresh_token, TokenKind::Refresh).await?
+    else {
+        return Err(expired);
+    };
+    // Deleted since the token was issued.
+    if state.users.find_by_id(claims.sub).await?.is_none() {
+        return Err(expired);
+    }
+
+    // Used by a concurrent refresh since it was verified.
+    if !state.jwt.revoke(&claims).await? {
+        return Err(expired);
+    }
+    let pair = state.jwt.issue(claims.sub)?;
+    Ok(Json(pair))
+}
+
+/// Revokes an access or refresh token. Unknown and invalid tokens are
+/// accepted too, as RFC 7009 asks.
+#[utoipa::path(
+    post,
+    path = "/token/revoke",
+    tag = "auth",
+    request_body = RevokeRequest,
+    responses((status = 204)),
+)]
+pub(crate) async fn handler_token_revoke(
+    State(state): State<Arc<AppState>>,
+    Json(input): Json<RevokeRequest>,
+) -> Result<StatusCode, ApiError> {
+    if let Some(claims) = state.jwt.decode(&input.token) {
+        state.jwt.revoke(&claims).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
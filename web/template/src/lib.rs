@@ -0,0 +1,176 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::net::SocketAddr;
+
+use tokio::net::TcpListener;
+use tracing::info;
+
+mod access_log;
+mod admin;
+mod api;
+mod api_key;
+mod audit;
+mod auth;
+mod banner;
+mod body_log;
+mod cache;
+mod captcha;
+mod consent;
+mod contact;
+mod crud;
+mod csrf;
+mod database;
+mod email;
+mod error_page;
+mod error_reporting;
+mod export;
+mod feed;
+mod flags;
+mod flash;
+mod form;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod heartbeat;
+mod helpers;
+mod honeypot;
+mod http_client;
+mod impersonation;
+mod incoming_webhook;
+mod jobs;
+mod jwt;
+mod log_file;
+mod mailer;
+mod media;
+mod meta;
+mod metric;
+mod newsletter;
+mod oidc;
+#[cfg(feature = "sea-orm")]
+mod orm;
+mod outbox;
+mod pagination;
+mod password_reset;
+mod payments;
+mod pdf;
+mod posts;
+mod preferences;
+mod privacy;
+mod problem;
+mod profiling;
+mod rate_limit;
+mod rbac;
+mod redis;
+mod robots;
+mod router;
+mod scheduler;
+mod search;
+mod seed;
+mod session;
+mod settings;
+mod shortener;
+mod signed_url;
+mod sitemap;
+mod slow_request;
+mod soft_delete;
+mod sse;
+mod state;
+mod storage;
+mod sudo;
+mod tenancy;
+mod theme;
+mod token;
+mod transaction;
+mod upload;
+mod users;
+mod verification;
+mod version;
+mod view;
+mod webhooks;
+mod websocket;
+mod worker;
+
+// TODO(msi): from config
+const LISTEN_ADDR: &str = "0.0.0.0:3000";
+
+/// Entry point of the `server` binary: serves the application, unless
+/// asked for the `migrate` or `seed` command.
+pub async fn server() -> anyhow::Result<()> {
+    let settings = settings::Settings::new()?;
+    let (log_filter, _log_guard) = helpers::init_tracing(&settings.log)?;
+
+    match std::env::args().nth(1).as_deref() {
+        None | Some("serve") => serve(settings, log_filter).await,
+        Some("migrate") => database::migrate(&settings.database).await,
+        Some("seed") => seed::run(&settings).await,
+        Some(command) => anyhow::bail!(
+            "unknown command `{command}`, expected `serve`, `migrate` or \
+             `seed`"
+        ),
+    }
+}
+
+/// Entry point of the `worker` binary, running the jobs and the background
+/// tasks, see `worker::run`.
+pub async fn worker() -> anyhow::Result<()> {
+    let settings = settings::Settings::new()?;
+    let (log_filter, _log_guard) = helpers::init_tracing(&settings.log)?;
+    worker::run(settings, log_filter).await
+}
+
+async fn serve(
+    settings: settings::Settings,
+    log_filter: helpers::LogFilter,
+) -> anyhow::Result<()> {
+    banner::log(&settings, "server", LISTEN_ADDR);
+    let _report_guard =
+        error_reporting::init(&settings.error_reporting, &settings.run_mode)?;
+    let metrics = settings.metrics.clone();
+
+    tokio::try_join!(
+        start_main_server(settings, log_filter),
+        metric::start_metrics_server(metrics)
+    )?;
+    Ok(())
+}
+
+async fn start_main_server(
+    settings: settings::Settings,
+    log_filter: helpers::LogFilter,
+) -> anyhow::Result<()> {
+    let (app_state, job_receiver) = state::build(settings, log_filter).await?;
+    jobs::spawn_workers(app_state.clone(), job_receiver);
+    if app_state.settings.worker.embedded {
+        worker::spawn_background(app_state.clone())?;
+    }
+
+    let app = router::route(app_state.clone());
+
+    let listener = TcpListener::bind(LISTEN_ADDR).await?;
+    info!("listening on http://{}", listener.local_addr().unwrap());
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(health::drain(app_state.clone()))
+    .await?;
+
+    if let Some(db) = &app_state.db {
+        db.close().await;
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing_appender::{
+    non_blocking::{NonBlocking, WorkerGuard},
+    rolling::{self, Rotation},
+};
+
+use crate::helpers::LogFormat;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LogFileSettings {
+    /// Directory of the log files, created when missing.
+    pub(crate) directory: PathBuf,
+    /// Name of the current file. Time rotated files get the date as a
+    /// suffix, size rotated ones a number, `.1` being the newest.
+    pub(crate) name: String,
+    pub(crate) format: LogFormat,
+    pub(crate) rotation: LogRotation,
+    /// Bytes after which a `size` rotated file rolls over.
+    pub(crate) max_size: u64,
+    /// Rotated files kept, older ones are deleted.
+    pub(crate) max_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogRotation {
+    Hourly,
+    Daily,
+    Size,
+    Never,
+}
+
+/// Writer appending to the log file from a background thread. Dropping
+/// the guard flushes the buffered lines, so it must live until `main`
+/// returns.
+pub(crate) fn writer(
+    settings: &LogFileSettings,
+) -> anyhow::Result<(NonBlocking, WorkerGuard)> {
+    fs::create_dir_all(&settings.directory)?;
+
+    let rotation = match settings.rotation {
+        LogRotation::Size => {
+            let file = SizeRotating::open(
+                settings.directory.join(&settings.name),
+                settings.max_size,
+                settings.max_files,
+            )?;
+            return Ok(tracing_appender::non_blocking(file));
+        }
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+
+    let appender = rolling::Builder::new()
+        .rotation(rotation)
+        .filename_prefix(&settings.name)
+        .max_log_files(settings.max_files.max(1))
+        .build(&settings.directory)?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// Appends to `path` until it would grow past `max_size`, then shifts
+/// `path.1` .. `path.{max_files - 1}` up by one, dropping the oldest, and
+/// moves the current file to `path.1`.
+struct SizeRotating {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl SizeRotating {
+    fn open(
+        path: PathBuf,
+        max_size: u64,
+        max_files: usize,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, max_size, max_files, file, size })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for n in (1..self.max_files).rev() {
+            let from = numbered(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, numbered(&self.path, n + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+        // Truncates the file when no rotated copy is kept.
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotating {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// `path` with `.n` appended, e.g. `app.log.1`.
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{n}"));
+    name.into()
+}
@@ -0,0 +1,182 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::HashMap, io::Cursor, sync::Arc};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use image::{DynamicImage, ImageFormat, imageops::FilterType};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::router::ServerError;
+use crate::signed_url::SignedUrl;
+use crate::state::AppState;
+use crate::storage;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct MediaSettings {
+    /// Derived sizes served at `/media/<id>/<variant>`.
+    pub(crate) variants: HashMap<String, Variant>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(crate) struct Variant {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Fill the whole box, cropping the overflow, instead of fitting the
+    /// image inside it.
+    #[serde(default)]
+    pub(crate) crop: bool,
+    pub(crate) format: Format,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Format {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl Format {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+            Format::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+/// Resizes and re-encodes `original` as `variant`. CPU bound, call it from
+/// a blocking task.
+pub(crate) fn process(
+    original: &[u8],
+    variant: Variant,
+) -> Result<Vec<u8>, image::ImageError> {
+    let image = image::load_from_memory(original)?;
+    let resized = if variant.crop {
+        image.resize_to_fill(
+            variant.width,
+            variant.height,
+            FilterType::Lanczos3,
+        )
+    } else {
+        image.thumbnail(variant.width, variant.height)
+    };
+    // JPEG has no alpha channel.
+    let resized = match variant.format {
+        Format::Jpeg => DynamicImage::ImageRgb8(resized.to_rgb8()),
+        _ => resized,
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    resized.write_to(&mut encoded, variant.format.image_format())?;
+    Ok(encoded.into_inner())
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+/// Serves the `variant` of the uploaded image `id`, generating and storing
+/// it on first request.
+pub(crate) async fn handler_media(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Response, ServerError> {
+    let Some(variant) = state.settings.media.variants.get(&name).copied()
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if !valid_id(&id) {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let stem = id.split('.').next().unwrap_or_default();
+    let extension = variant.format.image_format().extensions_str()[0];
+    let derived_key = format!("media/{name}/{stem}.{extension}");
+    let content_type = variant.format.image_format().to_mime_type();
+
+    let bytes = match state.storage.get(&derived_key).await {
+        Ok(bytes) => bytes.to_vec(),
+        Err(_) => {
+            let original =
+                match state.storage.get(&format!("uploads/{id}")).await {
+                    Ok(original) => original,
+                    Err(_) => return Ok(StatusCode::NOT_FOUND.into_response()),
+                };
+
+            let processed = tokio::task::spawn_blocking(move || {
+                process(&original, variant)
+            })
+            .await
+            .map_err(|e| anyhow!("image processing of {id} failed: {e}"))?;
+            let bytes = match processed {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    error!("could not process {id}: {error}");
+                    return Ok(
+                        StatusCode::UNPROCESSABLE_ENTITY.into_response()
+                    );
+                }
+            };
+
+            if let Err(error) =
+                state.storage.put(&derived_key, bytes.clone().into()).await
+            {
+                error!("could not store {derived_key}: {error}");
+            }
+            bytes
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Serves an upload as an attachment, only through the signed link shown
+/// after the upload.
+pub(crate) async fn handler_download(
+    State(state): State<Arc<AppState>>,
+    _: SignedUrl,
+    Path(id): Path<String>,
+) -> Response {
+    if !valid_id(&id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    storage::download(
+        state.storage.as_ref(),
+        &format!("uploads/{id}"),
+        "application/octet-stream",
+        &id,
+    )
+    .await
+}
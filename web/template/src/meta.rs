@@ -0,0 +1,76 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use serde::Serialize;
+
+use crate::settings::Site;
+
+/// Page metadata rendered into `<head>` by the `meta_tags` layout macro.
+///
+/// Handlers build it and pass it to the template context as `meta`:
+///
+/// ```ignore
+/// let meta = Meta::new(&state.settings.site, "About", "/about")
+///     .description("What this site is about");
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Meta {
+    site_name: String,
+    title: String,
+    description: Option<String>,
+    image: Option<String>,
+    canonical: String,
+    kind: &'static str,
+}
+
+impl Meta {
+    /// Metadata for the page served at `path` on `site`.
+    pub(crate) fn new(
+        site: &Site,
+        title: impl Into<String>,
+        path: &str,
+    ) -> Self {
+        Self {
+            site_name: site.name.clone(),
+            title: title.into(),
+            description: None,
+            image: None,
+            canonical: site.url_for(path),
+            kind: "website",
+        }
+    }
+
+    pub(crate) fn description(
+        mut self,
+        description: impl Into<String>,
+    ) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Absolute URL of the image used by social previews (`og:image`).
+    #[allow(unused)]
+    pub(crate) fn image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self
+    }
+
+    /// The `og:type` of the page, `website` by default.
+    pub(crate) fn kind(mut self, kind: &'static str) -> Self {
+        self.kind = kind;
+        self
+    }
+}
@@ -16,52 +16,523 @@
 
 use std::{
     future::ready,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
     time::{Duration, Instant},
 };
 
+use anyhow::Context;
 use axum::{
     Router,
-    extract::{MatchedPath, Request},
-    middleware::Next,
-    response::IntoResponse,
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::get,
+    serve::Listener,
 };
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use metrics_exporter_prometheus::{
     Matcher, PrometheusBuilder, PrometheusHandle,
 };
+use metrics_exporter_statsd::StatsdBuilder;
+use metrics_process::Collector;
+use serde::Deserialize;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        RootCertStore, ServerConfig,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::WebPkiClientVerifier,
+    },
+    server::TlsStream,
+};
 
 use crate::helpers;
+use crate::state::AppState;
+use crate::tenancy::{Resolver, Tenant};
+use crate::version;
+
+#[cfg(all(feature = "runtime-metrics", not(tokio_unstable)))]
+compile_error!(
+    "the runtime-metrics feature needs RUSTFLAGS=\"--cfg tokio_unstable\""
+);
+
+/// Path label of requests matching no route, so scanners probing random
+/// URLs do not add a series each.
+pub(crate) const UNMATCHED_PATH: &str = "unmatched";
+
+/// Buckets of the request latency histogram.
+const EXPONENTIAL_SECONDS: &[f64] =
+    &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Time a scraper gets to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Handshaken connections waiting for the server to take them.
+const HANDSHAKE_BACKLOG: usize = 16;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MetricsSettings {
+    #[serde(flatten)]
+    pub(crate) exporter: ExporterSettings,
+    /// Address of the Prometheus `/metrics` listener.
+    pub(crate) address: SocketAddr,
+    /// Credentials scrapers must send, for listeners reachable beyond the
+    /// host.
+    pub(crate) basic_auth: Option<BasicAuth>,
+    /// Serves `/metrics` over HTTPS, verifying client certificates when
+    /// `client_ca` is set.
+    pub(crate) tls: Option<MetricsTls>,
+    /// Path prefixes left out of the HTTP metrics, e.g. health checks and
+    /// static files.
+    pub(crate) exclude: Vec<String>,
+}
+
+impl MetricsSettings {
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|prefix| path.starts_with(prefix.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BasicAuth {
+    username: String,
+    password: String,
+}
+
+impl BasicAuth {
+    fn accepts(&self, authorization: Option<&HeaderValue>) -> bool {
+        let expected =
+            BASE64.encode(format!("{}:{}", self.username, self.password));
+        authorization
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Basic "))
+            .is_some_and(|given| {
+                helpers::constant_time_eq(
+                    given.trim().as_bytes(),
+                    expected.as_bytes(),
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MetricsTls {
+    /// PEM certificate chain and private key of the listener.
+    cert: PathBuf,
+    key: PathBuf,
+    /// PEM certificates of the CAs signing the client certificates of the
+    /// scrapers. Connections without one are refused.
+    client_ca: Option<PathBuf>,
+}
+
+impl MetricsTls {
+    fn acceptor(&self) -> anyhow::Result<TlsAcceptor> {
+        let certs = read_certs(&self.cert)?;
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .with_context(|| format!("reading {}", self.key.display()))?;
+
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+        let builder = match &self.client_ca {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(path)? {
+                    roots.add(cert)?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(roots),
+                    provider,
+                )
+                .build()?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder.with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+fn read_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect)
+        .with_context(|| format!("reading {}", path.display()))
+}
+
+/// Accepts the connections of a [`TcpListener`] completing the TLS
+/// handshake, dropping the ones that fail it.
+///
+/// Handshakes run in a task of their own, so a client stalling its
+/// handshake does not hold up the scrapes of the others.
+struct TlsListener {
+    handshaken: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
 
-pub(crate) async fn start_metrics_server() -> anyhow::Result<()> {
-    let app = metrics_app();
+impl TlsListener {
+    fn new(mut inner: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = inner.local_addr()?;
+        let (sender, handshaken) = mpsc::channel(HANDSHAKE_BACKLOG);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    accepted = Listener::accept(&mut inner) => accepted,
+                    () = sender.closed() => return,
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let handshake = acceptor.accept(stream);
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+                        .await
+                    {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => {
+                            tracing::warn!(
+                                "metrics TLS handshake from {addr}: {e}"
+                            );
+                        }
+                        Err(_) => {
+                            tracing::debug!(
+                                "metrics TLS handshake from {addr} timed out"
+                            );
+                        }
+                    }
+                });
+            }
+        });
+        Ok(TlsListener { handshaken, local_addr })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaken.recv().await {
+            Some(accepted) => accepted,
+            // The accepting task holds a sender for as long as the
+            // listener lives, this is not reached.
+            None => std::future::pending().await,
+        }
+    }
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3001").await?;
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "exporter", rename_all = "lowercase")]
+pub(crate) enum ExporterSettings {
+    /// Text format scraped from `/metrics` on 127.0.0.1:3001.
+    Prometheus,
+    /// Same text format pushed every `interval` seconds to a Prometheus
+    /// Pushgateway group, e.g. `http://127.0.0.1:9091/metrics/job/web`.
+    Pushgateway { endpoint: String, interval: u64 },
+    /// Sent over UDP to a statsd server as they are recorded, `prefix`
+    /// naming the application. The sampled gauges are sent every `interval`
+    /// seconds.
+    Statsd { host: String, port: u16, prefix: String, interval: u64 },
+    /// Pushed every `interval` seconds to an OTLP/HTTP collector, e.g.
+    /// `http://127.0.0.1:4318/v1/metrics`. Needs the `otlp` feature.
+    Otlp { endpoint: String, interval: u64 },
+}
+
+pub(crate) async fn start_metrics_server(
+    settings: MetricsSettings,
+) -> anyhow::Result<()> {
+    match settings.exporter {
+        ExporterSettings::Prometheus => {
+            serve_prometheus(
+                settings.address,
+                settings.basic_auth,
+                settings.tls.as_ref(),
+            )
+            .await
+        }
+        ExporterSettings::Pushgateway { endpoint, interval } => {
+            push_gateway(endpoint, Duration::from_secs(interval)).await
+        }
+        ExporterSettings::Statsd { host, port, prefix, interval } => {
+            push_statsd(&host, port, &prefix, Duration::from_secs(interval))
+                .await
+        }
+        ExporterSettings::Otlp { endpoint, interval } => {
+            push_otlp(endpoint, Duration::from_secs(interval)).await
+        }
+    }
+}
+
+async fn serve_prometheus(
+    address: SocketAddr,
+    basic_auth: Option<BasicAuth>,
+    tls: Option<&MetricsTls>,
+) -> anyhow::Result<()> {
+    let acceptor = tls.map(MetricsTls::acceptor).transpose()?;
+    let app = metrics_app(basic_auth);
+
+    let listener = TcpListener::bind(address).await?;
     tracing::info!("metrics listening on {}", listener.local_addr()?);
-    axum::serve(listener, app)
-        .with_graceful_shutdown(helpers::shutdown_signal())
-        .await?;
+    match acceptor {
+        Some(acceptor) => {
+            let listener = TlsListener::new(listener, acceptor)?;
+            axum::serve(listener, app)
+                .with_graceful_shutdown(helpers::shutdown_signal())
+                .await?;
+        }
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(helpers::shutdown_signal())
+                .await?;
+        }
+    }
 
     Ok(())
 }
 
-fn metrics_app() -> Router {
+fn metrics_app(basic_auth: Option<BasicAuth>) -> Router {
     let recorder_handle = setup_metrics_recorder();
-    Router::new()
-        .route("/metrics", get(move || ready(recorder_handle.render())))
+    let sampler = Sampler::new();
+    let app = Router::new().route(
+        "/metrics",
+        get(move || {
+            sampler.sample();
+            ready(recorder_handle.render())
+        }),
+    );
+    match basic_auth {
+        Some(auth) => app.layer(middleware::from_fn(move |req, next| {
+            require_basic_auth(auth.clone(), req, next)
+        })),
+        None => app,
+    }
 }
 
-fn setup_metrics_recorder() -> PrometheusHandle {
-    const EXPONENTIAL_SECONDS: &[f64] =
-        &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+async fn require_basic_auth(
+    auth: BasicAuth,
+    req: Request,
+    next: Next,
+) -> Response {
+    if auth.accepts(req.headers().get(header::AUTHORIZATION)) {
+        return next.run(req).await;
+    }
+    let challenge = HeaderValue::from_static("Basic realm=\"metrics\"");
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, challenge)])
+        .into_response()
+}
+
+/// Replaces the metrics of the Pushgateway group every `interval`, for
+/// batch jobs and hosts a scraper can not reach, and once more on shutdown
+/// for the last counts.
+async fn push_gateway(
+    endpoint: String,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    let recorder_handle = setup_metrics_recorder();
+    let sampler = Sampler::new();
+    let client = reqwest::Client::builder().timeout(interval).build()?;
+    let push = || async {
+        sampler.sample();
+        let result = client
+            .put(&endpoint)
+            .body(recorder_handle.render())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = result {
+            tracing::warn!("could not push metrics: {e}");
+        }
+    };
+    tracing::info!("pushing metrics every {interval:?}");
 
+    let mut ticker = tokio::time::interval(interval);
+    let shutdown = helpers::shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => push().await,
+            () = &mut shutdown => break,
+        }
+    }
+    push().await;
+    Ok(())
+}
+
+/// Sends every metric to statsd as it is recorded, histograms as timers.
+async fn push_statsd(
+    host: &str,
+    port: u16,
+    prefix: &str,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    let recorder = StatsdBuilder::from(host, port)
+        .histogram_is_timer()
+        .build(Some(prefix))?;
+    metrics::set_global_recorder(recorder)
+        .map_err(|_| anyhow::anyhow!("a metrics recorder is already set"))?;
+    record_build_info();
+    tracing::info!("sending metrics to statsd at {host}:{port}");
+
+    Sampler::new().sample_until_shutdown(interval).await;
+    Ok(())
+}
+
+/// Exports through the OpenTelemetry SDK, sampling the gauges right
+/// before each push, and flushes the last batch on shutdown.
+#[cfg(feature = "otlp")]
+async fn push_otlp(
+    endpoint: String,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    use metrics_exporter_otel::OpenTelemetryRecorder;
+    use opentelemetry::metrics::MeterProvider;
+
+    // The exporter's blocking HTTP client must be built and dropped off
+    // the runtime threads.
+    let provider = tokio::task::spawn_blocking(move || {
+        otlp_provider(&endpoint, interval)
+    })
+    .await??;
+
+    let recorder =
+        OpenTelemetryRecorder::new(provider.meter(env!("CARGO_PKG_NAME")));
+    recorder.set_histogram_bounds(
+        &metrics::KeyName::from("http_requests_duration_seconds"),
+        EXPONENTIAL_SECONDS.to_vec(),
+    );
+    metrics::set_global_recorder(recorder)
+        .map_err(|_| anyhow::anyhow!("a metrics recorder is already set"))?;
+    record_build_info();
+    tracing::info!("pushing metrics every {interval:?}");
+
+    Sampler::new().sample_until_shutdown(interval).await;
+
+    tokio::task::spawn_blocking(move || provider.shutdown()).await??;
+    Ok(())
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_provider(
+    endpoint: &str,
+    interval: Duration,
+) -> anyhow::Result<opentelemetry_sdk::metrics::SdkMeterProvider> {
+    use opentelemetry_otlp::{MetricExporter, WithExportConfig};
+    use opentelemetry_sdk::{
+        Resource,
+        metrics::{PeriodicReader, SdkMeterProvider},
+    };
+
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let reader =
+        PeriodicReader::builder(exporter).with_interval(interval).build();
+    let resource =
+        Resource::builder().with_service_name(env!("CARGO_PKG_NAME")).build();
+
+    Ok(SdkMeterProvider::builder()
+        .with_reader(reader)
+        .with_resource(resource)
+        .build())
+}
+
+#[cfg(not(feature = "otlp"))]
+async fn push_otlp(_: String, _: Duration) -> anyhow::Result<()> {
+    anyhow::bail!("metrics exporter \"otlp\" needs the otlp feature")
+}
+
+/// Gauges read from the process and the runtime rather than recorded as
+/// things happen, refreshed before each scrape or push.
+#[derive(Clone)]
+struct Sampler {
+    /// RSS, virtual memory, open file descriptors, CPU time and threads.
+    collector: Collector,
+    started: Instant,
+}
+
+impl Sampler {
+    /// Must run after the recorder is set for the descriptions to stick.
+    fn new() -> Self {
+        let collector = Collector::default();
+        collector.describe();
+        Self { collector, started: Instant::now() }
+    }
+
+    fn sample(&self) {
+        self.collector.collect();
+        metrics::gauge!("app_uptime_seconds")
+            .set(self.started.elapsed().as_secs_f64());
+        #[cfg(feature = "runtime-metrics")]
+        record_runtime_metrics(&tokio::runtime::Handle::current());
+    }
+
+    /// Samples every `interval` for recorders pushing on their own.
+    async fn sample_until_shutdown(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        let shutdown = helpers::shutdown_signal();
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => self.sample(),
+                () = &mut shutdown => break,
+            }
+        }
+    }
+}
+
+/// Saturation of the tokio runtime: workers, queued and alive tasks, and
+/// per worker busy time, polls and queue depth.
+#[cfg(feature = "runtime-metrics")]
+fn record_runtime_metrics(handle: &tokio::runtime::Handle) {
+    let runtime = handle.metrics();
+    metrics::gauge!("tokio_workers").set(runtime.num_workers() as f64);
+    metrics::gauge!("tokio_alive_tasks").set(runtime.num_alive_tasks() as f64);
+    metrics::gauge!("tokio_global_queue_depth")
+        .set(runtime.global_queue_depth() as f64);
+    metrics::gauge!("tokio_blocking_threads")
+        .set(runtime.num_blocking_threads() as f64);
+    metrics::gauge!("tokio_blocking_queue_depth")
+        .set(runtime.blocking_queue_depth() as f64);
+    metrics::counter!("tokio_spawned_tasks_total")
+        .absolute(runtime.spawned_tasks_count());
+
+    for worker in 0..runtime.num_workers() {
+        let labels = [("worker", worker.to_string())];
+        metrics::gauge!("tokio_worker_busy_seconds", &labels)
+            .set(runtime.worker_total_busy_duration(worker).as_secs_f64());
+        metrics::counter!("tokio_worker_polls_total", &labels)
+            .absolute(runtime.worker_poll_count(worker));
+        metrics::gauge!("tokio_worker_local_queue_depth", &labels)
+            .set(runtime.worker_local_queue_depth(worker) as f64);
+    }
+}
+
+fn setup_metrics_recorder() -> PrometheusHandle {
     let recorder_handle = PrometheusBuilder::new()
         .set_buckets_for_metric(
-            Matcher::Full("http_requests_duration_seconds".to_string()),
+            Matcher::Suffix("_seconds".to_string()),
             EXPONENTIAL_SECONDS,
         )
         .unwrap()
         .install_recorder()
         .unwrap();
+    record_build_info();
 
     let upkeep_handle = recorder_handle.clone();
     tokio::spawn(async move {
@@ -74,26 +545,121 @@ fn setup_metrics_recorder() -> PrometheusHandle {
     recorder_handle
 }
 
+/// Counts one `app_<name>_total` event, labelled by the `key = value`
+/// pairs, e.g. `count!("signup")` or `count!("upload", kind = mime)`.
+///
+/// Domain metrics go through this and [`time!`] so they share the `app_`
+/// prefix and the Prometheus suffixes, whatever the exporter. Names are
+/// snake_case nouns, labels take a bounded set of values: never ids, emails
+/// or raw paths.
+macro_rules! count {
+    ($name:literal $(,)?) => {
+        ::metrics::counter!(concat!("app_", $name, "_total")).increment(1)
+    };
+    ($name:literal $(, $key:ident = $value:expr)+ $(,)?) => {
+        ::metrics::counter!(
+            concat!("app_", $name, "_total"),
+            &[$((
+                stringify!($key),
+                ::std::string::ToString::to_string(&$value),
+            )),+]
+        )
+        .increment(1)
+    };
+}
+pub(crate) use count;
+
+/// Starts a [`Timer`] recording into the `app_<name>_seconds` histogram when
+/// dropped, e.g. `let _timer = time!("render", template = name);`.
+macro_rules! time {
+    ($name:literal $(,)?) => {
+        $crate::metric::Timer::new(::metrics::histogram!(concat!(
+            "app_", $name, "_seconds"
+        )))
+    };
+    ($name:literal $(, $key:ident = $value:expr)+ $(,)?) => {
+        $crate::metric::Timer::new(::metrics::histogram!(
+            concat!("app_", $name, "_seconds"),
+            &[$((
+                stringify!($key),
+                ::std::string::ToString::to_string(&$value),
+            )),+]
+        ))
+    };
+}
+pub(crate) use time;
+
+/// Records the time since it was started when dropped, see [`time!`].
+#[must_use = "the timer records when dropped"]
+pub(crate) struct Timer {
+    histogram: metrics::Histogram,
+    start: Instant,
+}
+
+impl Timer {
+    pub(crate) fn new(histogram: metrics::Histogram) -> Self {
+        Self { histogram, start: Instant::now() }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.histogram.record(self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Constant 1, the labels tell which build is running.
+fn record_build_info() {
+    metrics::gauge!(
+        "app_info",
+        "version" => version::VERSION,
+        "git_hash" => version::GIT_HASH,
+        "build_date" => version::BUILD_DATE,
+    )
+    .set(1.0);
+}
+
+/// `2xx`, `4xx`, ... for the `status` label.
+fn status_class(status: StatusCode) -> String {
+    format!("{}xx", status.as_u16() / 100)
+}
+
+/// Records the count and latency of requests labelled by route pattern,
+/// e.g. `/account/api-keys/{id}/revoke` rather than each id, method and
+/// status class.
 pub(crate) async fn track_metrics(
+    State(state): State<Arc<AppState>>,
     req: Request,
     next: Next,
 ) -> impl IntoResponse {
+    if state.settings.metrics.is_excluded(req.uri().path()) {
+        return next.run(req).await;
+    }
+
     let start = Instant::now();
-    let path =
-        if let Some(matched_path) = req.extensions().get::<MatchedPath>() {
-            matched_path.as_str().to_owned()
-        } else {
-            req.uri().path().to_owned()
-        };
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str)
+        .to_owned();
     let method = req.method().clone();
 
     let response = next.run(req).await;
 
     let latency = start.elapsed().as_secs_f64();
-    let status = response.status().as_u16().to_string();
+    let status = status_class(response.status());
 
-    let labels =
-        [("method", method.to_string()), ("path", path), ("status", status)];
+    let mut labels = vec![
+        ("method", method.to_string()),
+        ("path", path),
+        ("status", status),
+    ];
+    if state.settings.tenancy.resolver != Resolver::None {
+        let tenant = response.extensions().get::<Tenant>();
+        let tenant =
+            tenant.map(|tenant| tenant.id.clone()).unwrap_or_default();
+        labels.push(("tenant", tenant));
+    }
 
     metrics::counter!("http_requests_total", &labels).increment(1);
     metrics::histogram!("http_requests_duration_seconds", &labels)
@@ -0,0 +1,792 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::HashMap, sync::Arc, sync::RwLock};
+
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use axum_client_ip::ClientIp;
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{AnyConnection, Row, any::AnyRow, query};
+use time::OffsetDateTime;
+use tracing::{error, info};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::captcha::CaptchaVerified;
+use crate::crud::{self, Column, Fields, Resource};
+use crate::database::{Access, Database};
+use crate::form::{Field, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::honeypot::Honeypot;
+use crate::mailer;
+use crate::meta::Meta;
+use crate::metric;
+use crate::pagination::{Pagination, limit_offset};
+use crate::privacy::PersonalData;
+use crate::rate_limit::RateLimitSettings;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::token::UserToken;
+use crate::view::View;
+
+const CONFIRM_PURPOSE: &str = "newsletter-confirm";
+const UNSUBSCRIBE_PURPOSE: &str = "newsletter-unsubscribe";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct NewsletterSettings {
+    /// Seconds the link of the confirmation email stays valid.
+    pub(crate) confirm_token_ttl: i64,
+    /// Seconds an unsubscribe link stays valid, put in every email sent to
+    /// the subscribers.
+    pub(crate) unsubscribe_token_ttl: i64,
+    /// Signups allowed per client IP and per email.
+    pub(crate) rate_limit: RateLimitSettings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Subscriber {
+    pub(crate) id: Uuid,
+    pub(crate) email: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+    /// Set once the confirmation link was followed, only confirmed
+    /// subscribers are sent the newsletter.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) confirmed_at: Option<OffsetDateTime>,
+}
+
+/// Subscribers and the suppression list, the addresses never emailed
+/// again.
+pub(crate) trait SubscriberStore: Send + Sync {
+    /// Adds `email` as a pending subscriber, or returns the existing one.
+    fn subscribe<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Subscriber>>;
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Subscriber>>>;
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Subscriber>>>;
+
+    /// Subscribers from the most recently subscribed.
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Subscriber>>>;
+
+    /// Number of subscribers, confirmed or not.
+    fn count(&self) -> BoxFuture<'_, anyhow::Result<u64>>;
+
+    /// Marks the subscriber confirmed, keeping the first confirmation.
+    fn confirm(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Removes the subscriber of `email`, if any.
+    fn remove<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Removes the subscriber of `email` and adds it to the suppression
+    /// list for `reason`, e.g. `unsubscribed` or `bounced`.
+    fn suppress<'a>(
+        &'a self,
+        email: &'a str,
+        reason: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    fn is_suppressed<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+}
+
+/// Tables of the database, kept in memory without one.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn SubscriberStore> {
+    match db {
+        None => Box::new(MemorySubscriberStore::default()),
+        Some(db) => Box::new(SqlSubscriberStore { db: db.clone() }),
+    }
+}
+
+/// Subscribers lasting until the process exits, local to it.
+#[derive(Default)]
+pub(crate) struct MemorySubscriberStore {
+    subscribers: RwLock<HashMap<String, Subscriber>>,
+    /// Reasons by email.
+    suppressions: RwLock<HashMap<String, String>>,
+}
+
+impl SubscriberStore for MemorySubscriberStore {
+    fn subscribe<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Subscriber>> {
+        Box::pin(async move {
+            let mut subscribers = self.subscribers.write().unwrap();
+            let subscriber = subscribers
+                .entry(email.to_string())
+                .or_insert_with(|| Subscriber {
+                    id: Uuid::new_v4(),
+                    email: email.to_string(),
+                    created_at: OffsetDateTime::now_utc(),
+                    confirmed_at: None,
+                });
+            Ok(subscriber.clone())
+        })
+    }
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Subscriber>>> {
+        Box::pin(async move {
+            let subscribers = self.subscribers.read().unwrap();
+            Ok(subscribers.values().find(|s| s.id == id).cloned())
+        })
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Subscriber>>> {
+        Box::pin(async move {
+            Ok(self.subscribers.read().unwrap().get(email).cloned())
+        })
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Subscriber>>> {
+        Box::pin(async move {
+            let mut subscribers: Vec<Subscriber> =
+                self.subscribers.read().unwrap().values().cloned().collect();
+            subscribers.sort_by(|a, b| {
+                b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id))
+            });
+            Ok(pagination.apply(subscribers))
+        })
+    }
+
+    fn count(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(
+            async move { Ok(self.subscribers.read().unwrap().len() as u64) },
+        )
+    }
+
+    fn confirm(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut subscribers = self.subscribers.write().unwrap();
+            if let Some(subscriber) =
+                subscribers.values_mut().find(|s| s.id == id)
+            {
+                subscriber
+                    .confirmed_at
+                    .get_or_insert_with(OffsetDateTime::now_utc);
+            }
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.subscribers.write().unwrap().remove(email);
+            Ok(())
+        })
+    }
+
+    fn suppress<'a>(
+        &'a self,
+        email: &'a str,
+        reason: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.suppressions
+                .write()
+                .unwrap()
+                .entry(email.to_string())
+                .or_insert_with(|| reason.to_string());
+            self.subscribers.write().unwrap().remove(email);
+            Ok(())
+        })
+    }
+
+    fn is_suppressed<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            Ok(self.suppressions.read().unwrap().contains_key(email))
+        })
+    }
+}
+
+pub(crate) struct SqlSubscriberStore {
+    db: Database,
+}
+
+const SUBSCRIBER_COLUMNS: &str = "id, email, created_at, confirmed_at";
+
+fn subscriber_from_row(row: &AnyRow) -> anyhow::Result<Subscriber> {
+    Ok(Subscriber {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        email: row.try_get(1)?,
+        created_at: from_micros(row.try_get(2)?)?,
+        confirmed_at: row
+            .try_get::<Option<i64>, _>(3)?
+            .map(from_micros)
+            .transpose()?,
+    })
+}
+
+impl SubscriberStore for SqlSubscriberStore {
+    fn subscribe<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Subscriber>> {
+        Box::pin(async move {
+            let sql = "INSERT INTO newsletter_subscribers \
+                       (id, email, created_at) VALUES ($1, $2, $3) \
+                       ON CONFLICT (email) DO NOTHING";
+            let insert = query(sql)
+                .bind(Uuid::new_v4().to_string())
+                .bind(email)
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("newsletter.subscribe", sql, insert).await?;
+
+            // Read from the primary, a replica may not have the row yet.
+            let sql = format!(
+                "SELECT {SUBSCRIBER_COLUMNS} FROM newsletter_subscribers \
+                 WHERE email = $1"
+            );
+            let query = query(&sql)
+                .bind(email)
+                .fetch_one(self.db.pool(Access::ReadWrite));
+            let row = self
+                .db
+                .observe("newsletter.find_by_email", &sql, query)
+                .await?;
+            subscriber_from_row(&row)
+        })
+    }
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Subscriber>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {SUBSCRIBER_COLUMNS} FROM newsletter_subscribers \
+                 WHERE id = $1"
+            );
+            let query = query(&sql)
+                .bind(id.to_string())
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("newsletter.find", &sql, query).await?;
+            row.as_ref().map(subscriber_from_row).transpose()
+        })
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Subscriber>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {SUBSCRIBER_COLUMNS} FROM newsletter_subscribers \
+                 WHERE email = $1"
+            );
+            let query = query(&sql)
+                .bind(email)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self
+                .db
+                .observe("newsletter.find_by_email", &sql, query)
+                .await?;
+            row.as_ref().map(subscriber_from_row).transpose()
+        })
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Subscriber>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {SUBSCRIBER_COLUMNS} FROM newsletter_subscribers \
+                 ORDER BY created_at DESC, id {}",
+                limit_offset(1)
+            );
+            let query = query(&sql)
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64)
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("newsletter.list", &sql, query).await?;
+            rows.iter().map(subscriber_from_row).collect()
+        })
+    }
+
+    fn count(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let sql = "SELECT COUNT(*) FROM newsletter_subscribers";
+            let query = query(sql).fetch_one(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("newsletter.count", sql, query).await?;
+            Ok(row.try_get::<i64, _>(0)? as u64)
+        })
+    }
+
+    fn confirm(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "UPDATE newsletter_subscribers SET confirmed_at = $2 \
+                       WHERE id = $1 AND confirmed_at IS NULL";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("newsletter.confirm", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "DELETE FROM newsletter_subscribers WHERE email = $1";
+            let query = query(sql)
+                .bind(email)
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("newsletter.remove", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn suppress<'a>(
+        &'a self,
+        email: &'a str,
+        reason: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            // Suppressed first, so a failure in between never leaves the
+            // address emailed.
+            let sql = "INSERT INTO newsletter_suppressions \
+                       (email, reason, created_at) VALUES ($1, $2, $3) \
+                       ON CONFLICT (email) DO NOTHING";
+            let query = query(sql)
+                .bind(email)
+                .bind(reason)
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("newsletter.suppress", sql, query).await?;
+            self.remove(email).await
+        })
+    }
+
+    fn is_suppressed<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let sql = "SELECT 1 FROM newsletter_suppressions WHERE email = $1";
+            let query = query(sql)
+                .bind(email)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self
+                .db
+                .observe("newsletter.is_suppressed", sql, query)
+                .await?;
+            Ok(row.is_some())
+        })
+    }
+}
+
+/// Signs a `purpose` token for `subscriber`, bound to their email.
+fn token(
+    state: &AppState,
+    purpose: &str,
+    subscriber: &Subscriber,
+    ttl: i64,
+) -> String {
+    UserToken::issue(
+        state.settings.cookies.key.as_bytes(),
+        purpose,
+        subscriber.id,
+        ttl,
+        subscriber.email.as_bytes(),
+    )
+}
+
+/// Returns the subscriber of a valid, unexpired `purpose` token.
+async fn verify_token(
+    state: &AppState,
+    purpose: &str,
+    token: &str,
+) -> anyhow::Result<Option<Subscriber>> {
+    let Some(token) = UserToken::parse(token) else {
+        return Ok(None);
+    };
+    let subscriber = state.newsletter.find(token.user_id).await?;
+    Ok(subscriber.filter(|subscriber| {
+        token.verify(
+            state.settings.cookies.key.as_bytes(),
+            purpose,
+            subscriber.email.as_bytes(),
+        )
+    }))
+}
+
+/// Absolute unsubscribe link of `subscriber`, for the emails they are
+/// sent.
+pub(crate) fn unsubscribe_url(
+    state: &AppState,
+    subscriber: &Subscriber,
+) -> String {
+    let ttl = state.settings.newsletter.unsubscribe_token_ttl;
+    state.settings.site.url_for(&format!(
+        "/newsletter/unsubscribe?token={}",
+        token(state, UNSUBSCRIBE_PURPOSE, subscriber, ttl)
+    ))
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct SubscribeInput {
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+}
+
+impl FormDefinition for SubscribeInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/newsletter")
+            .submit("Subscribe")
+            .field(Field::email("email", "Email").required())
+    }
+}
+
+/// Renders the newsletter page in `step`: `form`, `sent`, `confirmed`,
+/// `unsubscribe`, `unsubscribed` or `invalid`.
+fn render(
+    state: &AppState,
+    view: &View,
+    step: &str,
+    ctx: minijinja::Value,
+) -> Html<String> {
+    view.render(
+        "newsletter",
+        context! {
+            title => "Newsletter",
+            meta => Meta::new(&state.settings.site, "Newsletter", "/newsletter"),
+            step => step,
+            ..ctx,
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_newsletter(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Html<String> {
+    let form = SubscribeInput::form();
+    render(&state, &view, "form", context! { form => form.empty() })
+}
+
+/// Emails a confirmation link to new and pending subscribers. The answer
+/// is the same for confirmed and suppressed addresses, which are sent
+/// nothing, so the form can not be used to probe the list.
+pub(crate) async fn handler_newsletter_post(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    view: View,
+    CaptchaVerified(Honeypot(Form(input))): CaptchaVerified<
+        Honeypot<Form<SubscribeInput>>,
+    >,
+) -> Result<Response, ServerError> {
+    let form = SubscribeInput::form();
+    let fail = |status: StatusCode, errors: &FormErrors| {
+        let ctx = context! { form => form.bind(&input, errors) };
+        (status, render(&state, &view, "form", ctx)).into_response()
+    };
+
+    if let Err(errors) = input.validate() {
+        return Ok(fail(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &FormErrors::from(&errors),
+        ));
+    }
+
+    let email = input.email.to_lowercase();
+    let allowed = state.newsletter_limiter.check(&format!("ip:{ip}")).await
+        && state.newsletter_limiter.check(&format!("email:{email}")).await;
+    if !allowed {
+        let mut errors = FormErrors::default();
+        errors.add("email", "Too many attempts, try again later");
+        return Ok(fail(StatusCode::TOO_MANY_REQUESTS, &errors));
+    }
+
+    if !state.newsletter.is_suppressed(&email).await? {
+        let subscriber = state.newsletter.subscribe(&email).await?;
+        if subscriber.confirmed_at.is_none() {
+            send_confirmation(&state, &subscriber).await;
+        }
+    }
+    Ok(render(&state, &view, "sent", context! {}).into_response())
+}
+
+async fn send_confirmation(state: &AppState, subscriber: &Subscriber) {
+    let ttl = state.settings.newsletter.confirm_token_ttl;
+    let url = state.settings.site.url_for(&format!(
+        "/newsletter/confirm?token={}",
+        token(state, CONFIRM_PURPOSE, subscriber, ttl)
+    ));
+    let ctx = context! {
+        url => url,
+        hours => ttl / 3600,
+        site => &state.settings.site.name,
+    };
+    let to = &subscriber.email;
+    match mailer::deliver(state, to, "newsletter_confirm", ctx).await {
+        Ok(()) => info!(subscriber = %subscriber.id, "newsletter signup"),
+        Err(e) => {
+            error!("could not send the newsletter confirmation: {e:#}");
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenQuery {
+    pub(crate) token: String,
+}
+
+/// Confirms the subscription of the link's subscriber.
+pub(crate) async fn handler_newsletter_confirm(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Query(query): Query<TokenQuery>,
+) -> Result<Response, ServerError> {
+    let subscriber =
+        verify_token(&state, CONFIRM_PURPOSE, &query.token).await?;
+    let Some(subscriber) = subscriber else {
+        let rendered = render(&state, &view, "invalid", context! {});
+        return Ok((StatusCode::BAD_REQUEST, rendered).into_response());
+    };
+
+    if subscriber.confirmed_at.is_none() {
+        state.newsletter.confirm(subscriber.id).await?;
+        info!(subscriber = %subscriber.id, "newsletter subscription confirmed");
+        metric::count!("newsletter_subscribed");
+    }
+    let ctx =
+        context! { unsubscribe_url => unsubscribe_url(&state, &subscriber) };
+    Ok(render(&state, &view, "confirmed", ctx).into_response())
+}
+
+/// Asks to confirm the unsubscription, mail scanners following the links
+/// of the emails must not unsubscribe anyone.
+pub(crate) async fn handler_newsletter_unsubscribe(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Query(query): Query<TokenQuery>,
+) -> Result<Response, ServerError> {
+    let subscriber =
+        verify_token(&state, UNSUBSCRIBE_PURPOSE, &query.token).await?;
+    if subscriber.is_none() {
+        let rendered = render(&state, &view, "invalid", context! {});
+        return Ok((StatusCode::BAD_REQUEST, rendered).into_response());
+    }
+    let ctx = context! { token => query.token };
+    Ok(render(&state, &view, "unsubscribe", ctx).into_response())
+}
+
+/// Unsubscribes the link's subscriber and suppresses their address.
+pub(crate) async fn handler_newsletter_unsubscribe_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Form(input): Form<TokenQuery>,
+) -> Result<Response, ServerError> {
+    let subscriber =
+        verify_token(&state, UNSUBSCRIBE_PURPOSE, &input.token).await?;
+    let Some(subscriber) = subscriber else {
+        let rendered = render(&state, &view, "invalid", context! {});
+        return Ok((StatusCode::BAD_REQUEST, rendered).into_response());
+    };
+
+    state.newsletter.suppress(&subscriber.email, "unsubscribed").await?;
+    info!(subscriber = %subscriber.id, "newsletter unsubscribed");
+    metric::count!("newsletter_unsubscribed");
+    Ok(render(&state, &view, "unsubscribed", context! {}).into_response())
+}
+
+/// The newsletter subscription of the user's email.
+pub(crate) struct Newsletter;
+
+impl PersonalData for Newsletter {
+    fn name(&self) -> &'static str {
+        "newsletter"
+    }
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        Box::pin(async move {
+            let Some(user) = state.users.find_by_id(user_id).await? else {
+                return Ok(Value::Null);
+            };
+            let email = user.email.to_lowercase();
+            let subscriber = state.newsletter.find_by_email(&email).await?;
+            Ok(serde_json::to_value(subscriber)?)
+        })
+    }
+
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if let Some(user) = state.users.find_by_id(user_id).await? {
+                let email = user.email.to_lowercase();
+                state.newsletter.remove(&email).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Subscribers on `/admin/subscribers`, where addresses which opted in
+/// elsewhere, e.g. on a paper form, are added already confirmed.
+pub(crate) struct SubscriberResource;
+
+impl Resource for SubscriberResource {
+    fn name(&self) -> &'static str {
+        "subscribers"
+    }
+
+    fn title(&self) -> &'static str {
+        "Newsletter subscribers"
+    }
+
+    fn singular(&self) -> &'static str {
+        "subscriber"
+    }
+
+    fn permission(&self) -> &'static str {
+        "newsletter.manage"
+    }
+
+    fn columns(&self) -> &'static [Column] {
+        &[
+            Column { key: "email", label: "Email" },
+            Column { key: "created_at", label: "Subscribed" },
+            Column { key: "confirmed_at", label: "Confirmed" },
+        ]
+    }
+
+    fn list<'a>(
+        &'a self,
+        state: &'a AppState,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Value>>> {
+        Box::pin(async move {
+            let subscribers = state.newsletter.list(pagination).await?;
+            subscribers
+                .iter()
+                .map(|subscriber| Ok(serde_json::to_value(subscriber)?))
+                .collect()
+        })
+    }
+
+    fn count<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        state.newsletter.count()
+    }
+
+    fn fields(&self, editing: bool) -> Option<Vec<Field>> {
+        (!editing).then(|| {
+            vec![
+                Field::email("email", "Email").required().help(
+                    "Added confirmed, only add addresses which opted in",
+                ),
+            ]
+        })
+    }
+
+    fn create<'a>(
+        &'a self,
+        state: &'a AppState,
+        _conn: Option<&'a mut AnyConnection>,
+        fields: &'a Fields,
+    ) -> BoxFuture<'a, anyhow::Result<Result<String, FormErrors>>> {
+        Box::pin(async move {
+            let input: SubscribeInput = match crud::parse(fields) {
+                Ok(input) => input,
+                Err(errors) => return Ok(Err(errors)),
+            };
+            let email = input.email.to_lowercase();
+            let mut errors = FormErrors::default();
+            if state.newsletter.is_suppressed(&email).await? {
+                errors.add("email", "Unsubscribed or bounced, never emailed");
+                return Ok(Err(errors));
+            }
+            if state.newsletter.find_by_email(&email).await?.is_some() {
+                errors.add("email", "Is already subscribed");
+                return Ok(Err(errors));
+            }
+            let subscriber = state.newsletter.subscribe(&email).await?;
+            state.newsletter.confirm(subscriber.id).await?;
+            Ok(Ok(subscriber.id.to_string()))
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        state: &'a AppState,
+        _conn: Option<&'a mut AnyConnection>,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let Ok(id) = id.parse() else {
+                return Ok(false);
+            };
+            let Some(subscriber) = state.newsletter.find(id).await? else {
+                return Ok(false);
+            };
+            state.newsletter.remove(&subscriber.email).await?;
+            Ok(true)
+        })
+    }
+}
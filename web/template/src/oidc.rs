@@ -0,0 +1,288 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context, anyhow};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, IssuerUrl, Nonce, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, TokenResponse,
+    core::{
+        CoreAuthenticationFlow, CoreClient, CoreIdTokenClaims,
+        CoreProviderMetadata,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tower_sessions::Session;
+use tracing::{info, warn};
+
+use crate::audit::Audit;
+use crate::auth::login_session;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::users::{NewUser, User};
+
+/// Client built from discovered metadata, whose token and user info
+/// endpoints are optional.
+type OidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// Session key holding the [`PendingLogin`] between redirect and callback.
+const PENDING_KEY: &str = "oidc.pending";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct OidcSettings {
+    /// Providers keyed by the name used in `/auth/oidc/<name>`.
+    pub(crate) providers: BTreeMap<String, OidcProvider>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct OidcProvider {
+    /// Text of the login button.
+    pub(crate) label: String,
+    /// Issuer serving `/.well-known/openid-configuration`.
+    pub(crate) issuer_url: String,
+    pub(crate) client_id: String,
+    pub(crate) client_secret: String,
+    /// Scopes asked on top of `openid`.
+    pub(crate) scopes: Vec<String>,
+    /// Claim used as the local user name.
+    pub(crate) name_claim: NameClaim,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NameClaim {
+    Name,
+    PreferredUsername,
+    GivenName,
+    Nickname,
+}
+
+/// Provider list exposed to templates as the `oidc_providers` global.
+#[derive(Debug, Serialize)]
+pub(crate) struct ProviderLink<'a> {
+    name: &'a str,
+    label: &'a str,
+}
+
+impl OidcSettings {
+    pub(crate) fn links(&self) -> Vec<ProviderLink<'_>> {
+        self.providers
+            .iter()
+            .map(|(name, provider)| ProviderLink {
+                name,
+                label: &provider.label,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PendingLogin {
+    provider: String,
+    state: String,
+    nonce: String,
+    pkce_verifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Callback {
+    code: String,
+    state: String,
+}
+
+/// Client for `name`, discovered from its issuer on every login so key
+/// rotations on the provider side are picked up.
+async fn client(
+    state: &AppState,
+    name: &str,
+) -> anyhow::Result<Option<OidcClient>> {
+    let Some(provider) = state.settings.oidc.providers.get(name) else {
+        return Ok(None);
+    };
+    let metadata = CoreProviderMetadata::discover_async(
+        IssuerUrl::new(provider.issuer_url.clone())?,
+        &state.oidc_http,
+    )
+    .await
+    .with_context(|| format!("could not discover the {name} provider"))?;
+    let redirect_url =
+        state.settings.site.url_for(&format!("/auth/oidc/{name}/callback"));
+    let client = CoreClient::from_provider_metadata(
+        metadata,
+        ClientId::new(provider.client_id.clone()),
+        Some(ClientSecret::new(provider.client_secret.clone())),
+    )
+    .set_redirect_uri(RedirectUrl::new(redirect_url)?);
+    Ok(Some(client))
+}
+
+/// Sends the browser to the provider's authorization endpoint.
+pub(crate) async fn handler_oidc_login(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    Path(name): Path<String>,
+) -> Result<Response, ServerError> {
+    let Some(client) = client(&state, &name).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let provider = &state.settings.oidc.providers[&name];
+
+    let (pkce_challenge, pkce_verifier) =
+        PkceCodeChallenge::new_random_sha256();
+    let mut request = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .set_pkce_challenge(pkce_challenge);
+    for scope in &provider.scopes {
+        request = request.add_scope(Scope::new(scope.clone()));
+    }
+    let (url, csrf_state, nonce) = request.url();
+
+    let pending = PendingLogin {
+        provider: name,
+        state: csrf_state.secret().clone(),
+        nonce: nonce.secret().clone(),
+        pkce_verifier: pkce_verifier.secret().clone(),
+    };
+    session.insert(PENDING_KEY, pending).await.map_err(anyhow::Error::from)?;
+    Ok(Redirect::to(url.as_str()).into_response())
+}
+
+/// Checks the ID token returned by the provider and logs its user in,
+/// creating the local account on first login.
+pub(crate) async fn handler_oidc_callback(
+    State(state): State<Arc<AppState>>,
+    session: Session,
+    audit: Audit,
+    Path(name): Path<String>,
+    Query(callback): Query<Callback>,
+) -> Result<Response, ServerError> {
+    let pending = session
+        .remove::<PendingLogin>(PENDING_KEY)
+        .await
+        .map_err(anyhow::Error::from)?;
+    let Some(pending) = pending.filter(|pending| {
+        pending.provider == name && pending.state == callback.state
+    }) else {
+        warn!(provider = name, "oidc callback without a matching login");
+        return Ok((StatusCode::BAD_REQUEST, "Login failed").into_response());
+    };
+    let Some(client) = client(&state, &name).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let token = client
+        .exchange_code(AuthorizationCode::new(callback.code))
+        .map_err(anyhow::Error::from)?
+        .set_pkce_verifier(PkceCodeVerifier::new(pending.pkce_verifier))
+        .request_async(&state.oidc_http)
+        .await
+        .map_err(|e| anyhow!("oidc code exchange failed: {e}"))?;
+    let id_token = token
+        .id_token()
+        .ok_or_else(|| anyhow!("{name} returned no ID token"))?;
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &Nonce::new(pending.nonce))
+        .map_err(|e| anyhow!("invalid ID token from {name}: {e}"))?;
+
+    let name_claim = state.settings.oidc.providers[&name].name_claim;
+    let Some(user) = local_user(&state, claims, name_claim).await? else {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            "The identity provider did not return a verified email",
+        )
+            .into_response());
+    };
+
+    info!(user = %user.id, provider = name, "oidc login");
+    login_session(&session, user.id).await?;
+    audit
+        .actor(user.id)
+        .record(
+            "auth.login",
+            Some(user.id.to_string()),
+            json!({ "method": "oidc", "provider": name }),
+        )
+        .await;
+    Ok(Redirect::to("/").into_response())
+}
+
+/// Maps the claims to the local user with the same email, or a new one.
+///
+/// Only verified emails are trusted, otherwise anyone able to set an
+/// arbitrary address at the provider could take over a local account.
+async fn local_user(
+    state: &AppState,
+    claims: &CoreIdTokenClaims,
+    name_claim: NameClaim,
+) -> Result<Option<User>, ServerError> {
+    let (Some(email), Some(true)) = (claims.email(), claims.email_verified())
+    else {
+        return Ok(None);
+    };
+    if let Some(user) = state.users.find_by_email(email).await? {
+        return Ok(Some(user));
+    }
+
+    let name = match name_claim {
+        NameClaim::Name => claims
+            .name()
+            .and_then(|name| name.get(None))
+            .map(|name| name.to_string()),
+        NameClaim::GivenName => claims
+            .given_name()
+            .and_then(|name| name.get(None))
+            .map(|name| name.to_string()),
+        NameClaim::Nickname => claims
+            .nickname()
+            .and_then(|name| name.get(None))
+            .map(|name| name.to_string()),
+        NameClaim::PreferredUsername => None,
+    }
+    .or_else(|| claims.preferred_username().map(|name| name.to_string()))
+    .unwrap_or_else(|| email.to_string());
+
+    let user = state
+        .users
+        .create(NewUser {
+            name,
+            email: email.to_string(),
+            // No local password, `verify_password` never accepts it.
+            password_hash: String::new(),
+            roles: state.settings.rbac.roles_for_new_user(email),
+        })
+        .await?;
+    state.users.mark_email_verified(user.id).await?;
+    Ok(state.users.find_by_id(user.id).await?)
+}
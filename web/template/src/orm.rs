@@ -0,0 +1,502 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectOptions,
+    DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, SqlErr,
+    TransactionTrait,
+    sea_query::{Expr, SimpleExpr},
+};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::sqlite::SqliteJournalMode;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::database::{
+    BackendSettings, DatabaseSettings, SQLITE_BUSY_TIMEOUT_MS, record_query,
+    sqlite_url,
+};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::outbox::NewEvent;
+use crate::pagination::Pagination;
+use crate::users::{
+    NewUser, USER_CREATED, USER_DELETED, USER_RESTORED, USER_TRASHED, User,
+    UserStore, UserStoreError,
+};
+
+/// The `users` table of the `0001_create_users` migration.
+mod user {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "users")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: String,
+        pub name: String,
+        pub email: String,
+        pub password_hash: String,
+        pub email_verified: i64,
+        pub roles: String,
+        pub created_at: i64,
+        pub deletion_scheduled_at: Option<i64>,
+        pub deleted_at: Option<i64>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// The `outbox` table of the `0004_create_outbox` migration.
+mod event {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "outbox")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: String,
+        pub topic: String,
+        pub payload: String,
+        pub created_at: i64,
+        pub attempts: i64,
+        pub available_at: i64,
+        pub published_at: Option<i64>,
+        pub last_error: Option<String>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Writes an outbox event on `txn`, the transaction of the change, like
+/// `outbox::record`.
+async fn record_event(
+    txn: &DatabaseTransaction,
+    topic: &'static str,
+    payload: &impl Serialize,
+) -> Result<(), UserStoreError> {
+    let event = NewEvent::new(topic, payload)?;
+    event::ActiveModel {
+        id: Set(event.id.to_string()),
+        topic: Set(event.topic.to_string()),
+        payload: Set(event.payload),
+        created_at: Set(event.created_at),
+        attempts: Set(0),
+        available_at: Set(event.created_at),
+        published_at: Set(None),
+        last_error: Set(None),
+    }
+    .insert(txn)
+    .await?;
+    Ok(())
+}
+
+/// `<table>.<verb>` label of a statement built by sea-orm, e.g.
+/// `users.select`, whose first quoted identifier is always the table.
+fn statement_name(sql: &str) -> String {
+    let verb = sql.split_whitespace().next().unwrap_or_default();
+    let table = sql.split('"').nth(1).unwrap_or("unknown");
+    format!("{table}.{}", verb.to_lowercase())
+}
+
+impl TryFrom<user::Model> for User {
+    type Error = anyhow::Error;
+
+    fn try_from(model: user::Model) -> anyhow::Result<Self> {
+        Ok(User {
+            id: Uuid::try_parse(&model.id)?,
+            name: model.name,
+            email: model.email,
+            password_hash: model.password_hash,
+            email_verified: model.email_verified != 0,
+            roles: serde_json::from_str(&model.roles)
+                .context("malformed user roles")?,
+            created_at: from_micros(model.created_at)?,
+            deletion_scheduled_at: model
+                .deletion_scheduled_at
+                .map(from_micros)
+                .transpose()?,
+            deleted_at: model.deleted_at.map(from_micros).transpose()?,
+        })
+    }
+}
+
+impl From<DbErr> for UserStoreError {
+    /// The email is the only unique column besides the id.
+    fn from(e: DbErr) -> Self {
+        match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                UserStoreError::EmailTaken
+            }
+            _ => UserStoreError::Backend(e.into()),
+        }
+    }
+}
+
+/// [`UserStore`] on sea-orm, reading and writing the same table as
+/// `SqlUserStore` through its own pool, sized by the `[database]` settings.
+/// Every query goes to the primary, replicas are left out.
+pub(crate) struct SeaOrmUserStore {
+    conn: DatabaseConnection,
+}
+
+impl SeaOrmUserStore {
+    pub(crate) async fn connect(
+        settings: &DatabaseSettings,
+    ) -> anyhow::Result<Self> {
+        let mut options = match &settings.backend {
+            BackendSettings::None => bail!("no database configured"),
+            BackendSettings::Postgres { url, .. } => ConnectOptions::new(url),
+            BackendSettings::Sqlite { path, wal } => {
+                let journal = if *wal {
+                    SqliteJournalMode::Wal
+                } else {
+                    SqliteJournalMode::Delete
+                };
+                let mut options = ConnectOptions::new(sqlite_url(path));
+                options.map_sqlx_sqlite_opts(move |options| {
+                    options
+                        .journal_mode(journal)
+                        .foreign_keys(true)
+                        .busy_timeout(Duration::from_millis(u64::from(
+                            SQLITE_BUSY_TIMEOUT_MS,
+                        )))
+                });
+                options
+            }
+        };
+        options
+            .max_connections(settings.max_connections)
+            .min_connections(settings.min_connections)
+            .acquire_timeout(Duration::from_secs(settings.acquire_timeout))
+            .idle_timeout(Duration::from_secs(settings.idle_timeout))
+            .max_lifetime(Duration::from_secs(settings.max_lifetime))
+            // Statements would be logged at the info level.
+            .sqlx_logging(false);
+        let mut conn = sea_orm::Database::connect(options)
+            .await
+            .context("connecting to the database")?;
+        let slow = Duration::from_millis(settings.slow_query_ms);
+        conn.set_metric_callback(move |info| {
+            let sql = &info.statement.sql;
+            record_query(
+                &statement_name(sql),
+                sql,
+                info.elapsed,
+                info.failed,
+                slow,
+            );
+        });
+        Ok(SeaOrmUserStore { conn })
+    }
+
+    async fn find_one(
+        &self,
+        condition: Condition,
+    ) -> Result<Option<User>, UserStoreError> {
+        let model =
+            user::Entity::find().filter(condition).one(&self.conn).await?;
+        Ok(model.map(User::try_from).transpose()?)
+    }
+
+    async fn list_where(
+        &self,
+        condition: Condition,
+        order: user::Column,
+        pagination: Pagination,
+    ) -> Result<Vec<User>, UserStoreError> {
+        let models = user::Entity::find()
+            .filter(condition)
+            .order_by_desc(order)
+            .order_by_asc(user::Column::Id)
+            .limit(pagination.limit() as u64)
+            .offset(pagination.offset() as u64)
+            .all(&self.conn)
+            .await?;
+        Ok(models
+            .into_iter()
+            .map(User::try_from)
+            .collect::<anyhow::Result<_>>()?)
+    }
+
+    /// Sets `deleted_at` on the user `id` when its trash state is
+    /// `trashed`, recording a `topic` event, false when there is no such
+    /// user.
+    async fn set_deleted_at(
+        &self,
+        id: Uuid,
+        trashed: bool,
+        deleted_at: Option<i64>,
+        topic: &'static str,
+    ) -> Result<bool, UserStoreError> {
+        let txn = self.conn.begin().await?;
+        let state = if trashed {
+            user::Column::DeletedAt.is_not_null()
+        } else {
+            user::Column::DeletedAt.is_null()
+        };
+        let result = user::Entity::update_many()
+            .col_expr(user::Column::DeletedAt, Expr::value(deleted_at))
+            .filter(user::Column::Id.eq(id.to_string()))
+            .filter(state)
+            .exec(&txn)
+            .await?;
+        let changed = result.rows_affected > 0;
+        if changed {
+            record_event(&txn, topic, &json!({ "id": id })).await?;
+        }
+        txn.commit().await?;
+        Ok(changed)
+    }
+
+    /// Sets `values` on the user `id`, a no-op when there is none.
+    async fn update(
+        &self,
+        id: Uuid,
+        values: Vec<(user::Column, SimpleExpr)>,
+    ) -> Result<(), UserStoreError> {
+        let mut update = user::Entity::update_many();
+        for (column, value) in values {
+            update = update.col_expr(column, value);
+        }
+        update
+            .filter(user::Column::Id.eq(id.to_string()))
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+}
+
+impl UserStore for SeaOrmUserStore {
+    fn find_by_id(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(
+            self.find_one(
+                Condition::all()
+                    .add(user::Column::Id.eq(id.to_string()))
+                    .add(user::Column::DeletedAt.is_null()),
+            ),
+        )
+    }
+
+    fn find_by_id_with_trashed(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(self.find_one(
+            Condition::all().add(user::Column::Id.eq(id.to_string())),
+        ))
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<Option<User>, UserStoreError>> {
+        Box::pin(
+            self.find_one(
+                Condition::all()
+                    .add(user::Column::Email.eq(email.to_lowercase()))
+                    .add(user::Column::DeletedAt.is_null()),
+            ),
+        )
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(self.list_where(
+            Condition::all().add(user::Column::DeletedAt.is_null()),
+            user::Column::CreatedAt,
+            pagination,
+        ))
+    }
+
+    fn list_trashed(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(self.list_where(
+            Condition::all().add(user::Column::DeletedAt.is_not_null()),
+            user::Column::DeletedAt,
+            pagination,
+        ))
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<u64, UserStoreError>> {
+        Box::pin(async move {
+            Ok(user::Entity::find()
+                .filter(user::Column::DeletedAt.is_null())
+                .count(&self.conn)
+                .await?)
+        })
+    }
+
+    fn create(
+        &self,
+        user: NewUser,
+    ) -> BoxFuture<'_, Result<User, UserStoreError>> {
+        Box::pin(async move {
+            let roles = serde_json::to_string(&user.roles)
+                .map_err(anyhow::Error::from)?;
+            let txn = self.conn.begin().await?;
+            let model = user::ActiveModel {
+                id: Set(Uuid::new_v4().to_string()),
+                name: Set(user.name),
+                email: Set(user.email.to_lowercase()),
+                password_hash: Set(user.password_hash),
+                email_verified: Set(0),
+                roles: Set(roles),
+                created_at: Set(to_micros(OffsetDateTime::now_utc())),
+                deletion_scheduled_at: Set(None),
+                deleted_at: Set(None),
+            }
+            .insert(&txn)
+            .await?;
+            let user = User::try_from(model)?;
+            let event = json!({ "id": user.id, "name": user.name, "email": user.email });
+            record_event(&txn, USER_CREATED, &event).await?;
+            txn.commit().await?;
+            Ok(user)
+        })
+    }
+
+    fn update_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        roles: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let roles =
+                serde_json::to_string(&roles).map_err(anyhow::Error::from)?;
+            self.update(
+                id,
+                vec![
+                    (user::Column::Name, Expr::value(name)),
+                    (user::Column::Roles, Expr::value(roles)),
+                ],
+            )
+            .await
+        })
+    }
+
+    fn update_password(
+        &self,
+        id: Uuid,
+        password_hash: String,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(self.update(
+            id,
+            vec![(user::Column::PasswordHash, Expr::value(password_hash))],
+        ))
+    }
+
+    fn mark_email_verified(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(self.update(
+            id,
+            vec![(user::Column::EmailVerified, Expr::value(1_i64))],
+        ))
+    }
+
+    fn update_email<'a>(
+        &'a self,
+        id: Uuid,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<(), UserStoreError>> {
+        Box::pin(self.update(
+            id,
+            vec![
+                (user::Column::Email, Expr::value(email.to_lowercase())),
+                (user::Column::EmailVerified, Expr::value(0_i64)),
+            ],
+        ))
+    }
+
+    fn schedule_deletion(
+        &self,
+        id: Uuid,
+        at: Option<OffsetDateTime>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(self.update(
+            id,
+            vec![(
+                user::Column::DeletionScheduledAt,
+                Expr::value(at.map(to_micros)),
+            )],
+        ))
+    }
+
+    fn due_for_deletion(
+        &self,
+        now: OffsetDateTime,
+    ) -> BoxFuture<'_, Result<Vec<Uuid>, UserStoreError>> {
+        Box::pin(async move {
+            let ids: Vec<String> = user::Entity::find()
+                .select_only()
+                .column(user::Column::Id)
+                .filter(user::Column::DeletionScheduledAt.lte(to_micros(now)))
+                .into_tuple()
+                .all(&self.conn)
+                .await?;
+            Ok(ids
+                .iter()
+                .map(|id| Uuid::try_parse(id))
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::from)?)
+        })
+    }
+
+    fn trash(&self, id: Uuid) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        let now = to_micros(OffsetDateTime::now_utc());
+        Box::pin(self.set_deleted_at(id, false, Some(now), USER_TRASHED))
+    }
+
+    fn restore(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(self.set_deleted_at(id, true, None, USER_RESTORED))
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let txn = self.conn.begin().await?;
+            let result =
+                user::Entity::delete_by_id(id.to_string()).exec(&txn).await?;
+            if result.rows_affected > 0 {
+                record_event(&txn, USER_DELETED, &json!({ "id": id })).await?;
+            }
+            txn.commit().await?;
+            Ok(())
+        })
+    }
+}
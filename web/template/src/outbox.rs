@@ -0,0 +1,276 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::{AnyConnection, Row, any::AnyRow, query};
+use time::OffsetDateTime;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::database::{Access, Backend, Database};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::jobs::Job;
+use crate::state::AppState;
+use crate::webhooks;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct OutboxSettings {
+    /// Seconds between two looks for pending events.
+    pub(crate) poll_interval: u64,
+    /// Events claimed by a look.
+    pub(crate) batch_size: i64,
+    /// Seconds a claimed event is left to its relay, after which another
+    /// one delivers it again.
+    pub(crate) lease: u64,
+    /// Deliveries of an event before it is given up.
+    pub(crate) max_attempts: i64,
+    /// Seconds before the first retry, doubled for each following one.
+    pub(crate) retry_backoff: u64,
+    /// Days the published events are kept.
+    pub(crate) retention_days: u64,
+}
+
+/// Row of an event about to be written, see [`record`].
+pub(crate) struct NewEvent {
+    pub(crate) id: Uuid,
+    pub(crate) topic: &'static str,
+    /// JSON object.
+    pub(crate) payload: String,
+    /// Microseconds since the epoch.
+    pub(crate) created_at: i64,
+}
+
+impl NewEvent {
+    pub(crate) fn new(
+        topic: &'static str,
+        payload: &impl Serialize,
+    ) -> anyhow::Result<Self> {
+        Ok(NewEvent {
+            id: Uuid::new_v4(),
+            topic,
+            payload: serde_json::to_string(payload)?,
+            created_at: to_micros(OffsetDateTime::now_utc()),
+        })
+    }
+}
+
+/// Event read back by the relay, as POSTed to the webhooks.
+#[derive(Debug, Serialize)]
+pub(crate) struct Event {
+    pub(crate) id: Uuid,
+    pub(crate) topic: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+    pub(crate) payload: serde_json::Value,
+}
+
+/// Writes an event about a change on `conn`, which should be the
+/// transaction of the change: the event is published if and only if the
+/// change is committed, whatever crashes in between.
+pub(crate) async fn record(
+    db: &Database,
+    conn: &mut AnyConnection,
+    topic: &'static str,
+    payload: &impl Serialize,
+) -> anyhow::Result<()> {
+    let event = NewEvent::new(topic, payload)?;
+    let sql = "INSERT INTO outbox \
+               (id, topic, payload, created_at, attempts, available_at) \
+               VALUES ($1, $2, $3, $4, 0, $4)";
+    let query = query(sql)
+        .bind(event.id.to_string())
+        .bind(event.topic)
+        .bind(event.payload)
+        .bind(event.created_at)
+        .execute(conn);
+    db.observe("outbox.record", sql, query).await?;
+    Ok(())
+}
+
+/// In-process consumer of the events of some topics, run by the job
+/// queue.
+pub(crate) trait Subscriber: Send + Sync + 'static {
+    /// Name of the job in the logs.
+    fn name(&self) -> &'static str;
+
+    fn topics(&self) -> &'static [&'static str];
+
+    fn handle<'a>(
+        &'a self,
+        state: &'a AppState,
+        event: &'a Event,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+struct EventJob {
+    subscriber: Arc<dyn Subscriber>,
+    event: Arc<Event>,
+}
+
+impl Job for EventJob {
+    fn name(&self) -> &'static str {
+        self.subscriber.name()
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.subscriber.handle(state, &self.event)
+    }
+}
+
+/// Starts the relay publishing the recorded events, every
+/// `outbox.poll_interval` seconds, when there is a database. Events go to
+/// the subscribers and webhooks at least once, again when queueing them
+/// failed, so they should skip the event ids they already handled.
+pub(crate) fn spawn_relay(state: Arc<AppState>) {
+    let Some(db) = state.db.clone() else {
+        return;
+    };
+    let period = Duration::from_secs(state.settings.outbox.poll_interval);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = relay(&state, &db).await {
+                error!("outbox relay failed: {e:#}");
+            }
+        }
+    });
+}
+
+async fn relay(state: &AppState, db: &Database) -> anyhow::Result<()> {
+    let settings = &state.settings.outbox;
+    for (event, attempts) in claim(state, db).await? {
+        let event = Arc::new(event);
+        let Err(e) = publish(state, &event).await else {
+            info!(id = %event.id, topic = event.topic, "event published");
+            let sql = "UPDATE outbox SET published_at = $2, last_error = NULL \
+                       WHERE id = $1";
+            let query = query(sql)
+                .bind(event.id.to_string())
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(db.pool(Access::ReadWrite));
+            db.observe("outbox.published", sql, query).await?;
+            continue;
+        };
+
+        let delay = settings
+            .retry_backoff
+            .saturating_mul(2u64.saturating_pow(attempts as u32 - 1));
+        if attempts >= settings.max_attempts {
+            error!(
+                id = %event.id,
+                topic = event.topic,
+                attempts,
+                "event not published, giving up: {e:#}"
+            );
+        } else {
+            warn!(
+                id = %event.id,
+                topic = event.topic,
+                attempts,
+                "event not published, retrying in {delay}s: {e:#}"
+            );
+        }
+        let sql = "UPDATE outbox SET available_at = $2, last_error = $3 \
+                   WHERE id = $1";
+        let retry_at = OffsetDateTime::now_utc() + Duration::from_secs(delay);
+        let query = query(sql)
+            .bind(event.id.to_string())
+            .bind(to_micros(retry_at))
+            .bind(format!("{e:#}"))
+            .execute(db.pool(Access::ReadWrite));
+        db.observe("outbox.failed", sql, query).await?;
+    }
+
+    let retention = Duration::from_secs(settings.retention_days * 86400);
+    let sql = "DELETE FROM outbox WHERE published_at < $1";
+    let query = query(sql)
+        .bind(to_micros(OffsetDateTime::now_utc() - retention))
+        .execute(db.pool(Access::ReadWrite));
+    db.observe("outbox.purge", sql, query).await?;
+    Ok(())
+}
+
+/// Claims the next batch of pending events, with their attempts counting
+/// this one, by pushing their `available_at` past the lease. Postgres
+/// skips the rows other relays are claiming, SQLite runs one write at a
+/// time.
+async fn claim(
+    state: &AppState,
+    db: &Database,
+) -> anyhow::Result<Vec<(Event, i64)>> {
+    let settings = &state.settings.outbox;
+    let lock = match db.backend() {
+        Backend::Postgres => "FOR UPDATE SKIP LOCKED",
+        Backend::Sqlite => "",
+    };
+    let sql = format!(
+        "UPDATE outbox SET attempts = attempts + 1, available_at = $1 \
+         WHERE id IN (SELECT id FROM outbox \
+         WHERE published_at IS NULL AND available_at <= $2 \
+         AND attempts < $3 ORDER BY created_at LIMIT $4 {lock}) \
+         RETURNING id, topic, payload, created_at, attempts"
+    );
+    let now = OffsetDateTime::now_utc();
+    let lease = Duration::from_secs(settings.lease);
+    let query = query(&sql)
+        .bind(to_micros(now + lease))
+        .bind(to_micros(now))
+        .bind(settings.max_attempts)
+        .bind(settings.batch_size)
+        .fetch_all(db.pool(Access::ReadWrite));
+    let rows = db.observe("outbox.claim", &sql, query).await?;
+    // RETURNING does not keep the order of the subquery.
+    let mut events: Vec<_> =
+        rows.iter().map(event_from_row).collect::<anyhow::Result<_>>()?;
+    events.sort_by_key(|(event, _)| event.created_at);
+    Ok(events)
+}
+
+fn event_from_row(row: &AnyRow) -> anyhow::Result<(Event, i64)> {
+    let payload: String = row.try_get(2)?;
+    let event = Event {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        topic: row.try_get(1)?,
+        created_at: from_micros(row.try_get(3)?)?,
+        payload: serde_json::from_str(&payload)
+            .context("malformed event payload")?,
+    };
+    Ok((event, row.try_get(4)?))
+}
+
+/// Queues a job for each subscriber of the topic of `event` and a delivery
+/// to each webhook endpoint of the topic.
+async fn publish(state: &AppState, event: &Arc<Event>) -> anyhow::Result<()> {
+    for subscriber in &state.outbox_subscribers {
+        if subscriber.topics().contains(&event.topic.as_str()) {
+            let job = EventJob {
+                subscriber: subscriber.clone(),
+                event: event.clone(),
+            };
+            state.jobs.push(job).await?;
+        }
+    }
+    webhooks::dispatch(state, event).await
+}
@@ -0,0 +1,245 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{StatusCode, request::Parts},
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::helpers::{from_micros, to_micros};
+use crate::problem::Problem;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PaginationSettings {
+    /// Items per page when the request does not ask for a number.
+    pub(crate) per_page: usize,
+    /// Most items a request may ask for.
+    pub(crate) max_per_page: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    page: Option<usize>,
+    per_page: Option<usize>,
+    after: Option<String>,
+}
+
+/// Page asked for by the `page` and `per_page` query parameters, pages
+/// counting from 1, or by the `after` cursor of the previous page for the
+/// listings walked by [`keyset`], which the others ignore. Out of bounds
+/// values are rejected with a 400 problem.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Pagination {
+    pub(crate) page: usize,
+    pub(crate) per_page: usize,
+    pub(crate) after: Option<Cursor>,
+}
+
+/// Last row of a page ordered by `created_at` then `id`, the next page
+/// starting after it. Sent as `<created_at micros>.<id>`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Cursor {
+    pub(crate) created_at: OffsetDateTime,
+    pub(crate) id: Uuid,
+}
+
+impl Cursor {
+    fn parse(value: &str) -> Option<Self> {
+        let (micros, id) = value.split_once('.')?;
+        Some(Cursor {
+            created_at: from_micros(micros.parse().ok()?).ok()?,
+            id: Uuid::try_parse(id).ok()?,
+        })
+    }
+
+    /// Micros of `created_at`, as bound to the query.
+    pub(crate) fn micros(&self) -> i64 {
+        to_micros(self.created_at)
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.micros(), self.id)
+    }
+}
+
+impl Pagination {
+    /// Rows to fetch, one more than shown tells whether there is a next
+    /// page.
+    pub(crate) fn limit(&self) -> usize {
+        self.per_page + 1
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        (self.page - 1) * self.per_page
+    }
+
+    /// Slice of `items`, for sources listed in memory.
+    pub(crate) fn apply<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+    ) -> Vec<T> {
+        items.into_iter().skip(self.offset()).take(self.limit()).collect()
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Pagination {
+    type Rejection = Problem;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let settings = &state.settings.pagination;
+        let Query(query) =
+            Query::<PaginationQuery>::from_request_parts(parts, state)
+                .await
+                .map_err(|rejection| {
+                Problem::new(rejection.status()).detail(rejection.body_text())
+            })?;
+        let bad_request = |detail: String| {
+            Problem::new(StatusCode::BAD_REQUEST).detail(detail)
+        };
+
+        let after = match query.after.as_deref() {
+            None => None,
+            Some(_) if query.page.is_some() => {
+                return Err(bad_request(
+                    "page and after cannot be used together".to_string(),
+                ));
+            }
+            Some(after) => Some(Cursor::parse(after).ok_or_else(|| {
+                bad_request("after is not a cursor".to_string())
+            })?),
+        };
+        let page = query.page.unwrap_or(1);
+        let per_page = query.per_page.unwrap_or(settings.per_page);
+        if page == 0 {
+            return Err(bad_request("page starts at 1".to_string()));
+        }
+        if per_page == 0 || per_page > settings.max_per_page {
+            return Err(bad_request(format!(
+                "per_page must be between 1 and {}",
+                settings.max_per_page
+            )));
+        }
+        if (page - 1)
+            .checked_mul(per_page)
+            .is_none_or(|offset| i64::try_from(offset).is_err())
+        {
+            return Err(bad_request("page is out of range".to_string()));
+        }
+        Ok(Pagination { page, per_page, after })
+    }
+}
+
+/// `LIMIT` and `OFFSET` of a query, bound to [`Pagination::limit`] and
+/// [`Pagination::offset`] as parameters `$first` and the next.
+pub(crate) fn limit_offset(first: usize) -> String {
+    format!("LIMIT ${first} OFFSET ${}", first + 1)
+}
+
+/// Direction a listing sorts one of its columns in.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Order {
+    Asc,
+    Desc,
+}
+
+/// Keyset condition for a listing ordered by `columns`, each in its own
+/// direction, continuing after the row whose values are bound from `$first`
+/// on. Unlike an offset it costs the same on every page and does not skip
+/// or repeat rows inserted meanwhile, for long listings walked with a
+/// [`Cursor`].
+pub(crate) fn keyset(columns: &[(&str, Order)], first: usize) -> String {
+    let alternatives: Vec<String> = (0..columns.len())
+        .map(|n| {
+            let mut terms: Vec<String> = columns[..n]
+                .iter()
+                .enumerate()
+                .map(|(i, (column, _))| format!("{column} = ${}", first + i))
+                .collect();
+            let (column, order) = columns[n];
+            let op = match order {
+                Order::Asc => ">",
+                Order::Desc => "<",
+            };
+            terms.push(format!("{column} {op} ${}", first + n));
+            if terms.len() == 1 {
+                terms.remove(0)
+            } else {
+                format!("({})", terms.join(" AND "))
+            }
+        })
+        .collect();
+    format!("({})", alternatives.join(" OR "))
+}
+
+/// Items of one page and where it stands, rendered by the `pager` macro
+/// of the HTML listings and sent as is by the JSON ones.
+#[derive(Debug, Serialize)]
+pub(crate) struct Page<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) page: usize,
+    pub(crate) per_page: usize,
+    pub(crate) has_prev: bool,
+    pub(crate) has_next: bool,
+    /// Set for the listings walked by [`keyset`], which link to the next
+    /// page by its `after` cursor instead of its number.
+    pub(crate) keyset: bool,
+    pub(crate) next: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Page of the `items` fetched with [`Pagination::limit`], dropping the
+    /// extra one.
+    pub(crate) fn new(mut items: Vec<T>, pagination: Pagination) -> Self {
+        let has_next = items.len() > pagination.per_page;
+        items.truncate(pagination.per_page);
+        Page {
+            items,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            has_prev: pagination.page > 1,
+            has_next,
+            keyset: false,
+            next: None,
+        }
+    }
+
+    /// Page of the `items` fetched after [`Pagination::after`], with the
+    /// `cursor` of its last item when there is a next one.
+    pub(crate) fn keyset(
+        items: Vec<T>,
+        pagination: Pagination,
+        cursor: impl Fn(&T) -> Cursor,
+    ) -> Self {
+        let mut page = Page::new(items, pagination);
+        page.has_prev = pagination.after.is_some();
+        page.keyset = true;
+        if page.has_next {
+            page.next = page.items.last().map(|item| cursor(item).to_string());
+        }
+        page
+    }
+}
@@ -0,0 +1,267 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_client_ip::ClientIp;
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info};
+use validator::Validate;
+
+use crate::audit::Audit;
+use crate::auth::hash_password;
+use crate::form::{Field, FieldKind, FormDefinition, FormErrors, FormSpec};
+use crate::mailer;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::token::UserToken;
+use crate::users::User;
+use crate::view::View;
+
+const PURPOSE: &str = "password-reset";
+
+/// Signs a reset link token for `user`. It is bound to the current password
+/// hash, so it stops working once used.
+fn reset_token(state: &AppState, user: &User) -> String {
+    UserToken::issue(
+        state.settings.cookies.key.as_bytes(),
+        PURPOSE,
+        user.id,
+        state.settings.auth.reset_token_ttl,
+        user.password_hash.as_bytes(),
+    )
+}
+
+/// Returns the user of a valid, unexpired token.
+async fn verify_reset_token(
+    state: &AppState,
+    token: &str,
+) -> Result<Option<User>, ServerError> {
+    let Some(token) = UserToken::parse(token) else {
+        return Ok(None);
+    };
+    let user = state.users.find_by_id(token.user_id).await?;
+    Ok(user.filter(|user| {
+        token.verify(
+            state.settings.cookies.key.as_bytes(),
+            PURPOSE,
+            user.password_hash.as_bytes(),
+        )
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct ForgotPasswordInput {
+    #[validate(email(message = "Must be a valid email"))]
+    pub(crate) email: String,
+}
+
+impl FormDefinition for ForgotPasswordInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/forgot-password")
+            .submit("Send reset link")
+            .field(Field::email("email", "Email").required())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct ResetPasswordInput {
+    pub(crate) token: String,
+    #[serde(skip_serializing)]
+    #[validate(length(
+        min = 8,
+        max = 128,
+        message = "Must have between 8 and 128 characters"
+    ))]
+    pub(crate) password: String,
+    #[serde(skip_serializing)]
+    #[validate(must_match(other = "password", message = "Does not match"))]
+    pub(crate) password_confirmation: String,
+}
+
+impl FormDefinition for ResetPasswordInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/reset-password")
+            .submit("Change password")
+            .field(Field::new("token", "", FieldKind::Hidden))
+            .field(
+                Field::password("password", "New password")
+                    .length(Some(8), Some(128)),
+            )
+            .field(
+                Field::password("password_confirmation", "Confirm password")
+                    .required(),
+            )
+    }
+}
+
+pub(crate) async fn handler_forgot_password(view: View) -> Html<String> {
+    let form = ForgotPasswordInput::form();
+    view.render(
+        "forgot_password",
+        context! {
+            title => "Forgot password",
+            form => form.empty(),
+        },
+    )
+    .unwrap()
+}
+
+/// Emails a reset link when the account exists. The answer is the same
+/// either way, so the form can not be used to probe for accounts.
+pub(crate) async fn handler_forgot_password_post(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    view: View,
+    Form(input): Form<ForgotPasswordInput>,
+) -> Result<Response, ServerError> {
+    let form = ForgotPasswordInput::form();
+    let render = |errors: &FormErrors| {
+        view.render(
+            "forgot_password",
+            context! {
+                title => "Forgot password",
+                form => form.bind(&input, errors),
+            },
+        )
+        .unwrap()
+    };
+
+    if let Err(errors) = input.validate() {
+        let rendered = render(&FormErrors::from(&errors));
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let email = input.email.to_lowercase();
+    let allowed = state.reset_limiter.check(&format!("ip:{ip}")).await
+        && state.reset_limiter.check(&format!("email:{email}")).await;
+    if !allowed {
+        let mut errors = FormErrors::default();
+        errors.add("email", "Too many attempts, try again later");
+        return Ok(
+            (StatusCode::TOO_MANY_REQUESTS, render(&errors)).into_response()
+        );
+    }
+
+    if let Some(user) = state.users.find_by_email(&email).await? {
+        let url = state.settings.site.url_for(&format!(
+            "/reset-password?token={}",
+            reset_token(&state, &user)
+        ));
+        let ctx = context! {
+            name => user.name,
+            url => url,
+            minutes => state.settings.auth.reset_token_ttl / 60,
+        };
+        match mailer::deliver(&state, &user.email, "password_reset", ctx).await
+        {
+            Ok(()) => info!(user = %user.id, "password reset requested"),
+            Err(e) => {
+                error!("could not send the password reset email: {e:#}");
+            }
+        }
+    }
+
+    let rendered = view
+        .render(
+            "forgot_password",
+            context! {
+                title => "Forgot password",
+                sent => true,
+            },
+        )
+        .unwrap();
+    Ok(rendered.into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenQuery {
+    #[serde(default)]
+    token: String,
+}
+
+fn render_reset(
+    view: &View,
+    input: &ResetPasswordInput,
+    errors: &FormErrors,
+    invalid: bool,
+) -> Html<String> {
+    view.render(
+        "reset_password",
+        context! {
+            title => "Reset password",
+            form => ResetPasswordInput::form().bind(input, errors),
+            invalid => invalid,
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_reset_password(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Query(TokenQuery { token }): Query<TokenQuery>,
+) -> Result<Response, ServerError> {
+    let valid = verify_reset_token(&state, &token).await?.is_some();
+    let input = ResetPasswordInput {
+        token,
+        password: String::new(),
+        password_confirmation: String::new(),
+    };
+    let rendered = render_reset(&view, &input, &FormErrors::default(), !valid);
+    let status = if valid { StatusCode::OK } else { StatusCode::BAD_REQUEST };
+    Ok((status, rendered).into_response())
+}
+
+pub(crate) async fn handler_reset_password_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    Form(input): Form<ResetPasswordInput>,
+) -> Result<Response, ServerError> {
+    let Some(user) = verify_reset_token(&state, &input.token).await? else {
+        let rendered =
+            render_reset(&view, &input, &FormErrors::default(), true);
+        return Ok((StatusCode::BAD_REQUEST, rendered).into_response());
+    };
+
+    if let Err(errors) = input.validate() {
+        let rendered =
+            render_reset(&view, &input, &FormErrors::from(&errors), false);
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let password_hash = hash_password(input.password).await?;
+    state.users.update_password(user.id, password_hash).await?;
+    info!(user = %user.id, "password reset");
+    audit
+        .actor(user.id)
+        .record("auth.password_reset", Some(user.id.to_string()), json!({}))
+        .await;
+    Ok(Redirect::to("/login").into_response())
+}
@@ -0,0 +1,736 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+//! Payments through a Stripe compatible API: a checkout session is
+//! created for each order, and the events signed by the provider move the
+//! order along, once each, from the job queue.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use axum::{
+    Form,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Row, any::AnyRow, query};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::auth::CurrentUser;
+use crate::database::{Access, Database};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::incoming_webhook::{self, Scheme};
+use crate::jobs::Job;
+use crate::metric;
+use crate::pdf::money;
+use crate::privacy::PersonalData;
+use crate::problem::Problem;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::view::View;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PaymentsSettings {
+    /// Base URL of the API, e.g. `https://api.stripe.com` or a local
+    /// `stripe-mock`.
+    pub(crate) api_url: String,
+    /// Secret API key, checkout is off while it is empty.
+    pub(crate) secret_key: String,
+    /// Secret signing the events POSTed to `/payments/webhook`, which
+    /// answers 404 while it is empty.
+    pub(crate) webhook_secret: String,
+    /// Lowercase ISO code of the currency of the prices, e.g. `usd`.
+    pub(crate) currency: String,
+    /// Products for sale, by id.
+    #[serde(default)]
+    pub(crate) products: BTreeMap<String, Product>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Product {
+    pub(crate) name: String,
+    /// Price in the smallest unit of the currency, e.g. cents.
+    pub(crate) amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OrderStatus {
+    /// Waiting for the payment of the checkout session.
+    Pending,
+    Paid,
+    /// The payment was declined, the customer may still pay.
+    Failed,
+    /// The checkout session ended without a payment.
+    Expired,
+}
+
+impl OrderStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Failed => "failed",
+            OrderStatus::Expired => "expired",
+        }
+    }
+
+    fn parse(status: &str) -> anyhow::Result<Self> {
+        Ok(match status {
+            "pending" => OrderStatus::Pending,
+            "paid" => OrderStatus::Paid,
+            "failed" => OrderStatus::Failed,
+            "expired" => OrderStatus::Expired,
+            _ => anyhow::bail!("unknown order status {status}"),
+        })
+    }
+
+    /// Statuses an order may move to this one from. Events can arrive out
+    /// of order, so a paid order is never taken back.
+    fn previous(self) -> &'static [OrderStatus] {
+        match self {
+            OrderStatus::Pending => &[],
+            OrderStatus::Paid => &[OrderStatus::Pending, OrderStatus::Failed],
+            OrderStatus::Failed | OrderStatus::Expired => {
+                &[OrderStatus::Pending]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Order {
+    pub(crate) id: Uuid,
+    /// None once the user erased their account.
+    pub(crate) user_id: Option<Uuid>,
+    /// Id of the product in `[payments.products]`.
+    pub(crate) product: String,
+    /// Name of the product when it was ordered.
+    pub(crate) description: String,
+    pub(crate) amount: u64,
+    pub(crate) currency: String,
+    pub(crate) status: OrderStatus,
+    /// Id of the checkout session at the provider.
+    pub(crate) checkout_session: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) updated_at: OffsetDateTime,
+}
+
+/// Orders, and the payment events already applied to them.
+pub(crate) trait OrderStore: Send + Sync {
+    fn create(&self, order: Order) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    fn find(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<Option<Order>>>;
+
+    /// Orders of the user, from the most recent.
+    fn for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Order>>>;
+
+    fn set_checkout_session<'a>(
+        &'a self,
+        id: Uuid,
+        session: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Moves the order to `status` for the event `event_id` of `kind`,
+    /// when it may move there. False when the event was already applied
+    /// or the order can not move, e.g. an expiry after the payment.
+    fn apply<'a>(
+        &'a self,
+        event_id: &'a str,
+        kind: &'a str,
+        order_id: Uuid,
+        status: OrderStatus,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+
+    /// Keeps the orders of the user, for the accounts, without the user.
+    fn detach_user(&self, user_id: Uuid) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// Tables of the database, kept in memory without one.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn OrderStore> {
+    match db {
+        None => Box::new(MemoryOrderStore::default()),
+        Some(db) => Box::new(SqlOrderStore { db: db.clone() }),
+    }
+}
+
+/// Orders lasting until the process exits, local to it.
+#[derive(Default)]
+pub(crate) struct MemoryOrderStore {
+    orders: RwLock<HashMap<Uuid, Order>>,
+    /// Kinds of the applied events, by id.
+    events: RwLock<HashMap<String, String>>,
+}
+
+impl OrderStore for MemoryOrderStore {
+    fn create(&self, order: Order) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.orders.write().unwrap().insert(order.id, order);
+            Ok(())
+        })
+    }
+
+    fn find(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<Option<Order>>> {
+        Box::pin(
+            async move { Ok(self.orders.read().unwrap().get(&id).cloned()) },
+        )
+    }
+
+    fn for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Order>>> {
+        Box::pin(async move {
+            let mut orders: Vec<Order> = self
+                .orders
+                .read()
+                .unwrap()
+                .values()
+                .filter(|order| order.user_id == Some(user_id))
+                .cloned()
+                .collect();
+            orders.sort_by_key(|order| std::cmp::Reverse(order.created_at));
+            Ok(orders)
+        })
+    }
+
+    fn set_checkout_session<'a>(
+        &'a self,
+        id: Uuid,
+        session: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if let Some(order) = self.orders.write().unwrap().get_mut(&id) {
+                order.checkout_session = Some(session.to_string());
+            }
+            Ok(())
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        event_id: &'a str,
+        kind: &'a str,
+        order_id: Uuid,
+        status: OrderStatus,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut events = self.events.write().unwrap();
+            if events.contains_key(event_id) {
+                return Ok(false);
+            }
+            events.insert(event_id.to_string(), kind.to_string());
+            let mut orders = self.orders.write().unwrap();
+            let Some(order) = orders.get_mut(&order_id) else {
+                return Ok(false);
+            };
+            if !status.previous().contains(&order.status) {
+                return Ok(false);
+            }
+            order.status = status;
+            order.updated_at = OffsetDateTime::now_utc();
+            Ok(true)
+        })
+    }
+
+    fn detach_user(&self, user_id: Uuid) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            for order in self.orders.write().unwrap().values_mut() {
+                if order.user_id == Some(user_id) {
+                    order.user_id = None;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The `orders` and `payment_events` tables.
+pub(crate) struct SqlOrderStore {
+    db: Database,
+}
+
+const ORDER_COLUMNS: &str = "id, user_id, product, description, amount, \
+                             currency, status, checkout_session, \
+                             created_at, updated_at";
+
+fn order_from_row(row: &AnyRow) -> anyhow::Result<Order> {
+    Ok(Order {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        user_id: row
+            .try_get::<Option<String>, _>(1)?
+            .map(|id| Uuid::try_parse(&id))
+            .transpose()?,
+        product: row.try_get(2)?,
+        description: row.try_get(3)?,
+        amount: row.try_get::<i64, _>(4)?.try_into()?,
+        currency: row.try_get(5)?,
+        status: OrderStatus::parse(&row.try_get::<String, _>(6)?)?,
+        checkout_session: row.try_get(7)?,
+        created_at: from_micros(row.try_get(8)?)?,
+        updated_at: from_micros(row.try_get(9)?)?,
+    })
+}
+
+impl OrderStore for SqlOrderStore {
+    fn create(&self, order: Order) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = format!(
+                "INSERT INTO orders ({ORDER_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+            );
+            let insert = query(&sql)
+                .bind(order.id.to_string())
+                .bind(order.user_id.map(|id| id.to_string()))
+                .bind(order.product)
+                .bind(order.description)
+                .bind(i64::try_from(order.amount)?)
+                .bind(order.currency)
+                .bind(order.status.as_str())
+                .bind(order.checkout_session)
+                .bind(to_micros(order.created_at))
+                .bind(to_micros(order.updated_at))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("orders.create", &sql, insert).await?;
+            Ok(())
+        })
+    }
+
+    fn find(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<Option<Order>>> {
+        Box::pin(async move {
+            let sql =
+                format!("SELECT {ORDER_COLUMNS} FROM orders WHERE id = $1");
+            let query = query(&sql)
+                .bind(id.to_string())
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("orders.find", &sql, query).await?;
+            row.as_ref().map(order_from_row).transpose()
+        })
+    }
+
+    fn for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Order>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {ORDER_COLUMNS} FROM orders WHERE user_id = $1 \
+                 ORDER BY created_at DESC"
+            );
+            let query = query(&sql)
+                .bind(user_id.to_string())
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("orders.for_user", &sql, query).await?;
+            rows.iter().map(order_from_row).collect()
+        })
+    }
+
+    fn set_checkout_session<'a>(
+        &'a self,
+        id: Uuid,
+        session: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "UPDATE orders SET checkout_session = $2 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(session)
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("orders.set_checkout_session", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn apply<'a>(
+        &'a self,
+        event_id: &'a str,
+        kind: &'a str,
+        order_id: Uuid,
+        status: OrderStatus,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let now = to_micros(OffsetDateTime::now_utc());
+            let mut tx = self.db.begin().await?;
+            let sql = "INSERT INTO payment_events \
+                       (id, order_id, kind, received_at) \
+                       VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO NOTHING";
+            let insert = query(sql)
+                .bind(event_id)
+                .bind(order_id.to_string())
+                .bind(kind)
+                .bind(now)
+                .execute(&mut *tx);
+            let recorded =
+                self.db.observe("orders.record_event", sql, insert).await?;
+            if recorded.rows_affected() == 0 {
+                return Ok(false);
+            }
+
+            let previous: Vec<String> = (0..status.previous().len())
+                .map(|n| format!("${}", n + 4))
+                .collect();
+            if previous.is_empty() {
+                tx.commit().await?;
+                return Ok(false);
+            }
+            let sql = format!(
+                "UPDATE orders SET status = $2, updated_at = $3 \
+                 WHERE id = $1 AND status IN ({})",
+                previous.join(", ")
+            );
+            let mut update = query(&sql)
+                .bind(order_id.to_string())
+                .bind(status.as_str())
+                .bind(now);
+            for previous in status.previous() {
+                update = update.bind(previous.as_str());
+            }
+            let update = update.execute(&mut *tx);
+            let result = self.db.observe("orders.apply", &sql, update).await?;
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn detach_user(&self, user_id: Uuid) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "UPDATE orders SET user_id = NULL WHERE user_id = $1";
+            let query = query(sql)
+                .bind(user_id.to_string())
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("orders.detach_user", sql, query).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Checkout session created at the provider, where the customer pays.
+#[derive(Debug, Deserialize)]
+struct CheckoutSession {
+    id: String,
+    url: String,
+}
+
+/// Creates the checkout session of `order`. The order id is the
+/// idempotency key, so a retried request creates a single session.
+async fn create_checkout_session(
+    state: &AppState,
+    order: &Order,
+) -> anyhow::Result<CheckoutSession> {
+    let settings = &state.settings.payments;
+    let order_url =
+        state.settings.site.url_for(&format!("/orders/{}", order.id));
+    let amount = order.amount.to_string();
+    let request = state
+        .http
+        .post(format!("{}/v1/checkout/sessions", settings.api_url))
+        .bearer_auth(&settings.secret_key)
+        .header("idempotency-key", order.id.to_string())
+        .form(&[
+            ("mode", "payment"),
+            ("client_reference_id", &order.id.to_string()),
+            ("success_url", &format!("{order_url}?checkout=success")),
+            ("cancel_url", &format!("{order_url}?checkout=cancelled")),
+            ("line_items[0][quantity]", "1"),
+            ("line_items[0][price_data][currency]", &order.currency),
+            ("line_items[0][price_data][unit_amount]", &amount),
+            (
+                "line_items[0][price_data][product_data][name]",
+                &order.description,
+            ),
+        ]);
+    let response = state.http.send(request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("checkout session refused with {status}: {body}");
+    }
+    Ok(response.json().await?)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CheckoutInput {
+    product: String,
+}
+
+/// Products for sale, each with a button starting its checkout.
+pub(crate) async fn handler_checkout(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, ServerError> {
+    let settings = &state.settings.payments;
+    let products: Vec<_> = settings
+        .products
+        .iter()
+        .map(|(id, product)| {
+            context! {
+                id => id,
+                name => product.name,
+                price => money(product.amount),
+            }
+        })
+        .collect();
+    let orders = state.orders.for_user(user.id).await?;
+    Ok(view
+        .render(
+            "checkout",
+            context! {
+                title => "Checkout",
+                products => products,
+                currency => settings.currency.to_uppercase(),
+                enabled => !settings.secret_key.is_empty(),
+                orders => orders,
+            },
+        )
+        .unwrap())
+}
+
+/// Creates a pending order of the product and sends the customer to the
+/// checkout session paying it.
+pub(crate) async fn handler_checkout_post(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(user): CurrentUser,
+    Form(input): Form<CheckoutInput>,
+) -> Result<Response, ServerError> {
+    let settings = &state.settings.payments;
+    if settings.secret_key.is_empty() {
+        return Ok((StatusCode::SERVICE_UNAVAILABLE, "Payments are off")
+            .into_response());
+    }
+    let Some(product) = settings.products.get(&input.product) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let now = OffsetDateTime::now_utc();
+    let order = Order {
+        id: Uuid::new_v4(),
+        user_id: Some(user.id),
+        product: input.product.clone(),
+        description: product.name.clone(),
+        amount: product.amount,
+        currency: settings.currency.clone(),
+        status: OrderStatus::Pending,
+        checkout_session: None,
+        created_at: now,
+        updated_at: now,
+    };
+    state.orders.create(order.clone()).await?;
+    let session = match create_checkout_session(&state, &order).await {
+        Ok(session) => session,
+        Err(e) => {
+            error!(order = %order.id, "could not create the checkout: {e:#}");
+            return Ok((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The payment provider is unavailable, try again later",
+            )
+                .into_response());
+        }
+    };
+    state.orders.set_checkout_session(order.id, &session.id).await?;
+
+    info!(order = %order.id, session = session.id, "checkout started");
+    metric::count!("checkout", product = input.product);
+    Ok(Redirect::to(&session.url).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct OrderQuery {
+    /// `success` or `cancelled`, set by the provider redirecting back.
+    checkout: Option<String>,
+}
+
+/// Status of an order of the user, where the checkout sends them back.
+/// The status only changes once the provider's event was applied, so a
+/// returning customer may still see the order pending.
+pub(crate) async fn handler_order(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<Uuid>,
+    Query(params): Query<OrderQuery>,
+) -> Result<Response, ServerError> {
+    let order = state.orders.find(id).await?;
+    let Some(order) = order.filter(|order| order.user_id == Some(user.id))
+    else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    Ok(view
+        .render(
+            "order",
+            context! {
+                title => format!("Order {}", order.id),
+                price => money(order.amount),
+                order => order,
+                checkout => params.checkout,
+            },
+        )
+        .unwrap()
+        .into_response())
+}
+
+/// Event of the provider, only the fields the orders need.
+#[derive(Debug, Deserialize)]
+pub(crate) struct PaymentEvent {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: EventData,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventData {
+    object: Value,
+}
+
+impl PaymentEvent {
+    /// Status the event moves its order to, if it is about an order.
+    fn order_status(&self) -> Option<(Uuid, OrderStatus)> {
+        let object = &self.data.object;
+        let order_id = object
+            .get("client_reference_id")
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::try_parse(id).ok())?;
+        let paid = object.get("payment_status").and_then(Value::as_str)
+            == Some("paid");
+        let status = match self.kind.as_str() {
+            // Delayed payment methods complete unpaid, then succeed or fail.
+            "checkout.session.completed" if paid => OrderStatus::Paid,
+            "checkout.session.async_payment_succeeded" => OrderStatus::Paid,
+            "checkout.session.async_payment_failed" => OrderStatus::Failed,
+            "checkout.session.expired" => OrderStatus::Expired,
+            _ => return None,
+        };
+        Some((order_id, status))
+    }
+}
+
+/// Applies a payment event to its order. Safe to run again: the store
+/// applies each event once.
+pub(crate) struct PaymentEventJob(PaymentEvent);
+
+impl Job for PaymentEventJob {
+    fn name(&self) -> &'static str {
+        "payments.event"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let event = &self.0;
+            let Some((order_id, status)) = event.order_status() else {
+                info!(event = event.id, kind = event.kind, "event ignored");
+                return Ok(());
+            };
+            let applied = state
+                .orders
+                .apply(&event.id, &event.kind, order_id, status)
+                .await
+                .context("could not apply the payment event")?;
+            if applied {
+                info!(order = %order_id, status = status.as_str(), "order updated");
+                metric::count!("order", status = status.as_str());
+            } else {
+                warn!(
+                    event = event.id,
+                    order = %order_id,
+                    "event already applied or out of order, skipped"
+                );
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Receives the events of the provider, signed with the Stripe scheme
+/// and `payments.webhook_secret`. They are applied by the job queue, the
+/// provider only waits for them to be accepted.
+pub(crate) async fn handler_payments_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, Problem> {
+    let secret = &state.settings.payments.webhook_secret;
+    if secret.is_empty() {
+        return Err(Problem::new(StatusCode::NOT_FOUND));
+    }
+    incoming_webhook::verify(
+        &state,
+        "payments",
+        Scheme::Stripe,
+        secret,
+        &headers,
+        &body,
+    )
+    .await?;
+    let event: PaymentEvent = serde_json::from_slice(&body).map_err(|e| {
+        Problem::new(StatusCode::UNPROCESSABLE_ENTITY)
+            .detail(format!("Invalid event: {e}"))
+    })?;
+
+    info!(event = event.id, kind = event.kind, "payment event received");
+    state.jobs.push(PaymentEventJob(event)).await.map_err(|e| {
+        error!("could not queue the payment event: {e:#}");
+        Problem::new(StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+    Ok(StatusCode::OK)
+}
+
+/// The orders of the user, kept without the user on erasure since they
+/// belong to the accounts.
+pub(crate) struct Orders;
+
+impl PersonalData for Orders {
+    fn name(&self) -> &'static str {
+        "orders"
+    }
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        Box::pin(async move {
+            let orders = state.orders.for_user(user_id).await?;
+            Ok(serde_json::to_value(orders)?)
+        })
+    }
+
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        state.orders.detach_user(user_id)
+    }
+}
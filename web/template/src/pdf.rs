@@ -0,0 +1,260 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{path::PathBuf, process::Stdio, sync::Arc, time::Duration};
+
+use anyhow::{Context, bail};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::auth::CurrentUser;
+use crate::helpers::BoxFuture;
+use crate::http_client::{self, HttpClientSettings};
+use crate::metric;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::view::View;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "renderer", rename_all = "lowercase")]
+pub(crate) enum PdfSettings {
+    /// Prints with a local headless Chromium, e.g. `chromium` or
+    /// `google-chrome`, run once per document.
+    Chromium {
+        command: String,
+        /// Extra arguments, e.g. `--no-sandbox` in containers.
+        #[serde(default)]
+        args: Vec<String>,
+        /// Seconds before the browser is killed.
+        timeout: u64,
+    },
+    /// Posts the HTML to a Gotenberg server, e.g. `http://127.0.0.1:3002`.
+    Gotenberg {
+        url: String,
+        /// Seconds to wait for the server.
+        timeout: u64,
+    },
+}
+
+/// Turns a rendered HTML document into a PDF.
+pub(crate) trait PdfRenderer: Send + Sync {
+    fn render(&self, html: String) -> BoxFuture<'_, anyhow::Result<Bytes>>;
+}
+
+/// Renderer of the `[pdf]` settings.
+pub(crate) fn from_settings(
+    settings: &PdfSettings,
+    http: &HttpClientSettings,
+) -> anyhow::Result<Box<dyn PdfRenderer>> {
+    Ok(match settings {
+        PdfSettings::Chromium { command, args, timeout } => {
+            Box::new(ChromiumRenderer {
+                command: command.clone(),
+                args: args.clone(),
+                timeout: Duration::from_secs(*timeout),
+            })
+        }
+        PdfSettings::Gotenberg { url, timeout } => {
+            Box::new(GotenbergRenderer {
+                endpoint: format!(
+                    "{}/forms/chromium/convert/html",
+                    url.trim_end_matches('/')
+                ),
+                client: http_client::builder(http)?
+                    .timeout(Duration::from_secs(*timeout))
+                    .build()?,
+            })
+        }
+    })
+}
+
+/// Prints pages with `<command> --headless --print-to-pdf`, from a
+/// temporary directory removed afterwards.
+pub(crate) struct ChromiumRenderer {
+    command: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl PdfRenderer for ChromiumRenderer {
+    fn render(&self, html: String) -> BoxFuture<'_, anyhow::Result<Bytes>> {
+        Box::pin(async move {
+            let _timer = metric::time!("pdf", renderer = "chromium");
+            let dir = TempDir::new().await?;
+            let page = dir.0.join("page.html");
+            let output = dir.0.join("page.pdf");
+            tokio::fs::write(&page, html).await?;
+
+            let child = tokio::process::Command::new(&self.command)
+                .args([
+                    "--headless",
+                    "--disable-gpu",
+                    "--no-pdf-header-footer",
+                ])
+                .args(&self.args)
+                .arg(format!("--print-to-pdf={}", output.display()))
+                .arg(format!("file://{}", page.display()))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("starting {}", self.command))?;
+            let result =
+                tokio::time::timeout(self.timeout, child.wait_with_output())
+                    .await
+                    .context("chromium timed out")??;
+            if !result.status.success() {
+                bail!(
+                    "chromium exited with {}: {}",
+                    result.status,
+                    String::from_utf8_lossy(&result.stderr).trim()
+                );
+            }
+            let pdf = tokio::fs::read(&output)
+                .await
+                .context("chromium wrote no PDF")?;
+            Ok(Bytes::from(pdf))
+        })
+    }
+}
+
+/// Directory under the system one, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    async fn new() -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "{}-pdf-{}",
+            env!("CARGO_PKG_NAME"),
+            uuid::Uuid::new_v4()
+        ));
+        tokio::fs::create_dir(&path).await?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Converts pages on a Gotenberg server, which keeps Chromium out of the
+/// application image.
+pub(crate) struct GotenbergRenderer {
+    endpoint: String,
+    client: reqwest::Client,
+}
+
+impl PdfRenderer for GotenbergRenderer {
+    fn render(&self, html: String) -> BoxFuture<'_, anyhow::Result<Bytes>> {
+        Box::pin(async move {
+            let _timer = metric::time!("pdf", renderer = "gotenberg");
+            let page = reqwest::multipart::Part::text(html)
+                .file_name("index.html")
+                .mime_str("text/html")?;
+            let form = reqwest::multipart::Form::new().part("files", page);
+            let response = self
+                .client
+                .post(&self.endpoint)
+                .multipart(form)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(response.bytes().await?)
+        })
+    }
+}
+
+/// A PDF answered inline as `filename`, opened by the browser's viewer.
+pub(crate) struct Pdf {
+    pub(crate) filename: String,
+    pub(crate) bytes: Bytes,
+}
+
+impl IntoResponse for Pdf {
+    fn into_response(self) -> Response {
+        (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("inline; filename=\"{}\"", self.filename),
+                ),
+            ],
+            self.bytes,
+        )
+            .into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct InvoiceLine {
+    description: &'static str,
+    quantity: u64,
+    unit_price: String,
+    amount: String,
+}
+
+/// `cents` as a decimal amount, e.g. `19.00`.
+pub(crate) fn money(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+/// Example invoice of the logged in user, rendered by the `invoice`
+/// template and printed to PDF.
+pub(crate) async fn handler_invoice(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Result<Pdf, ServerError> {
+    let items = [("Pro plan, monthly", 1, 1900), ("Extra seats", 3, 500)];
+    let lines: Vec<_> = items
+        .iter()
+        .map(|&(description, quantity, unit_price)| InvoiceLine {
+            description,
+            quantity,
+            unit_price: money(unit_price),
+            amount: money(quantity * unit_price),
+        })
+        .collect();
+    let total = money(items.iter().map(|(_, q, price)| q * price).sum());
+    let number = format!("INV-{}", &user.id.simple().to_string()[..8]);
+
+    let html = view
+        .render(
+            "invoice",
+            context! {
+                site => &state.settings.site.name,
+                number => &number,
+                issued => OffsetDateTime::now_utc().date().to_string(),
+                user => user,
+                lines => lines,
+                total => total,
+            },
+        )
+        .unwrap();
+    let bytes = state.pdf.render(html.0).await?;
+    Ok(Pdf { filename: format!("{number}.pdf"), bytes })
+}
@@ -0,0 +1,840 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::BTreeSet,
+    path::{Path as FsPath, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::{Context, bail};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+use pulldown_cmark::{Options, Parser, html};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::{AnyConnection, Row, any::AnyRow, query};
+use time::{
+    Date, OffsetDateTime,
+    format_description::well_known::{Iso8601, Rfc3339},
+};
+use tracing::info;
+use validator::Validate;
+
+use crate::auth::OptionalUser;
+use crate::crud::{self, Column, Fields, Resource};
+use crate::database::{Access, Database};
+use crate::form::{Field, FieldKind, FormErrors};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::meta::Meta;
+use crate::pagination::{Page, Pagination, limit_offset};
+use crate::router::ServerError;
+use crate::settings::Site;
+use crate::sitemap::{SitemapEntry, SitemapSource};
+use crate::state::AppState;
+use crate::view::{Negotiate, View};
+
+/// Permission to read drafts and scheduled posts.
+const DRAFTS_PERMISSION: &str = "posts.drafts";
+/// Permission to write the posts of the database on `/admin/posts`.
+const EDIT_PERMISSION: &str = "posts.edit";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PostsSettings {
+    #[serde(flatten)]
+    pub(crate) source: PostSource,
+    /// Most recent posts listed in `/feed.xml`.
+    pub(crate) feed_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "source", rename_all = "lowercase")]
+pub(crate) enum PostSource {
+    /// Markdown files of `dir`, e.g. `content/posts/hello.md` for
+    /// `/blog/hello`, read on start.
+    Files { dir: PathBuf },
+    /// The `posts` and `post_tags` tables.
+    Database,
+}
+
+/// A blog post, a draft until it has a publication date, scheduled while
+/// that date is ahead.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Post {
+    pub(crate) slug: String,
+    pub(crate) title: String,
+    pub(crate) summary: String,
+    pub(crate) tags: Vec<String>,
+    /// HTML rendered from the markdown body.
+    pub(crate) content: String,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) published_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) updated_at: OffsetDateTime,
+}
+
+impl Post {
+    pub(crate) fn is_published(&self, now: OffsetDateTime) -> bool {
+        self.published_at.is_some_and(|at| at <= now)
+    }
+
+    pub(crate) fn path(&self) -> String {
+        format!("/blog/{}", self.slug)
+    }
+}
+
+/// Renders `markdown` to HTML. Raw HTML is kept, posts are written by the
+/// site's authors.
+fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let mut content = String::new();
+    html::push_html(&mut content, Parser::new_ext(markdown, options));
+    content
+}
+
+pub(crate) trait PostStore: Send + Sync {
+    /// Posts published by now, most recent first, only those tagged `tag`
+    /// when given.
+    fn published<'a>(
+        &'a self,
+        tag: Option<&'a str>,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Post>>>;
+
+    /// The post of `slug`, published or not.
+    fn find<'a>(
+        &'a self,
+        slug: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Post>>>;
+}
+
+/// Store of the `[posts]` settings.
+pub(crate) fn from_settings(
+    settings: &PostsSettings,
+    db: Option<&Database>,
+) -> anyhow::Result<Arc<dyn PostStore>> {
+    Ok(match &settings.source {
+        PostSource::Files { dir } => Arc::new(FilePostStore::load(dir)?),
+        PostSource::Database => Arc::new(SqlPostStore {
+            db: db
+                .context("posts.source is database, but there is none")?
+                .clone(),
+        }),
+    })
+}
+
+/// Front matter of a post file, the TOML between the `+++` lines that
+/// start it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    title: String,
+    #[serde(default)]
+    summary: String,
+    /// Publication date, `2025-01-31` or a RFC 3339 timestamp.
+    date: Option<String>,
+    /// Date of the last change, the file's when left out.
+    updated: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Kept unpublished whatever its date.
+    #[serde(default)]
+    draft: bool,
+}
+
+fn parse_date(value: &str) -> anyhow::Result<OffsetDateTime> {
+    if let Ok(at) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(at);
+    }
+    let date = Date::parse(value, &Iso8601::DATE)
+        .with_context(|| format!("invalid date {value:?}"))?;
+    Ok(date.midnight().assume_utc())
+}
+
+/// Posts of the markdown files of a directory, read once.
+pub(crate) struct FilePostStore {
+    /// Most recently published first, then the drafts.
+    posts: Vec<Post>,
+}
+
+impl FilePostStore {
+    fn load(dir: &FsPath) -> anyhow::Result<Self> {
+        let mut posts = Vec::new();
+        let entries = std::fs::read_dir(dir).with_context(|| {
+            format!("reading posts from {}", dir.display())
+        })?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let post = Self::read(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            posts.push(post);
+        }
+        posts.sort_by_key(|post| std::cmp::Reverse(post.published_at));
+        info!(count = posts.len(), dir = %dir.display(), "posts loaded");
+        Ok(FilePostStore { posts })
+    }
+
+    fn read(path: &FsPath) -> anyhow::Result<Post> {
+        let text = std::fs::read_to_string(path)?;
+        let Some((front, body)) =
+            text.strip_prefix("+++").and_then(|rest| rest.split_once("\n+++"))
+        else {
+            bail!("missing the +++ front matter");
+        };
+        let front: FrontMatter = toml::from_str(front)?;
+        let slug = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("file name is not UTF-8")?
+            .to_string();
+
+        let date = front.date.as_deref().map(parse_date).transpose()?;
+        if date.is_none() && !front.draft {
+            bail!("a date is needed unless it is a draft");
+        }
+        let updated_at = match front.updated.as_deref() {
+            Some(updated) => parse_date(updated)?,
+            None => match date {
+                Some(date) => date,
+                None => std::fs::metadata(path)?
+                    .modified()
+                    .unwrap_or(SystemTime::now())
+                    .into(),
+            },
+        };
+
+        Ok(Post {
+            slug,
+            title: front.title,
+            summary: front.summary,
+            tags: front.tags,
+            content: render_markdown(body.trim_start_matches('\n')),
+            published_at: date.filter(|_| !front.draft),
+            updated_at,
+        })
+    }
+}
+
+impl PostStore for FilePostStore {
+    fn published<'a>(
+        &'a self,
+        tag: Option<&'a str>,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Post>>> {
+        Box::pin(async move {
+            let now = OffsetDateTime::now_utc();
+            let posts = self.posts.iter().filter(|post| {
+                post.is_published(now)
+                    && tag.is_none_or(|tag| post.tags.iter().any(|t| t == tag))
+            });
+            Ok(pagination.apply(posts.cloned()))
+        })
+    }
+
+    fn find<'a>(
+        &'a self,
+        slug: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Post>>> {
+        Box::pin(async move {
+            Ok(self.posts.iter().find(|post| post.slug == slug).cloned())
+        })
+    }
+}
+
+pub(crate) struct SqlPostStore {
+    db: Database,
+}
+
+const POST_COLUMNS: &str = "slug, title, summary, body, published_at, \
+                            updated_at";
+
+fn post_from_row(row: &AnyRow) -> anyhow::Result<Post> {
+    Ok(Post {
+        slug: row.try_get(0)?,
+        title: row.try_get(1)?,
+        summary: row.try_get(2)?,
+        tags: Vec::new(),
+        content: render_markdown(row.try_get(3)?),
+        published_at: row
+            .try_get::<Option<i64>, _>(4)?
+            .map(from_micros)
+            .transpose()?,
+        updated_at: from_micros(row.try_get(5)?)?,
+    })
+}
+
+impl SqlPostStore {
+    /// Fills the tags of `posts`.
+    async fn with_tags(
+        &self,
+        mut posts: Vec<Post>,
+    ) -> anyhow::Result<Vec<Post>> {
+        if posts.is_empty() {
+            return Ok(posts);
+        }
+        let params: Vec<String> =
+            (1..=posts.len()).map(|n| format!("${n}")).collect();
+        let sql = format!(
+            "SELECT slug, tag FROM post_tags WHERE slug IN ({}) ORDER BY tag",
+            params.join(", ")
+        );
+        let mut tags = query(&sql);
+        for post in &posts {
+            tags = tags.bind(post.slug.clone());
+        }
+        let tags = tags.fetch_all(self.db.pool(Access::ReadOnly));
+        let rows = self.db.observe("posts.tags", &sql, tags).await?;
+        for row in &rows {
+            let slug: String = row.try_get(0)?;
+            if let Some(post) = posts.iter_mut().find(|p| p.slug == slug) {
+                post.tags.push(row.try_get(1)?);
+            }
+        }
+        Ok(posts)
+    }
+}
+
+impl PostStore for SqlPostStore {
+    fn published<'a>(
+        &'a self,
+        tag: Option<&'a str>,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Post>>> {
+        Box::pin(async move {
+            let now = to_micros(OffsetDateTime::now_utc());
+            let pool = self.db.pool(Access::ReadOnly);
+            let rows = match tag {
+                None => {
+                    let sql = format!(
+                        "SELECT {POST_COLUMNS} FROM posts \
+                         WHERE published_at <= $1 \
+                         ORDER BY published_at DESC {}",
+                        limit_offset(2)
+                    );
+                    let query = query(&sql)
+                        .bind(now)
+                        .bind(pagination.limit() as i64)
+                        .bind(pagination.offset() as i64)
+                        .fetch_all(pool);
+                    self.db.observe("posts.published", &sql, query).await?
+                }
+                Some(tag) => {
+                    let sql = format!(
+                        "SELECT {POST_COLUMNS} FROM posts \
+                         WHERE published_at <= $1 AND slug IN \
+                         (SELECT slug FROM post_tags WHERE tag = $2) \
+                         ORDER BY published_at DESC {}",
+                        limit_offset(3)
+                    );
+                    let query = query(&sql)
+                        .bind(now)
+                        .bind(tag)
+                        .bind(pagination.limit() as i64)
+                        .bind(pagination.offset() as i64)
+                        .fetch_all(pool);
+                    self.db.observe("posts.tagged", &sql, query).await?
+                }
+            };
+            let posts = rows
+                .iter()
+                .map(post_from_row)
+                .collect::<anyhow::Result<_>>()?;
+            self.with_tags(posts).await
+        })
+    }
+
+    fn find<'a>(
+        &'a self,
+        slug: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Post>>> {
+        Box::pin(async move {
+            let sql =
+                format!("SELECT {POST_COLUMNS} FROM posts WHERE slug = $1");
+            let query = query(&sql)
+                .bind(slug)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("posts.find", &sql, query).await?;
+            let Some(post) = row.as_ref().map(post_from_row).transpose()?
+            else {
+                return Ok(None);
+            };
+            Ok(self.with_tags(vec![post]).await?.pop())
+        })
+    }
+}
+
+/// Adds the published posts to the sitemap.
+pub(crate) struct PostSitemap(pub(crate) Arc<dyn PostStore>);
+
+impl SitemapSource for PostSitemap {
+    fn entries<'a>(
+        &'a self,
+        site: &'a Site,
+    ) -> BoxFuture<'a, Vec<SitemapEntry>> {
+        Box::pin(async move {
+            let mut entries = Vec::new();
+            let mut pagination =
+                Pagination { page: 1, per_page: 500, after: None };
+            loop {
+                let posts = match self.0.published(None, pagination).await {
+                    Ok(posts) => posts,
+                    Err(e) => {
+                        tracing::error!("could not list the posts: {e:#}");
+                        break;
+                    }
+                };
+                let more = posts.len() > pagination.per_page;
+                entries.extend(posts.iter().take(pagination.per_page).map(
+                    |post| SitemapEntry {
+                        loc: site.url_for(&post.path()),
+                        lastmod: post.updated_at.format(&Rfc3339).ok(),
+                        changefreq: None,
+                        priority: Some(0.6),
+                    },
+                ));
+                if !more {
+                    break;
+                }
+                pagination.page += 1;
+            }
+            entries
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BlogQuery {
+    pub(crate) tag: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Posts {
+    pub(crate) posts: Page<Post>,
+    /// Tag the listing is filtered by.
+    pub(crate) tag: Option<String>,
+}
+
+/// The published posts, as a page or as JSON, see [`Negotiate`].
+pub(crate) async fn handler_blog(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    pagination: Pagination,
+    Query(query): Query<BlogQuery>,
+) -> Result<Negotiate<Posts>, ServerError> {
+    let tag = query.tag.filter(|tag| !tag.is_empty());
+    let posts = state.posts.published(tag.as_deref(), pagination).await?;
+    let title = match &tag {
+        Some(tag) => format!("Posts tagged {tag}"),
+        None => "Blog".to_string(),
+    };
+    let meta = Meta::new(&state.settings.site, &title, "/blog");
+    let posts = Posts { posts: Page::new(posts, pagination), tag };
+    Ok(Negotiate::new(view, "posts", posts).page(context! {
+        title => title,
+        meta => meta,
+    }))
+}
+
+/// A post, drafts and scheduled posts only for the users allowed to
+/// preview them.
+pub(crate) async fn handler_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    OptionalUser(user): OptionalUser,
+    Path(slug): Path<String>,
+) -> Result<Response, ServerError> {
+    let post = state.posts.find(&slug).await?;
+    let can_preview = user.is_some_and(|user| {
+        state.settings.rbac.permissions(&user).can(DRAFTS_PERMISSION)
+    });
+    let Some(post) = post.filter(|post| {
+        can_preview || post.is_published(OffsetDateTime::now_utc())
+    }) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let meta = Meta::new(&state.settings.site, &post.title, &post.path())
+        .description(&post.summary)
+        .kind("article");
+    let rendered: Html<String> = view
+        .render(
+            "post",
+            context! {
+                title => &post.title,
+                meta => meta,
+                published => post.is_published(OffsetDateTime::now_utc()),
+                post => post,
+            },
+        )
+        .unwrap();
+    Ok(rendered.into_response())
+}
+
+/// Post as written, in the form of `/admin/posts`.
+#[derive(Debug, Deserialize, Serialize, Validate)]
+struct PostInput {
+    /// Only set on creation, the slug of a post never changes.
+    #[serde(default)]
+    slug: String,
+    #[validate(length(min = 1, max = 200, message = "Can not be empty"))]
+    title: String,
+    #[serde(default)]
+    #[validate(length(max = 500, message = "Is too long"))]
+    summary: String,
+    /// Space separated tags.
+    #[serde(default)]
+    tags: String,
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    body: String,
+    /// RFC 3339 date, empty for a draft.
+    #[serde(default)]
+    published_at: String,
+}
+
+impl PostInput {
+    /// The publication date and the tags, lowercased and deduplicated.
+    fn check(
+        &self,
+    ) -> Result<(Option<OffsetDateTime>, Vec<String>), FormErrors> {
+        let mut errors = FormErrors::default();
+        let published_at = match self.published_at.trim() {
+            "" => None,
+            at => match OffsetDateTime::parse(at, &Rfc3339) {
+                Ok(at) => Some(at),
+                Err(_) => {
+                    errors.add(
+                        "published_at",
+                        "Must be a date like 2026-01-31T09:00:00Z",
+                    );
+                    None
+                }
+            },
+        };
+        let tags: BTreeSet<String> =
+            self.tags.split_whitespace().map(str::to_lowercase).collect();
+        if errors.is_empty() {
+            Ok((published_at, tags.into_iter().collect()))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Lowercase letters, digits and dashes, `new` being the path of the
+/// creation form.
+fn is_slug(slug: &str) -> bool {
+    slug != "new"
+        && (1..=100).contains(&slug.len())
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Request transaction missing, see `transaction::commit`.
+const NO_TRANSACTION: &str = "posts are written in the request transaction";
+
+/// Posts of the database source on `/admin/posts`, markdown files are
+/// edited in the repository. A post and its tags are written in the
+/// transaction of the request, rolled back with any error.
+pub(crate) struct PostResource {
+    db: Database,
+}
+
+/// Resource of the `[posts]` settings, none for files.
+pub(crate) fn resource(
+    settings: &PostsSettings,
+    db: Option<&Database>,
+) -> Option<Arc<dyn Resource>> {
+    match (&settings.source, db) {
+        (PostSource::Database, Some(db)) => {
+            Some(Arc::new(PostResource { db: db.clone() }))
+        }
+        _ => None,
+    }
+}
+
+impl PostResource {
+    /// Replaces the tags of `slug` on `tx`.
+    async fn set_tags(
+        &self,
+        tx: &mut AnyConnection,
+        slug: &str,
+        tags: &[String],
+    ) -> anyhow::Result<()> {
+        let sql = "DELETE FROM post_tags WHERE slug = $1";
+        let delete = query(sql).bind(slug).execute(&mut *tx);
+        self.db.observe("posts.clear_tags", sql, delete).await?;
+        let sql = "INSERT INTO post_tags (slug, tag) VALUES ($1, $2)";
+        for tag in tags {
+            let insert = query(sql).bind(slug).bind(tag).execute(&mut *tx);
+            self.db.observe("posts.add_tag", sql, insert).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Resource for PostResource {
+    fn name(&self) -> &'static str {
+        "posts"
+    }
+
+    fn title(&self) -> &'static str {
+        "Posts"
+    }
+
+    fn singular(&self) -> &'static str {
+        "post"
+    }
+
+    fn permission(&self) -> &'static str {
+        EDIT_PERMISSION
+    }
+
+    fn columns(&self) -> &'static [Column] {
+        &[
+            Column { key: "title", label: "Title" },
+            Column { key: "id", label: "Slug" },
+            Column { key: "status", label: "Status" },
+            Column { key: "updated_at", label: "Updated" },
+        ]
+    }
+
+    fn list<'a>(
+        &'a self,
+        _state: &'a AppState,
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<Value>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT slug, title, published_at, updated_at FROM posts \
+                 ORDER BY updated_at DESC, slug {}",
+                limit_offset(1)
+            );
+            let query = query(&sql)
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64)
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("posts.list", &sql, query).await?;
+            let now = OffsetDateTime::now_utc();
+            rows.iter()
+                .map(|row| {
+                    let published_at = row
+                        .try_get::<Option<i64>, _>(2)?
+                        .map(from_micros)
+                        .transpose()?;
+                    let status = match published_at {
+                        None => "draft",
+                        Some(at) if at > now => "scheduled",
+                        Some(_) => "published",
+                    };
+                    let updated_at = from_micros(row.try_get(3)?)?;
+                    Ok(json!({
+                        "id": row.try_get::<String, _>(0)?,
+                        "title": row.try_get::<String, _>(1)?,
+                        "status": status,
+                        "updated_at": updated_at.format(&Rfc3339)?,
+                    }))
+                })
+                .collect()
+        })
+    }
+
+    fn count<'a>(
+        &'a self,
+        _state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let sql = "SELECT COUNT(*) FROM posts";
+            let query = query(sql).fetch_one(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("posts.count", sql, query).await?;
+            Ok(row.try_get::<i64, _>(0)? as u64)
+        })
+    }
+
+    fn fields(&self, editing: bool) -> Option<Vec<Field>> {
+        let mut fields = Vec::new();
+        if !editing {
+            fields.push(
+                Field::text("slug", "Slug")
+                    .length(Some(1), Some(100))
+                    .pattern("[a-z0-9\\-]+")
+                    .help(
+                        "Path of the post, /blog/<slug>, fixed once created",
+                    ),
+            );
+        }
+        fields.extend([
+            Field::text("title", "Title").length(Some(1), Some(200)),
+            Field::text("summary", "Summary").length(None, Some(500)),
+            Field::text("tags", "Tags").help("Space separated"),
+            Field::new("body", "Body", FieldKind::Textarea)
+                .required()
+                .help("Markdown"),
+            Field::text("published_at", "Published at").help(
+                "e.g. 2026-01-31T09:00:00Z, empty for a draft, a future \
+                 date schedules the post",
+            ),
+        ]);
+        Some(fields)
+    }
+
+    fn create<'a>(
+        &'a self,
+        _state: &'a AppState,
+        conn: Option<&'a mut AnyConnection>,
+        fields: &'a Fields,
+    ) -> BoxFuture<'a, anyhow::Result<Result<String, FormErrors>>> {
+        Box::pin(async move {
+            let input: PostInput = match crud::parse(fields) {
+                Ok(input) => input,
+                Err(errors) => return Ok(Err(errors)),
+            };
+            if !is_slug(&input.slug) {
+                let mut errors = FormErrors::default();
+                errors.add("slug", "Must be lowercase letters, digits or -");
+                return Ok(Err(errors));
+            }
+            let (published_at, tags) = match input.check() {
+                Ok(checked) => checked,
+                Err(errors) => return Ok(Err(errors)),
+            };
+
+            let tx = conn.context(NO_TRANSACTION)?;
+            let sql = format!(
+                "INSERT INTO posts ({POST_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            );
+            let insert = query(&sql)
+                .bind(&input.slug)
+                .bind(&input.title)
+                .bind(&input.summary)
+                .bind(&input.body)
+                .bind(published_at.map(to_micros))
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(&mut *tx);
+            match self.db.observe("posts.create", &sql, insert).await {
+                Ok(_) => {}
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                    let mut errors = FormErrors::default();
+                    errors.add("slug", "Is already taken");
+                    return Ok(Err(errors));
+                }
+                Err(e) => return Err(e.into()),
+            }
+            self.set_tags(tx, &input.slug, &tags).await?;
+            Ok(Ok(input.slug))
+        })
+    }
+
+    fn find<'a>(
+        &'a self,
+        _state: &'a AppState,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<Value>>> {
+        Box::pin(async move {
+            let sql =
+                format!("SELECT {POST_COLUMNS} FROM posts WHERE slug = $1");
+            let post = query(&sql)
+                .bind(id)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let Some(row) =
+                self.db.observe("posts.find_source", &sql, post).await?
+            else {
+                return Ok(None);
+            };
+            let sql = "SELECT tag FROM post_tags WHERE slug = $1 ORDER BY tag";
+            let tags =
+                query(sql).bind(id).fetch_all(self.db.pool(Access::ReadOnly));
+            let tags = self.db.observe("posts.tags", sql, tags).await?;
+            let tags = tags
+                .iter()
+                .map(|row| row.try_get(0))
+                .collect::<Result<Vec<String>, _>>()?;
+            let published_at = match row.try_get::<Option<i64>, _>(4)? {
+                Some(at) => from_micros(at)?.format(&Rfc3339)?,
+                None => String::new(),
+            };
+            Ok(Some(json!({
+                "title": row.try_get::<String, _>(1)?,
+                "summary": row.try_get::<String, _>(2)?,
+                "tags": tags.join(" "),
+                "body": row.try_get::<String, _>(3)?,
+                "published_at": published_at,
+            })))
+        })
+    }
+
+    fn update<'a>(
+        &'a self,
+        _state: &'a AppState,
+        conn: Option<&'a mut AnyConnection>,
+        id: &'a str,
+        fields: &'a Fields,
+    ) -> BoxFuture<'a, anyhow::Result<Result<(), FormErrors>>> {
+        Box::pin(async move {
+            let input: PostInput = match crud::parse(fields) {
+                Ok(input) => input,
+                Err(errors) => return Ok(Err(errors)),
+            };
+            let (published_at, tags) = match input.check() {
+                Ok(checked) => checked,
+                Err(errors) => return Ok(Err(errors)),
+            };
+
+            let tx = conn.context(NO_TRANSACTION)?;
+            let sql = "UPDATE posts SET title = $2, summary = $3, body = $4, \
+                       published_at = $5, updated_at = $6 WHERE slug = $1";
+            let update = query(sql)
+                .bind(id)
+                .bind(&input.title)
+                .bind(&input.summary)
+                .bind(&input.body)
+                .bind(published_at.map(to_micros))
+                .bind(to_micros(OffsetDateTime::now_utc()))
+                .execute(&mut *tx);
+            self.db.observe("posts.update", sql, update).await?;
+            self.set_tags(tx, id, &tags).await?;
+            Ok(Ok(()))
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        _state: &'a AppState,
+        conn: Option<&'a mut AnyConnection>,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let tx = conn.context(NO_TRANSACTION)?;
+            self.set_tags(tx, id, &[]).await?;
+            let sql = "DELETE FROM posts WHERE slug = $1";
+            let delete = query(sql).bind(id).execute(&mut *tx);
+            let result = self.db.observe("posts.delete", sql, delete).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+}
@@ -0,0 +1,153 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{HeaderMap, StatusCode, request::Parts},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    SignedCookieJar,
+    cookie::{Cookie, Key, SameSite},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use time::Duration;
+use validator::Validate;
+
+use crate::helpers::referer_path;
+use crate::router::ValidatedForm;
+use crate::state::AppState;
+use crate::view::{View, ViewContext};
+
+pub(crate) const PREFERENCES_COOKIE: &str = "preferences";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Theme {
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Density {
+    #[default]
+    Comfortable,
+    Compact,
+}
+
+/// User preferences kept in a signed cookie, for state that must not
+/// depend on a session.
+///
+/// Extracting it never fails: a missing or tampered cookie yields the
+/// defaults. The [`inject`] middleware also exposes it to every template
+/// as `preferences`.
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+pub(crate) struct Preferences {
+    pub(crate) theme: Theme,
+    pub(crate) density: Density,
+    #[validate(length(min = 2, max = 16))]
+    pub(crate) locale: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            density: Density::default(),
+            locale: "en".to_string(),
+        }
+    }
+}
+
+impl Preferences {
+    fn from_headers(headers: &HeaderMap, key: Key) -> Self {
+        SignedCookieJar::from_headers(headers, key)
+            .get(PREFERENCES_COOKIE)
+            .and_then(|cookie| serde_urlencoded::from_str(cookie.value()).ok())
+            .unwrap_or_default()
+    }
+
+    fn into_cookie(self) -> Cookie<'static> {
+        let value = serde_urlencoded::to_string(&self).unwrap();
+        Cookie::build((PREFERENCES_COOKIE, value))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(Duration::days(365))
+            .build()
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Preferences {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(preferences) = parts.extensions.get::<Preferences>() {
+            return Ok(preferences.clone());
+        }
+        Ok(Preferences::from_headers(&parts.headers, state.cookie_key.clone()))
+    }
+}
+
+pub(crate) async fn inject(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let preferences =
+        Preferences::from_headers(req.headers(), state.cookie_key.clone());
+    ViewContext::insert(req.extensions_mut(), "preferences", &preferences);
+    req.extensions_mut().insert(preferences);
+    next.run(req).await
+}
+
+pub(crate) async fn handler_preferences(
+    view: View,
+    preferences: Preferences,
+) -> Result<Html<String>, StatusCode> {
+    let rendered = view
+        .render(
+            "preferences",
+            context! {
+                title => "Preferences",
+                current => preferences,
+            },
+        )
+        .unwrap();
+
+    Ok(rendered)
+}
+
+pub(crate) async fn update_preferences(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ValidatedForm(preferences): ValidatedForm<Preferences>,
+) -> impl IntoResponse {
+    let back = referer_path(&state.settings.site.url, &headers);
+    let jar =
+        SignedCookieJar::from_headers(&headers, state.cookie_key.clone());
+    (jar.add(preferences.into_cookie()), Redirect::to(&back))
+}
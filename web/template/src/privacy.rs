@@ -0,0 +1,403 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::audit::{Audit, AuditEvent};
+use crate::auth::CurrentUser;
+use crate::email::render_email;
+use crate::flash::Flash;
+use crate::helpers::BoxFuture;
+use crate::jobs::Job;
+use crate::router::ServerError;
+use crate::scheduler::Task;
+use crate::signed_url::SignedUrl;
+use crate::state::AppState;
+use crate::storage;
+use crate::sudo::RequireSudo;
+use crate::users::User;
+use crate::view::View;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PrivacySettings {
+    /// Days between a deletion request and the deletion itself.
+    pub(crate) deletion_grace_days: i64,
+    /// Seconds the emailed link to an export stays valid.
+    pub(crate) export_link_ttl: i64,
+}
+
+/// Personal data kept by a part of the application. Every part storing
+/// data about users registers one in `AppState::personal_data`, so exports
+/// and deletions cover it.
+pub(crate) trait PersonalData: Send + Sync {
+    /// Key of the section in the export.
+    fn name(&self) -> &'static str;
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>>;
+
+    /// Removes the data of `user_id`. Runs again when the deletion is
+    /// retried, so data already gone is not an error.
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// The user record and their latest export.
+pub(crate) struct Profile;
+
+impl PersonalData for Profile {
+    fn name(&self) -> &'static str {
+        "profile"
+    }
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        Box::pin(async move {
+            let user = state.users.find_by_id(user_id).await?;
+            Ok(serde_json::to_value(user)?)
+        })
+    }
+
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            match state.storage.delete(&export_key(user_id)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            state.users.delete(user_id).await?;
+            Ok(())
+        })
+    }
+}
+
+/// The API keys of the user, without their secret.
+pub(crate) struct ApiKeys;
+
+impl PersonalData for ApiKeys {
+    fn name(&self) -> &'static str {
+        "api_keys"
+    }
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        Box::pin(async move {
+            let keys = state.api_keys.list_for_user(user_id).await?;
+            Ok(serde_json::to_value(keys)?)
+        })
+    }
+
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(state.api_keys.delete_for_user(user_id))
+    }
+}
+
+/// Storage key of the export of `user_id`, replaced by each new export.
+fn export_key(user_id: Uuid) -> String {
+    format!("exports/{user_id}.json")
+}
+
+/// Assembles the export of a user and emails them a link to it, also sent
+/// to their open pages as an `export.ready` notification.
+pub(crate) struct ExportJob {
+    pub(crate) user_id: Uuid,
+}
+
+impl Job for ExportJob {
+    fn name(&self) -> &'static str {
+        "privacy.export"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            // Gone since the request, nothing left to export.
+            let Some(user) = state.users.find_by_id(self.user_id).await?
+            else {
+                return Ok(());
+            };
+
+            let mut sections = Map::new();
+            for source in &state.personal_data {
+                let data = source.export(state, user.id).await?;
+                sections.insert(source.name().to_string(), data);
+            }
+            let export = json!({
+                "exported_at": OffsetDateTime::now_utc().format(&Rfc3339)?,
+                "data": sections,
+            });
+            let bytes = Bytes::from(serde_json::to_vec_pretty(&export)?);
+            state.storage.put(&export_key(user.id), bytes).await?;
+
+            let path = state.url_signer.sign(
+                &format!("/account/export/{}", user.id),
+                state.settings.privacy.export_link_ttl,
+            );
+            let email = render_email(
+                &state.email_env,
+                "data_export",
+                context! {
+                    name => user.name,
+                    url => state.settings.site.url_for(&path),
+                    days => state.settings.privacy.export_link_ttl / 86400,
+                },
+            )?;
+            state.mailer.send(&user.email, &email).await?;
+            state.notifications.send_to(
+                user.id,
+                "export.ready",
+                &json!({ "url": path }),
+            );
+            Ok(())
+        })
+    }
+}
+
+/// Erases a user whose deletion is still scheduled and due.
+pub(crate) struct DeleteAccountJob {
+    pub(crate) user_id: Uuid,
+}
+
+impl Job for DeleteAccountJob {
+    fn name(&self) -> &'static str {
+        "privacy.delete_account"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let due = state
+                .users
+                .find_by_id_with_trashed(self.user_id)
+                .await?
+                .and_then(|user| user.deletion_scheduled_at)
+                .is_some_and(|at| at <= OffsetDateTime::now_utc());
+            if !due {
+                return Ok(());
+            }
+            // The profile goes last, so a failed run is found and retried by
+            // the next sweep.
+            for source in state.personal_data.iter().rev() {
+                source.erase(state, self.user_id).await?;
+            }
+            info!(user = %self.user_id, "account deleted");
+            let event = AuditEvent::new(
+                "privacy.account_deleted",
+                None,
+                Some(self.user_id.to_string()),
+                json!({}),
+            );
+            if let Err(e) = state.audit.record(event).await {
+                error!(
+                    "could not record the privacy.account_deleted audit \
+                     event: {e:#}"
+                );
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Scheduled task queueing the deletion of the accounts whose grace period
+/// is over.
+pub(crate) struct DeletionSweep;
+
+impl Task for DeletionSweep {
+    fn name(&self) -> &'static str {
+        "privacy.deletion_sweep"
+    }
+
+    fn schedule(&self) -> &'static str {
+        "@hourly"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(sweep_deletions(state))
+    }
+}
+
+async fn sweep_deletions(state: &AppState) -> anyhow::Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let due = state
+        .users
+        .due_for_deletion(now)
+        .await
+        .context("listing the accounts to delete")?;
+    for user_id in due {
+        state
+            .jobs
+            .push(DeleteAccountJob { user_id })
+            .await
+            .context("queueing the account deletion")?;
+    }
+    Ok(())
+}
+
+fn render_privacy(
+    state: &AppState,
+    view: &View,
+    user: User,
+    export_requested: bool,
+) -> Html<String> {
+    view.render(
+        "privacy",
+        context! {
+            title => "Privacy",
+            user => user,
+            sections => state
+                .personal_data
+                .iter()
+                .map(|source| source.name())
+                .collect::<Vec<_>>(),
+            grace_days => state.settings.privacy.deletion_grace_days,
+            export_requested => export_requested,
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_privacy(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Html<String> {
+    render_privacy(&state, &view, user, false)
+}
+
+/// Queues an export of the user's data, emailed once ready.
+pub(crate) async fn handler_export(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+) -> Result<Html<String>, ServerError> {
+    state.jobs.push(ExportJob { user_id: user.id }).await?;
+    audit
+        .record(
+            "privacy.export_requested",
+            Some(user.id.to_string()),
+            json!({}),
+        )
+        .await;
+    Ok(render_privacy(&state, &view, user, true))
+}
+
+/// Serves the export linked from the email, to its owner only.
+pub(crate) async fn handler_export_download(
+    State(state): State<Arc<AppState>>,
+    _: SignedUrl,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<Uuid>,
+) -> Response {
+    if id != user.id {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    storage::download(
+        state.storage.as_ref(),
+        &export_key(id),
+        "application/json",
+        "export.json",
+    )
+    .await
+}
+
+/// Schedules the deletion of the account after the grace period.
+pub(crate) async fn handler_delete(
+    _: RequireSudo,
+    State(state): State<Arc<AppState>>,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+) -> Result<Redirect, ServerError> {
+    let at = OffsetDateTime::now_utc()
+        + time::Duration::days(state.settings.privacy.deletion_grace_days);
+    state.users.schedule_deletion(user.id, Some(at)).await?;
+    info!(user = %user.id, "account deletion scheduled");
+    audit
+        .record(
+            "privacy.deletion_requested",
+            Some(user.id.to_string()),
+            json!({ "at": at.format(&Rfc3339).map_err(anyhow::Error::from)? }),
+        )
+        .await;
+    flash.info(format!(
+        "Your account will be deleted in {} days.",
+        state.settings.privacy.deletion_grace_days
+    ));
+    Ok(Redirect::to("/account/privacy"))
+}
+
+pub(crate) async fn handler_delete_cancel(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+) -> Result<Redirect, ServerError> {
+    if user.deletion_scheduled_at.is_some() {
+        state.users.schedule_deletion(user.id, None).await?;
+        info!(user = %user.id, "account deletion cancelled");
+        audit
+            .record(
+                "privacy.deletion_cancelled",
+                Some(user.id.to_string()),
+                json!({}),
+            )
+            .await;
+        flash.success("Your account will not be deleted.");
+    }
+    Ok(Redirect::to("/account/privacy"))
+}
@@ -0,0 +1,209 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use axum::{
+    Json,
+    extract::rejection::JsonRejection,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::error;
+use utoipa::ToSchema;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::users::UserStoreError;
+
+pub(crate) const PROBLEM_JSON: &str = "application/problem+json";
+
+/// RFC 9457 problem details, sent as `application/problem+json`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The request id, in the API scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Per-field validation failures, an extension member.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Violation {
+    /// Dotted path of the field, e.g. `address.city` or `items[0].name`.
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            kind: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: None,
+            instance: None,
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+impl From<JsonRejection> for Problem {
+    fn from(rejection: JsonRejection) -> Self {
+        Problem::new(rejection.status()).detail(rejection.body_text())
+    }
+}
+
+impl From<ValidationErrors> for Problem {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut problem = Problem::new(StatusCode::UNPROCESSABLE_ENTITY)
+            .detail("The request body failed validation.");
+        collect_violations(&errors, "", &mut problem.violations);
+        problem
+    }
+}
+
+pub(crate) fn collect_violations(
+    errors: &ValidationErrors,
+    prefix: &str,
+    violations: &mut Vec<Violation>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                violations.extend(errors.iter().map(|error| {
+                    Violation {
+                        field: path.clone(),
+                        code: error.code.to_string(),
+                        message: error
+                            .message
+                            .as_ref()
+                            .map(|message| message.to_string())
+                            .unwrap_or_else(|| error.code.to_string()),
+                    }
+                }));
+            }
+            ValidationErrorsKind::Struct(errors) => {
+                collect_violations(errors, &path, violations);
+            }
+            ValidationErrorsKind::List(list) => {
+                for (index, errors) in list {
+                    collect_violations(
+                        errors,
+                        &format!("{path}[{index}]"),
+                        violations,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Errors of the API handlers, answered as problem details.
+#[derive(Debug, Error)]
+pub(crate) enum ApiError {
+    #[error(transparent)]
+    Validation(#[from] ValidationErrors),
+
+    #[error(transparent)]
+    Json(#[from] JsonRejection),
+
+    /// Missing, invalid or expired credentials.
+    #[error("{0}")]
+    Unauthorized(&'static str),
+
+    /// Credentials lacking a permission or a scope.
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("not found")]
+    NotFound,
+
+    #[error(transparent)]
+    Users(#[from] UserStoreError),
+
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<ApiError> for Problem {
+    fn from(error: ApiError) -> Self {
+        match error {
+            ApiError::Validation(errors) => errors.into(),
+            ApiError::Json(rejection) => rejection.into(),
+            ApiError::Unauthorized(detail) => {
+                Problem::new(StatusCode::UNAUTHORIZED).detail(detail)
+            }
+            ApiError::Forbidden(detail) => {
+                Problem::new(StatusCode::FORBIDDEN).detail(detail)
+            }
+            ApiError::NotFound => Problem::new(StatusCode::NOT_FOUND),
+            ApiError::Users(error) => {
+                error!("user store failed: {error}");
+                Problem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            ApiError::Internal(error) => {
+                error!("{error:#}");
+                Problem::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        Problem::from(self).into_response()
+    }
+}
+
+/// Logs `error` and answers with a bare 500 problem.
+pub(crate) fn internal(error: impl Into<anyhow::Error>) -> Response {
+    error!("{:#}", error.into());
+    Problem::new(StatusCode::INTERNAL_SERVER_ERROR).into_response()
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, Json(&self)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON),
+        );
+        // Kept for the middlewares completing it, e.g. with the `instance`
+        // of the API scope.
+        response.extensions_mut().insert(self);
+        response
+    }
+}
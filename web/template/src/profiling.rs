@@ -0,0 +1,153 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_client_ip::ClientIp;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::audit::Audit;
+use crate::state::AppState;
+use crate::version;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ProfilingSettings {
+    /// Profile only for loopback and private addresses, on top of the
+    /// `debug.profile` permission.
+    pub(crate) internal_only: bool,
+    /// Samples per second.
+    #[cfg_attr(not(feature = "pprof"), allow(dead_code))]
+    pub(crate) frequency: i32,
+    /// Longest profile allowed, in seconds.
+    pub(crate) max_seconds: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProfileFormat {
+    /// SVG flame graph, opened in a browser.
+    #[default]
+    Flamegraph,
+    /// Protobuf profile for `go tool pprof` and compatible viewers.
+    Pprof,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ProfileQuery {
+    #[serde(default = "default_seconds")]
+    seconds: u64,
+    #[serde(default)]
+    format: ProfileFormat,
+}
+
+fn default_seconds() -> u64 {
+    10
+}
+
+/// Samples the CPU of the whole process for `seconds` and answers with a
+/// flame graph or a pprof profile, to look into latency in production
+/// without a redeploy. One profile runs at a time.
+pub(crate) async fn handler_profile(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    audit: Audit,
+    Query(query): Query<ProfileQuery>,
+) -> Response {
+    let settings = &state.settings.profiling;
+    if settings.internal_only && !version::is_internal(ip) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let seconds = query.seconds.clamp(1, settings.max_seconds);
+    audit
+        .record(
+            "admin.profile",
+            None,
+            json!({ "seconds": seconds, "format": query.format }),
+        )
+        .await;
+    profile(settings, seconds, query.format).await
+}
+
+#[cfg(feature = "pprof")]
+async fn profile(
+    settings: &ProfilingSettings,
+    seconds: u64,
+    format: ProfileFormat,
+) -> Response {
+    use axum::http::header;
+    use pprof::protos::Message;
+
+    let frequency = settings.frequency;
+    // Sampling is driven by a signal timer, the thread only waits, but
+    // symbolizing the report is blocking work.
+    let result = tokio::task::spawn_blocking(move || {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(frequency)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()?;
+        std::thread::sleep(std::time::Duration::from_secs(seconds));
+        let report = guard.report().build()?;
+        let mut body = Vec::new();
+        match format {
+            ProfileFormat::Flamegraph => report.flamegraph(&mut body)?,
+            ProfileFormat::Pprof => {
+                report.pprof()?.write_to_vec(&mut body)?;
+            }
+        }
+        anyhow::Ok(body)
+    })
+    .await;
+
+    let body = match result {
+        Ok(Ok(body)) => body,
+        Ok(Err(e))
+            if matches!(e.downcast_ref(), Some(pprof::Error::Running)) =>
+        {
+            return (StatusCode::CONFLICT, "A profile is already running")
+                .into_response();
+        }
+        Ok(Err(e)) => return crate::problem::internal(e),
+        Err(e) => return crate::problem::internal(e),
+    };
+    match format {
+        ProfileFormat::Flamegraph => {
+            ([(header::CONTENT_TYPE, "image/svg+xml")], body).into_response()
+        }
+        ProfileFormat::Pprof => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"profile.pb\"",
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(not(feature = "pprof"))]
+async fn profile(_: &ProfilingSettings, _: u64, _: ProfileFormat) -> Response {
+    (StatusCode::NOT_IMPLEMENTED, "Built without the pprof feature")
+        .into_response()
+}
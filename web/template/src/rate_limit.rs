@@ -0,0 +1,133 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use time::OffsetDateTime;
+use tower_sessions_redis_store::fred::prelude::KeysInterface;
+use tracing::error;
+
+use crate::redis::Redis;
+
+/// Keys tracked before stale ones are swept.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(crate) struct RateLimitSettings {
+    /// Attempts allowed per key within `window`.
+    pub(crate) max: usize,
+    /// Length of the sliding window, in seconds.
+    pub(crate) window: u64,
+}
+
+/// Limiter keyed by e.g. client IP or email.
+///
+/// In memory it is a sliding window counted per process, so every instance
+/// enforces its own limit. With `redis.rate_limits` the instances share a
+/// fixed window counter in Redis.
+pub(crate) struct RateLimiter {
+    max: usize,
+    window: Duration,
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+    /// Server and name of the limiter, the `rate` keys are
+    /// `<name>:<key>:<window>`.
+    redis: Option<(Redis, &'static str)>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(settings: RateLimitSettings) -> Self {
+        RateLimiter {
+            max: settings.max,
+            window: Duration::from_secs(settings.window),
+            hits: Mutex::default(),
+            redis: None,
+        }
+    }
+
+    /// Limiter counting in `redis` under `name`.
+    pub(crate) fn shared(
+        settings: RateLimitSettings,
+        redis: Redis,
+        name: &'static str,
+    ) -> Self {
+        RateLimiter {
+            redis: Some((redis, name)),
+            ..RateLimiter::new(settings)
+        }
+    }
+
+    /// Records an attempt for `key`, false when it is over the limit.
+    /// Attempts are let through while Redis fails, rather than locking
+    /// everyone out.
+    pub(crate) async fn check(&self, key: &str) -> bool {
+        let Some((redis, name)) = &self.redis else {
+            return self.check_memory(key);
+        };
+        match self.check_redis(redis, name, key).await {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                error!(limiter = name, "could not count the attempt: {e:#}");
+                true
+            }
+        }
+    }
+
+    async fn check_redis(
+        &self,
+        redis: &Redis,
+        name: &str,
+        key: &str,
+    ) -> anyhow::Result<bool> {
+        let window = self.window.as_secs().max(1);
+        let index = OffsetDateTime::now_utc().unix_timestamp() as u64 / window;
+        let key = redis.key("rate", &format!("{name}:{key}:{index}"));
+        let pipeline = redis.pool().next().pipeline();
+        pipeline.incr::<(), _>(&key).await?;
+        pipeline.expire::<(), _>(&key, window as i64, None).await?;
+        let (count, _): (usize, i64) = pipeline.all().await?;
+        Ok(count <= self.max)
+    }
+
+    fn check_memory(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() > SWEEP_THRESHOLD {
+            hits.retain(|_, attempts| {
+                attempts.back().is_some_and(|last| {
+                    now.duration_since(*last) < self.window
+                })
+            });
+        }
+
+        let attempts = hits.entry(key.to_string()).or_default();
+        while attempts
+            .front()
+            .is_some_and(|first| now.duration_since(*first) >= self.window)
+        {
+            attempts.pop_front();
+        }
+        if attempts.len() >= self.max {
+            return false;
+        }
+        attempts.push_back(now);
+        true
+    }
+}
@@ -0,0 +1,170 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::BTreeMap,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use minijinja::State;
+use serde::Deserialize;
+use tower::{Layer, Service};
+
+use crate::auth::CurrentUser;
+use crate::helpers::BoxFuture;
+use crate::users::User;
+
+/// Grants every permission.
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RbacSettings {
+    /// Permissions of each role, e.g. `editor = ["posts.*"]`.
+    pub(crate) roles: BTreeMap<String, Vec<String>>,
+    /// Roles given to new users.
+    pub(crate) default_roles: Vec<String>,
+    /// Emails given the `admin` role on sign up, to bootstrap the first
+    /// admin.
+    pub(crate) admins: Vec<String>,
+}
+
+impl RbacSettings {
+    /// Roles of a user signing up with `email`.
+    pub(crate) fn roles_for_new_user(&self, email: &str) -> Vec<String> {
+        let mut roles = self.default_roles.clone();
+        let is_admin =
+            self.admins.iter().any(|admin| admin.eq_ignore_ascii_case(email));
+        if is_admin {
+            roles.push("admin".to_string());
+        }
+        roles
+    }
+
+    /// Permissions granted to `user` through its roles, unknown roles grant
+    /// nothing.
+    pub(crate) fn permissions(&self, user: &User) -> Permissions {
+        let mut granted: Vec<String> = user
+            .roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .cloned()
+            .collect();
+        granted.sort();
+        granted.dedup();
+        Permissions(granted)
+    }
+}
+
+/// `granted` covers `permission` when equal, when it is `*`, or when it is
+/// a `prefix.*` covering the permissions under `prefix`.
+fn grants(granted: &str, permission: &str) -> bool {
+    if granted == WILDCARD || granted == permission {
+        return true;
+    }
+    granted.strip_suffix(".*").is_some_and(|prefix| {
+        permission
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Permissions of the logged in user, added to the request extensions by
+/// `auth::load_user`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Permissions(pub(crate) Vec<String>);
+
+impl Permissions {
+    pub(crate) fn can(&self, permission: &str) -> bool {
+        self.0.iter().any(|granted| grants(granted, permission))
+    }
+}
+
+/// `can("posts.edit")` template function, reading the `permissions` the
+/// view context holds for the logged in user.
+pub(crate) fn can(state: &State, permission: &str) -> bool {
+    let Some(granted) = state.lookup("permissions") else {
+        return false;
+    };
+    let Ok(granted) = granted.try_iter() else {
+        return false;
+    };
+    granted
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .any(|value| grants(&value, permission))
+}
+
+/// Route guard letting through users holding a permission, e.g.
+/// `.route_layer(RequirePermission("posts.edit"))`.
+///
+/// Anonymous requests are sent to the login page, users without the
+/// permission get a 403.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RequirePermission(pub(crate) &'static str);
+
+impl<S> Layer<S> for RequirePermission {
+    type Service = RequirePermissionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequirePermissionService { inner, permission: self.0 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RequirePermissionService<S> {
+    inner: S,
+    permission: &'static str,
+}
+
+impl<S> Service<Request> for RequirePermissionService<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        if req.extensions().get::<CurrentUser>().is_none() {
+            return Box::pin(async {
+                Ok(Redirect::to("/login").into_response())
+            });
+        }
+        let allowed = req
+            .extensions()
+            .get::<Permissions>()
+            .is_some_and(|permissions| permissions.can(self.permission));
+        if !allowed {
+            return Box::pin(async {
+                Ok(StatusCode::FORBIDDEN.into_response())
+            });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
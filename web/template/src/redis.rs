@@ -0,0 +1,160 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tower_sessions_redis_store::fred::prelude::{
+    ClientLike, Config, Expiration, KeysInterface, Pool, ReconnectPolicy,
+};
+use tracing::warn;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RedisSettings {
+    /// Server shared by the instances, e.g. `redis://127.0.0.1:6379/0`,
+    /// none when left out.
+    pub(crate) url: Option<String>,
+    pub(crate) pool_size: usize,
+    /// First part of every key, so applications can share a server.
+    pub(crate) namespace: String,
+    /// Count the rate limits in Redis, shared by every instance, instead
+    /// of in each process.
+    pub(crate) rate_limits: bool,
+}
+
+/// Connects a pool to `url`. The reconnect policy retries forever, so
+/// the start fails instead of hanging when the server is unreachable.
+pub(crate) async fn connect_pool(
+    url: &str,
+    pool_size: usize,
+) -> anyhow::Result<Pool> {
+    let pool = Pool::new(
+        Config::from_url(url)?,
+        None,
+        None,
+        Some(ReconnectPolicy::default()),
+        pool_size,
+    )?;
+    tokio::time::timeout(CONNECT_TIMEOUT, pool.init())
+        .await
+        .map_err(|_| anyhow!("timed out connecting to {url}"))??;
+    Ok(pool)
+}
+
+/// Connection to the `[redis]` server. Keys are named
+/// `<namespace>:<area>:<key>`, e.g. `app:cache:home`, see [`Redis::key`],
+/// and everything but the sessions expires.
+#[derive(Clone)]
+pub(crate) struct Redis {
+    pool: Pool,
+    namespace: String,
+}
+
+impl Redis {
+    /// Connects to the server of `settings`, `None` when it has no url.
+    pub(crate) async fn connect(
+        settings: &RedisSettings,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(url) = &settings.url else {
+            return Ok(None);
+        };
+        Ok(Some(Redis {
+            pool: connect_pool(url, settings.pool_size).await?,
+            namespace: settings.namespace.clone(),
+        }))
+    }
+
+    pub(crate) fn pool(&self) -> &Pool {
+        &self.pool
+    }
+
+    /// Full name of `key` in `area`, e.g. `cache` or `rate`.
+    pub(crate) fn key(&self, area: &str, key: &str) -> String {
+        format!("{}:{area}:{key}", self.namespace)
+    }
+
+    pub(crate) async fn ping(&self) -> anyhow::Result<()> {
+        self.pool.ping::<()>(None).await?;
+        Ok(())
+    }
+
+    /// Cached value of `key`, stored as JSON.
+    pub(crate) async fn cache_get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> anyhow::Result<Option<T>> {
+        let value: Option<String> =
+            self.pool.get(self.key("cache", key)).await?;
+        Ok(value.map(|value| serde_json::from_str(&value)).transpose()?)
+    }
+
+    /// Caches `value` under `key` for `ttl`, rounded up to the second.
+    pub(crate) async fn cache_set<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let seconds = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+        self.pool
+            .set::<(), _, _>(
+                self.key("cache", key),
+                serde_json::to_string(value)?,
+                Some(Expiration::EX(seconds.max(1) as i64)),
+                None,
+                false,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Drops the cached `key`, for the writes making it stale.
+    #[allow(dead_code)]
+    pub(crate) async fn cache_delete(&self, key: &str) -> anyhow::Result<()> {
+        self.pool.del::<(), _>(self.key("cache", key)).await?;
+        Ok(())
+    }
+
+    /// Cached value of `key`, computed by `compute` and cached for `ttl`
+    /// when missing. The cache is an optimization: when Redis fails the
+    /// value is computed and the error logged.
+    pub(crate) async fn cache_get_or_set<T, F, Fut>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        compute: F,
+    ) -> anyhow::Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        match self.cache_get(key).await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) => warn!(key, "could not read the cache: {e:#}"),
+        }
+        let value = compute().await?;
+        if let Err(e) = self.cache_set(key, &value, ttl).await {
+            warn!(key, "could not write the cache: {e:#}");
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,57 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{fmt::Write, sync::Arc};
+
+use axum::{extract::State, http::header, response::IntoResponse};
+use serde::Deserialize;
+
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RobotsSettings {
+    pub(crate) allow: Vec<String>,
+    pub(crate) disallow: Vec<String>,
+    /// Advertise `/sitemap.xml` when the sitemap is enabled.
+    pub(crate) sitemap: bool,
+}
+
+/// Serves `/robots.txt`. Outside of the production run mode every crawler
+/// is told to stay away, so staging environments never get indexed.
+pub(crate) async fn handler_robots(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let settings = &state.settings;
+    let robots = &settings.robots;
+
+    let mut body = String::from("User-agent: *\n");
+    if settings.is_production() {
+        for path in &robots.allow {
+            writeln!(body, "Allow: {path}").unwrap();
+        }
+        for path in &robots.disallow {
+            writeln!(body, "Disallow: {path}").unwrap();
+        }
+        if robots.sitemap && settings.sitemap.enabled {
+            let sitemap = settings.site.url_for("/sitemap.xml");
+            writeln!(body, "\nSitemap: {sitemap}").unwrap();
+        }
+    } else {
+        body.push_str("Disallow: /\n");
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body)
+}
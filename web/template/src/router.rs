@@ -14,24 +14,27 @@
 // OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 //
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use axum::{
-    Router,
-    extract::{Form, FromRequest, Request, State, rejection::FormRejection},
+    Json, Router,
+    extract::{
+        DefaultBodyLimit, Form, FromRequest, MatchedPath, Request, State,
+        rejection::{FormRejection, JsonRejection},
+    },
     http::{self, HeaderName, StatusCode},
     middleware,
     response::{Html, IntoResponse, Redirect, Response},
-    routing::get,
+    routing::{get, post},
 };
 use axum_client_ip::{ClientIp, ClientIpSource};
-use axum_csrf::{CsrfConfig, CsrfLayer, CsrfToken, Key};
-use axum_messages::{Messages, MessagesManagerLayer};
+use axum_csrf::{CsrfConfig, CsrfLayer};
+use axum_messages::MessagesManagerLayer;
+use futures_util::stream;
 use minijinja::context;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use time::Duration;
 use tower_http::{
     request_id::{
         MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer,
@@ -40,106 +43,508 @@ use tower_http::{
     timeout::TimeoutLayer,
     trace::TraceLayer,
 };
-use tower_sessions::{Expiry, MemoryStore, Session, SessionManagerLayer};
+use tower_sessions::{Session, SessionManagerLayer};
 use tracing::{error, info_span};
 use validator::Validate;
 
+use crate::access_log;
+use crate::admin::{handler_admin, handler_log_level, handler_log_level_put};
+use crate::api;
+use crate::api_key::{
+    handler_api_key_revoke, handler_api_keys, handler_api_keys_post,
+};
+use crate::audit::handler_audit;
+use crate::auth::{
+    self, OptionalUser, handler_account, handler_email, handler_email_post,
+    handler_login, handler_login_post, handler_logout, handler_register,
+    handler_register_post,
+};
+use crate::body_log;
+use crate::captcha::CaptchaVerified;
+use crate::consent::{self, handler_consent, update_consent};
+use crate::contact::{handler_contact, handler_contact_post};
+use crate::crud::{
+    handler_resource_delete, handler_resource_edit,
+    handler_resource_edit_post, handler_resource_list, handler_resource_new,
+    handler_resource_new_post,
+};
+use crate::csrf;
+use crate::email::render_email;
+use crate::error_page;
+use crate::error_reporting;
+use crate::export;
+use crate::feed::handler_feed;
+use crate::flags::{self, handler_flag_set, handler_flags};
+use crate::flash::{self, Flash};
+use crate::form::{Field, FormDefinition, FormErrors, FormSpec};
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::health::{handler_livez, handler_readyz};
+use crate::honeypot::Honeypot;
+use crate::impersonation::{handler_impersonate, handler_stop_impersonating};
+use crate::incoming_webhook::handler_incoming_webhook;
+use crate::media::{handler_download, handler_media};
+use crate::meta::Meta;
 use crate::metric::track_metrics;
+use crate::newsletter::{
+    handler_newsletter, handler_newsletter_confirm, handler_newsletter_post,
+    handler_newsletter_unsubscribe, handler_newsletter_unsubscribe_post,
+};
+use crate::oidc::{handler_oidc_callback, handler_oidc_login};
+use crate::password_reset::{
+    handler_forgot_password, handler_forgot_password_post,
+    handler_reset_password, handler_reset_password_post,
+};
+use crate::payments::{
+    handler_checkout, handler_checkout_post, handler_order,
+    handler_payments_webhook,
+};
+use crate::pdf::handler_invoice;
+use crate::posts::{handler_blog, handler_post};
+use crate::preferences::{self, handler_preferences, update_preferences};
+use crate::privacy::{
+    handler_delete, handler_delete_cancel, handler_export,
+    handler_export_download, handler_privacy,
+};
+use crate::problem::Problem;
+use crate::profiling::handler_profile;
+use crate::rbac::RequirePermission;
+use crate::robots::handler_robots;
+use crate::search::{Document, handler_search};
+use crate::session;
+use crate::shortener::{
+    handler_link_delete, handler_links, handler_links_post, handler_short_link,
+};
+use crate::sitemap::handler_sitemap;
+use crate::slow_request;
+use crate::sse::handler_events;
 use crate::state::AppState;
+use crate::sudo::{handler_sudo, handler_sudo_post};
+use crate::tenancy;
+use crate::transaction;
+use crate::upload::{UploadError, handler_upload, handler_upload_post};
+use crate::users::{
+    UserStoreError, handler_user_delete, handler_user_edit,
+    handler_user_edit_post, handler_user_new, handler_user_new_post,
+    handler_user_purge, handler_user_restore, handler_users,
+    handler_users_trash,
+};
+use crate::verification::{
+    self, handler_verify, handler_verify_resend, handler_verify_token,
+};
+use crate::version::handler_version;
+use crate::view::{Negotiate, View};
+use crate::webhooks::{
+    handler_webhook, handler_webhook_delete, handler_webhook_toggle,
+    handler_webhooks, handler_webhooks_post,
+};
+use crate::websocket::handler_ws;
 
 const COUNTER_KEY: &str = "counter";
 const REQUEST_ID_HEADER: &str = "x-request-id";
+// TODO(msi): from config
+/// Time a request may take before it is answered with 408.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Example listing shown on `/content` and published in `/feed.xml`.
+pub(crate) const EXAMPLE_ENTRIES: &[&str] = &["Data 1", "Data 2", "Data 3"];
+
+/// Text of the about page.
+const ABOUT_TEXT: &str = "Simple demonstration layout for an axum project with minijinja as templating engine.";
+
+/// Public pages and their sitemap priority.
+pub(crate) const PUBLIC_ROUTES: &[(&str, f64)] = &[
+    ("/", 1.0),
+    ("/blog", 0.9),
+    ("/content", 0.8),
+    ("/about", 0.5),
+    ("/contact", 0.3),
+];
+
+/// Search documents of the public pages, indexed on start.
+pub(crate) fn search_documents() -> Vec<Document> {
+    let mut documents = vec![Document {
+        kind: "page",
+        id: "about".to_string(),
+        title: "About".to_string(),
+        body: ABOUT_TEXT.to_string(),
+        url: "/about".to_string(),
+    }];
+    for (i, entry) in EXAMPLE_ENTRIES.iter().enumerate() {
+        documents.push(Document {
+            kind: "page",
+            id: format!("content-{}", i + 1),
+            title: "Content".to_string(),
+            body: entry.to_string(),
+            url: format!("/content#entry-{}", i + 1),
+        });
+    }
+    documents
+}
 
 #[derive(Default, Deserialize, Serialize)]
 struct Counter(usize);
 
-#[derive(Deserialize, Serialize)]
-struct Keys {
-    authenticity_token: String,
-}
-
 pub(crate) fn route(app_state: Arc<AppState>) -> Router {
     let x_request_id = HeaderName::from_static(REQUEST_ID_HEADER);
 
-    let session_store = MemoryStore::default();
-    let cookie_key = Key::generate();
+    let session_store = app_state.sessions.clone();
+    // Host-only cookie encrypted with `cookies.key`, so tokens survive
+    // restarts and are accepted by every instance.
     let config = CsrfConfig::default()
-        .with_key(Some(cookie_key))
-        .with_cookie_domain(Some("127.0.0.1"));
+        .with_key(Some(app_state.cookie_key.clone()))
+        .with_secure(app_state.settings.is_production());
 
     // TODO(msi): from config, if debug mode
     let ip_source = ClientIpSource::ConnectInfo;
 
-    Router::new()
+    // Pages only reachable by administrators.
+    let admin = Router::new()
+        .route("/admin", get(handler_admin))
+        .route(
+            "/admin/impersonate",
+            post(handler_impersonate)
+                .route_layer(RequirePermission("users.impersonate")),
+        )
+        .route(
+            "/admin/audit",
+            get(handler_audit).route_layer(RequirePermission("audit.view")),
+        )
+        .merge(
+            Router::new()
+                .route("/admin/users", get(handler_users))
+                .route(
+                    "/admin/users/new",
+                    get(handler_user_new).post(handler_user_new_post),
+                )
+                .route(
+                    "/admin/users/{id}",
+                    get(handler_user_edit).post(handler_user_edit_post),
+                )
+                .route("/admin/users/{id}/delete", post(handler_user_delete))
+                .route("/admin/users/trash", get(handler_users_trash))
+                .route("/admin/users/{id}/restore", post(handler_user_restore))
+                .route("/admin/users/{id}/purge", post(handler_user_purge))
+                .route_layer(RequirePermission("users.manage")),
+        )
+        .merge(
+            Router::new()
+                .route(
+                    "/admin/webhooks",
+                    get(handler_webhooks).post(handler_webhooks_post),
+                )
+                .route("/admin/webhooks/{id}", get(handler_webhook))
+                .route(
+                    "/admin/webhooks/{id}/toggle",
+                    post(handler_webhook_toggle),
+                )
+                .route(
+                    "/admin/webhooks/{id}/delete",
+                    post(handler_webhook_delete),
+                )
+                .route_layer(RequirePermission("webhooks.manage")),
+        )
+        .merge(
+            Router::new()
+                .route("/admin/flags", get(handler_flags))
+                .route("/admin/flags/{name}", post(handler_flag_set))
+                .route_layer(RequirePermission("flags.manage")),
+        )
+        .route(
+            "/admin/profile",
+            get(handler_profile)
+                .route_layer(RequirePermission("debug.profile")),
+        )
+        .route(
+            "/admin/log-level",
+            get(handler_log_level)
+                .put(handler_log_level_put)
+                .route_layer(RequirePermission("logs.manage")),
+        )
+        // Generic pages of the registered resources, each checking its own
+        // permission.
+        .route("/admin/{resource}", get(handler_resource_list))
+        .route(
+            "/admin/{resource}/new",
+            get(handler_resource_new).post(handler_resource_new_post),
+        )
+        .route(
+            "/admin/{resource}/{id}",
+            get(handler_resource_edit).post(handler_resource_edit_post),
+        )
+        .route("/admin/{resource}/{id}/delete", post(handler_resource_delete))
+        .route_layer(RequirePermission("admin.access"));
+
+    // Pages only reachable by logged in users.
+    let protected = Router::new()
+        .route("/account", get(handler_account))
+        .route("/checkout", get(handler_checkout).post(handler_checkout_post))
+        .route("/orders/{id}", get(handler_order))
+        .route(
+            "/account/api-keys",
+            get(handler_api_keys).post(handler_api_keys_post),
+        )
+        .route("/account/api-keys/{id}/revoke", post(handler_api_key_revoke))
+        .route("/account/links", get(handler_links).post(handler_links_post))
+        .route("/account/links/{code}/delete", post(handler_link_delete))
+        .route("/sudo", get(handler_sudo).post(handler_sudo_post))
+        .route("/account/email", get(handler_email).post(handler_email_post))
+        .route("/account/privacy", get(handler_privacy))
+        .route("/account/invoice.pdf", get(handler_invoice))
+        .route("/account/export", post(handler_export))
+        .route("/account/export/{id}", get(handler_export_download))
+        .route("/account/delete", post(handler_delete))
+        .route("/account/delete/cancel", post(handler_delete_cancel))
+        .merge(admin)
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            verification::require_verified,
+        ))
+        // Out of `require_verified`, an admin acting as an unverified user
+        // must still be able to switch back.
+        .route("/impersonate/stop", post(handler_stop_impersonating))
+        .route_layer(middleware::from_fn(auth::require_auth));
+
+    let app = Router::new()
         .route("/", get(handler_home))
         .route("/content", get(handler_content))
+        .route("/content.csv", get(handler_content_csv))
         .route("/about", get(handler_about))
+        .route("/blog", get(handler_blog))
+        .route("/blog/{slug}", get(handler_post))
+        .route("/s/{code}", get(handler_short_link))
+        .route("/contact", get(handler_contact).post(handler_contact_post))
+        .route(
+            "/newsletter",
+            get(handler_newsletter).post(handler_newsletter_post),
+        )
+        .route("/newsletter/confirm", get(handler_newsletter_confirm))
+        .route(
+            "/newsletter/unsubscribe",
+            get(handler_newsletter_unsubscribe)
+                .post(handler_newsletter_unsubscribe_post),
+        )
+        .route("/search", get(handler_search))
         .route("/session", get(handler_session))
         .route("/message", get(set_messages_handler))
-        .route("/read-messages", get(read_messages_handler))
         .route("/csrf", get(csrf_root).post(csrf_check_key))
         .route("/ip", get(ip_handler))
+        .route("/version", get(handler_version))
+        .route("/email-preview", get(handler_email_preview))
+        .route(
+            "/preferences",
+            get(handler_preferences).post(update_preferences),
+        )
+        .route("/consent", get(handler_consent).post(update_consent))
         .route(
             "/validation",
             get(get_validation_handler).post(post_validation_handler),
         )
-        .layer(MessagesManagerLayer)
-        // TODO(msi): from config folder asssets
-        .nest_service("/assets", ServeDir::new("assets"))
-        .layer((
-            SetRequestIdLayer::new(x_request_id.clone(), MakeRequestUuid),
-            TraceLayer::new_for_http().make_span_with(
-                |request: &http::Request<_>| {
-                    // Log the request id as generated.
-                    let request_id = request.headers().get(REQUEST_ID_HEADER);
-
-                    match request_id {
-                        Some(request_id) => info_span!(
-                            "http_request",
-                            request_id = ?request_id,
-                        ),
-                        None => {
-                            error!("could not extract request_id");
-                            info_span!("http_request")
-                        }
-                    }
-                },
+        .route("/validation.json", post(post_validation_json_handler))
+        .route(
+            "/upload",
+            get(handler_upload).post(handler_upload_post).layer(
+                DefaultBodyLimit::max(
+                    app_state.settings.upload.max_request_size,
+                ),
             ),
+        )
+        .route("/media/{id}/{variant}", get(handler_media))
+        .route("/files/{id}", get(handler_download))
+        .route("/register", get(handler_register).post(handler_register_post))
+        .route("/login", get(handler_login).post(handler_login_post))
+        .route("/logout", post(handler_logout))
+        .route(
+            "/forgot-password",
+            get(handler_forgot_password).post(handler_forgot_password_post),
+        )
+        .route(
+            "/reset-password",
+            get(handler_reset_password).post(handler_reset_password_post),
+        )
+        .route("/auth/oidc/{provider}", get(handler_oidc_login))
+        .route("/auth/oidc/{provider}/callback", get(handler_oidc_callback))
+        .route("/verify", get(handler_verify))
+        .route("/verify/resend", post(handler_verify_resend))
+        .route("/verify/{token}", get(handler_verify_token))
+        .route("/ws", get(handler_ws))
+        .route("/events", get(handler_events))
+        .merge(protected)
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            csrf::verify,
+        ))
+        .layer(middleware::from_fn(transaction::commit))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            error_page::render,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            preferences::inject,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            consent::inject,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            flags::inject,
+        ))
+        .layer(middleware::from_fn(error_reporting::capture))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::load_user,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            session::enforce_lifetime,
+        ))
+        .layer(middleware::from_fn(flash::load))
+        .layer((
             SessionManagerLayer::new(session_store)
-                .with_secure(false)
-                .with_expiry(Expiry::OnInactivity(Duration::seconds(10))),
+                .with_secure(app_state.settings.is_production())
+                .with_expiry(app_state.settings.session.expiry())
+                .with_always_save(app_state.settings.session.rolling),
             MessagesManagerLayer,
             CsrfLayer::new(config),
-            ip_source.into_extension(),
-            // TODO(msi): from config
-            TimeoutLayer::new(std::time::Duration::from_secs(10)),
-            PropagateRequestIdLayer::new(x_request_id),
         ))
-        .route_layer(middleware::from_fn(track_metrics))
-        .route("/healthz", get(healthz))
-        .with_state(app_state)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            session::cookie_session,
+        ))
+        // TODO(msi): from config folder asssets
+        .nest_service("/assets", ServeDir::new("assets"))
+        .merge(api::routes(&app_state))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            tenancy::resolve,
+        ));
+    // Internal consumers name no tenant.
+    #[cfg(feature = "grpc")]
+    let app = app.merge(grpc::routes(&app_state));
+    // Signed by their senders, without session, CSRF token or tenant, but
+    // with the request id, tracing and timeout of every request.
+    let app = app
+        .route("/webhooks/{source}", post(handler_incoming_webhook))
+        .route("/payments/webhook", post(handler_payments_webhook));
+
+    app.layer((
+        SetRequestIdLayer::new(x_request_id.clone(), MakeRequestUuid),
+        TraceLayer::new_for_http().make_span_with(
+            |request: &http::Request<_>| {
+                // Log the request id as generated.
+                let request_id = request.headers().get(REQUEST_ID_HEADER);
+
+                match request_id {
+                    Some(request_id) => info_span!(
+                        "http_request",
+                        request_id = request_id.to_str().unwrap_or_default(),
+                        method = %request.method(),
+                        path = request.uri().path(),
+                        route = request
+                            .extensions()
+                            .get::<MatchedPath>()
+                            .map(MatchedPath::as_str),
+                    ),
+                    None => {
+                        error!("could not extract request_id");
+                        info_span!("http_request")
+                    }
+                }
+            },
+        ),
+        ip_source.into_extension(),
+        middleware::from_fn_with_state(app_state.clone(), access_log::log),
+        middleware::from_fn_with_state(app_state.clone(), body_log::log),
+        middleware::from_fn_with_state(
+            app_state.clone(),
+            slow_request::detect,
+        ),
+        TimeoutLayer::new(REQUEST_TIMEOUT),
+        PropagateRequestIdLayer::new(x_request_id),
+    ))
+    .route("/livez", get(handler_livez))
+    .route("/readyz", get(handler_readyz))
+    .route("/sitemap.xml", get(handler_sitemap))
+    .route("/robots.txt", get(handler_robots))
+    .route("/feed.xml", get(handler_feed))
+    .layer(middleware::from_fn_with_state(app_state.clone(), track_metrics))
+    .with_state(app_state)
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct NameInput {
     #[validate(length(min = 2, message = "Can not be empty"))]
     pub name: String,
 }
 
+impl FormDefinition for NameInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/validation")
+            .field(Field::text("name", "Name").length(Some(2), None))
+    }
+}
+
 async fn get_validation_handler(
-    State(state): State<Arc<AppState>>,
+    view: View,
 ) -> Result<Html<String>, ServerError> {
-    let template = state.env.get_template("validation").unwrap();
-
-    let rendered = template.render(context! {}).unwrap();
+    let rendered = view
+        .render(
+            "validation",
+            context! {
+                title => "Validation",
+                form => NameInput::form().empty(),
+            },
+        )
+        .unwrap();
 
-    Ok(Html(rendered))
+    Ok(rendered)
 }
 
+/// Re-renders the form with the submitted values and the validation errors
+/// instead of answering with a bare 400.
 async fn post_validation_handler(
-    ValidatedForm(input): ValidatedForm<NameInput>,
-) -> Html<String> {
-    Html(format!("<h1>Hello, {}!</h1>", input.name))
+    view: View,
+    CaptchaVerified(Honeypot(Form(input))): CaptchaVerified<
+        Honeypot<Form<NameInput>>,
+    >,
+) -> Response {
+    if let Err(errors) = input.validate() {
+        let form = NameInput::form();
+        let rendered = view
+            .render(
+                "validation",
+                context! {
+                    title => "Validation",
+                    form => form.bind(&input, &FormErrors::from(&errors)),
+                },
+            )
+            .unwrap();
+        return (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response();
+    }
+
+    view.render(
+        "validation",
+        context! {
+            title => "Validation",
+            greeting => format!("Hello, {}!", input.name),
+            form => NameInput::form().empty(),
+        },
+    )
+    .unwrap()
+    .into_response()
+}
+
+#[derive(Debug, Serialize)]
+struct Greeting {
+    message: String,
+}
+
+async fn post_validation_json_handler(
+    ValidatedJson(input): ValidatedJson<NameInput>,
+) -> Json<Greeting> {
+    Json(Greeting { message: format!("Hello, {}!", input.name) })
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -163,6 +568,28 @@ where
     }
 }
 
+/// JSON counterpart of [`ValidatedForm`], rejecting with problem details.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+{
+    type Rejection = Problem;
+
+    async fn from_request(
+        req: Request,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ServerError {
     #[error(transparent)]
@@ -170,6 +597,15 @@ pub enum ServerError {
 
     #[error(transparent)]
     AxumFormRejection(#[from] FormRejection),
+
+    #[error(transparent)]
+    Upload(#[from] UploadError),
+
+    #[error(transparent)]
+    Users(#[from] UserStoreError),
+
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
 }
 
 impl IntoResponse for ServerError {
@@ -183,6 +619,30 @@ impl IntoResponse for ServerError {
             ServerError::AxumFormRejection(_) => {
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
+            ServerError::Upload(UploadError::Io(ref error)) => {
+                error!("upload failed: {error}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Upload failed".to_string(),
+                )
+            }
+            ServerError::Upload(ref error) => {
+                (error.status(), self.to_string())
+            }
+            ServerError::Users(ref error) => {
+                error!("user store failed: {error}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
+            ServerError::Internal(ref error) => {
+                error!("{error:#}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
         }
         .into_response()
     }
@@ -192,48 +652,32 @@ async fn ip_handler(ClientIp(ip): ClientIp) -> String {
     ip.to_string()
 }
 
-async fn csrf_root(
-    token: CsrfToken,
-    State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let template = state.env.get_template("csrf").unwrap();
-
-    let rendered = template
-        .render(context! {
-            title => "Csrf",
-            authenticity_token => token.authenticity_token().unwrap(),
-        })
+async fn csrf_root(view: View) -> Result<Html<String>, StatusCode> {
+    let rendered = view
+        .render(
+            "csrf",
+            context! {
+                title => "Csrf",
+            },
+        )
         .unwrap();
-    // We must return the token so that into_response will run and add it to our response cookies.
-    (token, Html(rendered)).into_response()
-}
 
-async fn csrf_check_key(
-    token: CsrfToken,
-    Form(payload): Form<Keys>,
-) -> &'static str {
-    // Verfiy the Hash and return the String message.
-    if token.verify(&payload.authenticity_token).is_err() {
-        "Token is invalid"
-    } else {
-        "Token is Valid lets do stuff!"
-    }
+    Ok(rendered)
 }
 
-async fn set_messages_handler(messages: Messages) -> impl IntoResponse {
-    messages.info("Hello, world!").debug("This is a debug message.");
-
-    Redirect::to("/read-messages")
+/// Only reached once [`csrf::verify`] accepted the authenticity token.
+async fn csrf_check_key() -> &'static str {
+    "Token is Valid lets do stuff!"
 }
 
-async fn read_messages_handler(messages: Messages) -> impl IntoResponse {
-    let messages = messages
-        .into_iter()
-        .map(|message| format!("{}: {}", message.level, message))
-        .collect::<Vec<_>>()
-        .join(", ");
+/// Shows one flash message of each level on the page it redirects to.
+async fn set_messages_handler(flash: Flash) -> Redirect {
+    flash
+        .success("Hello, world!")
+        .info("This is an info message.")
+        .error("This is an error message.");
 
-    if messages.is_empty() { "No messages yet!".to_string() } else { messages }
+    Redirect::to("/")
 }
 
 async fn handler_session(session: Session) -> impl IntoResponse {
@@ -243,51 +687,105 @@ async fn handler_session(session: Session) -> impl IntoResponse {
     format!("Current count: {}", counter.0)
 }
 
-async fn healthz() -> impl IntoResponse {
-    StatusCode::OK
-}
-
 async fn handler_home(
     State(state): State<Arc<AppState>>,
+    view: View,
+    OptionalUser(user): OptionalUser,
 ) -> Result<Html<String>, StatusCode> {
-    let template = state.env.get_template("home").unwrap();
-
-    let rendered = template
-        .render(context! {
-            title => "Home",
-            welcome_text => "Hello World!",
-        })
+    let welcome_text = match user {
+        Some(user) => format!("Hello {}!", user.name),
+        None => "Hello World!".to_string(),
+    };
+    let rendered = view
+        .render(
+            "home",
+            context! {
+                title => "Home",
+                meta => Meta::new(&state.settings.site, "Home", "/")
+                    .description("Hello World!"),
+                welcome_text => welcome_text,
+            },
+        )
         .unwrap();
 
-    Ok(Html(rendered))
+    Ok(rendered)
 }
 
-async fn handler_content(
-    State(state): State<Arc<AppState>>,
-) -> Result<Html<String>, StatusCode> {
-    let template = state.env.get_template("content").unwrap();
+#[derive(Serialize)]
+struct Entries {
+    entries: &'static [&'static str],
+}
 
-    let some_example_entries = vec!["Data 1", "Data 2", "Data 3"];
+#[derive(Serialize)]
+struct EntryRow {
+    id: usize,
+    entry: &'static str,
+}
 
-    let rendered = template
-        .render(context! {
-            title => "Content",
-            entries => some_example_entries,
-        })
-        .unwrap();
+/// The entries as a CSV download, see [`export::csv`].
+async fn handler_content_csv() -> Response {
+    let rows = EXAMPLE_ENTRIES
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| Ok(EntryRow { id: i + 1, entry }));
+    export::csv("content.csv", stream::iter(rows))
+}
 
-    Ok(Html(rendered))
+/// The entries, as a page or as JSON, see [`Negotiate`].
+async fn handler_content(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Negotiate<Entries> {
+    Negotiate::new(view, "content", Entries { entries: EXAMPLE_ENTRIES }).page(
+        context! {
+            title => "Content",
+            meta => Meta::new(&state.settings.site, "Content", "/content"),
+        },
+    )
 }
 
 async fn handler_about(
     State(state): State<Arc<AppState>>,
+    view: View,
 ) -> Result<Html<String>, StatusCode> {
-    let template = state.env.get_template("about").unwrap();
+    let rendered = view
+        .render(
+            "about",
+            context! {
+                title => "About",
+                meta => Meta::new(&state.settings.site, "About", "/about")
+                    .description(ABOUT_TEXT),
+                about_text => ABOUT_TEXT,
+            },
+        )
+        .unwrap();
+
+    Ok(rendered)
+}
 
-    let rendered = template.render(context!{
-        title => "About",
-        about_text => "Simple demonstration layout for an axum project with minijinja as templating engine.",
-    }).unwrap();
+async fn handler_email_preview(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Result<Html<String>, StatusCode> {
+    let email = render_email(
+        &state.email_env,
+        "welcome",
+        context! {
+            name => "World",
+            url => "http://127.0.0.1:3000",
+        },
+    )
+    .unwrap();
+
+    let rendered = view
+        .render(
+            "email_preview",
+            context! {
+                title => "Email Preview",
+                email => email,
+            },
+        )
+        .unwrap();
 
-    Ok(Html(rendered))
+    Ok(rendered)
 }
@@ -0,0 +1,288 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use time::{Date, OffsetDateTime};
+use tracing::{error, info, warn};
+
+use crate::heartbeat::{self, Ping};
+use crate::helpers::BoxFuture;
+use crate::state::AppState;
+
+/// Schedule turning a task off in `scheduler.tasks`.
+const OFF: &str = "off";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SchedulerSettings {
+    /// Upper bound, in seconds, of the random delay added to every run, so
+    /// instances on the same schedule do not all start at once.
+    pub(crate) jitter: u64,
+    /// Cron expressions replacing the schedule of tasks, by name, or `off`.
+    #[serde(default)]
+    pub(crate) tasks: HashMap<String, String>,
+}
+
+/// Work run on a schedule, e.g. a cleanup. Registered in
+/// `AppState::scheduled_tasks`. A run is not retried, the next one is
+/// expected to catch up.
+pub(crate) trait Task: Send + Sync + 'static {
+    /// Name of the task in the settings, the logs, the metrics and the
+    /// heartbeat checks.
+    fn name(&self) -> &'static str;
+
+    /// Cron expression of the runs, unless overridden in the settings.
+    fn schedule(&self) -> &'static str;
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+}
+
+/// Parsed cron expression: `minute hour day-of-month month day-of-week`,
+/// in UTC, each field a `*`, a value, a range `a-b` or a list of them,
+/// optionally stepped with `/n`. Sunday is 0 or 7. `@hourly`, `@daily`,
+/// `@weekly`, `@monthly` and `@yearly` are shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of the month, or of the week, is restricted. When
+    /// both are, a day matching either runs, as in crontab.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    pub(crate) fn parse(expression: &str) -> anyhow::Result<Self> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("expected 5 fields in `{expression}`");
+        };
+        let mut weekdays = field(weekday, 0, 7).context("day of the week")?;
+        // 7 is Sunday too.
+        if weekdays & 1 << 7 != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: field(minute, 0, 59).context("minute")?,
+            hours: field(hour, 0, 23).context("hour")?,
+            days: field(day, 1, 31).context("day of the month")?,
+            months: field(month, 1, 12).context("month")?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    /// First minute strictly after `after` matching the expression, none
+    /// when there is no such day in the next five years, e.g. February 30.
+    pub(crate) fn next_after(
+        &self,
+        after: OffsetDateTime,
+    ) -> Option<OffsetDateTime> {
+        let after = after.to_offset(time::UtcOffset::UTC);
+        let start =
+            after.replace_second(0).ok()?.replace_nanosecond(0).ok()?
+                + Duration::from_secs(60);
+        let mut date = start.date();
+        for _ in 0..5 * 366 {
+            if self.day_matches(date) {
+                for hour in 0..24u8 {
+                    if self.hours & 1 << hour == 0 {
+                        continue;
+                    }
+                    for minute in 0..60u8 {
+                        if self.minutes & 1 << minute == 0 {
+                            continue;
+                        }
+                        let at =
+                            date.with_hms(hour, minute, 0).ok()?.assume_utc();
+                        if at >= start {
+                            return Some(at);
+                        }
+                    }
+                }
+            }
+            date = date.next_day()?;
+        }
+        None
+    }
+
+    fn day_matches(&self, date: Date) -> bool {
+        if self.months & 1 << u8::from(date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & 1 << date.day() != 0;
+        let weekday =
+            self.weekdays & 1 << date.weekday().number_days_from_sunday() != 0;
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+/// Bits of the values of a cron field, between `min` and `max`.
+fn field(spec: &str, min: u8, max: u8) -> anyhow::Result<u64> {
+    let mut bits = 0;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u8>()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("step of 0 in `{spec}`");
+        }
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (first.parse()?, last.parse()?),
+            // `a/n` runs from a to the end.
+            None if step > 1 => (range.parse()?, max),
+            None => {
+                let value = range.parse()?;
+                (value, value)
+            }
+        };
+        if first < min || last > max || first > last {
+            bail!("`{part}` out of {min}-{max}");
+        }
+        for value in (first..=last).step_by(usize::from(step)) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Starts a loop for each of `AppState::scheduled_tasks` not turned off,
+/// failing on an invalid schedule. A run still going when the next one is
+/// due makes it skipped, so a task never overlaps itself in a process.
+/// Runs go to the `scheduler_runs_total` counter, by task and outcome
+/// (`ok`, `error` or `skipped`), and the `scheduler_run_duration_seconds`
+/// histogram, and ping the heartbeat check of the task name.
+pub(crate) fn spawn(state: Arc<AppState>) -> anyhow::Result<()> {
+    let settings = &state.settings.scheduler;
+    for index in 0..state.scheduled_tasks.len() {
+        let task = &state.scheduled_tasks[index];
+        let name = task.name();
+        let schedule = settings
+            .tasks
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or(task.schedule());
+        if schedule == OFF {
+            info!(task = name, "scheduled task turned off");
+            continue;
+        }
+        let cron = Cron::parse(schedule)
+            .with_context(|| format!("schedule of the task {name}"))?;
+        info!(task = name, schedule, "task scheduled");
+        tokio::spawn(schedule_loop(state.clone(), index, cron));
+    }
+    Ok(())
+}
+
+async fn schedule_loop(state: Arc<AppState>, index: usize, cron: Cron) {
+    let name = state.scheduled_tasks[index].name();
+    let running = Arc::new(AtomicBool::new(false));
+    loop {
+        let now = OffsetDateTime::now_utc();
+        let Some(next) = cron.next_after(now) else {
+            warn!(task = name, "the schedule never runs the task");
+            return;
+        };
+        let delay = Duration::try_from(next - now).unwrap_or_default()
+            + jitter(state.settings.scheduler.jitter);
+        tokio::time::sleep(delay).await;
+
+        if running.swap(true, Ordering::AcqRel) {
+            warn!(task = name, "previous run still going, run skipped");
+            record_run(name, "skipped", None);
+            continue;
+        }
+        let state = state.clone();
+        let running = running.clone();
+        tokio::spawn(async move {
+            run(&state, index).await;
+            running.store(false, Ordering::Release);
+        });
+    }
+}
+
+async fn run(state: &AppState, index: usize) {
+    let task = &state.scheduled_tasks[index];
+    let name = task.name();
+    heartbeat::ping(state, name, Ping::Start).await;
+    let start = Instant::now();
+    match task.run(state).await {
+        Ok(()) => {
+            info!(task = name, "scheduled task done");
+            record_run(name, "ok", Some(start.elapsed()));
+            heartbeat::ping(state, name, Ping::Success).await;
+        }
+        Err(e) => {
+            error!(task = name, "scheduled task failed: {e:#}");
+            record_run(name, "error", Some(start.elapsed()));
+            heartbeat::ping(state, name, Ping::Failure(&e)).await;
+        }
+    }
+}
+
+fn record_run(
+    task: &'static str,
+    outcome: &'static str,
+    took: Option<Duration>,
+) {
+    metrics::counter!("scheduler_runs_total", "task" => task, "outcome" => outcome)
+        .increment(1);
+    if let Some(took) = took {
+        metrics::histogram!("scheduler_run_duration_seconds", "task" => task)
+            .record(took.as_secs_f64());
+    }
+}
+
+/// Random delay of up to `max` seconds.
+fn jitter(max: u64) -> Duration {
+    if max == 0 {
+        return Duration::ZERO;
+    }
+    let mut bytes = [0; 8];
+    getrandom::fill(&mut bytes).expect("the system random source failed");
+    let millis = u64::from_le_bytes(bytes) % (max * 1000 + 1);
+    Duration::from_millis(millis)
+}
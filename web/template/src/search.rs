@@ -0,0 +1,678 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    Extension,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, query};
+use time::OffsetDateTime;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::database::{Access, Backend, Database};
+use crate::helpers::BoxFuture;
+use crate::metric;
+use crate::pagination::{Page, Pagination, limit_offset};
+use crate::rbac::Permissions;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::users::{NewUser, User, UserStore, UserStoreError};
+use crate::view::View;
+
+/// Text search configuration of Postgres, the one the generated
+/// `search_documents.document` column is built with.
+const LANGUAGE: &str = "english";
+
+/// Marks around the matched words of a highlighted text, split into
+/// [`Segment`]s so the words reach the templates escaped.
+const HIT_START: char = '\u{2}';
+const HIT_END: char = '\u{3}';
+
+/// Kinds of documents and the permission needed to find them, `None` for
+/// everyone.
+const KINDS: &[(&str, Option<&str>)] =
+    &[("page", None), ("user", Some("users.manage"))];
+
+/// Bounds of the characters of a query, shorter ones match nearly every
+/// document and longer ones are not typed by people.
+const MIN_QUERY_CHARS: usize = 2;
+const MAX_QUERY_CHARS: usize = 200;
+
+/// Content as the index sees it, found by its `title` and `body` and
+/// linking to `url`.
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub(crate) kind: &'static str,
+    pub(crate) id: String,
+    pub(crate) title: String,
+    pub(crate) body: String,
+    pub(crate) url: String,
+}
+
+/// Piece of a highlighted text, `hit` when it matched the query.
+#[derive(Debug, Serialize)]
+pub(crate) struct Segment {
+    text: String,
+    hit: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchHit {
+    kind: String,
+    url: String,
+    title: Vec<Segment>,
+    /// Parts of the body around the matches.
+    snippet: Vec<Segment>,
+}
+
+/// Full-text index of the documents, updated on every write of the
+/// content they come from.
+pub(crate) trait SearchIndex: Send + Sync {
+    /// Adds the document, replacing the one of the same kind and id.
+    fn index(&self, document: Document) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Removes a document, gone already is not an error.
+    fn remove<'a>(
+        &'a self,
+        kind: &'a str,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Documents of `kinds` matching `query`, best first.
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        kinds: &'a [&'a str],
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<SearchHit>>>;
+}
+
+/// Postgres `tsvector` or SQLite FTS5 index on the `search_documents`
+/// table, kept in memory without a database.
+pub(crate) fn from_db(db: Option<&Database>) -> Arc<dyn SearchIndex> {
+    match db {
+        None => Arc::new(MemorySearchIndex::default()),
+        Some(db) => Arc::new(SqlSearchIndex { db: db.clone() }),
+    }
+}
+
+/// Splits a text marked with [`HIT_START`] and [`HIT_END`].
+fn segments(marked: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for (i, part) in marked.split(HIT_START).enumerate() {
+        let (hit, rest) = match part.split_once(HIT_END) {
+            Some((hit, rest)) if i > 0 => (hit, rest),
+            _ => ("", part),
+        };
+        if !hit.is_empty() {
+            segments.push(Segment { text: hit.to_string(), hit: true });
+        }
+        if !rest.is_empty() {
+            segments.push(Segment { text: rest.to_string(), hit: false });
+        }
+    }
+    segments
+}
+
+/// Documents kept in process memory, matched word by word, ASCII letters
+/// ignoring case.
+#[derive(Debug, Default)]
+pub(crate) struct MemorySearchIndex {
+    documents: RwLock<BTreeMap<(String, String), Document>>,
+}
+
+/// Positions of the `terms` in `text`, in order and not overlapping.
+fn find_terms(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let lower = text.to_ascii_lowercase();
+    let mut found: Vec<(usize, usize)> = terms
+        .iter()
+        .flat_map(|term| {
+            lower
+                .match_indices(term.as_str())
+                .map(|(start, term)| (start, start + term.len()))
+        })
+        .collect();
+    found.sort();
+    let mut end = 0;
+    found.retain(|&(start, stop)| {
+        let keep = start >= end;
+        if keep {
+            end = stop;
+        }
+        keep
+    });
+    found
+}
+
+/// `text[from..to]` with the `found` terms marked.
+fn mark(
+    text: &str,
+    found: &[(usize, usize)],
+    from: usize,
+    to: usize,
+) -> String {
+    let mut marked = String::new();
+    let mut at = from;
+    for &(start, stop) in found {
+        if start < from || stop > to {
+            continue;
+        }
+        marked.push_str(&text[at..start]);
+        marked.push(HIT_START);
+        marked.push_str(&text[start..stop]);
+        marked.push(HIT_END);
+        at = stop;
+    }
+    marked.push_str(&text[at..to]);
+    marked
+}
+
+/// Bytes of context kept on each side of the first match of a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+impl SearchIndex for MemorySearchIndex {
+    fn index(&self, document: Document) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let key = (document.kind.to_string(), document.id.clone());
+            self.documents.write().unwrap().insert(key, document);
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(
+        &'a self,
+        kind: &'a str,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let key = (kind.to_string(), id.to_string());
+            self.documents.write().unwrap().remove(&key);
+            Ok(())
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        kinds: &'a [&'a str],
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<SearchHit>>> {
+        Box::pin(async move {
+            let terms: Vec<String> = query
+                .split_whitespace()
+                .map(str::to_ascii_lowercase)
+                .collect();
+            let documents = self.documents.read().unwrap();
+            let mut hits: Vec<(usize, SearchHit)> = documents
+                .values()
+                .filter(|document| kinds.contains(&document.kind))
+                .filter_map(|document| {
+                    let title = document.title.to_ascii_lowercase();
+                    let body = document.body.to_ascii_lowercase();
+                    let matched = !terms.is_empty()
+                        && terms.iter().all(|term| {
+                            title.contains(term.as_str())
+                                || body.contains(term.as_str())
+                        });
+                    if !matched {
+                        return None;
+                    }
+                    let in_title = find_terms(&document.title, &terms);
+                    let in_body = find_terms(&document.body, &terms);
+                    let body = &document.body;
+                    let first = in_body.first().map_or(0, |found| found.0);
+                    let mut from = first.saturating_sub(SNIPPET_CONTEXT);
+                    while !body.is_char_boundary(from) {
+                        from -= 1;
+                    }
+                    let mut to = (first + SNIPPET_CONTEXT * 2).min(body.len());
+                    while !body.is_char_boundary(to) {
+                        to += 1;
+                    }
+                    let title_len = document.title.len();
+                    Some((
+                        in_title.len(),
+                        SearchHit {
+                            kind: document.kind.to_string(),
+                            url: document.url.clone(),
+                            title: segments(&mark(
+                                &document.title,
+                                &in_title,
+                                0,
+                                title_len,
+                            )),
+                            snippet: segments(&mark(body, &in_body, from, to)),
+                        },
+                    ))
+                })
+                .collect();
+            hits.sort_by_key(|(title_hits, _)| Reverse(*title_hits));
+            Ok(pagination.apply(hits.into_iter().map(|(_, hit)| hit)))
+        })
+    }
+}
+
+/// Index in the `search_documents` table of the database: a `tsvector`
+/// column with a GIN index on Postgres, an FTS5 table on SQLite.
+pub(crate) struct SqlSearchIndex {
+    db: Database,
+}
+
+/// FTS5 query of the words of `query`, each quoted so operators typed by
+/// the visitor are taken as words.
+fn fts5_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl SearchIndex for SqlSearchIndex {
+    fn index(&self, document: Document) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            // FTS5 tables have no unique constraint to upsert on.
+            let mut tx = self.db.begin().await?;
+            let delete =
+                "DELETE FROM search_documents WHERE kind = $1 AND id = $2";
+            let query_delete = query(delete)
+                .bind(document.kind)
+                .bind(&document.id)
+                .execute(&mut *tx);
+            self.db
+                .observe("search_documents.delete", delete, query_delete)
+                .await?;
+            let insert = "INSERT INTO search_documents \
+                 (kind, id, title, body, url) VALUES ($1, $2, $3, $4, $5)";
+            let query_insert = query(insert)
+                .bind(document.kind)
+                .bind(&document.id)
+                .bind(&document.title)
+                .bind(&document.body)
+                .bind(&document.url)
+                .execute(&mut *tx);
+            self.db
+                .observe("search_documents.insert", insert, query_insert)
+                .await?;
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(
+        &'a self,
+        kind: &'a str,
+        id: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql =
+                "DELETE FROM search_documents WHERE kind = $1 AND id = $2";
+            let query = query(sql)
+                .bind(kind)
+                .bind(id)
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("search_documents.delete", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        text: &'a str,
+        kinds: &'a [&'a str],
+        pagination: Pagination,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<SearchHit>>> {
+        Box::pin(async move {
+            if kinds.is_empty() || text.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            let kinds_in = (4..4 + kinds.len())
+                .map(|n| format!("${n}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let (sql, text) = match self.db.backend() {
+                Backend::Postgres => (
+                    format!(
+                        "SELECT kind, url, \
+                         ts_headline('{LANGUAGE}', title, query, \
+                         'HighlightAll=true, StartSel=' || chr(2) || \
+                         ', StopSel=' || chr(3)), \
+                         ts_headline('{LANGUAGE}', body, query, \
+                         'MaxFragments=2, MaxWords=20, MinWords=8, \
+                         StartSel=' || chr(2) || ', StopSel=' || chr(3)) \
+                         FROM search_documents, \
+                         websearch_to_tsquery('{LANGUAGE}', $1) AS query \
+                         WHERE document @@ query AND kind IN ({kinds_in}) \
+                         ORDER BY ts_rank(document, query) DESC, title {}",
+                        limit_offset(2)
+                    ),
+                    text.to_string(),
+                ),
+                // Title words weigh ten times the body ones.
+                Backend::Sqlite => (
+                    format!(
+                        "SELECT kind, url, \
+                         highlight(search_documents, 2, char(2), char(3)), \
+                         snippet(search_documents, 3, char(2), char(3), \
+                         '…', 20) \
+                         FROM search_documents \
+                         WHERE search_documents MATCH $1 \
+                         AND kind IN ({kinds_in}) \
+                         ORDER BY bm25(search_documents, 0, 0, 10, 1, 0) {}",
+                        limit_offset(2)
+                    ),
+                    fts5_query(text),
+                ),
+            };
+            let mut query = query(&sql)
+                .bind(text)
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64);
+            for kind in kinds {
+                query = query.bind(*kind);
+            }
+            let query = query.fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self
+                .db
+                .observe("search_documents.search", &sql, query)
+                .await?;
+            rows.iter()
+                .map(|row| {
+                    Ok(SearchHit {
+                        kind: row.try_get(0)?,
+                        url: row.try_get(1)?,
+                        title: segments(&row.try_get::<String, _>(2)?),
+                        snippet: segments(&row.try_get::<String, _>(3)?),
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// Document of a user, found by administrators by name, email or role.
+fn user_document(user: &User) -> Document {
+    Document {
+        kind: "user",
+        id: user.id.to_string(),
+        title: user.name.clone(),
+        body: format!("{} {}", user.email, user.roles.join(" ")),
+        url: format!("/admin/users/{}", user.id),
+    }
+}
+
+/// Indexer hook of the users: every write going through the store updates
+/// the `user` documents, users in the trash are left out. A failed update
+/// is logged and the write stands, the next one of the user fixes it.
+pub(crate) struct IndexedUserStore {
+    users: Box<dyn UserStore>,
+    index: Arc<dyn SearchIndex>,
+}
+
+impl IndexedUserStore {
+    pub(crate) fn new(
+        users: Box<dyn UserStore>,
+        index: Arc<dyn SearchIndex>,
+    ) -> Self {
+        IndexedUserStore { users, index }
+    }
+
+    async fn reindex(&self, id: Uuid) {
+        let result = match self.users.find_by_id(id).await {
+            Ok(Some(user)) => self.index.index(user_document(&user)).await,
+            Ok(None) => self.index.remove("user", &id.to_string()).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            error!(user = %id, "could not index the user: {e:#}");
+        }
+    }
+}
+
+impl UserStore for IndexedUserStore {
+    fn find_by_id(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        self.users.find_by_id(id)
+    }
+
+    fn find_by_id_with_trashed(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        self.users.find_by_id_with_trashed(id)
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<Option<User>, UserStoreError>> {
+        self.users.find_by_email(email)
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        self.users.list(pagination)
+    }
+
+    fn list_trashed(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        self.users.list_trashed(pagination)
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<u64, UserStoreError>> {
+        self.users.count()
+    }
+
+    fn create(
+        &self,
+        user: NewUser,
+    ) -> BoxFuture<'_, Result<User, UserStoreError>> {
+        Box::pin(async move {
+            let user = self.users.create(user).await?;
+            self.reindex(user.id).await;
+            Ok(user)
+        })
+    }
+
+    fn update_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        roles: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            self.users.update_profile(id, name, roles).await?;
+            self.reindex(id).await;
+            Ok(())
+        })
+    }
+
+    fn update_password(
+        &self,
+        id: Uuid,
+        password_hash: String,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        self.users.update_password(id, password_hash)
+    }
+
+    fn mark_email_verified(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        self.users.mark_email_verified(id)
+    }
+
+    fn update_email<'a>(
+        &'a self,
+        id: Uuid,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            self.users.update_email(id, email).await?;
+            self.reindex(id).await;
+            Ok(())
+        })
+    }
+
+    fn schedule_deletion(
+        &self,
+        id: Uuid,
+        at: Option<OffsetDateTime>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        self.users.schedule_deletion(id, at)
+    }
+
+    fn due_for_deletion(
+        &self,
+        now: OffsetDateTime,
+    ) -> BoxFuture<'_, Result<Vec<Uuid>, UserStoreError>> {
+        self.users.due_for_deletion(now)
+    }
+
+    fn trash(&self, id: Uuid) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let trashed = self.users.trash(id).await?;
+            self.reindex(id).await;
+            Ok(trashed)
+        })
+    }
+
+    fn restore(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let restored = self.users.restore(id).await?;
+            self.reindex(id).await;
+            Ok(restored)
+        })
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            self.users.delete(id).await?;
+            self.reindex(id).await;
+            Ok(())
+        })
+    }
+}
+
+/// Indexes the `documents` of the public pages, replacing the ones of a
+/// previous start.
+pub(crate) async fn index_pages(
+    index: &dyn SearchIndex,
+    documents: Vec<Document>,
+) {
+    for document in documents {
+        let id = document.id.clone();
+        if let Err(e) = index.index(document).await {
+            error!(page = id, "could not index the page: {e:#}");
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+/// Message explaining why `q` is not searched, if it is not.
+fn validate_query(q: &str) -> Option<String> {
+    let chars = q.chars().count();
+    if chars < MIN_QUERY_CHARS {
+        Some(format!("Type at least {MIN_QUERY_CHARS} characters"))
+    } else if chars > MAX_QUERY_CHARS {
+        Some(format!("Type at most {MAX_QUERY_CHARS} characters"))
+    } else if q.chars().any(char::is_control) {
+        Some("Remove the control characters".to_string())
+    } else {
+        None
+    }
+}
+
+/// Search page, finding the documents of the kinds the visitor may see.
+///
+/// Queries are timed in the `app_search_seconds` histogram and counted in
+/// `app_search_total` by outcome (`hits` or `empty`), invalid ones are
+/// answered with a 422 and never reach the index.
+pub(crate) async fn handler_search(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    permissions: Option<Extension<Permissions>>,
+    pagination: Pagination,
+    Query(query): Query<SearchQuery>,
+) -> Result<Response, ServerError> {
+    let kinds: Vec<&str> = KINDS
+        .iter()
+        .filter(|(_, permission)| {
+            permission.is_none_or(|permission| {
+                permissions
+                    .as_ref()
+                    .is_some_and(|Extension(granted)| granted.can(permission))
+            })
+        })
+        .map(|(kind, _)| *kind)
+        .collect();
+    let q = query.q.trim();
+    let render = |page: Option<Page<SearchHit>>, error: Option<String>| {
+        view.render(
+            "search",
+            context! {
+                title => "Search",
+                q => q,
+                min_chars => MIN_QUERY_CHARS,
+                max_chars => MAX_QUERY_CHARS,
+                page => page,
+                error => error,
+            },
+        )
+        .unwrap()
+    };
+
+    if q.is_empty() {
+        return Ok(render(None, None).into_response());
+    }
+    if let Some(error) = validate_query(q) {
+        let rendered = render(None, Some(error));
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let hits = {
+        let _timer = metric::time!("search");
+        state.search.search(q, &kinds, pagination).await?
+    };
+    let outcome = if hits.is_empty() { "empty" } else { "hits" };
+    metric::count!("search", outcome = outcome);
+    Ok(render(Some(Page::new(hits, pagination)), None).into_response())
+}
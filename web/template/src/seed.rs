@@ -0,0 +1,114 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use anyhow::{Context, ensure};
+use fake::{
+    Fake,
+    faker::name::en::{FirstName, LastName},
+};
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::auth::hash_password;
+use crate::database::Database;
+use crate::search::{self, IndexedUserStore};
+use crate::settings::Settings;
+use crate::users::{self, NewUser, UserStore};
+
+/// Run modes the `seed` command accepts, never production.
+const RUN_MODES: [&str; 2] = ["development", "test"];
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SeedSettings {
+    /// Fake users to create besides the administrator.
+    pub(crate) users: usize,
+    /// Seed of the generator, the same seed gives the same data.
+    pub(crate) rng_seed: u64,
+    /// Password of every seeded account.
+    pub(crate) password: String,
+}
+
+/// `seed` command: fills the database of a development or test run mode
+/// with fake users. Accounts are looked up by email first, so running it
+/// again only adds the missing ones.
+pub(crate) async fn run(settings: &Settings) -> anyhow::Result<()> {
+    ensure!(
+        RUN_MODES.contains(&settings.run_mode.as_str()),
+        "refusing to seed in the {} run mode, only in {}",
+        settings.run_mode,
+        RUN_MODES.join(" or ")
+    );
+    let db = Database::connect(&settings.database)
+        .await?
+        .context("no database to seed, database.backend is none")?;
+    let pending = db.pending_migrations().await?;
+    ensure!(
+        pending.is_empty(),
+        "pending migrations {pending:?}, migrate first"
+    );
+
+    let users = IndexedUserStore::new(
+        users::from_settings(&settings.database, Some(&db)).await?,
+        search::from_db(Some(&db)),
+    );
+    let seed = &settings.seed;
+    let password_hash = hash_password(seed.password.clone()).await?;
+    let mut rng = StdRng::seed_from_u64(seed.rng_seed);
+    let mut created = 0;
+
+    let mut accounts = vec![(
+        "Admin".to_string(),
+        "admin@example.com".to_string(),
+        vec!["admin".to_string()],
+    )];
+    for i in 1..=seed.users {
+        let first: String = FirstName().fake_with_rng(&mut rng);
+        let last: String = LastName().fake_with_rng(&mut rng);
+        // The index keeps emails unique when names repeat.
+        let email = format!("{first}.{last}{i}@example.com").to_lowercase();
+        let mut roles = settings.rbac.default_roles.clone();
+        if rng.random_bool(0.1) {
+            roles.push("editor".to_string());
+        }
+        accounts.push((format!("{first} {last}"), email, roles));
+    }
+
+    for (name, email, roles) in accounts {
+        // Drawn for every account, so the data does not depend on which
+        // accounts already exist.
+        let verified = rng.random_bool(0.8);
+        if users.find_by_email(&email).await?.is_some() {
+            continue;
+        }
+        let user = users
+            .create(NewUser {
+                name,
+                email,
+                password_hash: password_hash.clone(),
+                roles,
+            })
+            .await?;
+        if verified {
+            users.mark_email_verified(user.id).await?;
+        }
+        created += 1;
+    }
+
+    info!(created, total = seed.users + 1, "database seeded");
+    db.close().await;
+    Ok(())
+}
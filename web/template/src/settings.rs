@@ -5,11 +5,65 @@ use tracing::{debug, info};
 use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
-#[allow(unused)]
-struct Database {
-    url: String,
-}
+use crate::access_log::AccessLogSettings;
+use crate::api::ApiSettings;
+use crate::api_key::ApiKeySettings;
+use crate::audit::AuditSettings;
+use crate::auth::AuthSettings;
+use crate::body_log::BodyLogSettings;
+use crate::cache::CacheSettings;
+use crate::captcha::CaptchaSettings;
+use crate::consent::ConsentSettings;
+use crate::contact::ContactSettings;
+use crate::csrf::CsrfSettings;
+use crate::database::DatabaseSettings;
+use crate::email::EmailSettings;
+use crate::error_reporting::ErrorReportingSettings;
+use crate::feed::FeedSettings;
+use crate::flags::FlagsSettings;
+#[cfg(feature = "grpc")]
+use crate::grpc::GrpcSettings;
+use crate::health::HealthSettings;
+use crate::heartbeat::HeartbeatSettings;
+use crate::helpers::LogSettings;
+use crate::http_client::HttpClientSettings;
+use crate::incoming_webhook::IncomingWebhookSettings;
+use crate::jobs::JobSettings;
+use crate::jwt::JwtSettings;
+use crate::mailer::MailerSettings;
+use crate::media::MediaSettings;
+use crate::metric::MetricsSettings;
+use crate::newsletter::NewsletterSettings;
+use crate::oidc::OidcSettings;
+use crate::outbox::OutboxSettings;
+use crate::pagination::PaginationSettings;
+use crate::payments::PaymentsSettings;
+use crate::pdf::PdfSettings;
+use crate::posts::PostsSettings;
+use crate::privacy::PrivacySettings;
+use crate::profiling::ProfilingSettings;
+use crate::rbac::RbacSettings;
+use crate::redis::RedisSettings;
+use crate::robots::RobotsSettings;
+use crate::scheduler::SchedulerSettings;
+use crate::seed::SeedSettings;
+use crate::session::SessionSettings;
+use crate::shortener::ShortenerSettings;
+use crate::signed_url::SignedUrlSettings;
+use crate::sitemap::SitemapSettings;
+use crate::slow_request::SlowRequestSettings;
+use crate::sse::SseSettings;
+use crate::storage::StorageSettings;
+use crate::tenancy::TenancySettings;
+use crate::theme::ThemeSettings;
+use crate::upload::UploadSettings;
+use crate::version::VersionSettings;
+use crate::webhooks::WebhookSettings;
+use crate::websocket::WebSocketSettings;
+use crate::worker::WorkerSettings;
+
+/// Start of the placeholder secrets of `config/default.toml`.
+const DEVELOPMENT_SECRET: &str = "development-";
 
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
@@ -35,11 +89,93 @@ struct Braintree {
     private_key: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub(crate) struct Site {
+    pub(crate) name: String,
+    pub(crate) url: String,
+}
+
+impl Site {
+    /// Absolute URL of `path` on this site.
+    pub(crate) fn url_for(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Cookies {
+    /// Secret of at least 64 bytes used to sign and encrypt cookies.
+    pub(crate) key: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
 pub(crate) struct Settings {
     debug: bool,
-    database: Database,
+    /// Value of `RUN_MODE`, `development` when unset.
+    pub(crate) run_mode: String,
+    pub(crate) site: Site,
+    pub(crate) tenancy: TenancySettings,
+    pub(crate) log: LogSettings,
+    pub(crate) access_log: AccessLogSettings,
+    pub(crate) body_log: BodyLogSettings,
+    pub(crate) slow_requests: SlowRequestSettings,
+    pub(crate) error_reporting: ErrorReportingSettings,
+    pub(crate) health: HealthSettings,
+    pub(crate) profiling: ProfilingSettings,
+    pub(crate) theme: ThemeSettings,
+    pub(crate) cookies: Cookies,
+    pub(crate) signed_urls: SignedUrlSettings,
+    pub(crate) session: SessionSettings,
+    pub(crate) auth: AuthSettings,
+    pub(crate) oidc: OidcSettings,
+    pub(crate) rbac: RbacSettings,
+    pub(crate) flags: FlagsSettings,
+    pub(crate) jwt: JwtSettings,
+    pub(crate) api: ApiSettings,
+    pub(crate) api_keys: ApiKeySettings,
+    pub(crate) audit: AuditSettings,
+    pub(crate) http_client: HttpClientSettings,
+    pub(crate) jobs: JobSettings,
+    pub(crate) worker: WorkerSettings,
+    pub(crate) scheduler: SchedulerSettings,
+    pub(crate) heartbeat: HeartbeatSettings,
+    pub(crate) privacy: PrivacySettings,
+    pub(crate) email: EmailSettings,
+    pub(crate) mailer: MailerSettings,
+    pub(crate) contact: ContactSettings,
+    pub(crate) newsletter: NewsletterSettings,
+    pub(crate) pdf: PdfSettings,
+    pub(crate) payments: PaymentsSettings,
+    pub(crate) shortener: ShortenerSettings,
+    pub(crate) upload: UploadSettings,
+    pub(crate) storage: StorageSettings,
+    pub(crate) media: MediaSettings,
+    pub(crate) captcha: CaptchaSettings,
+    pub(crate) csrf: CsrfSettings,
+    pub(crate) consent: ConsentSettings,
+    pub(crate) sitemap: SitemapSettings,
+    pub(crate) robots: RobotsSettings,
+    pub(crate) feed: FeedSettings,
+    pub(crate) posts: PostsSettings,
+    pub(crate) metrics: MetricsSettings,
+    pub(crate) version: VersionSettings,
+    pub(crate) database: DatabaseSettings,
+    pub(crate) outbox: OutboxSettings,
+    pub(crate) webhooks: WebhookSettings,
+    pub(crate) incoming_webhooks: IncomingWebhookSettings,
+    pub(crate) websocket: WebSocketSettings,
+    pub(crate) sse: SseSettings,
+    #[cfg(feature = "grpc")]
+    pub(crate) grpc: GrpcSettings,
+    pub(crate) redis: RedisSettings,
+    pub(crate) pagination: PaginationSettings,
+    pub(crate) cache: CacheSettings,
+    pub(crate) seed: SeedSettings,
     sparkpost: Sparkpost,
     twitter: Twitter,
     braintree: Braintree,
@@ -67,14 +203,44 @@ impl Settings {
             // Eg.. `APP_DEBUG=1 ./target/app` would set the `debug` key
             .add_source(Environment::with_prefix("app"))
             // You may also programmatically change settings
-            .set_override("database.url", "postgres://")?
+            .set_override("run_mode", run_mode.as_str())?
             .build()?;
 
         // Now that we're done, let's access our configuration
         debug!("debug: {:?}", s.get_bool("debug"));
-        debug!("database: {:?}", s.get::<String>("database.url"));
 
         // You can deserialize (and thus freeze) the entire configuration as
-        s.try_deserialize()
+        let settings: Settings = s.try_deserialize()?;
+        settings.check_secrets()?;
+        Ok(settings)
+    }
+
+    /// Refuses to run in production with the development secrets, anyone
+    /// could forge tokens signed with them.
+    fn check_secrets(&self) -> Result<(), ConfigError> {
+        if !self.is_production() {
+            return Ok(());
+        }
+        let secrets = [
+            ("jwt.secret", &self.jwt.secret),
+            ("cookies.key", &self.cookies.key),
+        ]
+        .into_iter()
+        .chain(
+            self.signed_urls.keys.iter().map(|key| ("signed_urls.keys", key)),
+        );
+        for (name, secret) in secrets {
+            if secret.starts_with(DEVELOPMENT_SECRET) {
+                return Err(ConfigError::Message(format!(
+                    "{name} still holds its development value, set it in \
+                     config/production.toml"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_production(&self) -> bool {
+        self.run_mode == "production"
     }
 }
@@ -0,0 +1,552 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use axum::{
+    Form,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Row, any::AnyRow, query};
+use time::OffsetDateTime;
+use tracing::info;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::auth::CurrentUser;
+use crate::cache::CacheKey;
+use crate::database::{Access, Database};
+use crate::flash::Flash;
+use crate::form::{Field, FieldKind, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::metric;
+use crate::pagination::{Page, Pagination, limit_offset};
+use crate::privacy::PersonalData;
+use crate::router::ServerError;
+use crate::settings::Site;
+use crate::state::AppState;
+use crate::view::View;
+
+/// Characters of the generated codes.
+const ALPHABET: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+/// Codes generated before giving up on finding a free one.
+const CODE_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ShortenerSettings {
+    /// Characters of the generated codes.
+    pub(crate) code_length: usize,
+    /// Seconds a code is resolved from the cache, unknown codes included.
+    pub(crate) cache_ttl: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ShortLink {
+    pub(crate) code: String,
+    pub(crate) url: String,
+    pub(crate) user_id: Uuid,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+}
+
+pub(crate) trait ShortLinkStore: Send + Sync {
+    /// Adds `link`, false when its code is taken.
+    fn create(&self, link: ShortLink) -> BoxFuture<'_, anyhow::Result<bool>>;
+
+    fn find<'a>(
+        &'a self,
+        code: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ShortLink>>>;
+
+    /// Links of the user, from the most recent.
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ShortLink>>>;
+
+    /// Deletes the link of `code` made by the user, false when there is
+    /// none.
+    fn delete<'a>(
+        &'a self,
+        code: &'a str,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+
+    /// Deletes every link of the user, returning their codes.
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<String>>>;
+}
+
+/// Table of the database, kept in memory without one.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn ShortLinkStore> {
+    match db {
+        None => Box::new(MemoryShortLinkStore::default()),
+        Some(db) => Box::new(SqlShortLinkStore { db: db.clone() }),
+    }
+}
+
+/// Links lasting until the process exits, local to it.
+#[derive(Default)]
+pub(crate) struct MemoryShortLinkStore {
+    links: RwLock<HashMap<String, ShortLink>>,
+}
+
+impl ShortLinkStore for MemoryShortLinkStore {
+    fn create(&self, link: ShortLink) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut links = self.links.write().unwrap();
+            if links.contains_key(&link.code) {
+                return Ok(false);
+            }
+            links.insert(link.code.clone(), link);
+            Ok(true)
+        })
+    }
+
+    fn find<'a>(
+        &'a self,
+        code: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ShortLink>>> {
+        Box::pin(
+            async move { Ok(self.links.read().unwrap().get(code).cloned()) },
+        )
+    }
+
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ShortLink>>> {
+        Box::pin(async move {
+            let mut links: Vec<ShortLink> = self
+                .links
+                .read()
+                .unwrap()
+                .values()
+                .filter(|link| link.user_id == user_id)
+                .cloned()
+                .collect();
+            links.sort_by(|a, b| {
+                b.created_at.cmp(&a.created_at).then(a.code.cmp(&b.code))
+            });
+            Ok(pagination.apply(links))
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        code: &'a str,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut links = self.links.write().unwrap();
+            if links.get(code).is_none_or(|link| link.user_id != user_id) {
+                return Ok(false);
+            }
+            links.remove(code);
+            Ok(true)
+        })
+    }
+
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut links = self.links.write().unwrap();
+            let codes: Vec<String> = links
+                .values()
+                .filter(|link| link.user_id == user_id)
+                .map(|link| link.code.clone())
+                .collect();
+            for code in &codes {
+                links.remove(code);
+            }
+            Ok(codes)
+        })
+    }
+}
+
+/// The `short_links` table.
+pub(crate) struct SqlShortLinkStore {
+    db: Database,
+}
+
+const LINK_COLUMNS: &str = "code, url, user_id, created_at";
+
+fn link_from_row(row: &AnyRow) -> anyhow::Result<ShortLink> {
+    Ok(ShortLink {
+        code: row.try_get(0)?,
+        url: row.try_get(1)?,
+        user_id: Uuid::try_parse(&row.try_get::<String, _>(2)?)?,
+        created_at: from_micros(row.try_get(3)?)?,
+    })
+}
+
+impl ShortLinkStore for SqlShortLinkStore {
+    fn create(&self, link: ShortLink) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let sql = format!(
+                "INSERT INTO short_links ({LINK_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4) ON CONFLICT (code) DO NOTHING"
+            );
+            let insert = query(&sql)
+                .bind(link.code)
+                .bind(link.url)
+                .bind(link.user_id.to_string())
+                .bind(to_micros(link.created_at))
+                .execute(self.db.pool(Access::ReadWrite));
+            let result =
+                self.db.observe("short_links.create", &sql, insert).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn find<'a>(
+        &'a self,
+        code: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Option<ShortLink>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {LINK_COLUMNS} FROM short_links WHERE code = $1"
+            );
+            let query = query(&sql)
+                .bind(code)
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("short_links.find", &sql, query).await?;
+            row.as_ref().map(link_from_row).transpose()
+        })
+    }
+
+    fn list_for_user(
+        &self,
+        user_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<ShortLink>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {LINK_COLUMNS} FROM short_links WHERE user_id = $1 \
+                 ORDER BY created_at DESC, code {}",
+                limit_offset(2)
+            );
+            let query = query(&sql)
+                .bind(user_id.to_string())
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64)
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self
+                .db
+                .observe("short_links.list_for_user", &sql, query)
+                .await?;
+            rows.iter().map(link_from_row).collect()
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        code: &'a str,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let sql =
+                "DELETE FROM short_links WHERE code = $1 AND user_id = $2";
+            let query = query(sql)
+                .bind(code)
+                .bind(user_id.to_string())
+                .execute(self.db.pool(Access::ReadWrite));
+            let result =
+                self.db.observe("short_links.delete", sql, query).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn delete_for_user(
+        &self,
+        user_id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let sql = "DELETE FROM short_links WHERE user_id = $1 \
+                       RETURNING code";
+            let query = query(sql)
+                .bind(user_id.to_string())
+                .fetch_all(self.db.pool(Access::ReadWrite));
+            let rows = self
+                .db
+                .observe("short_links.delete_for_user", sql, query)
+                .await?;
+            Ok(rows
+                .iter()
+                .map(|row| row.try_get(0))
+                .collect::<Result<_, _>>()?)
+        })
+    }
+}
+
+/// Cached target of `code`, none for unknown codes.
+fn cache_key(code: &str) -> CacheKey<Option<String>> {
+    CacheKey::scoped("short_link", code)
+}
+
+/// A fresh random code of `length` characters.
+fn generate_code(length: usize) -> anyhow::Result<String> {
+    let mut code = String::with_capacity(length);
+    let mut bytes = [0u8; 32];
+    while code.len() < length {
+        getrandom::fill(&mut bytes)
+            .map_err(|e| anyhow!("could not generate a code: {e}"))?;
+        // Bytes past the last multiple of the alphabet size would favor
+        // its first characters.
+        let fair = 256 - 256 % ALPHABET.len();
+        for byte in bytes.iter().filter(|&&byte| usize::from(byte) < fair) {
+            if code.len() == length {
+                break;
+            }
+            code.push(ALPHABET[usize::from(*byte) % ALPHABET.len()] as char);
+        }
+    }
+    Ok(code)
+}
+
+/// Redirects to the target of the code. The redirect is temporary so each
+/// click comes back here and is counted in `app_short_link_total`.
+pub(crate) async fn handler_short_link(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+) -> Result<Response, ServerError> {
+    let ttl = Duration::from_secs(state.settings.shortener.cache_ttl);
+    let url = state
+        .cache
+        .get_or_insert_with(&cache_key(&code), ttl, || async {
+            let link = state.short_links.find(&code).await?;
+            Ok(link.map(|link| link.url))
+        })
+        .await?;
+    let Some(url) = url.as_deref() else {
+        metric::count!("short_link", outcome = "unknown");
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    metric::count!("short_link", outcome = "redirect");
+    Ok(Redirect::temporary(url).into_response())
+}
+
+/// Only absolute http(s) URLs.
+fn validate_target(url: &str) -> Result<(), ValidationError> {
+    let invalid = |message: &'static str| {
+        Err(ValidationError::new("url").with_message(message.into()))
+    };
+    let Ok(parsed) = Url::parse(url) else {
+        return invalid("Must be a valid URL");
+    };
+    if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+        return invalid("Must be an http or https URL");
+    }
+    Ok(())
+}
+
+/// Whether `url` is a short link of this site, which could loop.
+fn is_short_link(site: &Site, url: &str) -> bool {
+    let (Ok(url), Ok(site)) = (Url::parse(url), Url::parse(&site.url)) else {
+        return false;
+    };
+    url.host() == site.host()
+        && url.port_or_known_default() == site.port_or_known_default()
+        && url.path().starts_with("/s/")
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct ShortLinkInput {
+    #[validate(
+        length(max = 2048, message = "Must have at most 2048 characters"),
+        custom(function = "validate_target")
+    )]
+    url: String,
+}
+
+impl FormDefinition for ShortLinkInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/account/links").submit("Shorten").field(
+            Field::new("url", "URL", FieldKind::Url)
+                .required()
+                .length(None, Some(2048)),
+        )
+    }
+}
+
+async fn render_links(
+    state: &AppState,
+    view: &View,
+    user_id: Uuid,
+    pagination: Pagination,
+    input: &impl Serialize,
+    errors: &FormErrors,
+) -> Result<Html<String>, ServerError> {
+    let links = state.short_links.list_for_user(user_id, pagination).await?;
+    Ok(view
+        .render(
+            "links",
+            context! {
+                title => "Short links",
+                base => state.settings.site.url_for("/s/"),
+                page => Page::new(links, pagination),
+                form => ShortLinkInput::form().bind(input, errors),
+            },
+        )
+        .unwrap())
+}
+
+/// Links of the user, with the form shortening a new one.
+pub(crate) async fn handler_links(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    pagination: Pagination,
+) -> Result<Html<String>, ServerError> {
+    let errors = FormErrors::default();
+    render_links(&state, &view, user.id, pagination, &(), &errors).await
+}
+
+pub(crate) async fn handler_links_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    flash: Flash,
+    CurrentUser(user): CurrentUser,
+    pagination: Pagination,
+    Form(input): Form<ShortLinkInput>,
+) -> Result<Response, ServerError> {
+    let mut errors = match input.validate() {
+        Ok(()) => FormErrors::default(),
+        Err(errors) => FormErrors::from(&errors),
+    };
+    if errors.get("url").is_empty()
+        && is_short_link(&state.settings.site, &input.url)
+    {
+        errors.add("url", "Can not be another short link");
+    }
+    if !errors.is_empty() {
+        let rendered =
+            render_links(&state, &view, user.id, pagination, &input, &errors)
+                .await?;
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let length = state.settings.shortener.code_length;
+    let mut created = None;
+    for _ in 0..CODE_ATTEMPTS {
+        let link = ShortLink {
+            code: generate_code(length)?,
+            url: input.url.clone(),
+            user_id: user.id,
+            created_at: OffsetDateTime::now_utc(),
+        };
+        let code = link.code.clone();
+        if state.short_links.create(link).await? {
+            created = Some(code);
+            break;
+        }
+    }
+    let code = created.ok_or_else(|| {
+        anyhow!(
+            "no free code after {CODE_ATTEMPTS} attempts, raise code_length"
+        )
+    })?;
+    // An unknown code may have been cached before it was taken.
+    state.cache.invalidate(&cache_key(&code)).await;
+
+    info!(code, "short link created");
+    let short = state.settings.site.url_for(&format!("/s/{code}"));
+    flash.success(format!("Created {short}."));
+    Ok(Redirect::to("/account/links").into_response())
+}
+
+pub(crate) async fn handler_link_delete(
+    State(state): State<Arc<AppState>>,
+    flash: Flash,
+    CurrentUser(user): CurrentUser,
+    Path(code): Path<String>,
+) -> Result<Response, ServerError> {
+    if !state.short_links.delete(&code, user.id).await? {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    state.cache.invalidate(&cache_key(&code)).await;
+
+    info!(code, "short link deleted");
+    flash.success(format!("Deleted /s/{code}."));
+    Ok(Redirect::to("/account/links").into_response())
+}
+
+/// The short links made by the user.
+pub(crate) struct ShortLinks;
+
+impl PersonalData for ShortLinks {
+    fn name(&self) -> &'static str {
+        "short_links"
+    }
+
+    fn export<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<Value>> {
+        Box::pin(async move {
+            let mut links = Vec::new();
+            let mut pagination =
+                Pagination { page: 1, per_page: 500, after: None };
+            loop {
+                let page = state
+                    .short_links
+                    .list_for_user(user_id, pagination)
+                    .await?;
+                let page = Page::new(page, pagination);
+                links.extend(page.items);
+                if !page.has_next {
+                    break;
+                }
+                pagination.page += 1;
+            }
+            Ok(serde_json::to_value(links)?)
+        })
+    }
+
+    fn erase<'a>(
+        &'a self,
+        state: &'a AppState,
+        user_id: Uuid,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            for code in state.short_links.delete_for_user(user_id).await? {
+                state.cache.invalidate(&cache_key(&code)).await;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,164 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use axum::{
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use time::OffsetDateTime;
+
+use crate::state::AppState;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shortest key accepted to sign URLs.
+const MIN_KEY_LEN: usize = 32;
+const EXPIRES_PARAM: &str = "expires";
+const SIGNATURE_PARAM: &str = "signature";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SignedUrlSettings {
+    /// Secrets of at least 32 bytes. The first signs new URLs and all of
+    /// them verify, so a key is rotated by prepending its successor and
+    /// dropping it once the URLs it signed have expired.
+    pub(crate) keys: Vec<String>,
+}
+
+/// Signs and verifies expiring URLs, e.g. private downloads or unsubscribe
+/// links, without storing anything server side.
+///
+/// The signature covers the path and the whole query, expiry included, and
+/// is appended as the last parameter.
+pub(crate) struct UrlSigner {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SignatureError {
+    /// Missing, malformed or forged signature.
+    Invalid,
+    /// Correctly signed but past its expiry.
+    Expired,
+}
+
+impl IntoResponse for SignatureError {
+    fn into_response(self) -> Response {
+        match self {
+            SignatureError::Invalid => {
+                (StatusCode::FORBIDDEN, "Invalid link").into_response()
+            }
+            SignatureError::Expired => {
+                (StatusCode::GONE, "This link has expired").into_response()
+            }
+        }
+    }
+}
+
+impl UrlSigner {
+    pub(crate) fn new(settings: &SignedUrlSettings) -> anyhow::Result<Self> {
+        if settings.keys.is_empty() {
+            bail!("signed_urls.keys needs at least one key");
+        }
+        if settings.keys.iter().any(|key| key.len() < MIN_KEY_LEN) {
+            bail!("signed_urls.keys must have at least {MIN_KEY_LEN} bytes");
+        }
+        Ok(UrlSigner {
+            keys: settings
+                .keys
+                .iter()
+                .map(|key| key.as_bytes().into())
+                .collect(),
+        })
+    }
+
+    fn mac(key: &[u8], unsigned: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(key)
+            .expect("hmac accepts keys of any size");
+        mac.update(unsigned.as_bytes());
+        mac
+    }
+
+    /// Signs `path`, which may carry a query, for `ttl` seconds. Returns a
+    /// path and query, see `Site::url_for` for an absolute URL.
+    pub(crate) fn sign(&self, path: &str, ttl: i64) -> String {
+        let expires = OffsetDateTime::now_utc().unix_timestamp() + ttl;
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let unsigned = format!("{path}{separator}{EXPIRES_PARAM}={expires}");
+        let signature = Self::mac(&self.keys[0], &unsigned).finalize();
+        format!(
+            "{unsigned}&{SIGNATURE_PARAM}={}",
+            URL_SAFE_NO_PAD.encode(signature.into_bytes())
+        )
+    }
+
+    /// Checks a path and query produced by [`UrlSigner::sign`] with any of
+    /// the keys.
+    pub(crate) fn verify(
+        &self,
+        path_and_query: &str,
+    ) -> Result<(), SignatureError> {
+        let (unsigned, signature) = path_and_query
+            .rsplit_once(&format!("&{SIGNATURE_PARAM}="))
+            .ok_or(SignatureError::Invalid)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SignatureError::Invalid)?;
+        let valid = self.keys.iter().any(|key| {
+            Self::mac(key, unsigned).verify_slice(&signature).is_ok()
+        });
+        if !valid {
+            return Err(SignatureError::Invalid);
+        }
+
+        // Signed by us, so the last parameter is the expiry.
+        let expires = unsigned
+            .rsplit_once(&format!("{EXPIRES_PARAM}="))
+            .and_then(|(_, expires)| expires.parse::<i64>().ok())
+            .ok_or(SignatureError::Invalid)?;
+        if expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return Err(SignatureError::Expired);
+        }
+        Ok(())
+    }
+}
+
+/// Guard for routes only reachable through a signed URL, checked before
+/// the handler runs. Rejects forged links with a 403 and expired ones with
+/// a 410.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SignedUrl;
+
+impl FromRequestParts<Arc<AppState>> for SignedUrl {
+    type Rejection = SignatureError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let path_and_query =
+            parts.uri.path_and_query().ok_or(SignatureError::Invalid)?;
+        state.url_signer.verify(path_and_query.as_str())?;
+        Ok(SignedUrl)
+    }
+}
@@ -0,0 +1,115 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::helpers::BoxFuture;
+use crate::router::PUBLIC_ROUTES;
+use crate::settings::Site;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SitemapSettings {
+    pub(crate) enabled: bool,
+    /// Value of `<changefreq>` for the static routes.
+    pub(crate) changefreq: String,
+    /// Static routes left out of the sitemap.
+    pub(crate) exclude: Vec<String>,
+    /// Seconds the entries stay cached in Redis, when there is one, 0 to
+    /// list them on every request.
+    pub(crate) cache_ttl: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SitemapEntry {
+    pub(crate) loc: String,
+    /// Last modification date, `YYYY-MM-DD` or a full W3C datetime.
+    pub(crate) lastmod: Option<String>,
+    pub(crate) changefreq: Option<String>,
+    pub(crate) priority: Option<f64>,
+}
+
+/// Hook adding dynamic entries (posts, products, ...) to the sitemap.
+pub(crate) trait SitemapSource: Send + Sync {
+    fn entries<'a>(
+        &'a self,
+        site: &'a Site,
+    ) -> BoxFuture<'a, Vec<SitemapEntry>>;
+}
+
+pub(crate) async fn handler_sitemap(
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let settings = &state.settings.sitemap;
+    if !settings.enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let entries = match &state.redis {
+        Some(redis) if settings.cache_ttl > 0 => {
+            let ttl = Duration::from_secs(settings.cache_ttl);
+            redis
+                .cache_get_or_set("sitemap", ttl, || async {
+                    Ok(entries(&state).await)
+                })
+                .await
+        }
+        _ => Ok(entries(&state).await),
+    };
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("could not list the sitemap entries: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let template = state.env.get_template("sitemap.xml").unwrap();
+    let rendered = template.render(context! { entries => entries }).unwrap();
+
+    ([(header::CONTENT_TYPE, "application/xml")], rendered).into_response()
+}
+
+/// Entries of the public routes and of every [`SitemapSource`].
+async fn entries(state: &AppState) -> Vec<SitemapEntry> {
+    let settings = &state.settings.sitemap;
+    let site = &state.settings.site;
+    let mut entries: Vec<SitemapEntry> = PUBLIC_ROUTES
+        .iter()
+        .filter(|(path, _)| !settings.exclude.iter().any(|e| e == path))
+        .map(|(path, priority)| SitemapEntry {
+            loc: site.url_for(path),
+            lastmod: None,
+            changefreq: Some(settings.changefreq.clone()),
+            priority: Some(*priority),
+        })
+        .collect();
+
+    for source in &state.sitemap_sources {
+        entries.extend(source.entries(site).await);
+    }
+    entries
+}
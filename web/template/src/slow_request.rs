@@ -0,0 +1,74 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::metric::UNMATCHED_PATH;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SlowRequestSettings {
+    /// Milliseconds past which a request is reported, 0 turns it off.
+    pub(crate) threshold_ms: u64,
+}
+
+/// Warns about requests slower than the threshold and counts them in
+/// `http_slow_requests_total`. The event is emitted in the `http_request`
+/// span of the TraceLayer, so it carries its request id, method, path and
+/// route, and the `user` span when logged in.
+pub(crate) async fn detect(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let threshold = state.settings.slow_requests.threshold_ms;
+    if threshold == 0 {
+        return next.run(req).await;
+    }
+
+    let start = Instant::now();
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str)
+        .to_owned();
+
+    let response = next.run(req).await;
+
+    let latency = start.elapsed();
+    if latency.as_millis() >= u128::from(threshold) {
+        warn!(
+            %method,
+            route,
+            status = response.status().as_u16(),
+            latency_ms = latency.as_secs_f64() * 1000.0,
+            threshold_ms = threshold,
+            "slow request"
+        );
+        let labels = [("method", method.to_string()), ("path", route)];
+        metrics::counter!("http_slow_requests_total", &labels).increment(1);
+    }
+    response
+}
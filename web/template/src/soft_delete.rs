@@ -0,0 +1,67 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use sqlx::AnyConnection;
+use time::OffsetDateTime;
+
+use crate::database::Database;
+use crate::helpers::to_micros;
+
+/// Rows of the tables following this convention are not removed but get a
+/// `deleted_at` timestamp, in microseconds since the epoch like the other
+/// timestamp columns. Queries leave them out unless they look at the trash,
+/// from which rows are restored or purged for good.
+pub(crate) const LIVE: &str = "deleted_at IS NULL";
+
+/// Condition of the rows in the trash.
+pub(crate) const TRASHED: &str = "deleted_at IS NOT NULL";
+
+/// Moves the row `id` of `table` to the trash, false when there is no such
+/// row out of it. Runs on `conn`, e.g. a transaction also recording the
+/// outbox event of the change.
+pub(crate) async fn trash(
+    db: &Database,
+    conn: &mut AnyConnection,
+    table: &'static str,
+    id: &str,
+) -> sqlx::Result<bool> {
+    let sql =
+        format!("UPDATE {table} SET deleted_at = $2 WHERE id = $1 AND {LIVE}");
+    let query = sqlx::query(&sql)
+        .bind(id)
+        .bind(to_micros(OffsetDateTime::now_utc()))
+        .execute(conn);
+    let statement = format!("{table}.trash");
+    let result = db.observe(&statement, &sql, query).await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Takes the row `id` of `table` out of the trash, false when it is not in
+/// there, on `conn` like [`trash`].
+pub(crate) async fn restore(
+    db: &Database,
+    conn: &mut AnyConnection,
+    table: &'static str,
+    id: &str,
+) -> sqlx::Result<bool> {
+    let sql = format!(
+        "UPDATE {table} SET deleted_at = NULL WHERE id = $1 AND {TRASHED}"
+    );
+    let query = sqlx::query(&sql).bind(id).execute(conn);
+    let statement = format!("{table}.restore");
+    let result = db.observe(&statement, &sql, query).await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,229 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{Stream, StreamExt, future, stream};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::{broadcast, watch};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::auth::OptionalUser;
+use crate::helpers::to_micros;
+use crate::state::AppState;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SseSettings {
+    /// Seconds between the keep-alive comments of an idle stream.
+    pub(crate) keep_alive: u64,
+    /// Notifications a slow stream can fall behind before it is ended, for
+    /// its client to resume.
+    pub(crate) capacity: usize,
+    /// Latest notifications kept for the clients resuming with
+    /// `Last-Event-ID`.
+    pub(crate) backlog: usize,
+}
+
+/// A notification, serialized once for every stream.
+#[derive(Debug)]
+struct Notification {
+    id: u64,
+    /// Only the streams of this user, every one when none.
+    to: Option<Uuid>,
+    event: String,
+    data: String,
+}
+
+impl Notification {
+    fn is_for(&self, user_id: Option<Uuid>) -> bool {
+        self.to.is_none() || self.to == user_id
+    }
+}
+
+/// Live notifications streamed to the pages by `/events`, on this instance.
+///
+/// Each has an id increasing across restarts. A client reconnecting with
+/// `Last-Event-ID`, as browsers do, is first sent the ones it missed that
+/// are still in the backlog. Streams falling behind are ended so their
+/// clients resume that way.
+pub(crate) struct Notifications {
+    sender: broadcast::Sender<Arc<Notification>>,
+    /// Oldest first.
+    backlog: Mutex<VecDeque<Arc<Notification>>>,
+    backlog_size: usize,
+    next_id: AtomicU64,
+    closing: watch::Sender<bool>,
+}
+
+impl Notifications {
+    pub(crate) fn new(settings: &SseSettings) -> Self {
+        let start = to_micros(OffsetDateTime::now_utc()).max(0) as u64;
+        Notifications {
+            sender: broadcast::channel(settings.capacity).0,
+            backlog: Mutex::new(VecDeque::with_capacity(settings.backlog)),
+            backlog_size: settings.backlog,
+            next_id: AtomicU64::new(start),
+            closing: watch::Sender::new(false),
+        }
+    }
+
+    /// Sends the `event` with `data` as JSON to every page.
+    #[allow(dead_code)]
+    pub(crate) fn publish(&self, event: &str, data: &impl Serialize) {
+        self.notify(None, event, data);
+    }
+
+    /// Sends the `event` with `data` as JSON to the pages of `user_id`.
+    pub(crate) fn send_to(
+        &self,
+        user_id: Uuid,
+        event: &str,
+        data: &impl Serialize,
+    ) {
+        self.notify(Some(user_id), event, data);
+    }
+
+    fn notify(&self, to: Option<Uuid>, event: &str, data: &impl Serialize) {
+        let data = match serde_json::to_string(data) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(event, "could not serialize a notification: {e}");
+                return;
+            }
+        };
+        // Under the lock, so a subscriber sees each one either in the
+        // backlog or on its receiver.
+        let mut backlog = self.backlog.lock().unwrap();
+        let notification = Arc::new(Notification {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            to,
+            event: event.to_string(),
+            data,
+        });
+        if backlog.len() >= self.backlog_size {
+            backlog.pop_front();
+        }
+        if self.backlog_size > 0 {
+            backlog.push_back(notification.clone());
+        }
+        // Fails when nobody listens.
+        let _ = self.sender.send(notification);
+    }
+
+    /// The notifications after `last_event_id` still in the backlog, and a
+    /// receiver of the next ones.
+    fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> (Vec<Arc<Notification>>, broadcast::Receiver<Arc<Notification>>) {
+        let backlog = self.backlog.lock().unwrap();
+        let missed = match last_event_id {
+            Some(last) => backlog
+                .iter()
+                .filter(|notification| notification.id > last)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        (missed, self.sender.subscribe())
+    }
+
+    /// Ends every stream, and the ones opened from now on, so the graceful
+    /// shutdown does not wait for them.
+    pub(crate) fn close(&self) {
+        self.closing.send_replace(true);
+    }
+}
+
+/// Decrements the open streams gauge when the stream is dropped.
+struct Connected;
+
+impl Connected {
+    fn new() -> Self {
+        metrics::gauge!("sse_connections").increment(1);
+        Connected
+    }
+}
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        metrics::gauge!("sse_connections").decrement(1);
+    }
+}
+
+/// Server-Sent Events stream of the notifications for every page, and for
+/// the logged in user's own. Each event is named, e.g. `export.ready`, and
+/// its data is JSON:
+///
+/// ```js
+/// new EventSource("/events")
+///   .addEventListener("export.ready", (e) => JSON.parse(e.data));
+/// ```
+pub(crate) async fn handler_events(
+    State(state): State<Arc<AppState>>,
+    OptionalUser(user): OptionalUser,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let user_id = user.map(|user| user.id);
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let (missed, receiver) = state.notifications.subscribe(last_event_id);
+    let mut closing = state.notifications.closing.subscribe();
+
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        // Lagging behind ends the stream, the client resumes from the
+        // backlog.
+        let notification = receiver.recv().await.ok()?;
+        Some((notification, receiver))
+    });
+    let connected = Connected::new();
+    let events = stream::iter(missed)
+        .chain(live)
+        .filter(move |notification| {
+            future::ready(notification.is_for(user_id))
+        })
+        .map(move |notification| {
+            let _ = &connected;
+            Ok(Event::default()
+                .id(notification.id.to_string())
+                .event(&notification.event)
+                .data(&notification.data))
+        })
+        .take_until(async move {
+            closing.wait_for(|closing| *closing).await.is_ok()
+        });
+    let interval = Duration::from_secs(state.settings.sse.keep_alive);
+    Sse::new(events).keep_alive(KeepAlive::new().interval(interval))
+}
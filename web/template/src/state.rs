@@ -14,8 +14,280 @@
 // OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 //
 
-use minijinja::Environment;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use axum_extra::extract::cookie::Key;
+use minijinja::{Environment, Value};
+
+use crate::api_key::{self, ApiKeyRateLimit, ApiKeyStore};
+use crate::audit::{self, AuditSink};
+use crate::cache::{self, Cache};
+use crate::crud::Resource;
+use crate::database::{self, Database};
+use crate::email;
+use crate::flags::{self, FlagStore};
+use crate::health::{self, HealthCheck};
+use crate::helpers::LogFilter;
+use crate::http_client::{self, HttpClient};
+use crate::jobs::{self, JobQueue, JobReceiver};
+use crate::jwt::{self, Jwt};
+use crate::mailer::{self, Mailer};
+use crate::newsletter::{self, SubscriberStore};
+use crate::outbox::Subscriber;
+use crate::payments::{self, OrderStore};
+use crate::pdf::{self, PdfRenderer};
+use crate::posts::{self, PostStore};
+use crate::privacy::{self, PersonalData};
+use crate::rate_limit::{self, RateLimiter};
+use crate::rbac;
+use crate::redis::{self, Redis};
+use crate::router;
+use crate::scheduler::Task;
+use crate::search::{self, SearchIndex};
+use crate::session::{self, SessionBackend, SessionSweep};
+use crate::settings::Settings;
+use crate::shortener::{self, ShortLinkStore};
+use crate::signed_url::{self, UrlSigner};
+use crate::sitemap::SitemapSource;
+use crate::sse::Notifications;
+use crate::storage::{self, Storage};
+use crate::theme;
+use crate::users::{self, UserStore};
+use crate::webhooks::{self, WebhookStore};
+use crate::websocket::Hub;
 
 pub(crate) struct AppState {
+    pub(crate) settings: Settings,
     pub(crate) env: Environment<'static>,
+    pub(crate) email_env: Environment<'static>,
+    /// Key signing and encrypting the application cookies.
+    pub(crate) cookie_key: Key,
+    pub(crate) storage: Box<dyn Storage>,
+    /// Postgres or SQLite pool, unless `database.backend` is `none`.
+    pub(crate) db: Option<Database>,
+    /// Shared Redis server, unless `redis.url` is left out.
+    pub(crate) redis: Option<Redis>,
+    /// In-process cache of computed values, local to this instance.
+    pub(crate) cache: Cache,
+    pub(crate) sessions: SessionBackend,
+    pub(crate) users: Box<dyn UserStore>,
+    /// Full-text index, shared with the indexer hook of `users`.
+    pub(crate) search: Arc<dyn SearchIndex>,
+    pub(crate) mailer: Box<dyn Mailer>,
+    /// Prints rendered pages to PDF, e.g. the invoice.
+    pub(crate) pdf: Box<dyn PdfRenderer>,
+    pub(crate) reset_limiter: RateLimiter,
+    pub(crate) verify_limiter: RateLimiter,
+    /// Limits the messages of each client IP to `/contact`.
+    pub(crate) contact_limiter: RateLimiter,
+    /// Newsletter subscribers and the addresses never emailed again.
+    pub(crate) newsletter: Box<dyn SubscriberStore>,
+    pub(crate) newsletter_limiter: RateLimiter,
+    /// Orders of the checkout and the payment events applied to them.
+    pub(crate) orders: Box<dyn OrderStore>,
+    /// Links redirected from `/s/{code}`.
+    pub(crate) short_links: Box<dyn ShortLinkStore>,
+    pub(crate) jwt: Jwt,
+    /// Limits the requests of each client IP to `/api/v1`.
+    pub(crate) api_limiter: RateLimiter,
+    pub(crate) api_keys: Box<dyn ApiKeyStore>,
+    pub(crate) api_key_limiter: Box<dyn ApiKeyRateLimit>,
+    pub(crate) audit: Box<dyn AuditSink>,
+    pub(crate) url_signer: UrlSigner,
+    pub(crate) jobs: JobQueue,
+    /// Client for outgoing HTTP requests, with retries and circuit
+    /// breakers.
+    pub(crate) http: HttpClient,
+    /// Client for OpenID Connect providers, which does not follow redirects.
+    pub(crate) oidc_http: reqwest::Client,
+    /// Blog posts, shared with their sitemap source.
+    pub(crate) posts: Arc<dyn PostStore>,
+    pub(crate) sitemap_sources: Vec<Box<dyn SitemapSource>>,
+    /// Consumers of the events published by the outbox relay.
+    pub(crate) outbox_subscribers: Vec<Arc<dyn Subscriber>>,
+    /// Endpoints the outbox events are POSTed to, and their deliveries.
+    pub(crate) webhooks: Box<dyn WebhookStore>,
+    /// WebSocket connections of this instance.
+    pub(crate) hub: Hub,
+    /// Notifications streamed to the pages by `/events`.
+    pub(crate) notifications: Notifications,
+    /// Feature flags forced on or off on `/admin/flags`.
+    pub(crate) flags: Box<dyn FlagStore>,
+    /// Tasks run on a schedule by the process running the background tasks.
+    pub(crate) scheduled_tasks: Vec<Box<dyn Task>>,
+    /// Parts of the application holding personal data, exported and erased
+    /// in this order, erased in reverse.
+    pub(crate) personal_data: Vec<Box<dyn PersonalData>>,
+    /// Records managed on the generic pages of `/admin/<resource>`.
+    pub(crate) admin_resources: Vec<Arc<dyn Resource>>,
+    /// Filter of the tracing subscriber, swapped on `/admin/log-level`.
+    pub(crate) log_filter: LogFilter,
+    /// Dependencies `/readyz` checks.
+    pub(crate) health_checks: Vec<Box<dyn HealthCheck>>,
+    /// Set by [`health::drain`] on shutdown, `/readyz` fails from then on.
+    ///
+    /// [`health::drain`]: crate::health::drain
+    pub(crate) draining: AtomicBool,
+}
+
+/// Builds the state shared by the server and the worker, with the
+/// receiving end of its job queue, see `jobs::spawn_workers`.
+pub(crate) async fn build(
+    settings: Settings,
+    log_filter: LogFilter,
+) -> anyhow::Result<(Arc<AppState>, JobReceiver)> {
+    let mut env = theme::environment(&settings.theme);
+    env.add_global(
+        "captcha",
+        Value::from_serialize(settings.captcha.widget()),
+    );
+    env.add_global(
+        "oidc_providers",
+        Value::from_serialize(settings.oidc.links()),
+    );
+    env.add_function("can", rbac::can);
+    env.add_function("feature", flags::feature);
+    let email_env = email::environment()?;
+    let cookie_key = Key::try_from(settings.cookies.key.as_bytes())?;
+    let storage = storage::from_settings(&settings.storage)?;
+    let db = database::Database::connect(&settings.database).await?;
+    if let Some(db) = &db
+        && settings.database.run_migrations
+    {
+        db.migrate().await?;
+    }
+    let redis = redis::Redis::connect(&settings.redis).await?;
+    let sessions = session::SessionBackend::from_settings(
+        &settings.session.store,
+        redis.as_ref(),
+    )
+    .await?;
+    let url_signer = signed_url::UrlSigner::new(&settings.signed_urls)?;
+    let audit = audit::from_settings(&settings.audit).await?;
+    let http = HttpClient::new(&settings.http_client)?;
+    // Following redirects would open the token requests to SSRF.
+    let oidc_http = http_client::builder(&settings.http_client)?
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    let mailer =
+        mailer::from_settings(&settings.mailer, &settings.email.from)?;
+    let pdf = pdf::from_settings(&settings.pdf, &settings.http_client)?;
+    let limiter = |limit, name| match &redis {
+        Some(redis) if settings.redis.rate_limits => {
+            rate_limit::RateLimiter::shared(limit, redis.clone(), name)
+        }
+        _ => rate_limit::RateLimiter::new(limit),
+    };
+    let reset_limiter = limiter(settings.auth.reset_rate_limit, "reset");
+    let verify_limiter = limiter(settings.auth.verify_rate_limit, "verify");
+    let contact_limiter = limiter(settings.contact.rate_limit, "contact");
+    let newsletter_limiter =
+        limiter(settings.newsletter.rate_limit, "newsletter");
+    let api_limiter = limiter(settings.api.rate_limit, "api");
+    let api_key_limiter =
+        Box::new(limiter(settings.api_keys.rate_limit, "api_key"));
+    let (jobs, job_receiver) = jobs::JobQueue::new(&settings.jobs);
+    let mut health_checks: Vec<Box<dyn health::HealthCheck>> = vec![
+        Box::new(health::Sessions),
+        Box::new(health::Storage),
+        Box::new(health::DiskSpace),
+    ];
+    if redis.is_some() {
+        health_checks.push(Box::new(health::Redis));
+    }
+    if let Some(db) = &db {
+        health_checks.push(Box::new(health::Database::primary()));
+        for index in 0..db.replica_count() {
+            health_checks.push(Box::new(health::Database::replica(index)));
+        }
+        health_checks.push(Box::new(health::Migrations));
+    }
+    let search = search::from_db(db.as_ref());
+    search::index_pages(&*search, router::search_documents()).await;
+    let users = Box::new(search::IndexedUserStore::new(
+        users::from_settings(&settings.database, db.as_ref()).await?,
+        search.clone(),
+    ));
+    let webhooks = webhooks::from_db(db.as_ref());
+    let flags = flags::from_db(db.as_ref());
+    let newsletter = newsletter::from_db(db.as_ref());
+    let orders = payments::from_db(db.as_ref());
+    let short_links = shortener::from_db(db.as_ref());
+    let api_keys = api_key::from_db(db.as_ref());
+    let posts = posts::from_settings(&settings.posts, db.as_ref())?;
+    let mut admin_resources: Vec<Arc<dyn Resource>> =
+        vec![Arc::new(newsletter::SubscriberResource)];
+    admin_resources.extend(posts::resource(&settings.posts, db.as_ref()));
+    let hub = Hub::new(&settings.websocket);
+    let notifications = Notifications::new(&settings.sse);
+    let cache = cache::Cache::new(&settings.cache);
+    let mut scheduled_tasks: Vec<Box<dyn Task>> = vec![
+        Box::new(privacy::DeletionSweep),
+        Box::new(webhooks::DeliveryPurge),
+    ];
+    if matches!(
+        sessions,
+        SessionBackend::Postgres(..) | SessionBackend::Sqlite(..)
+    ) {
+        scheduled_tasks.push(Box::new(SessionSweep));
+    }
+    let jwt = jwt::Jwt::new(
+        &settings.jwt,
+        &settings.site.url,
+        jwt::denylist(redis.as_ref(), db.as_ref()),
+    )?;
+
+    let state = Arc::new(AppState {
+        settings,
+        env,
+        email_env,
+        cookie_key,
+        storage,
+        db,
+        redis,
+        cache,
+        sessions,
+        users,
+        search,
+        mailer,
+        pdf,
+        reset_limiter,
+        verify_limiter,
+        contact_limiter,
+        newsletter,
+        newsletter_limiter,
+        orders,
+        short_links,
+        jwt,
+        api_limiter,
+        api_keys,
+        api_key_limiter,
+        audit,
+        url_signer,
+        jobs,
+        http,
+        oidc_http,
+        posts: posts.clone(),
+        sitemap_sources: vec![Box::new(posts::PostSitemap(posts))],
+        outbox_subscribers: Vec::new(),
+        webhooks,
+        hub,
+        notifications,
+        flags,
+        scheduled_tasks,
+        personal_data: vec![
+            Box::new(privacy::Profile),
+            Box::new(privacy::ApiKeys),
+            Box::new(newsletter::Newsletter),
+            Box::new(payments::Orders),
+            Box::new(shortener::ShortLinks),
+        ],
+        admin_resources,
+        log_filter,
+        health_checks,
+        draining: AtomicBool::new(false),
+    });
+    Ok((state, job_receiver))
 }
@@ -0,0 +1,383 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    http::{StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use opendal::{Operator, services::S3};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncReadExt};
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::helpers::BoxFuture;
+
+/// Size of the parts sent to S3, above its 5 MiB multipart minimum.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// Validity of the presigned URLs [`download`] redirects to, the request
+/// being checked already.
+const REDIRECT_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub(crate) enum StorageSettings {
+    Local {
+        /// Directory holding the objects.
+        root: String,
+    },
+    S3 {
+        bucket: String,
+        region: String,
+        /// Endpoint of S3-compatible services (MinIO, R2, ...).
+        endpoint: Option<String>,
+        access_key_id: String,
+        secret_access_key: String,
+        /// Prefix prepended to every key.
+        #[serde(default)]
+        root: String,
+    },
+}
+
+/// Object storage used for uploads and generated files.
+///
+/// Keys are `/` separated relative paths such as `uploads/<uuid>.png`.
+pub(crate) trait Storage: Send + Sync {
+    /// Stores the local file at `path` under `key`, consuming the file.
+    fn put_file<'a>(
+        &'a self,
+        key: &'a str,
+        path: &'a Path,
+    ) -> BoxFuture<'a, io::Result<()>>;
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, io::Result<()>>;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Bytes>>;
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>>;
+
+    /// URL downloading `key` for `ttl` with the `Content-Disposition`
+    /// header `disposition`, none when the backend can not sign URLs.
+    fn presign_get<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+        disposition: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<String>>>;
+
+    /// Request uploading `key` of `content_type` for `ttl`, straight from
+    /// the client to the backend, none when it can not sign URLs.
+    fn presign_put<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+        content_type: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<PresignedRequest>>>;
+
+    /// Fails when the backend is unreachable, for `/readyz`.
+    fn check(&self) -> BoxFuture<'_, io::Result<()>>;
+}
+
+/// Request a client sends as is to the storage backend.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct PresignedRequest {
+    pub(crate) method: String,
+    pub(crate) url: String,
+    /// Headers to send along with the request.
+    pub(crate) headers: BTreeMap<String, String>,
+}
+
+/// Downloads `key` as an attachment named `filename`, redirecting to a
+/// presigned URL when the backend signs them, so the bytes do not go
+/// through the application.
+pub(crate) async fn download(
+    storage: &dyn Storage,
+    key: &str,
+    content_type: &str,
+    filename: &str,
+) -> Response {
+    let disposition = format!("attachment; filename=\"{filename}\"");
+    match storage.presign_get(key, REDIRECT_TTL, &disposition).await {
+        Ok(Some(url)) => return Redirect::to(&url).into_response(),
+        Ok(None) => {}
+        Err(e) => {
+            error!(key, "could not presign a download: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+    match storage.get(key).await {
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Builds the storage backend selected in the settings.
+pub(crate) fn from_settings(
+    settings: &StorageSettings,
+) -> anyhow::Result<Box<dyn Storage>> {
+    Ok(match settings {
+        StorageSettings::Local { root } => {
+            Box::new(LocalStorage { root: PathBuf::from(root) })
+        }
+        StorageSettings::S3 {
+            bucket,
+            region,
+            endpoint,
+            access_key_id,
+            secret_access_key,
+            root,
+        } => {
+            let mut builder = S3::default()
+                .bucket(bucket)
+                .region(region)
+                .access_key_id(access_key_id)
+                .secret_access_key(secret_access_key)
+                .root(root);
+            if let Some(endpoint) = endpoint {
+                builder = builder.endpoint(endpoint);
+            }
+            Box::new(S3Storage { operator: Operator::new(builder)?.finish() })
+        }
+    })
+}
+
+/// Rejects keys escaping the storage root.
+fn check_key(key: &str) -> io::Result<()> {
+    if key.is_empty()
+        || key.starts_with('/')
+        || key.split('/').any(|part| part == ".." || part.is_empty())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid storage key `{key}`"),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    async fn path(&self, key: &str) -> io::Result<PathBuf> {
+        check_key(key)?;
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        Ok(path)
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put_file<'a>(
+        &'a self,
+        key: &'a str,
+        path: &'a Path,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let target = self.path(key).await?;
+            // Renaming fails across file systems, fall back to a copy.
+            if fs::rename(path, &target).await.is_err() {
+                fs::copy(path, &target).await?;
+                fs::remove_file(path).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move { fs::write(self.path(key).await?, bytes).await })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Bytes>> {
+        Box::pin(async move {
+            check_key(key)?;
+            fs::read(self.root.join(key)).await.map(Bytes::from)
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            check_key(key)?;
+            fs::remove_file(self.root.join(key)).await
+        })
+    }
+
+    fn presign_get<'a>(
+        &'a self,
+        _key: &'a str,
+        _ttl: Duration,
+        _disposition: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<String>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn presign_put<'a>(
+        &'a self,
+        _key: &'a str,
+        _ttl: Duration,
+        _content_type: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<PresignedRequest>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    fn check(&self) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            fs::create_dir_all(&self.root).await?;
+            let probe = self.root.join(".readyz");
+            fs::write(&probe, b"").await?;
+            fs::remove_file(probe).await
+        })
+    }
+}
+
+pub(crate) struct S3Storage {
+    operator: Operator,
+}
+
+impl Storage for S3Storage {
+    fn put_file<'a>(
+        &'a self,
+        key: &'a str,
+        path: &'a Path,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            check_key(key)?;
+            let mut file = fs::File::open(path).await?;
+            let mut writer = self.operator.writer(key).await?;
+            let mut buf = vec![0; UPLOAD_CHUNK_SIZE];
+            let copied: io::Result<()> = async {
+                loop {
+                    let read = file.read(&mut buf).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    writer.write(buf[..read].to_vec()).await?;
+                }
+                writer.close().await?;
+                Ok(())
+            }
+            .await;
+            if copied.is_err() {
+                let _ = writer.abort().await;
+            }
+            copied?;
+            fs::remove_file(path).await
+        })
+    }
+
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            check_key(key)?;
+            self.operator.write(key, bytes).await?;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Bytes>> {
+        Box::pin(async move {
+            check_key(key)?;
+            Ok(self.operator.read(key).await?.to_bytes())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            check_key(key)?;
+            Ok(self.operator.delete(key).await?)
+        })
+    }
+    fn presign_get<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+        disposition: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<String>>> {
+        Box::pin(async move {
+            check_key(key)?;
+            let request = self
+                .operator
+                .presign_read_with(key, ttl)
+                .override_content_disposition(disposition)
+                .await?;
+            Ok(Some(request.uri().to_string()))
+        })
+    }
+
+    fn presign_put<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+        content_type: &'a str,
+    ) -> BoxFuture<'a, io::Result<Option<PresignedRequest>>> {
+        Box::pin(async move {
+            check_key(key)?;
+            let request = self
+                .operator
+                .presign_write_with(key, ttl)
+                .content_type(content_type)
+                .await?;
+            let mut headers: BTreeMap<_, _> = request
+                .header()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect();
+            headers
+                .entry(header::CONTENT_TYPE.to_string())
+                .or_insert_with(|| content_type.to_string());
+            Ok(Some(PresignedRequest {
+                method: request.method().to_string(),
+                url: request.uri().to_string(),
+                headers,
+            }))
+        })
+    }
+
+    fn check(&self) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { Ok(self.operator.check().await?) })
+    }
+}
@@ -0,0 +1,171 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    Form,
+    extract::FromRequestParts,
+    http::{Method, StatusCode, request::Parts},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::OffsetDateTime;
+use tower_sessions::Session;
+use tracing::{error, info};
+use validator::Validate;
+
+use crate::audit::Audit;
+use crate::auth::{CurrentUser, verify_password};
+use crate::form::{Field, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::referer_path;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::users::User;
+use crate::view::View;
+
+/// Session key holding when the user last proved their password, as a unix
+/// timestamp.
+const SUDO_KEY: &str = "auth.sudo_at";
+/// Session key holding the page to go back to once confirmed.
+const SUDO_NEXT_KEY: &str = "auth.sudo_next";
+
+/// Marks the session as recently authenticated, e.g. on login.
+pub(crate) async fn grant(session: &Session) -> anyhow::Result<()> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    session.insert(SUDO_KEY, now).await?;
+    Ok(())
+}
+
+/// Ends the sudo mode, e.g. when an admin starts impersonating a user, who
+/// they cannot re-authenticate as.
+pub(crate) async fn revoke(session: &Session) -> anyhow::Result<()> {
+    session.remove::<i64>(SUDO_KEY).await?;
+    Ok(())
+}
+
+/// Guard for sensitive actions, only extracted when the user confirmed
+/// their password in the last `auth.sudo_window` seconds.
+///
+/// Otherwise the user is sent to `/sudo`, which brings them back to the
+/// page, or for a form post to the page holding the form.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RequireSudo;
+
+impl FromRequestParts<Arc<AppState>> for RequireSudo {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let confirmed_at =
+            session.get::<i64>(SUDO_KEY).await.ok().flatten().unwrap_or(0);
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        if now - confirmed_at <= state.settings.auth.sudo_window {
+            return Ok(RequireSudo);
+        }
+
+        let back = if parts.method == Method::GET {
+            parts
+                .uri
+                .path_and_query()
+                .map_or("/", |path| path.as_str())
+                .to_string()
+        } else {
+            referer_path(&state.settings.site.url, &parts.headers)
+        };
+        if let Err(e) = session.insert(SUDO_NEXT_KEY, back).await {
+            error!("could not remember the page before sudo: {e}");
+        }
+        Err(Redirect::to("/sudo").into_response())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct SudoInput {
+    #[serde(skip_serializing)]
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    password: String,
+}
+
+impl FormDefinition for SudoInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/sudo")
+            .submit("Confirm")
+            .field(Field::password("password", "Password").required())
+    }
+}
+
+/// Users signed up through OpenID Connect have no password and confirm by
+/// logging in again with their provider instead.
+fn render_sudo(view: &View, user: &User, errors: &FormErrors) -> Html<String> {
+    view.render(
+        "sudo",
+        context! {
+            title => "Confirm your password",
+            form => SudoInput::form().bind(&(), errors),
+            has_password => !user.password_hash.is_empty(),
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_sudo(
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Html<String> {
+    render_sudo(&view, &user, &FormErrors::default())
+}
+
+pub(crate) async fn handler_sudo_post(
+    session: Session,
+    view: View,
+    CurrentUser(user): CurrentUser,
+    audit: Audit,
+    Form(input): Form<SudoInput>,
+) -> Result<Response, ServerError> {
+    let valid = input.validate().is_ok()
+        && verify_password(input.password, user.password_hash.clone()).await?;
+    if !valid {
+        audit
+            .record("auth.sudo_failed", Some(user.id.to_string()), json!({}))
+            .await;
+        let mut errors = FormErrors::default();
+        errors.add("password", "Invalid password");
+        return Ok((
+            StatusCode::UNAUTHORIZED,
+            render_sudo(&view, &user, &errors),
+        )
+            .into_response());
+    }
+
+    grant(&session).await?;
+    info!(user = %user.id, "sudo mode granted");
+    audit.record("auth.sudo", Some(user.id.to_string()), json!({})).await;
+    let next = session
+        .remove::<String>(SUDO_NEXT_KEY)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "/account".to_string());
+    Ok(Redirect::to(&next).into_response())
+}
@@ -0,0 +1,170 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{StatusCode, header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::{Instrument, info_span};
+
+use crate::problem::Problem;
+use crate::state::AppState;
+use crate::view::ViewContext;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TenancySettings {
+    pub(crate) resolver: Resolver,
+    /// Domain the tenants are subdomains of, e.g. `example.com` for
+    /// `acme.example.com`.
+    pub(crate) base_domain: String,
+    /// Header naming the tenant, set by the proxy in front.
+    pub(crate) header: String,
+    /// Tenants by id, the others are refused.
+    #[serde(default)]
+    pub(crate) tenants: BTreeMap<String, TenantSettings>,
+}
+
+/// Where the tenant of a request is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Resolver {
+    /// A single tenant application, requests have no tenant.
+    None,
+    Subdomain,
+    Header,
+}
+
+/// Overrides of the application settings for one tenant.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct TenantSettings {
+    /// Shown to its users, the id when unset.
+    #[serde(default)]
+    pub(crate) name: Option<String>,
+    /// Feature flags forced on or off for its requests, above their
+    /// definitions in `[flags]` but below the overrides of `/admin/flags`.
+    #[serde(default)]
+    pub(crate) flags: BTreeMap<String, bool>,
+    /// Settings of the application's own features, read with
+    /// [`Tenant::setting`].
+    #[serde(default)]
+    pub(crate) settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// Tenant of the request, resolved by [`resolve`].
+///
+/// Rows belonging to a tenant live in tables with a `tenant_id TEXT NOT
+/// NULL` column, first in their primary key and indexes. Their stores take
+/// the `&Tenant` and filter every query on it, binding [`Tenant::id`] to
+/// `tenant_id = $1`, so a request only ever sees its tenant's rows. Cache
+/// keys holding tenant data are scoped by id the same way.
+///
+/// Extracting it fails with a 404 on requests without a tenant, routes
+/// serving both take an `Option<Extension<Tenant>>`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Tenant {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    #[serde(skip)]
+    pub(crate) settings: TenantSettings,
+}
+
+impl Tenant {
+    /// The tenant's value of `key` in its `settings`, none when unset or of
+    /// another type.
+    #[allow(dead_code)]
+    pub(crate) fn setting<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.settings.settings.get(key)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Tenant {
+    type Rejection = Problem;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Tenant>().cloned().ok_or_else(|| {
+            Problem::new(StatusCode::NOT_FOUND).detail("No tenant.")
+        })
+    }
+}
+
+impl TenancySettings {
+    /// Id of the tenant named by the request, none when it names none, e.g.
+    /// on the base domain itself.
+    fn tenant_id<'a>(&self, parts: &'a Parts) -> Option<&'a str> {
+        match self.resolver {
+            Resolver::None => None,
+            Resolver::Header => parts
+                .headers
+                .get(&self.header)
+                .and_then(|value| value.to_str().ok())
+                .filter(|id| !id.is_empty()),
+            Resolver::Subdomain => {
+                let host = parts
+                    .headers
+                    .get(header::HOST)
+                    .and_then(|value| value.to_str().ok())
+                    .or_else(|| parts.uri.host())?;
+                let host =
+                    host.rsplit_once(':').map_or(host, |(host, _)| host);
+                host.strip_suffix(self.base_domain.as_str())?
+                    .strip_suffix('.')
+                    .filter(|id| !id.is_empty() && !id.contains('.'))
+            }
+        }
+    }
+}
+
+/// Resolves the [`Tenant`] of the request, refusing unknown ones with a
+/// 404. The rest of the request runs in a `tenant` span naming it, the
+/// tenant is exposed to templates as `tenant`, and added to the response
+/// for the access log and the metrics.
+pub(crate) async fn resolve(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let settings = &state.settings.tenancy;
+    let (mut parts, body) = req.into_parts();
+    let Some(id) = settings.tenant_id(&parts) else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+    let Some(tenant_settings) = settings.tenants.get(id) else {
+        return Problem::new(StatusCode::NOT_FOUND)
+            .detail("Unknown tenant.")
+            .into_response();
+    };
+    let tenant = Tenant {
+        id: id.to_string(),
+        name: tenant_settings.name.clone().unwrap_or_else(|| id.to_string()),
+        settings: tenant_settings.clone(),
+    };
+    let span = info_span!("tenant", id = tenant.id);
+    ViewContext::insert(&mut parts.extensions, "tenant", &tenant);
+    parts.extensions.insert(tenant.clone());
+    let mut response =
+        next.run(Request::from_parts(parts, body)).instrument(span).await;
+    response.extensions_mut().insert(tenant);
+    response
+}
@@ -0,0 +1,62 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::path::Path;
+
+use minijinja::{AutoEscape, Environment, path_loader};
+use serde::Deserialize;
+use tracing::info;
+
+/// Theme every lookup falls back to.
+pub(crate) const DEFAULT_THEME: &str = "default";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ThemeSettings {
+    /// Directory holding one sub directory per theme.
+    pub(crate) path: String,
+    pub(crate) name: String,
+}
+
+/// Builds the page environment for the configured theme.
+///
+/// A template `name` is loaded from `<path>/<theme>/<name>.jinja` and, when
+/// the theme does not override it, from `<path>/default/<name>.jinja`. A
+/// theme therefore only ships the templates it wants to restyle.
+pub(crate) fn environment(settings: &ThemeSettings) -> Environment<'static> {
+    info!("loading templates from theme {}", settings.name);
+    let root = Path::new(&settings.path);
+
+    let mut dirs = vec![root.join(&settings.name)];
+    if settings.name != DEFAULT_THEME {
+        dirs.push(root.join(DEFAULT_THEME));
+    }
+    let loaders: Vec<_> = dirs.into_iter().map(path_loader).collect();
+
+    let mut env = Environment::new();
+    // Page names carry no extension, escape everything as HTML since
+    // templates render user submitted values.
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.set_loader(move |name| {
+        let file = format!("{name}.jinja");
+        for loader in &loaders {
+            if let Some(source) = loader(&file)? {
+                return Ok(Some(source));
+            }
+        }
+        Ok(None)
+    });
+    env
+}
@@ -0,0 +1,97 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Stateless `<user id>.<expiry>.<signature>` token sent in emails.
+///
+/// The signature covers a `purpose`, so a token of one flow is useless in
+/// another, and a `binding` taken from the user record, so a token stops
+/// working once the bound value changes (e.g. the password hash after a
+/// reset).
+#[derive(Debug)]
+pub(crate) struct UserToken {
+    pub(crate) user_id: Uuid,
+    expires: i64,
+    signature: Vec<u8>,
+}
+
+fn mac(
+    key: &[u8],
+    purpose: &str,
+    user_id: Uuid,
+    expires: i64,
+    binding: &[u8],
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key)
+        .expect("hmac accepts keys of any size");
+    mac.update(purpose.as_bytes());
+    mac.update(b"\0");
+    mac.update(user_id.as_bytes());
+    mac.update(&expires.to_be_bytes());
+    mac.update(binding);
+    mac
+}
+
+impl UserToken {
+    /// Signs a token valid for `ttl` seconds.
+    pub(crate) fn issue(
+        key: &[u8],
+        purpose: &str,
+        user_id: Uuid,
+        ttl: i64,
+        binding: &[u8],
+    ) -> String {
+        let expires = OffsetDateTime::now_utc().unix_timestamp() + ttl;
+        let signature =
+            mac(key, purpose, user_id, expires, binding).finalize();
+        format!(
+            "{}.{expires}.{}",
+            user_id.simple(),
+            URL_SAFE_NO_PAD.encode(signature.into_bytes())
+        )
+    }
+
+    /// Parses an unexpired token. Its signature is checked by
+    /// [`UserToken::verify`] once the user is loaded.
+    pub(crate) fn parse(token: &str) -> Option<Self> {
+        let mut parts = token.splitn(3, '.');
+        let user_id = Uuid::try_parse(parts.next()?).ok()?;
+        let expires = parts.next()?.parse::<i64>().ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(parts.next()?).ok()?;
+        if expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return None;
+        }
+        Some(UserToken { user_id, expires, signature })
+    }
+
+    pub(crate) fn verify(
+        &self,
+        key: &[u8],
+        purpose: &str,
+        binding: &[u8],
+    ) -> bool {
+        mac(key, purpose, self.user_id, self.expires, binding)
+            .verify_slice(&self.signature)
+            .is_ok()
+    }
+}
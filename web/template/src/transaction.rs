@@ -0,0 +1,122 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use anyhow::{Context, anyhow};
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts, Request},
+    http::{StatusCode, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::{Any, AnyConnection, Transaction};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::{error, warn};
+
+use crate::router::ServerError;
+use crate::state::AppState;
+
+type Slot = Arc<Mutex<Option<Transaction<'static, Any>>>>;
+
+/// Transaction of the request, begun by the first [`Tx`] extractor and
+/// finished by [`commit`] once the handler answered.
+#[derive(Clone, Default)]
+struct TxSlot(Slot);
+
+/// Database transaction spanning the request: committed when the response
+/// is a success or a redirect, rolled back on any other status, an error
+/// or a panic. Queries run on `&mut *tx`.
+///
+/// Handlers also working without a database take an `Option<Tx>`, none
+/// when there is no database.
+pub(crate) struct Tx(OwnedMutexGuard<Option<Transaction<'static, Any>>>);
+
+impl FromRequestParts<Arc<AppState>> for Tx {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let slot =
+            parts.extensions.get::<TxSlot>().cloned().ok_or_else(|| {
+                anyhow!("Tx extracted without the commit layer")
+            })?;
+        let mut guard = slot.0.lock_owned().await;
+        if guard.is_none() {
+            let db = state.db.as_ref().context("no database configured")?;
+            *guard = Some(db.begin().await.map_err(anyhow::Error::from)?);
+        }
+        Ok(Tx(guard))
+    }
+}
+
+impl OptionalFromRequestParts<Arc<AppState>> for Tx {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        if state.db.is_none() {
+            return Ok(None);
+        }
+        let tx = <Tx as FromRequestParts<_>>::from_request_parts(parts, state)
+            .await?;
+        Ok(Some(tx))
+    }
+}
+
+impl Deref for Tx {
+    type Target = AnyConnection;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().expect("the transaction is set on extraction")
+    }
+}
+
+impl DerefMut for Tx {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().expect("the transaction is set on extraction")
+    }
+}
+
+/// Commits or rolls back the transaction a handler opened with [`Tx`]. A
+/// failed commit turns the response into a 500, the changes are lost.
+pub(crate) async fn commit(mut req: Request, next: Next) -> Response {
+    let slot = TxSlot::default();
+    req.extensions_mut().insert(slot.clone());
+
+    let response = next.run(req).await;
+
+    let Some(tx) = slot.0.lock().await.take() else {
+        return response;
+    };
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        if let Err(e) = tx.commit().await {
+            error!("could not commit the transaction: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    } else if let Err(e) = tx.rollback().await {
+        warn!("could not roll back the transaction: {e}");
+    }
+    response
+}
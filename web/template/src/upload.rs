@@ -0,0 +1,431 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Json,
+    extract::{
+        FromRequest, Multipart, Request, State,
+        multipart::{Field, MultipartError, MultipartRejection},
+    },
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::jwt::Claims;
+use crate::problem::{PROBLEM_JSON, Problem, internal};
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::storage::{PresignedRequest, Storage};
+use crate::view::View;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct UploadSettings {
+    /// Directory holding the files while they are being received, before
+    /// they are handed to the storage backend.
+    pub(crate) temp_dir: String,
+    /// Maximum size in bytes of a single file.
+    pub(crate) max_file_size: usize,
+    /// Maximum size in bytes of a whole multipart request.
+    pub(crate) max_request_size: usize,
+    /// Accepted content types, `type/*` matches a whole family.
+    pub(crate) allowed_types: Vec<String>,
+    /// Seconds the signed download links stay valid.
+    pub(crate) download_ttl: i64,
+    /// Seconds the presigned direct upload requests stay valid.
+    pub(crate) presign_ttl: u64,
+}
+
+impl UploadSettings {
+    fn allows(&self, content_type: &str) -> bool {
+        self.allowed_types.iter().any(|allowed| {
+            match allowed.strip_suffix('*') {
+                Some(family) => content_type.starts_with(family),
+                None => content_type == allowed,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UploadError {
+    #[error(transparent)]
+    Multipart(#[from] MultipartError),
+
+    #[error(transparent)]
+    MultipartRejection(#[from] MultipartRejection),
+
+    #[error("invalid form fields: {0}")]
+    Fields(#[from] serde_urlencoded::de::Error),
+
+    #[error("file `{0}` is larger than {1} bytes")]
+    TooLarge(String, usize),
+
+    #[error("content type `{0}` is not allowed")]
+    ContentType(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl UploadError {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            UploadError::Multipart(error) => error.status(),
+            UploadError::MultipartRejection(rejection) => rejection.status(),
+            UploadError::Fields(_) => StatusCode::BAD_REQUEST,
+            UploadError::TooLarge(..) => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::ContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// File streamed to a temporary path of the upload directory.
+///
+/// The temporary file is removed when dropped, so a request failing
+/// halfway never leaves partial uploads behind. Call
+/// [`UploadedFile::persist`] to move it to the storage backend.
+#[derive(Debug)]
+pub(crate) struct UploadedFile {
+    pub(crate) field: String,
+    pub(crate) file_name: String,
+    pub(crate) content_type: String,
+    pub(crate) size: usize,
+    temp: Option<PathBuf>,
+}
+
+impl UploadedFile {
+    /// Moves the file to `storage` under a random key and returns the key.
+    pub(crate) async fn persist(
+        mut self,
+        storage: &dyn Storage,
+    ) -> std::io::Result<String> {
+        let key = upload_key(&self.file_name);
+        let temp = self.temp.take().expect("temporary file already moved");
+        let stored = storage.put_file(&key, &temp).await;
+        if stored.is_err() {
+            self.temp = Some(temp);
+        }
+        stored.map(|_| key)
+    }
+}
+
+impl Drop for UploadedFile {
+    fn drop(&mut self) {
+        if let Some(temp) = self.temp.take() {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+/// Random key of an upload, `uploads/<uuid>` and the lowercased extension
+/// of `file_name`, if any.
+fn upload_key(file_name: &str) -> String {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| {
+            extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .map(|extension| format!(".{}", extension.to_ascii_lowercase()))
+        .unwrap_or_default();
+    format!("uploads/{}{extension}", Uuid::new_v4())
+}
+
+/// Streams the file of `field` to the upload directory, none when it is
+/// empty, as for the file inputs left untouched.
+async fn stream_to_disk(
+    mut field: Field<'_>,
+    settings: &UploadSettings,
+) -> Result<Option<UploadedFile>, UploadError> {
+    let name = field.name().unwrap_or_default().to_string();
+    let file_name = field.file_name().unwrap_or_default().to_string();
+    let content_type =
+        field.content_type().unwrap_or("application/octet-stream").to_string();
+
+    let first = loop {
+        match field.chunk().await? {
+            Some(chunk) if chunk.is_empty() => continue,
+            Some(chunk) => break chunk,
+            None => return Ok(None),
+        }
+    };
+    if !settings.allows(&content_type) {
+        return Err(UploadError::ContentType(content_type));
+    }
+
+    let dir = PathBuf::from(&settings.temp_dir);
+    fs::create_dir_all(&dir).await?;
+    let temp = dir.join(format!(".upload-{}.part", Uuid::new_v4()));
+
+    // Owning the temporary path right away removes it on every error below.
+    let mut upload = UploadedFile {
+        field: name,
+        file_name,
+        content_type,
+        size: 0,
+        temp: Some(temp.clone()),
+    };
+
+    let mut file = fs::File::create(&temp).await?;
+    let mut next = Some(first);
+    while let Some(chunk) = next {
+        upload.size += chunk.len();
+        if upload.size > settings.max_file_size {
+            return Err(UploadError::TooLarge(
+                std::mem::take(&mut upload.file_name),
+                settings.max_file_size,
+            ));
+        }
+        file.write_all(&chunk).await?;
+        next = field.chunk().await?;
+    }
+    file.flush().await?;
+
+    Ok(Some(upload))
+}
+
+/// Multipart counterpart of [`ValidatedForm`](crate::router::ValidatedForm).
+///
+/// Text fields are deserialized into `T` and validated, file fields are
+/// streamed to the upload directory while enforcing the size and content
+/// type limits from the settings.
+#[derive(Debug)]
+pub struct ValidatedMultipart<T> {
+    pub fields: T,
+    pub(crate) files: Vec<UploadedFile>,
+}
+
+impl<T> FromRequest<Arc<AppState>> for ValidatedMultipart<T>
+where
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = ServerError;
+
+    async fn from_request(
+        req: Request,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let settings = &state.settings.upload;
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(UploadError::from)?;
+
+        let mut values = Vec::new();
+        let mut files = Vec::new();
+        while let Some(field) =
+            multipart.next_field().await.map_err(UploadError::from)?
+        {
+            if field.file_name().is_some() {
+                // Browsers send an untouched file input as an empty part
+                // without a file name.
+                if field.file_name() == Some("") {
+                    continue;
+                }
+                files.extend(stream_to_disk(field, settings).await?);
+            } else {
+                let name = field.name().unwrap_or_default().to_string();
+                let value = field.text().await.map_err(UploadError::from)?;
+                values.push((name, value));
+            }
+        }
+
+        let encoded = serde_urlencoded::to_string(&values)
+            .expect("text fields are always encodable");
+        let fields: T =
+            serde_urlencoded::from_str(&encoded).map_err(UploadError::from)?;
+        fields.validate()?;
+
+        Ok(ValidatedMultipart { fields, files })
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UploadInput {
+    #[validate(length(max = 200, message = "Too long"))]
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Stored {
+    field: String,
+    name: String,
+    content_type: String,
+    size: usize,
+    key: String,
+    /// Id of the image served under `/media`.
+    media_id: Option<String>,
+    /// Signed, expiring link to the file.
+    download_url: String,
+}
+
+pub(crate) async fn handler_upload(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Result<Html<String>, StatusCode> {
+    let rendered = view
+        .render(
+            "upload",
+            context! {
+                title => "Upload",
+                settings => context! {
+                    max_file_size => state.settings.upload.max_file_size,
+                    allowed_types => state.settings.upload.allowed_types,
+                },
+            },
+        )
+        .unwrap();
+
+    Ok(rendered)
+}
+
+pub(crate) async fn handler_upload_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    upload: ValidatedMultipart<UploadInput>,
+) -> Result<Html<String>, ServerError> {
+    let mut stored = Vec::new();
+    for file in upload.files {
+        let field = file.field.clone();
+        let name = file.file_name.clone();
+        let content_type = file.content_type.clone();
+        let size = file.size;
+        let key = file
+            .persist(state.storage.as_ref())
+            .await
+            .map_err(UploadError::from)?;
+        info!(key, size, "stored upload");
+        let id = key.trim_start_matches("uploads/");
+        let media_id =
+            content_type.starts_with("image/").then(|| id.to_string());
+        let download_url = state
+            .url_signer
+            .sign(&format!("/files/{id}"), state.settings.upload.download_ttl);
+        stored.push(Stored {
+            field,
+            name,
+            content_type,
+            size,
+            key,
+            media_id,
+            download_url,
+        });
+    }
+
+    let rendered = view
+        .render(
+            "upload",
+            context! {
+                title => "Upload",
+                settings => context! {
+                    max_file_size => state.settings.upload.max_file_size,
+                    allowed_types => state.settings.upload.allowed_types,
+                },
+                description => upload.fields.description,
+                stored => stored,
+            },
+        )
+        .unwrap();
+
+    Ok(rendered)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct PresignInput {
+    pub(crate) file_name: String,
+    pub(crate) content_type: String,
+    /// Bytes the client is about to send.
+    pub(crate) size: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct PresignedUpload {
+    key: String,
+    /// Request to send the file with, before it expires.
+    upload: PresignedRequest,
+    /// Signed, expiring link to the file once uploaded.
+    download_url: String,
+}
+
+/// Presigns a direct upload to the storage backend, for files too large to
+/// go through the application. The declared size and content type are
+/// checked like those of `/upload`, but nothing holds the client to them
+/// once the URL is signed.
+#[utoipa::path(
+    post,
+    path = "/uploads",
+    tag = "uploads",
+    security(("bearer" = [])),
+    request_body = PresignInput,
+    responses(
+        (status = 200, body = PresignedUpload),
+        (status = 401, body = Problem, content_type = PROBLEM_JSON),
+        (status = 413, body = Problem, content_type = PROBLEM_JSON),
+        (status = 415, body = Problem, content_type = PROBLEM_JSON),
+    ),
+)]
+pub(crate) async fn handler_upload_presign(
+    State(state): State<Arc<AppState>>,
+    _: Claims,
+    Json(input): Json<PresignInput>,
+) -> Result<Json<PresignedUpload>, Response> {
+    let settings = &state.settings.upload;
+    if !settings.allows(&input.content_type) {
+        let error = UploadError::ContentType(input.content_type);
+        return Err(Problem::new(error.status())
+            .detail(error.to_string())
+            .into_response());
+    }
+    if input.size > settings.max_file_size {
+        let error =
+            UploadError::TooLarge(input.file_name, settings.max_file_size);
+        return Err(Problem::new(error.status())
+            .detail(error.to_string())
+            .into_response());
+    }
+
+    let key = upload_key(&input.file_name);
+    let ttl = Duration::from_secs(settings.presign_ttl);
+    let upload = state
+        .storage
+        .presign_put(&key, ttl, &input.content_type)
+        .await
+        .map_err(internal)?;
+    let Some(upload) = upload else {
+        return Err(Problem::new(StatusCode::NOT_IMPLEMENTED)
+            .detail("The storage backend does not sign upload URLs.")
+            .into_response());
+    };
+    info!(key, size = input.size, "presigned upload");
+    let id = key.trim_start_matches("uploads/");
+    let download_url =
+        state.url_signer.sign(&format!("/files/{id}"), settings.download_ttl);
+    Ok(Json(PresignedUpload { key, upload, download_url }))
+}
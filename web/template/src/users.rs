@@ -0,0 +1,1190 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use axum::{
+    Form,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Row, any::AnyRow, query};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::audit::Audit;
+use crate::auth::{CurrentUser, hash_password};
+use crate::database::{Access, Database, DatabaseSettings};
+use crate::flash::Flash;
+use crate::form::{Field, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::outbox;
+use crate::pagination::{Page, Pagination, limit_offset};
+use crate::privacy::DeleteAccountJob;
+use crate::router::ServerError;
+use crate::soft_delete::{self, LIVE, TRASHED};
+use crate::state::AppState;
+use crate::sudo::RequireSudo;
+use crate::view::View;
+
+/// Topics of the outbox events recorded by the SQL stores, with the user
+/// `id`, and the `name` and `email` of the created ones.
+pub(crate) const USER_CREATED: &str = "user.created";
+pub(crate) const USER_TRASHED: &str = "user.trashed";
+pub(crate) const USER_RESTORED: &str = "user.restored";
+pub(crate) const USER_DELETED: &str = "user.deleted";
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct User {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) email: String,
+    #[serde(skip)]
+    pub(crate) password_hash: String,
+    pub(crate) email_verified: bool,
+    /// Names of the roles, mapped to permissions by `rbac.roles`.
+    pub(crate) roles: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+    /// When the account will be deleted, see `privacy`.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) deletion_scheduled_at: Option<OffsetDateTime>,
+    /// When an administrator moved the user to the trash, see
+    /// `soft_delete`.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) deleted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug)]
+pub(crate) struct NewUser {
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) password_hash: String,
+    pub(crate) roles: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum UserStoreError {
+    #[error("email already registered")]
+    EmailTaken,
+    #[error(transparent)]
+    Backend(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for UserStoreError {
+    /// The email is the only unique column besides the id.
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::EmailTaken
+            }
+            e => UserStoreError::Backend(e.into()),
+        }
+    }
+}
+
+/// Persistence of the application users.
+///
+/// Emails are compared case-insensitively, implementations store them
+/// lowercased. Users in the trash are left out of the lookups and lists,
+/// unless named otherwise, but keep their email taken.
+pub(crate) trait UserStore: Send + Sync {
+    fn find_by_id(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>>;
+
+    /// Like `find_by_id`, users in the trash included.
+    fn find_by_id_with_trashed(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>>;
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<Option<User>, UserStoreError>>;
+
+    /// Users from the most recently created.
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>>;
+
+    /// Users in the trash, from the most recently trashed.
+    fn list_trashed(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>>;
+
+    /// Number of users outside of the trash.
+    fn count(&self) -> BoxFuture<'_, Result<u64, UserStoreError>>;
+
+    fn create(
+        &self,
+        user: NewUser,
+    ) -> BoxFuture<'_, Result<User, UserStoreError>>;
+
+    fn update_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        roles: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>>;
+
+    fn update_password(
+        &self,
+        id: Uuid,
+        password_hash: String,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>>;
+
+    fn mark_email_verified(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>>;
+
+    /// Changes the email, which then needs to be verified again.
+    fn update_email<'a>(
+        &'a self,
+        id: Uuid,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<(), UserStoreError>>;
+
+    /// Sets or, with `None`, cancels the deletion of the account.
+    fn schedule_deletion(
+        &self,
+        id: Uuid,
+        at: Option<OffsetDateTime>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>>;
+
+    /// Users whose scheduled deletion is at or before `now`, in the trash
+    /// or not.
+    fn due_for_deletion(
+        &self,
+        now: OffsetDateTime,
+    ) -> BoxFuture<'_, Result<Vec<Uuid>, UserStoreError>>;
+
+    /// Moves the user to the trash, false when there is no such user out
+    /// of it.
+    fn trash(&self, id: Uuid) -> BoxFuture<'_, Result<bool, UserStoreError>>;
+
+    /// Takes the user out of the trash, false when they are not in it.
+    fn restore(&self, id: Uuid)
+    -> BoxFuture<'_, Result<bool, UserStoreError>>;
+
+    /// Removes the user for good, in the trash or not.
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, Result<(), UserStoreError>>;
+}
+
+/// Store of the configured database, in memory without one. The `sea-orm`
+/// feature swaps `SqlUserStore` for `SeaOrmUserStore` on the same table.
+pub(crate) async fn from_settings(
+    #[cfg_attr(not(feature = "sea-orm"), allow(unused_variables))]
+    settings: &DatabaseSettings,
+    db: Option<&Database>,
+) -> anyhow::Result<Box<dyn UserStore>> {
+    Ok(match db {
+        None => Box::new(MemoryUserStore::default()),
+        #[cfg(feature = "sea-orm")]
+        Some(_) => {
+            Box::new(crate::orm::SeaOrmUserStore::connect(settings).await?)
+        }
+        #[cfg(not(feature = "sea-orm"))]
+        Some(db) => Box::new(SqlUserStore::new(db.clone())),
+    })
+}
+
+/// Users kept in process memory, lost on restart.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryUserStore {
+    users: RwLock<HashMap<Uuid, User>>,
+}
+
+impl UserStore for MemoryUserStore {
+    fn find_by_id(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(async move {
+            let users = self.users.read().unwrap();
+            Ok(users
+                .get(&id)
+                .filter(|user| user.deleted_at.is_none())
+                .cloned())
+        })
+    }
+
+    fn find_by_id_with_trashed(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(
+            async move { Ok(self.users.read().unwrap().get(&id).cloned()) },
+        )
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<Option<User>, UserStoreError>> {
+        Box::pin(async move {
+            let email = email.to_lowercase();
+            let users = self.users.read().unwrap();
+            Ok(users
+                .values()
+                .find(|user| user.email == email && user.deleted_at.is_none())
+                .cloned())
+        })
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(async move {
+            let mut users: Vec<User> = self
+                .users
+                .read()
+                .unwrap()
+                .values()
+                .filter(|user| user.deleted_at.is_none())
+                .cloned()
+                .collect();
+            users.sort_by(|a, b| {
+                b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id))
+            });
+            Ok(pagination.apply(users))
+        })
+    }
+
+    fn list_trashed(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(async move {
+            let mut users: Vec<User> = self
+                .users
+                .read()
+                .unwrap()
+                .values()
+                .filter(|user| user.deleted_at.is_some())
+                .cloned()
+                .collect();
+            users.sort_by(|a, b| {
+                b.deleted_at.cmp(&a.deleted_at).then(a.id.cmp(&b.id))
+            });
+            Ok(pagination.apply(users))
+        })
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<u64, UserStoreError>> {
+        Box::pin(async move {
+            let users = self.users.read().unwrap();
+            Ok(users.values().filter(|user| user.deleted_at.is_none()).count()
+                as u64)
+        })
+    }
+
+    fn create(
+        &self,
+        user: NewUser,
+    ) -> BoxFuture<'_, Result<User, UserStoreError>> {
+        Box::pin(async move {
+            let email = user.email.to_lowercase();
+            let mut users = self.users.write().unwrap();
+            if users.values().any(|existing| existing.email == email) {
+                return Err(UserStoreError::EmailTaken);
+            }
+
+            let user = User {
+                id: Uuid::new_v4(),
+                name: user.name,
+                email,
+                password_hash: user.password_hash,
+                email_verified: false,
+                roles: user.roles,
+                deletion_scheduled_at: None,
+                created_at: OffsetDateTime::now_utc(),
+                deleted_at: None,
+            };
+            users.insert(user.id, user.clone());
+            Ok(user)
+        })
+    }
+
+    fn update_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        roles: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            if let Some(user) = self.users.write().unwrap().get_mut(&id) {
+                user.name = name;
+                user.roles = roles;
+            }
+            Ok(())
+        })
+    }
+
+    fn update_password(
+        &self,
+        id: Uuid,
+        password_hash: String,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            if let Some(user) = self.users.write().unwrap().get_mut(&id) {
+                user.password_hash = password_hash;
+            }
+            Ok(())
+        })
+    }
+
+    fn mark_email_verified(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            if let Some(user) = self.users.write().unwrap().get_mut(&id) {
+                user.email_verified = true;
+            }
+            Ok(())
+        })
+    }
+
+    fn update_email<'a>(
+        &'a self,
+        id: Uuid,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let email = email.to_lowercase();
+            let mut users = self.users.write().unwrap();
+            if users
+                .values()
+                .any(|existing| existing.email == email && existing.id != id)
+            {
+                return Err(UserStoreError::EmailTaken);
+            }
+            if let Some(user) = users.get_mut(&id) {
+                user.email = email;
+                user.email_verified = false;
+            }
+            Ok(())
+        })
+    }
+
+    fn schedule_deletion(
+        &self,
+        id: Uuid,
+        at: Option<OffsetDateTime>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            if let Some(user) = self.users.write().unwrap().get_mut(&id) {
+                user.deletion_scheduled_at = at;
+            }
+            Ok(())
+        })
+    }
+
+    fn due_for_deletion(
+        &self,
+        now: OffsetDateTime,
+    ) -> BoxFuture<'_, Result<Vec<Uuid>, UserStoreError>> {
+        Box::pin(async move {
+            let users = self.users.read().unwrap();
+            Ok(users
+                .values()
+                .filter(|user| {
+                    user.deletion_scheduled_at.is_some_and(|at| at <= now)
+                })
+                .map(|user| user.id)
+                .collect())
+        })
+    }
+
+    fn trash(&self, id: Uuid) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let mut users = self.users.write().unwrap();
+            let Some(user) =
+                users.get_mut(&id).filter(|user| user.deleted_at.is_none())
+            else {
+                return Ok(false);
+            };
+            user.deleted_at = Some(OffsetDateTime::now_utc());
+            Ok(true)
+        })
+    }
+
+    fn restore(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let mut users = self.users.write().unwrap();
+            let Some(user) =
+                users.get_mut(&id).filter(|user| user.deleted_at.is_some())
+            else {
+                return Ok(false);
+            };
+            user.deleted_at = None;
+            Ok(true)
+        })
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            self.users.write().unwrap().remove(&id);
+            Ok(())
+        })
+    }
+}
+
+/// Columns read into a [`User`], in the order of [`user_from_row`].
+#[cfg_attr(feature = "sea-orm", allow(dead_code))]
+const COLUMNS: &str = "id, name, email, password_hash, email_verified, \
+                       roles, created_at, deletion_scheduled_at, deleted_at";
+
+/// Users in the `users` table of the application database, created by the
+/// `0001_create_users` migration. Roles are a JSON array, flags integers and
+/// timestamps microseconds since the epoch, so the queries run on Postgres
+/// and SQLite. Lookups serve logins and checks right after writes, only the
+/// listings read from replicas.
+#[cfg_attr(feature = "sea-orm", allow(dead_code))]
+pub(crate) struct SqlUserStore {
+    db: Database,
+}
+
+#[cfg_attr(feature = "sea-orm", allow(dead_code))]
+impl SqlUserStore {
+    pub(crate) fn new(db: Database) -> Self {
+        SqlUserStore { db }
+    }
+
+    /// The user matching `condition`, whose `$1` is `value`, skipping the
+    /// trash unless `with_trashed`.
+    async fn find_one(
+        &self,
+        statement: &'static str,
+        condition: &str,
+        value: String,
+        with_trashed: bool,
+    ) -> Result<Option<User>, UserStoreError> {
+        let mut sql = format!("SELECT {COLUMNS} FROM users WHERE {condition}");
+        if !with_trashed {
+            sql = format!("{sql} AND {LIVE}");
+        }
+        let query = query(&sql)
+            .bind(value)
+            .fetch_optional(self.db.pool(Access::ReadWrite));
+        let row = self.db.observe(statement, &sql, query).await?;
+        Ok(row.as_ref().map(user_from_row).transpose()?)
+    }
+}
+
+#[cfg_attr(feature = "sea-orm", allow(dead_code))]
+fn user_from_row(row: &AnyRow) -> anyhow::Result<User> {
+    let roles: String = row.try_get(5)?;
+    let deletion_scheduled_at: Option<i64> = row.try_get(7)?;
+    let deleted_at: Option<i64> = row.try_get(8)?;
+    Ok(User {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        name: row.try_get(1)?,
+        email: row.try_get(2)?,
+        password_hash: row.try_get(3)?,
+        email_verified: row.try_get::<i64, _>(4)? != 0,
+        roles: serde_json::from_str(&roles).context("malformed user roles")?,
+        created_at: from_micros(row.try_get(6)?)?,
+        deletion_scheduled_at: deletion_scheduled_at
+            .map(from_micros)
+            .transpose()?,
+        deleted_at: deleted_at.map(from_micros).transpose()?,
+    })
+}
+
+impl UserStore for SqlUserStore {
+    fn find_by_id(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(self.find_one(
+            "users.find_by_id",
+            "id = $1",
+            id.to_string(),
+            false,
+        ))
+    }
+
+    fn find_by_id_with_trashed(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<Option<User>, UserStoreError>> {
+        Box::pin(self.find_one(
+            "users.find_by_id_with_trashed",
+            "id = $1",
+            id.to_string(),
+            true,
+        ))
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<Option<User>, UserStoreError>> {
+        Box::pin(self.find_one(
+            "users.find_by_email",
+            "email = $1",
+            email.to_lowercase(),
+            false,
+        ))
+    }
+
+    fn list(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {COLUMNS} FROM users WHERE {LIVE} \
+                 ORDER BY created_at DESC, id {}",
+                limit_offset(1)
+            );
+            let query = query(&sql)
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64)
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("users.list", &sql, query).await?;
+            Ok(rows
+                .iter()
+                .map(user_from_row)
+                .collect::<anyhow::Result<_>>()?)
+        })
+    }
+
+    fn list_trashed(
+        &self,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, Result<Vec<User>, UserStoreError>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {COLUMNS} FROM users WHERE {TRASHED} \
+                 ORDER BY deleted_at DESC, id {}",
+                limit_offset(1)
+            );
+            let query = query(&sql)
+                .bind(pagination.limit() as i64)
+                .bind(pagination.offset() as i64)
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let rows =
+                self.db.observe("users.list_trashed", &sql, query).await?;
+            Ok(rows
+                .iter()
+                .map(user_from_row)
+                .collect::<anyhow::Result<_>>()?)
+        })
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<u64, UserStoreError>> {
+        Box::pin(async move {
+            let sql = format!("SELECT COUNT(*) FROM users WHERE {LIVE}");
+            let query = query(&sql).fetch_one(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("users.count", &sql, query).await?;
+            Ok(row.try_get::<i64, _>(0)? as u64)
+        })
+    }
+
+    fn create(
+        &self,
+        user: NewUser,
+    ) -> BoxFuture<'_, Result<User, UserStoreError>> {
+        Box::pin(async move {
+            let user = User {
+                id: Uuid::new_v4(),
+                name: user.name,
+                email: user.email.to_lowercase(),
+                password_hash: user.password_hash,
+                email_verified: false,
+                roles: user.roles,
+                deletion_scheduled_at: None,
+                created_at: OffsetDateTime::now_utc(),
+                deleted_at: None,
+            };
+            let mut tx = self.db.begin().await?;
+            let sql = format!(
+                "INSERT INTO users ({COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, NULL, NULL)"
+            );
+            let query = query(&sql)
+                .bind(user.id.to_string())
+                .bind(&user.name)
+                .bind(&user.email)
+                .bind(&user.password_hash)
+                .bind(i64::from(user.email_verified))
+                .bind(
+                    serde_json::to_string(&user.roles)
+                        .map_err(anyhow::Error::from)?,
+                )
+                .bind(to_micros(user.created_at))
+                .execute(&mut *tx);
+            self.db.observe("users.create", &sql, query).await?;
+            let event = json!({ "id": user.id, "name": user.name, "email": user.email });
+            outbox::record(&self.db, &mut tx, USER_CREATED, &event).await?;
+            tx.commit().await?;
+            Ok(user)
+        })
+    }
+
+    fn update_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        roles: Vec<String>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let roles =
+                serde_json::to_string(&roles).map_err(anyhow::Error::from)?;
+            let sql = "UPDATE users SET name = $2, roles = $3 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(name)
+                .bind(roles)
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("users.update_profile", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn update_password(
+        &self,
+        id: Uuid,
+        password_hash: String,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let sql = "UPDATE users SET password_hash = $2 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(password_hash)
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("users.update_password", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn mark_email_verified(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let sql = "UPDATE users SET email_verified = 1 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("users.mark_email_verified", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn update_email<'a>(
+        &'a self,
+        id: Uuid,
+        email: &'a str,
+    ) -> BoxFuture<'a, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let sql = "UPDATE users SET email = $2, email_verified = 0 \
+                       WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(email.to_lowercase())
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("users.update_email", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn schedule_deletion(
+        &self,
+        id: Uuid,
+        at: Option<OffsetDateTime>,
+    ) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let sql =
+                "UPDATE users SET deletion_scheduled_at = $2 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(at.map(to_micros))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("users.schedule_deletion", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn due_for_deletion(
+        &self,
+        now: OffsetDateTime,
+    ) -> BoxFuture<'_, Result<Vec<Uuid>, UserStoreError>> {
+        Box::pin(async move {
+            let sql = "SELECT id FROM users WHERE deletion_scheduled_at <= $1";
+            let query = sqlx::query_scalar(sql)
+                .bind(to_micros(now))
+                .fetch_all(self.db.pool(Access::ReadOnly));
+            let ids: Vec<String> =
+                self.db.observe("users.due_for_deletion", sql, query).await?;
+            Ok(ids
+                .iter()
+                .map(|id| Uuid::try_parse(id))
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::from)?)
+        })
+    }
+
+    fn trash(&self, id: Uuid) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let mut tx = self.db.begin().await?;
+            let trashed = soft_delete::trash(
+                &self.db,
+                &mut tx,
+                "users",
+                &id.to_string(),
+            )
+            .await?;
+            if trashed {
+                let event = json!({ "id": id });
+                outbox::record(&self.db, &mut tx, USER_TRASHED, &event)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(trashed)
+        })
+    }
+
+    fn restore(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, Result<bool, UserStoreError>> {
+        Box::pin(async move {
+            let mut tx = self.db.begin().await?;
+            let restored = soft_delete::restore(
+                &self.db,
+                &mut tx,
+                "users",
+                &id.to_string(),
+            )
+            .await?;
+            if restored {
+                let event = json!({ "id": id });
+                outbox::record(&self.db, &mut tx, USER_RESTORED, &event)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(restored)
+        })
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, Result<(), UserStoreError>> {
+        Box::pin(async move {
+            let mut tx = self.db.begin().await?;
+            let sql = "DELETE FROM users WHERE id = $1";
+            let query = query(sql).bind(id.to_string()).execute(&mut *tx);
+            let result = self.db.observe("users.delete", sql, query).await?;
+            if result.rows_affected() > 0 {
+                let event = json!({ "id": id });
+                outbox::record(&self.db, &mut tx, USER_DELETED, &event)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+}
+
+pub(crate) async fn handler_users(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    pagination: Pagination,
+) -> Result<Html<String>, ServerError> {
+    let users = state.users.list(pagination).await?;
+    Ok(view
+        .render(
+            "users",
+            context! {
+                title => "Users",
+                page => Page::new(users, pagination),
+            },
+        )
+        .unwrap())
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct NewUserInput {
+    #[validate(length(min = 1, max = 100, message = "Can not be empty"))]
+    name: String,
+    #[validate(email(message = "Must be a valid email"))]
+    email: String,
+    #[serde(skip_serializing)]
+    #[validate(length(
+        min = 8,
+        max = 128,
+        message = "Must have between 8 and 128 characters"
+    ))]
+    password: String,
+    /// Space separated role names.
+    #[serde(default)]
+    roles: String,
+}
+
+impl FormDefinition for NewUserInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/admin/users/new")
+            .submit("Create user")
+            .field(Field::text("name", "Name").length(Some(1), Some(100)))
+            .field(Field::email("email", "Email").required())
+            .field(
+                Field::password("password", "Password")
+                    .length(Some(8), Some(128)),
+            )
+            .field(Field::text("roles", "Roles").help("Space separated"))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct EditUserInput {
+    #[validate(length(min = 1, max = 100, message = "Can not be empty"))]
+    name: String,
+    #[validate(email(message = "Must be a valid email"))]
+    email: String,
+    #[serde(default)]
+    roles: String,
+}
+
+impl EditUserInput {
+    fn form(id: Uuid) -> FormSpec {
+        FormSpec::new(format!("/admin/users/{id}"))
+            .submit("Save")
+            .field(Field::text("name", "Name").length(Some(1), Some(100)))
+            .field(
+                Field::email("email", "Email")
+                    .required()
+                    .help("Changing it asks for a new verification"),
+            )
+            .field(Field::text("roles", "Roles").help("Space separated"))
+    }
+}
+
+impl From<&User> for EditUserInput {
+    fn from(user: &User) -> Self {
+        EditUserInput {
+            name: user.name.clone(),
+            email: user.email.clone(),
+            roles: user.roles.join(" "),
+        }
+    }
+}
+
+/// Validates `input` and its `roles` against `rbac.roles`, the role names
+/// on success.
+fn check_input(
+    state: &AppState,
+    input: &impl Validate,
+    roles: &str,
+) -> Result<Vec<String>, FormErrors> {
+    let mut errors = match input.validate() {
+        Ok(()) => FormErrors::default(),
+        Err(errors) => FormErrors::from(&errors),
+    };
+    let roles: Vec<String> =
+        roles.split_whitespace().map(Into::into).collect();
+    let known = &state.settings.rbac.roles;
+    if let Some(role) = roles.iter().find(|role| !known.contains_key(*role)) {
+        let known: Vec<&str> = known.keys().map(String::as_str).collect();
+        errors.add(
+            "roles",
+            &format!(
+                "Unknown role {role}, must be among {}",
+                known.join(", ")
+            ),
+        );
+    }
+    if errors.is_empty() { Ok(roles) } else { Err(errors) }
+}
+
+fn render_user_form<T: Serialize>(
+    view: &View,
+    title: &str,
+    form: &FormSpec,
+    values: &T,
+    errors: &FormErrors,
+    user: Option<&User>,
+) -> Html<String> {
+    view.render(
+        "user_form",
+        context! {
+            title => title,
+            form => form.bind(values, errors),
+            user => user,
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_user_new(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Html<String> {
+    let values = json!({
+        "roles": state.settings.rbac.default_roles.join(" "),
+    });
+    render_user_form(
+        &view,
+        "New user",
+        &NewUserInput::form(),
+        &values,
+        &FormErrors::default(),
+        None,
+    )
+}
+
+pub(crate) async fn handler_user_new_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Form(input): Form<NewUserInput>,
+) -> Result<Response, ServerError> {
+    let form = NewUserInput::form();
+    let invalid = |errors: &FormErrors| {
+        let rendered =
+            render_user_form(&view, "New user", &form, &input, errors, None);
+        Ok((StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response())
+    };
+    let roles = match check_input(&state, &input, &input.roles) {
+        Ok(roles) => roles,
+        Err(errors) => return invalid(&errors),
+    };
+
+    let password_hash = hash_password(input.password.clone()).await?;
+    let user = match state
+        .users
+        .create(NewUser {
+            name: input.name.clone(),
+            email: input.email.clone(),
+            password_hash,
+            roles,
+        })
+        .await
+    {
+        Ok(user) => user,
+        Err(UserStoreError::EmailTaken) => {
+            let mut errors = FormErrors::default();
+            errors.add("email", "Is already registered");
+            return invalid(&errors);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    info!(user = %user.id, "user created by an administrator");
+    audit
+        .record(
+            "admin.user_create",
+            Some(user.id.to_string()),
+            json!({ "email": user.email, "roles": user.roles }),
+        )
+        .await;
+    flash.success(format!("Created {}.", user.email));
+    Ok(Redirect::to("/admin/users").into_response())
+}
+
+pub(crate) async fn handler_user_edit(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Path(id): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    let Some(user) = state.users.find_by_id(id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let rendered = render_user_form(
+        &view,
+        &user.name,
+        &EditUserInput::form(id),
+        &EditUserInput::from(&user),
+        &FormErrors::default(),
+        Some(&user),
+    );
+    Ok(rendered.into_response())
+}
+
+pub(crate) async fn handler_user_edit_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+    Form(input): Form<EditUserInput>,
+) -> Result<Response, ServerError> {
+    let Some(user) = state.users.find_by_id(id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let form = EditUserInput::form(id);
+    let invalid = |errors: &FormErrors| {
+        let rendered = render_user_form(
+            &view,
+            &user.name,
+            &form,
+            &input,
+            errors,
+            Some(&user),
+        );
+        Ok((StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response())
+    };
+    let roles = match check_input(&state, &input, &input.roles) {
+        Ok(roles) => roles,
+        Err(errors) => return invalid(&errors),
+    };
+
+    if !input.email.eq_ignore_ascii_case(&user.email) {
+        match state.users.update_email(id, &input.email).await {
+            Ok(()) => {}
+            Err(UserStoreError::EmailTaken) => {
+                let mut errors = FormErrors::default();
+                errors.add("email", "Is already registered");
+                return invalid(&errors);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    state.users.update_profile(id, input.name.clone(), roles.clone()).await?;
+
+    info!(user = %id, "user updated by an administrator");
+    audit
+        .record(
+            "admin.user_update",
+            Some(id.to_string()),
+            json!({ "email": input.email.to_lowercase(), "roles": roles }),
+        )
+        .await;
+    flash.success(format!("Saved {}.", input.email.to_lowercase()));
+    Ok(Redirect::to("/admin/users").into_response())
+}
+
+/// Moves the user to the trash, out of every page and unable to log in
+/// until restored.
+pub(crate) async fn handler_user_delete(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(admin): CurrentUser,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    let Some(user) = state.users.find_by_id(id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if user.id == admin.id {
+        flash.error("You can not delete your own account from here.");
+        return Ok(Redirect::to(&format!("/admin/users/{id}")).into_response());
+    }
+
+    state.users.trash(id).await?;
+    info!(user = %id, "user moved to the trash");
+    audit
+        .record(
+            "admin.user_trash",
+            Some(id.to_string()),
+            json!({ "email": user.email }),
+        )
+        .await;
+    flash.success(format!("Moved {} to the trash.", user.email));
+    Ok(Redirect::to("/admin/users").into_response())
+}
+
+pub(crate) async fn handler_users_trash(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    pagination: Pagination,
+) -> Result<Html<String>, ServerError> {
+    let users = state.users.list_trashed(pagination).await?;
+    Ok(view
+        .render(
+            "users_trash",
+            context! {
+                title => "Trash",
+                page => Page::new(users, pagination),
+            },
+        )
+        .unwrap())
+}
+
+pub(crate) async fn handler_user_restore(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, ServerError> {
+    if state.users.restore(id).await? {
+        info!(user = %id, "user restored from the trash");
+        audit
+            .record("admin.user_restore", Some(id.to_string()), json!({}))
+            .await;
+        flash.success("User restored.");
+    } else {
+        flash.error("No such user in the trash.");
+    }
+    Ok(Redirect::to("/admin/users/trash"))
+}
+
+/// Erases a user of the trash right away, through the same job as a
+/// deletion asked for by the user, so every `PersonalData` source is
+/// covered.
+pub(crate) async fn handler_user_purge(
+    _: RequireSudo,
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, ServerError> {
+    let user = state
+        .users
+        .find_by_id_with_trashed(id)
+        .await?
+        .filter(|user| user.deleted_at.is_some());
+    let Some(user) = user else {
+        flash.error("No such user in the trash.");
+        return Ok(Redirect::to("/admin/users/trash"));
+    };
+
+    state.users.schedule_deletion(id, Some(OffsetDateTime::now_utc())).await?;
+    state.jobs.push(DeleteAccountJob { user_id: id }).await?;
+    info!(user = %id, "user purged by an administrator");
+    audit
+        .record(
+            "admin.user_purge",
+            Some(id.to_string()),
+            json!({ "email": user.email }),
+        )
+        .await;
+    flash.success(format!("Deleted {} for good.", user.email));
+    Ok(Redirect::to("/admin/users/trash"))
+}
@@ -0,0 +1,166 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use minijinja::context;
+use tracing::{error, info};
+
+use crate::auth::CurrentUser;
+use crate::mailer;
+use crate::router::ServerError;
+use crate::state::AppState;
+use crate::token::UserToken;
+use crate::users::User;
+use crate::view::View;
+
+const PURPOSE: &str = "email-verification";
+
+/// Emails `user` a link proving they own their address. The token is bound
+/// to the address, so changing it voids older links.
+pub(crate) async fn send_verification_email(state: &AppState, user: &User) {
+    let token = UserToken::issue(
+        state.settings.cookies.key.as_bytes(),
+        PURPOSE,
+        user.id,
+        state.settings.auth.verify_token_ttl,
+        user.email.as_bytes(),
+    );
+    let ctx = context! {
+        name => user.name,
+        url => state.settings.site.url_for(&format!("/verify/{token}")),
+        hours => state.settings.auth.verify_token_ttl / 3600,
+    };
+    if let Err(e) =
+        mailer::deliver(state, &user.email, "verify_email", ctx).await
+    {
+        error!("could not send the verification email: {e:#}");
+    }
+}
+
+/// Explains how to verify the address and offers to resend the link.
+pub(crate) async fn handler_verify(
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Html<String> {
+    view.render(
+        "verify_email",
+        context! {
+            title => "Verify your email",
+            user => user,
+        },
+    )
+    .unwrap()
+}
+
+pub(crate) async fn handler_verify_token(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    Path(token): Path<String>,
+) -> Result<Response, ServerError> {
+    let user = match UserToken::parse(&token) {
+        Some(token) => {
+            state.users.find_by_id(token.user_id).await?.filter(|user| {
+                token.verify(
+                    state.settings.cookies.key.as_bytes(),
+                    PURPOSE,
+                    user.email.as_bytes(),
+                )
+            })
+        }
+        None => None,
+    };
+
+    let Some(user) = user else {
+        let rendered = view
+            .render(
+                "verify_email",
+                context! {
+                    title => "Verify your email",
+                    invalid => true,
+                },
+            )
+            .unwrap();
+        return Ok((StatusCode::BAD_REQUEST, rendered).into_response());
+    };
+
+    if !user.email_verified {
+        state.users.mark_email_verified(user.id).await?;
+        info!(user = %user.id, "email verified");
+    }
+    let rendered = view
+        .render(
+            "verify_email",
+            context! {
+                title => "Verify your email",
+                verified => true,
+            },
+        )
+        .unwrap();
+    Ok(rendered.into_response())
+}
+
+pub(crate) async fn handler_verify_resend(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    CurrentUser(user): CurrentUser,
+) -> Response {
+    if user.email_verified {
+        return Redirect::to("/account").into_response();
+    }
+
+    let allowed = state.verify_limiter.check(&user.id.to_string()).await;
+    if allowed {
+        send_verification_email(&state, &user).await;
+    }
+    let rendered = view
+        .render(
+            "verify_email",
+            context! {
+                title => "Verify your email",
+                user => user,
+                resent => allowed,
+                throttled => !allowed,
+            },
+        )
+        .unwrap();
+    let status =
+        if allowed { StatusCode::OK } else { StatusCode::TOO_MANY_REQUESTS };
+    (status, rendered).into_response()
+}
+
+/// Route layer sending users with an unverified email to `/verify` when
+/// `auth.require_verified_email` is set. Goes after `require_auth`.
+pub(crate) async fn require_verified(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let unverified = req
+        .extensions()
+        .get::<CurrentUser>()
+        .is_some_and(|CurrentUser(user)| !user.email_verified);
+    if unverified && state.settings.auth.require_verified_email {
+        return Redirect::to("/verify").into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,89 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{net::IpAddr, sync::Arc};
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_client_ip::ClientIp;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+/// Version of the crate.
+pub(crate) const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short hash of the commit built, `unknown` outside of a git checkout.
+pub(crate) const GIT_HASH: &str = env!("GIT_HASH");
+/// Date of the commit built.
+pub(crate) const BUILD_DATE: &str = env!("BUILD_DATE");
+/// Output of `rustc --version` for the compiler used.
+pub(crate) const RUSTC_VERSION: &str = env!("RUSTC_VERSION");
+/// Cargo profile built, `debug` or `release`.
+pub(crate) const BUILD_PROFILE: &str = env!("BUILD_PROFILE");
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct VersionSettings {
+    /// Answer `/version` to loopback and private addresses only.
+    pub(crate) internal_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    version: &'static str,
+    git_hash: &'static str,
+    build_date: &'static str,
+    rustc: &'static str,
+    profile: &'static str,
+}
+
+/// Loopback, private and link-local addresses.
+pub(crate) fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback() || ip.is_private() || ip.is_link_local()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+/// Build metadata of the running binary, to check what a deploy runs.
+pub(crate) async fn handler_version(
+    State(state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+) -> Response {
+    if state.settings.version.internal_only && !is_internal(ip) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(VersionInfo {
+        version: VERSION,
+        git_hash: GIT_HASH,
+        build_date: BUILD_DATE,
+        rustc: RUSTC_VERSION,
+        profile: BUILD_PROFILE,
+    })
+    .into_response()
+}
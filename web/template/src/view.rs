@@ -0,0 +1,173 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{collections::BTreeMap, convert::Infallible, sync::Arc};
+
+use axum::{
+    Json,
+    extract::FromRequestParts,
+    http::{Extensions, HeaderMap, HeaderValue, header, request::Parts},
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::{Value, context};
+use serde::Serialize;
+
+use crate::flash::Flash;
+use crate::metric;
+use crate::state::AppState;
+
+/// Values merged into the context of every page rendered through [`View`].
+///
+/// Middlewares and extractors add their entries to the request extensions
+/// with [`ViewContext::insert`], so handlers never pass them by hand.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ViewContext(BTreeMap<&'static str, Value>);
+
+impl ViewContext {
+    /// Exposes `value` as `key` to the templates rendered for this request.
+    pub(crate) fn insert(
+        extensions: &mut Extensions,
+        key: &'static str,
+        value: impl Serialize,
+    ) {
+        extensions
+            .get_or_insert_default::<ViewContext>()
+            .0
+            .insert(key, Value::from_serialize(value));
+    }
+}
+
+/// Renders page templates with the request's [`ViewContext`] and pending
+/// flash messages.
+pub(crate) struct View {
+    state: Arc<AppState>,
+    context: ViewContext,
+    flash: Option<Flash>,
+    /// Whether the `Accept` header ranks JSON above HTML.
+    json: bool,
+}
+
+impl View {
+    /// Renders `name` with `ctx`. Keys from `ctx` take precedence over the
+    /// ones of the view context. The flash messages given to the template as
+    /// `flashes` count as shown.
+    pub(crate) fn render(
+        &self,
+        name: &str,
+        ctx: Value,
+    ) -> Result<Html<String>, minijinja::Error> {
+        let _timer = metric::time!("render", template = name);
+        let template = self.state.env.get_template(name)?;
+        let mut globals = self.context.0.clone();
+        if let Some(flash) = &self.flash {
+            globals.insert("flashes", Value::from_serialize(flash.take()));
+        }
+        let globals = Value::from_iter(globals);
+        template.render(context! { ..ctx, ..globals }).map(Html)
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for View {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(View {
+            state: state.clone(),
+            context: parts
+                .extensions
+                .get::<ViewContext>()
+                .cloned()
+                .unwrap_or_default(),
+            flash: parts.extensions.get::<Flash>().cloned(),
+            json: prefers_json(&parts.headers),
+        })
+    }
+}
+
+/// Responds with the template for browsers and with the data as JSON for
+/// clients asking for `application/json`, so a page doubles as an API
+/// endpoint.
+///
+/// ```ignore
+/// Negotiate::new(view, "content", Entries { entries })
+///     .page(context! { title => "Content" })
+/// ```
+pub(crate) struct Negotiate<T> {
+    view: View,
+    template: &'static str,
+    data: T,
+    page: Value,
+}
+
+impl<T: Serialize> Negotiate<T> {
+    /// Renders `template` with the fields of `data`, a struct or a map.
+    pub(crate) fn new(view: View, template: &'static str, data: T) -> Self {
+        Self { view, template, data, page: Value::UNDEFINED }
+    }
+
+    /// Adds values given to the template only, e.g. its title and meta
+    /// tags. The fields of the data take precedence.
+    pub(crate) fn page(mut self, ctx: Value) -> Self {
+        self.page = ctx;
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for Negotiate<T> {
+    fn into_response(self) -> Response {
+        let mut response = if self.view.json {
+            Json(self.data).into_response()
+        } else {
+            let data = Value::from_serialize(&self.data);
+            self.view
+                .render(self.template, context! { ..data, ..self.page })
+                .unwrap()
+                .into_response()
+        };
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+/// Whether JSON ranks above HTML in the `Accept` header. Wildcards count
+/// for HTML, so browsers and clients sending `*/*` get the page.
+fn prefers_json(headers: &HeaderMap) -> bool {
+    let Some(accept) =
+        headers.get(header::ACCEPT).and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let (mut json, mut html) = (0.0, 0.0);
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let media = params.next().unwrap_or_default().trim();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media {
+            "application/json" => json = f32::max(json, quality),
+            "text/html" | "text/*" | "*/*" => html = f32::max(html, quality),
+            _ => {}
+        }
+    }
+    json > html
+}
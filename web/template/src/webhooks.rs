@@ -0,0 +1,846 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow};
+use axum::{
+    Form,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use hmac::{Hmac, Mac};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{Row, any::AnyRow, query};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::audit::Audit;
+use crate::database::{Access, Database};
+use crate::flash::Flash;
+use crate::form::{Field, FieldKind, FormDefinition, FormErrors, FormSpec};
+use crate::helpers::{BoxFuture, from_micros, to_micros};
+use crate::jobs::Job;
+use crate::outbox::Event;
+use crate::pagination::{
+    Cursor, Order, Page, Pagination, keyset, limit_offset,
+};
+use crate::router::ServerError;
+use crate::scheduler::Task;
+use crate::state::AppState;
+use crate::users::{USER_CREATED, USER_DELETED, USER_RESTORED, USER_TRASHED};
+use crate::view::View;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix of the generated secrets, so leaked ones are easy to spot.
+const SECRET_PREFIX: &str = "whsec_";
+/// Deliveries kept by the memory store, the oldest dropped first.
+const MEMORY_DELIVERIES: usize = 1000;
+
+/// Topics an endpoint can subscribe to.
+pub(crate) const TOPICS: &[&str] =
+    &[USER_CREATED, USER_TRASHED, USER_RESTORED, USER_DELETED];
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct WebhookSettings {
+    /// Seconds to wait for an endpoint.
+    pub(crate) timeout: u64,
+    /// Days the delivery attempts are kept.
+    pub(crate) retention_days: u64,
+}
+
+/// A registered endpoint. The secret is shown once when created.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Endpoint {
+    pub(crate) id: Uuid,
+    pub(crate) url: String,
+    #[serde(skip)]
+    pub(crate) secret: String,
+    /// Every topic when empty.
+    pub(crate) topics: Vec<String>,
+    pub(crate) enabled: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+}
+
+impl Endpoint {
+    fn wants(&self, topic: &str) -> bool {
+        self.enabled
+            && (self.topics.is_empty()
+                || self.topics.iter().any(|t| t == topic))
+    }
+}
+
+pub(crate) struct NewEndpoint {
+    pub(crate) url: String,
+    pub(crate) secret: String,
+    pub(crate) topics: Vec<String>,
+}
+
+/// One attempt to POST an event to an endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Delivery {
+    pub(crate) id: Uuid,
+    pub(crate) endpoint_id: Uuid,
+    pub(crate) event_id: Uuid,
+    pub(crate) topic: String,
+    /// Counting from 1.
+    pub(crate) attempt: i64,
+    /// HTTP status of the response, none when the request failed.
+    pub(crate) status: Option<i64>,
+    pub(crate) error: Option<String>,
+    pub(crate) duration_ms: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+}
+
+/// Where the endpoints and their deliveries are kept.
+pub(crate) trait WebhookStore: Send + Sync {
+    /// Oldest first.
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Endpoint>>>;
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Endpoint>>>;
+
+    fn create(
+        &self,
+        endpoint: NewEndpoint,
+    ) -> BoxFuture<'_, anyhow::Result<Endpoint>>;
+
+    /// False when there is no endpoint `id`.
+    fn set_enabled(
+        &self,
+        id: Uuid,
+        enabled: bool,
+    ) -> BoxFuture<'_, anyhow::Result<bool>>;
+
+    /// Deletes the endpoint and its deliveries, false when there is none.
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<bool>>;
+
+    fn record(&self, delivery: Delivery) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Deliveries to `endpoint_id`, newest first, walked by [`keyset`].
+    fn deliveries(
+        &self,
+        endpoint_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Delivery>>>;
+
+    /// Deletes the deliveries made before `before`, returning how many.
+    fn purge(
+        &self,
+        before: OffsetDateTime,
+    ) -> BoxFuture<'_, anyhow::Result<u64>>;
+}
+
+/// Tables of the database, kept in memory without one.
+pub(crate) fn from_db(db: Option<&Database>) -> Box<dyn WebhookStore> {
+    match db {
+        None => Box::new(MemoryWebhookStore::default()),
+        Some(db) => Box::new(SqlWebhookStore { db: db.clone() }),
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct MemoryWebhookStore {
+    endpoints: RwLock<Vec<Endpoint>>,
+    deliveries: RwLock<VecDeque<Delivery>>,
+}
+
+impl WebhookStore for MemoryWebhookStore {
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Endpoint>>> {
+        Box::pin(async move { Ok(self.endpoints.read().unwrap().clone()) })
+    }
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Endpoint>>> {
+        Box::pin(async move {
+            let endpoints = self.endpoints.read().unwrap();
+            Ok(endpoints.iter().find(|e| e.id == id).cloned())
+        })
+    }
+
+    fn create(
+        &self,
+        endpoint: NewEndpoint,
+    ) -> BoxFuture<'_, anyhow::Result<Endpoint>> {
+        Box::pin(async move {
+            let endpoint = Endpoint {
+                id: Uuid::new_v4(),
+                url: endpoint.url,
+                secret: endpoint.secret,
+                topics: endpoint.topics,
+                enabled: true,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            self.endpoints.write().unwrap().push(endpoint.clone());
+            Ok(endpoint)
+        })
+    }
+
+    fn set_enabled(
+        &self,
+        id: Uuid,
+        enabled: bool,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut endpoints = self.endpoints.write().unwrap();
+            let endpoint = endpoints.iter_mut().find(|e| e.id == id);
+            Ok(endpoint.map(|e| e.enabled = enabled).is_some())
+        })
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut endpoints = self.endpoints.write().unwrap();
+            let before = endpoints.len();
+            endpoints.retain(|e| e.id != id);
+            self.deliveries.write().unwrap().retain(|d| d.endpoint_id != id);
+            Ok(endpoints.len() < before)
+        })
+    }
+
+    fn record(&self, delivery: Delivery) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let mut deliveries = self.deliveries.write().unwrap();
+            if deliveries.len() >= MEMORY_DELIVERIES {
+                deliveries.pop_front();
+            }
+            deliveries.push_back(delivery);
+            Ok(())
+        })
+    }
+
+    fn deliveries(
+        &self,
+        endpoint_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Delivery>>> {
+        Box::pin(async move {
+            let mut deliveries: Vec<Delivery> = self
+                .deliveries
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|d| d.endpoint_id == endpoint_id)
+                .cloned()
+                .collect();
+            deliveries.sort_by(|a, b| {
+                b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id))
+            });
+            Ok(pagination.apply(deliveries.into_iter().filter(|d| {
+                pagination.after.is_none_or(|after| {
+                    d.created_at < after.created_at
+                        || (d.created_at == after.created_at
+                            && d.id > after.id)
+                })
+            })))
+        })
+    }
+
+    fn purge(
+        &self,
+        before: OffsetDateTime,
+    ) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let mut deliveries = self.deliveries.write().unwrap();
+            let count = deliveries.len();
+            deliveries.retain(|d| d.created_at >= before);
+            Ok((count - deliveries.len()) as u64)
+        })
+    }
+}
+
+pub(crate) struct SqlWebhookStore {
+    db: Database,
+}
+
+const ENDPOINT_COLUMNS: &str = "id, url, secret, topics, enabled, created_at";
+
+fn endpoint_from_row(row: &AnyRow) -> anyhow::Result<Endpoint> {
+    let topics: String = row.try_get(3)?;
+    Ok(Endpoint {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        url: row.try_get(1)?,
+        secret: row.try_get(2)?,
+        topics: serde_json::from_str(&topics)
+            .context("malformed webhook topics")?,
+        enabled: row.try_get::<i64, _>(4)? != 0,
+        created_at: from_micros(row.try_get(5)?)?,
+    })
+}
+
+const DELIVERY_COLUMNS: &str = "id, endpoint_id, event_id, topic, attempt, \
+     status, error, duration_ms, created_at";
+
+fn delivery_from_row(row: &AnyRow) -> anyhow::Result<Delivery> {
+    Ok(Delivery {
+        id: Uuid::try_parse(&row.try_get::<String, _>(0)?)?,
+        endpoint_id: Uuid::try_parse(&row.try_get::<String, _>(1)?)?,
+        event_id: Uuid::try_parse(&row.try_get::<String, _>(2)?)?,
+        topic: row.try_get(3)?,
+        attempt: row.try_get(4)?,
+        status: row.try_get(5)?,
+        error: row.try_get(6)?,
+        duration_ms: row.try_get(7)?,
+        created_at: from_micros(row.try_get(8)?)?,
+    })
+}
+
+impl WebhookStore for SqlWebhookStore {
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Endpoint>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {ENDPOINT_COLUMNS} FROM webhook_endpoints \
+                 ORDER BY created_at"
+            );
+            let query = query(&sql).fetch_all(self.db.pool(Access::ReadOnly));
+            let rows = self.db.observe("webhooks.list", &sql, query).await?;
+            rows.iter().map(endpoint_from_row).collect()
+        })
+    }
+
+    fn find(
+        &self,
+        id: Uuid,
+    ) -> BoxFuture<'_, anyhow::Result<Option<Endpoint>>> {
+        Box::pin(async move {
+            let sql = format!(
+                "SELECT {ENDPOINT_COLUMNS} FROM webhook_endpoints \
+                 WHERE id = $1"
+            );
+            let query = query(&sql)
+                .bind(id.to_string())
+                .fetch_optional(self.db.pool(Access::ReadOnly));
+            let row = self.db.observe("webhooks.find", &sql, query).await?;
+            row.as_ref().map(endpoint_from_row).transpose()
+        })
+    }
+
+    fn create(
+        &self,
+        endpoint: NewEndpoint,
+    ) -> BoxFuture<'_, anyhow::Result<Endpoint>> {
+        Box::pin(async move {
+            let endpoint = Endpoint {
+                id: Uuid::new_v4(),
+                url: endpoint.url,
+                secret: endpoint.secret,
+                topics: endpoint.topics,
+                enabled: true,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            let sql = format!(
+                "INSERT INTO webhook_endpoints ({ENDPOINT_COLUMNS}) \
+                 VALUES ($1, $2, $3, $4, 1, $5)"
+            );
+            let query = query(&sql)
+                .bind(endpoint.id.to_string())
+                .bind(&endpoint.url)
+                .bind(&endpoint.secret)
+                .bind(serde_json::to_string(&endpoint.topics)?)
+                .bind(to_micros(endpoint.created_at))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("webhooks.create", &sql, query).await?;
+            Ok(endpoint)
+        })
+    }
+
+    fn set_enabled(
+        &self,
+        id: Uuid,
+        enabled: bool,
+    ) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let sql =
+                "UPDATE webhook_endpoints SET enabled = $2 WHERE id = $1";
+            let query = query(sql)
+                .bind(id.to_string())
+                .bind(i64::from(enabled))
+                .execute(self.db.pool(Access::ReadWrite));
+            let result =
+                self.db.observe("webhooks.set_enabled", sql, query).await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn delete(&self, id: Uuid) -> BoxFuture<'_, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let mut tx = self.db.begin().await?;
+            let deliveries =
+                "DELETE FROM webhook_deliveries WHERE endpoint_id = $1";
+            let query_deliveries =
+                query(deliveries).bind(id.to_string()).execute(&mut *tx);
+            self.db
+                .observe(
+                    "webhooks.delete_deliveries",
+                    deliveries,
+                    query_deliveries,
+                )
+                .await?;
+            let endpoint = "DELETE FROM webhook_endpoints WHERE id = $1";
+            let query_endpoint =
+                query(endpoint).bind(id.to_string()).execute(&mut *tx);
+            let result = self
+                .db
+                .observe("webhooks.delete", endpoint, query_endpoint)
+                .await?;
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    fn record(&self, delivery: Delivery) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            let sql = "INSERT INTO webhook_deliveries \
+                       (id, endpoint_id, event_id, topic, attempt, status, \
+                       error, duration_ms, created_at) \
+                       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+            let query = query(sql)
+                .bind(delivery.id.to_string())
+                .bind(delivery.endpoint_id.to_string())
+                .bind(delivery.event_id.to_string())
+                .bind(delivery.topic)
+                .bind(delivery.attempt)
+                .bind(delivery.status)
+                .bind(delivery.error)
+                .bind(delivery.duration_ms)
+                .bind(to_micros(delivery.created_at))
+                .execute(self.db.pool(Access::ReadWrite));
+            self.db.observe("webhooks.record", sql, query).await?;
+            Ok(())
+        })
+    }
+
+    fn deliveries(
+        &self,
+        endpoint_id: Uuid,
+        pagination: Pagination,
+    ) -> BoxFuture<'_, anyhow::Result<Vec<Delivery>>> {
+        Box::pin(async move {
+            let pool = self.db.pool(Access::ReadOnly);
+            let rows = match pagination.after {
+                None => {
+                    let sql = format!(
+                        "SELECT {DELIVERY_COLUMNS} FROM webhook_deliveries \
+                         WHERE endpoint_id = $1 \
+                         ORDER BY created_at DESC, id {}",
+                        limit_offset(2)
+                    );
+                    let query = query(&sql)
+                        .bind(endpoint_id.to_string())
+                        .bind(pagination.limit() as i64)
+                        .bind(pagination.offset() as i64)
+                        .fetch_all(pool);
+                    self.db.observe("webhooks.deliveries", &sql, query).await?
+                }
+                Some(after) => {
+                    let sql = format!(
+                        "SELECT {DELIVERY_COLUMNS} FROM webhook_deliveries \
+                         WHERE endpoint_id = $1 AND {} \
+                         ORDER BY created_at DESC, id {}",
+                        keyset(
+                            &[("created_at", Order::Desc), ("id", Order::Asc)],
+                            2
+                        ),
+                        limit_offset(4)
+                    );
+                    let query = query(&sql)
+                        .bind(endpoint_id.to_string())
+                        .bind(after.micros())
+                        .bind(after.id.to_string())
+                        .bind(pagination.limit() as i64)
+                        .bind(pagination.offset() as i64)
+                        .fetch_all(pool);
+                    self.db.observe("webhooks.deliveries", &sql, query).await?
+                }
+            };
+            rows.iter().map(delivery_from_row).collect()
+        })
+    }
+
+    fn purge(
+        &self,
+        before: OffsetDateTime,
+    ) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move {
+            let sql = "DELETE FROM webhook_deliveries WHERE created_at < $1";
+            let query = query(sql)
+                .bind(to_micros(before))
+                .execute(self.db.pool(Access::ReadWrite));
+            let result = self.db.observe("webhooks.purge", sql, query).await?;
+            Ok(result.rows_affected())
+        })
+    }
+}
+
+/// Queues a delivery of `event` to each enabled endpoint of its topic.
+pub(crate) async fn dispatch(
+    state: &AppState,
+    event: &Arc<Event>,
+) -> anyhow::Result<()> {
+    for endpoint in state.webhooks.list().await? {
+        if endpoint.wants(&event.topic) {
+            let job = DeliveryJob {
+                endpoint_id: endpoint.id,
+                event: event.clone(),
+                attempts: AtomicU32::new(0),
+            };
+            state.jobs.push(job).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Delivery of an event to an endpoint, retried with the backoff of the
+/// job queue. Each attempt is recorded, and the endpoint read again so
+/// the ones disabled or deleted meanwhile are skipped.
+struct DeliveryJob {
+    endpoint_id: Uuid,
+    event: Arc<Event>,
+    attempts: AtomicU32,
+}
+
+impl Job for DeliveryJob {
+    fn name(&self) -> &'static str {
+        "webhooks.deliver"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let endpoint = state.webhooks.find(self.endpoint_id).await?;
+            let Some(endpoint) = endpoint.filter(|e| e.enabled) else {
+                info!(
+                    endpoint = %self.endpoint_id,
+                    event = %self.event.id,
+                    "webhook endpoint gone or disabled, skipping delivery"
+                );
+                return Ok(());
+            };
+
+            let attempt = self.attempts.fetch_add(1, Ordering::Relaxed) + 1;
+            let start = Instant::now();
+            let outcome = send(state, &endpoint, &self.event).await;
+            let (status, error) = match &outcome {
+                Ok(status) if status.is_success() => {
+                    (Some(status.as_u16()), None)
+                }
+                Ok(status) => {
+                    (Some(status.as_u16()), Some(format!("HTTP {status}")))
+                }
+                Err(e) => (None, Some(format!("{e:#}"))),
+            };
+            let delivery = Delivery {
+                id: Uuid::new_v4(),
+                endpoint_id: endpoint.id,
+                event_id: self.event.id,
+                topic: self.event.topic.clone(),
+                attempt: i64::from(attempt),
+                status: status.map(i64::from),
+                error: error.clone(),
+                duration_ms: start.elapsed().as_millis() as i64,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            if let Err(e) = state.webhooks.record(delivery).await {
+                warn!("could not record a webhook delivery: {e:#}");
+            }
+            match error {
+                None => Ok(()),
+                Some(e) => Err(anyhow!("webhook {}: {e}", endpoint.url)),
+            }
+        })
+    }
+}
+
+/// `v1,` and the base64 HMAC-SHA256, keyed by `secret`, of
+/// `<id>.<timestamp>.<body>`.
+pub(crate) fn sign(
+    secret: &str,
+    id: &str,
+    timestamp: i64,
+    body: &[u8],
+) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("hmac accepts keys of any size");
+    mac.update(format!("{id}.{timestamp}.").as_bytes());
+    mac.update(body);
+    format!("v1,{}", STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+/// POSTs `event` as JSON, with its id in `webhook-id`, the seconds since
+/// the epoch in `webhook-timestamp` and their [`sign`]ature with the body
+/// in `webhook-signature`.
+async fn send(
+    state: &AppState,
+    endpoint: &Endpoint,
+    event: &Event,
+) -> anyhow::Result<StatusCode> {
+    let body = serde_json::to_string(event)?;
+    let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    let signature = sign(
+        &endpoint.secret,
+        &event.id.to_string(),
+        timestamp,
+        body.as_bytes(),
+    );
+    let timeout = Duration::from_secs(state.settings.webhooks.timeout);
+    let request = state
+        .http
+        .post(&endpoint.url)
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("webhook-id", event.id.to_string())
+        .header("webhook-timestamp", timestamp.to_string())
+        .header("webhook-signature", signature)
+        .body(body);
+    let response = state.http.send(request).await?;
+    Ok(response.status())
+}
+
+/// Scheduled task deleting the deliveries older than
+/// `webhooks.retention_days`.
+pub(crate) struct DeliveryPurge;
+
+impl Task for DeliveryPurge {
+    fn name(&self) -> &'static str {
+        "webhooks.purge_deliveries"
+    }
+
+    fn schedule(&self) -> &'static str {
+        "@daily"
+    }
+
+    fn run<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let retention = time::Duration::days(
+                state.settings.webhooks.retention_days as i64,
+            );
+            let before = OffsetDateTime::now_utc() - retention;
+            let purged = state.webhooks.purge(before).await?;
+            info!(purged, "webhook deliveries purged");
+            Ok(())
+        })
+    }
+}
+
+fn generate_secret() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| anyhow!("could not generate a webhook secret: {e}"))?;
+    Ok(format!("{SECRET_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+fn validate_url(url: &str) -> Result<(), ValidationError> {
+    match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        _ => Err(ValidationError::new("url")
+            .with_message("Must be an http or https URL".into())),
+    }
+}
+
+fn validate_topics(topics: &str) -> Result<(), ValidationError> {
+    if topics.split_whitespace().all(|topic| TOPICS.contains(&topic)) {
+        return Ok(());
+    }
+    Err(ValidationError::new("topics")
+        .with_message(format!("Must be among {}", TOPICS.join(", ")).into()))
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub(crate) struct EndpointInput {
+    #[validate(custom(function = "validate_url"))]
+    pub(crate) url: String,
+    /// Space separated, every topic when empty.
+    #[serde(default)]
+    #[validate(custom(function = "validate_topics"))]
+    pub(crate) topics: String,
+}
+
+impl FormDefinition for EndpointInput {
+    fn form() -> FormSpec {
+        FormSpec::new("/admin/webhooks")
+            .submit("Add endpoint")
+            .field(Field::new("url", "URL", FieldKind::Url).required())
+            .field(Field::text("topics", "Topics").help(
+                "Space separated, every topic when empty: user.created, \
+                 user.trashed, user.restored, user.deleted",
+            ))
+    }
+}
+
+async fn render_endpoints(
+    state: &AppState,
+    view: &View,
+    input: &impl Serialize,
+    errors: &FormErrors,
+    created: Option<&Endpoint>,
+) -> Result<Html<String>, ServerError> {
+    let endpoints = state.webhooks.list().await?;
+    Ok(view
+        .render(
+            "webhooks",
+            context! {
+                title => "Webhooks",
+                endpoints => endpoints,
+                created => created,
+                created_secret => created.map(|e| &e.secret),
+                form => EndpointInput::form().bind(input, errors),
+            },
+        )
+        .unwrap())
+}
+
+pub(crate) async fn handler_webhooks(
+    State(state): State<Arc<AppState>>,
+    view: View,
+) -> Result<Html<String>, ServerError> {
+    render_endpoints(&state, &view, &(), &FormErrors::default(), None).await
+}
+
+pub(crate) async fn handler_webhooks_post(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    audit: Audit,
+    Form(input): Form<EndpointInput>,
+) -> Result<Response, ServerError> {
+    if let Err(errors) = input.validate() {
+        let errors = FormErrors::from(&errors);
+        let rendered =
+            render_endpoints(&state, &view, &input, &errors, None).await?;
+        return Ok(
+            (StatusCode::UNPROCESSABLE_ENTITY, rendered).into_response()
+        );
+    }
+
+    let endpoint = state
+        .webhooks
+        .create(NewEndpoint {
+            url: input.url,
+            secret: generate_secret()?,
+            topics: input.topics.split_whitespace().map(Into::into).collect(),
+        })
+        .await?;
+    info!(endpoint = %endpoint.id, url = endpoint.url, "webhook created");
+    audit
+        .record(
+            "webhook.create",
+            Some(endpoint.id.to_string()),
+            json!({ "url": endpoint.url, "topics": endpoint.topics }),
+        )
+        .await;
+    let rendered = render_endpoints(
+        &state,
+        &view,
+        &(),
+        &FormErrors::default(),
+        Some(&endpoint),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, rendered).into_response())
+}
+
+/// Endpoint page listing its deliveries, newest first.
+pub(crate) async fn handler_webhook(
+    State(state): State<Arc<AppState>>,
+    view: View,
+    pagination: Pagination,
+    Path(id): Path<Uuid>,
+) -> Result<Response, ServerError> {
+    let Some(endpoint) = state.webhooks.find(id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let deliveries = state.webhooks.deliveries(id, pagination).await?;
+    Ok(view
+        .render(
+            "webhook",
+            context! {
+                title => format!("Webhook {}", endpoint.url),
+                endpoint => endpoint,
+                page => Page::keyset(deliveries, pagination, |d| Cursor {
+                    created_at: d.created_at,
+                    id: d.id,
+                }),
+            },
+        )
+        .unwrap()
+        .into_response())
+}
+
+/// Enables a disabled endpoint and disables an enabled one.
+pub(crate) async fn handler_webhook_toggle(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, ServerError> {
+    let Some(endpoint) = state.webhooks.find(id).await? else {
+        flash.error("No such webhook.");
+        return Ok(Redirect::to("/admin/webhooks"));
+    };
+    let enabled = !endpoint.enabled;
+    state.webhooks.set_enabled(id, enabled).await?;
+    let action = if enabled { "enable" } else { "disable" };
+    info!(endpoint = %id, "webhook {action}d");
+    audit
+        .record(&format!("webhook.{action}"), Some(id.to_string()), json!({}))
+        .await;
+    flash.success(format!("Webhook {action}d."));
+    Ok(Redirect::to(&format!("/admin/webhooks/{id}")))
+}
+
+pub(crate) async fn handler_webhook_delete(
+    State(state): State<Arc<AppState>>,
+    audit: Audit,
+    flash: Flash,
+    Path(id): Path<Uuid>,
+) -> Result<Redirect, ServerError> {
+    if state.webhooks.delete(id).await? {
+        info!(endpoint = %id, "webhook deleted");
+        audit.record("webhook.delete", Some(id.to_string()), json!({})).await;
+        flash.success("Webhook deleted.");
+    } else {
+        flash.error("No such webhook.");
+    }
+    Ok(Redirect::to("/admin/webhooks"))
+}
@@ -0,0 +1,247 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{
+        State,
+        ws::{
+            CloseFrame, Message, Utf8Bytes, WebSocket, WebSocketUpgrade,
+            close_code,
+        },
+    },
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::auth::OptionalUser;
+use crate::state::AppState;
+use crate::users::User;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct WebSocketSettings {
+    /// Seconds between the pings sent to each connection.
+    pub(crate) heartbeat: u64,
+    /// Seconds without hearing from a connection, pongs included, before
+    /// closing it.
+    pub(crate) idle_timeout: u64,
+    /// Messages a slow connection can fall behind the hub before it misses
+    /// some.
+    pub(crate) capacity: usize,
+    /// Bytes of a message sent by a client.
+    pub(crate) max_message_size: usize,
+    /// Seconds the connections are given to close on shutdown.
+    pub(crate) close_timeout: u64,
+}
+
+/// Message of the hub, serialized once for every connection.
+#[derive(Debug, Clone)]
+struct Envelope {
+    /// Only the connections of this user, every one when none.
+    to: Option<Uuid>,
+    text: Utf8Bytes,
+}
+
+/// Broadcast hub of the WebSocket connections of this instance.
+///
+/// Messages are JSON objects sent to every connection with
+/// [`Hub::broadcast`], or to the ones of a user with [`Hub::send_to`].
+/// Connections falling more than `capacity` messages behind miss the
+/// oldest. On shutdown [`Hub::close`] tells every connection to close.
+pub(crate) struct Hub {
+    sender: broadcast::Sender<Envelope>,
+    /// Each connection holds a receiver, so its count is theirs.
+    closing: watch::Sender<bool>,
+    close_timeout: Duration,
+}
+
+impl Hub {
+    pub(crate) fn new(settings: &WebSocketSettings) -> Self {
+        Hub {
+            sender: broadcast::channel(settings.capacity).0,
+            closing: watch::Sender::new(false),
+            close_timeout: Duration::from_secs(settings.close_timeout),
+        }
+    }
+
+    /// Sends `message` to every connection, returning how many.
+    pub(crate) fn broadcast(&self, message: &impl Serialize) -> usize {
+        self.publish(None, message)
+    }
+
+    /// Sends `message` to the connections of `user_id`, returning how many
+    /// connections of this instance were sent it, theirs or not.
+    #[allow(dead_code)]
+    pub(crate) fn send_to(
+        &self,
+        user_id: Uuid,
+        message: &impl Serialize,
+    ) -> usize {
+        self.publish(Some(user_id), message)
+    }
+
+    fn publish(&self, to: Option<Uuid>, message: &impl Serialize) -> usize {
+        let text = match serde_json::to_string(message) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("could not serialize a websocket message: {e}");
+                return 0;
+            }
+        };
+        // Fails when nobody is connected.
+        self.sender.send(Envelope { to, text: text.into() }).unwrap_or(0)
+    }
+
+    /// Open connections.
+    pub(crate) fn connections(&self) -> usize {
+        self.closing.receiver_count()
+    }
+
+    /// Tells every connection, and the ones opened from now on, to close,
+    /// and waits up to `close_timeout` for them to.
+    pub(crate) async fn close(&self) {
+        self.closing.send_replace(true);
+        let connections = self.connections();
+        if connections == 0 {
+            return;
+        }
+        info!(connections, "closing the websocket connections");
+        let closed = self.closing.closed();
+        if tokio::time::timeout(self.close_timeout, closed).await.is_err() {
+            warn!(
+                connections = self.connections(),
+                "websocket connections still open after {:?}",
+                self.close_timeout
+            );
+        }
+    }
+}
+
+/// Chat message relayed to every connection by the `/ws` demo.
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    user: &'a str,
+    text: &'a str,
+    #[serde(with = "time::serde::rfc3339")]
+    at: OffsetDateTime,
+}
+
+/// Upgrades to a WebSocket for the logged in user, a chat demo: each text
+/// message is sent to every connection as `{"user", "text", "at"}`, along
+/// with the messages of [`Hub::broadcast`].
+///
+/// Browsers send the session cookie with the upgrade whatever the page
+/// opening it, so pages of other origins are refused.
+pub(crate) async fn handler_ws(
+    State(state): State<Arc<AppState>>,
+    OptionalUser(user): OptionalUser,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(user) = user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if !same_origin(&state.settings.site.url, &headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    ws.max_message_size(state.settings.websocket.max_message_size)
+        .on_upgrade(move |socket| connection(state, socket, user))
+}
+
+/// False when the `Origin` header names another site than `site_url`.
+/// Clients other than browsers may leave it out.
+fn same_origin(site_url: &str, headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    origin.as_bytes() == site_url.trim_end_matches('/').as_bytes()
+}
+
+async fn connection(state: Arc<AppState>, mut socket: WebSocket, user: User) {
+    let settings = &state.settings.websocket;
+    let mut closing = state.hub.closing.subscribe();
+    let mut messages = state.hub.sender.subscribe();
+    let mut heartbeat =
+        tokio::time::interval(Duration::from_secs(settings.heartbeat));
+    let idle_timeout = Duration::from_secs(settings.idle_timeout);
+    let mut last_seen = Instant::now();
+    metrics::gauge!("websocket_connections").increment(1);
+    debug!(user = %user.id, "websocket connected");
+
+    let close = loop {
+        tokio::select! {
+            received = socket.recv() => {
+                last_seen = Instant::now();
+                match received {
+                    Some(Ok(Message::Text(text))) => {
+                        state.hub.broadcast(&ChatMessage {
+                            user: &user.name,
+                            text: text.as_str(),
+                            at: OffsetDateTime::now_utc(),
+                        });
+                    }
+                    Some(Ok(Message::Close(_))) | None => break None,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        debug!(user = %user.id, "websocket failed: {e}");
+                        break None;
+                    }
+                }
+            }
+            message = messages.recv() => match message {
+                Ok(Envelope { to, text }) => {
+                    if to.is_some_and(|id| id != user.id) {
+                        continue;
+                    }
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break None;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(user = %user.id, missed, "websocket fell behind");
+                }
+                Err(broadcast::error::RecvError::Closed) => break None,
+            },
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > idle_timeout {
+                    break Some((close_code::AWAY, "idle"));
+                }
+                if socket.send(Message::Ping(Bytes::new())).await.is_err() {
+                    break None;
+                }
+            }
+            _ = async { closing.wait_for(|closing| *closing).await.is_ok() } => {
+                break Some((close_code::AWAY, "server shutting down"));
+            }
+        }
+    };
+    if let Some((code, reason)) = close {
+        let frame = CloseFrame { code, reason: reason.into() };
+        let _ = socket.send(Message::Close(Some(frame))).await;
+    }
+    metrics::gauge!("websocket_connections").decrement(1);
+    debug!(user = %user.id, "websocket disconnected");
+}
@@ -0,0 +1,68 @@
+//
+// Copyright (c) 2025 murilo ijanc' <murilo@ijanc.org>
+//
+// Permission to use, copy, modify, and distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tracing::info;
+
+use crate::banner;
+use crate::error_reporting;
+use crate::helpers::{self, LogFilter};
+use crate::jobs;
+use crate::outbox;
+use crate::scheduler;
+use crate::settings::Settings;
+use crate::state::{self, AppState};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct WorkerSettings {
+    /// Run the background tasks in the server, for deployments without the
+    /// `worker` binary.
+    pub(crate) embedded: bool,
+}
+
+/// Starts the tasks not tied to a request: the scheduled tasks and the
+/// outbox relay. Fails on an invalid schedule.
+pub(crate) fn spawn_background(state: Arc<AppState>) -> anyhow::Result<()> {
+    scheduler::spawn(state.clone())?;
+    outbox::spawn_relay(state);
+    Ok(())
+}
+
+/// Runs the job workers and the background tasks until the shutdown
+/// signal. The worker shares the settings, state and database of the
+/// server but serves no request, so it scales and deploys on its own.
+pub(crate) async fn run(
+    settings: Settings,
+    log_filter: LogFilter,
+) -> anyhow::Result<()> {
+    banner::log(&settings, "worker", "none");
+    let _report_guard =
+        error_reporting::init(&settings.error_reporting, &settings.run_mode)?;
+
+    let (state, job_receiver) = state::build(settings, log_filter).await?;
+    jobs::spawn_workers(state.clone(), job_receiver);
+    spawn_background(state.clone())?;
+    info!("worker running");
+
+    helpers::shutdown_signal().await;
+    info!("worker stopping");
+    if let Some(db) = &state.db {
+        db.close().await;
+    }
+    Ok(())
+}